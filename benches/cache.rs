@@ -0,0 +1,97 @@
+// benches/cache.rs
+//
+// Times full runs over a generated tree against repeat runs reusing the
+// render cache, with and without trusting modification times, both plain
+// and with --collapse-data. Run with `cargo bench --bench cache`;
+// SOURCEWEAVER_BENCH_FILES sets the number of files (5,000 of about 10 KB
+// by default).
+use std::{
+    env, fs, io,
+    path::Path,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const RUNS: usize = 5;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let files: usize = match env::var("SOURCEWEAVER_BENCH_FILES") {
+        Ok(files) => files.parse()?,
+        Err(_) => 5_000,
+    };
+    let dir = env::temp_dir().join(format!("sourceweaver-bench-{}", std::process::id()));
+    let root = dir.join("tree");
+    write_tree(&root, files)?;
+    // Let the files' modification times age past the slack that keeps
+    // freshly written files from being trusted
+    std::thread::sleep(Duration::from_secs(3));
+
+    println!("{} files, best of {} runs", files, RUNS);
+    for extra in [&[][..], &["--collapse-data"][..]] {
+        let cache = dir.join(format!("cache{}.json", extra.len()));
+        let cache = cache.to_str().ok_or("temp dir isn't UTF-8")?;
+        for (name, options) in [
+            ("no cache", &[][..]),
+            ("cache", &["--cache", cache][..]),
+            (
+                "cache, no mtime trust",
+                &["--cache", cache, "--no-cache-trust-mtime"][..],
+            ),
+        ] {
+            let args: Vec<&str> = extra.iter().chain(options).copied().collect();
+            // The first run warms the page cache and fills the render cache
+            weave(&root, &dir, &args)?;
+            let best = (0..RUNS)
+                .map(|_| weave(&root, &dir, &args))
+                .collect::<io::Result<Vec<_>>>()?
+                .into_iter()
+                .min()
+                .unwrap_or_default();
+            println!(
+                "{:<40}{:>8.1} ms",
+                format!("{} {}", name, extra.join(" ")),
+                best.as_secs_f64() * 1000.0
+            );
+        }
+    }
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// One run of the binary over `root`, writing the document into `dir`
+fn weave(root: &Path, dir: &Path, args: &[&str]) -> io::Result<Duration> {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_sourceweaver"))
+        .arg(root)
+        .args(["--no-progress", "--output"])
+        .arg(dir.join("document.md"))
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    let elapsed = start.elapsed();
+    match status.success() {
+        true => Ok(elapsed),
+        false => Err(io::Error::other(format!(
+            "sourceweaver {:?}: {}",
+            args, status
+        ))),
+    }
+}
+
+// About 10 KB of Rust per file, in directories of 100 files
+fn write_tree(root: &Path, files: usize) -> io::Result<()> {
+    let body: String = (0..250)
+        .map(|line| format!("    let value_{line} = compute({line}) + offset;\n"))
+        .collect();
+    for index in 0..files {
+        let dir = root.join(format!("module_{:03}", index / 100));
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join(format!("file_{index}.rs")),
+            format!("fn function_{index}(offset: u64) {{\n{body}}}\n"),
+        )?;
+    }
+    Ok(())
+}
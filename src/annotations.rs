@@ -0,0 +1,148 @@
+// src/annotations.rs
+use crate::{Result, WeaveError};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// Descriptions shown under the headings of files matching their globs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Annotations {
+    matchers: Vec<(Gitignore, String)>,
+}
+
+impl Annotations {
+    pub(crate) fn new(root: &Path, annotations: &[(String, String)]) -> Result<Self> {
+        let matchers = annotations
+            .iter()
+            .map(|(glob, description)| {
+                let invalid = |e: ignore::Error| {
+                    WeaveError::InvalidOptions(format!("invalid annotation glob '{}': {}", glob, e))
+                };
+                let mut builder = GitignoreBuilder::new(root);
+                builder.add_line(None, glob).map_err(invalid)?;
+                Ok((builder.build().map_err(invalid)?, description.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Annotations { matchers })
+    }
+
+    /// The description for the file at `relative_path`: every matching
+    /// description in the order they were added, joined by spaces. A glob
+    /// matching a directory above the file counts as a match.
+    pub(crate) fn describe(&self, relative_path: &Path) -> Option<String> {
+        let descriptions: Vec<&str> = self
+            .matchers
+            .iter()
+            .filter(|(matcher, _)| {
+                relative_path
+                    .ancestors()
+                    .filter(|level| !level.as_os_str().is_empty())
+                    .enumerate()
+                    .any(|(depth, level)| !matcher.matched(level, depth > 0).is_none())
+            })
+            .map(|(_, description)| description.as_str())
+            .collect();
+        let text = descriptions.join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+/// `description` as a Markdown italic paragraph, with asterisks escaped so
+/// they can't end the emphasis early.
+pub(crate) fn italic(description: &str) -> String {
+    format!("*{}*", description.replace('*', "\\*"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WeaveOptions, generate_markdown};
+    use std::fs;
+
+    fn annotations(pairs: &[(&str, &str)]) -> Annotations {
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|(glob, text)| (glob.to_string(), text.to_string()))
+            .collect();
+        Annotations::new(Path::new("/project"), &pairs).unwrap()
+    }
+
+    #[test]
+    fn matching_descriptions_join_in_the_order_given() {
+        let broad_first = annotations(&[
+            ("src/", "Library code."),
+            ("retry.rs", "Implements the retry policy."),
+            ("*.md", "Documentation."),
+        ]);
+        assert_eq!(
+            broad_first.describe(Path::new("src/net/retry.rs")).unwrap(),
+            "Library code. Implements the retry policy."
+        );
+        // The more specific glob doesn't win; order alone decides
+        let specific_first = annotations(&[
+            ("retry.rs", "Implements the retry policy."),
+            ("src/", "Library code."),
+        ]);
+        assert_eq!(
+            specific_first
+                .describe(Path::new("src/net/retry.rs"))
+                .unwrap(),
+            "Implements the retry policy. Library code."
+        );
+        assert_eq!(
+            broad_first.describe(Path::new("src/lib.rs")).unwrap(),
+            "Library code."
+        );
+    }
+
+    #[test]
+    fn unmatched_and_blank_descriptions_give_none() {
+        let set = annotations(&[("/src/*.rs", "Sources."), ("*.txt", "  \n ")]);
+        assert_eq!(set.describe(Path::new("build.rs")), None);
+        // Anchored to the root, so a nested src doesn't match
+        assert_eq!(set.describe(Path::new("vendor/src/lib.rs")), None);
+        assert_eq!(set.describe(Path::new("notes.txt")), None);
+        assert_eq!(Annotations::default().describe(Path::new("main.rs")), None);
+    }
+
+    #[test]
+    fn descriptions_are_italic_paragraphs_between_heading_and_fence() {
+        assert_eq!(italic("uses *unsafe*"), "*uses \\*unsafe\\**");
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/retry.rs"), "fn retry() {}\n").unwrap();
+        fs::write(dir.path().join("build.rs"), "fn main() {}\n").unwrap();
+        let options = WeaveOptions::new(dir.path())
+            .annotate("src/*.rs", "Implements the\nretry policy.")
+            .build()
+            .unwrap();
+        let mut document = Vec::new();
+        generate_markdown(&mut document, &options).unwrap();
+        let document = String::from_utf8(document).unwrap();
+        assert!(
+            document.contains(
+                "## `src/retry.rs`\n\n*Implements the retry policy.*\n\n```rust\nfn retry() {}\n```"
+            ),
+            "{}",
+            document
+        );
+        assert!(
+            document.contains("## `build.rs`\n\n```rust\nfn main() {}\n```"),
+            "{}",
+            document
+        );
+    }
+
+    #[test]
+    fn invalid_globs_name_the_glob() {
+        let pairs = [("src/**[".to_string(), "Broken.".to_string())];
+        let err = Annotations::new(Path::new("/project"), &pairs).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid options: invalid annotation glob 'src/**[': "),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,288 @@
+// src/archive.rs
+use crate::{
+    FilterStage, Result, SkipReason, WeaveError, WeaveOptions, filter_stages, is_lock_file,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+use tracing::{trace, warn};
+
+// Ignore files honoured inside an archive, like the directory walk does
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// Supported archive formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Files selected from an archive, with their content already in memory.
+pub(crate) struct ArchiveContents {
+    /// Selected files by in-archive path (wrapper directory stripped), sorted.
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+    /// Files skipped by the built-in filters.
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+/// Whether `path` is an archive file to be read in place of a directory.
+pub(crate) fn is_archive(path: &Path) -> bool {
+    path.is_file() && kind(path).is_some()
+}
+
+// Identify the format by extension, or by magic bytes for unknown extensions
+fn kind(path: &Path) -> Option<Kind> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        return Some(Kind::Zip);
+    }
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        return Some(Kind::TarGz);
+    }
+    if name.ends_with(".tar") {
+        return Some(Kind::Tar);
+    }
+    let mut magic = [0; 4];
+    File::open(path).ok()?.read_exact(&mut magic).ok()?;
+    match magic {
+        [b'P', b'K', 3, 4] => Some(Kind::Zip),
+        [0x1f, 0x8b, _, _] => Some(Kind::TarGz),
+        _ => None,
+    }
+}
+
+/// Read the archive at `options.root` and apply the same filters as the
+/// directory walk: hidden files, include and exclude globs, `.gitignore`/`.ignore` files
+/// found inside the archive, lock files, and the size limit. Entries with
+/// absolute or `..` paths are rejected, and a single top-level directory
+/// wrapping everything (as in GitHub downloads) is stripped from the paths.
+pub(crate) fn read_archive(options: &WeaveOptions) -> Result<ArchiveContents> {
+    let root = options.root();
+    let archive_error = |source| WeaveError::Archive {
+        path: root.to_path_buf(),
+        source,
+    };
+    let kind = kind(root).ok_or_else(|| {
+        archive_error(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a zip or tar archive",
+        ))
+    })?;
+    let entries = read_entries(root, kind).map_err(archive_error)?;
+
+    let mut contents = ArchiveContents {
+        files: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut safe = Vec::with_capacity(entries.len());
+    for (name, data) in entries {
+        let path = PathBuf::from(&name);
+        if is_safe(&path) {
+            safe.push((path, data));
+        } else {
+            warn!("Rejecting archive entry with unsafe path: {}", name);
+            contents.skipped.push((path, SkipReason::UnsafePath));
+        }
+    }
+    strip_wrapper(&mut safe);
+
+    let ignores = build_ignores(&safe);
+    let stages = filter_stages(options);
+    let lock_files: &[String] = match stages.contains(&FilterStage::LockFiles) {
+        true => options.lock_files(),
+        false => &[],
+    };
+    safe.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, data) in safe {
+        if let Some(dir) = excluded_dir(options, &path) {
+            // Record the directory once, as the walk does when it prunes it
+            if !contents
+                .skipped
+                .contains(&(dir.clone(), SkipReason::ExcludedDir))
+            {
+                contents.skipped.push((dir, SkipReason::ExcludedDir));
+            }
+        } else if stages.contains(&FilterStage::Hidden) && is_hidden(&path) {
+            trace!("Skipping hidden archive entry {}", path.display());
+        } else if is_excluded(options, &path)
+            || stages.contains(&FilterStage::IgnoreFiles) && is_ignored(&ignores, &path)
+        {
+            trace!("Skipping ignored archive entry {}", path.display());
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| is_lock_file(name, lock_files, options.lock_files_case_insensitive))
+        {
+            contents.skipped.push((path, SkipReason::LockFile));
+        } else if options
+            .max_filesize()
+            .is_some_and(|max| data.len() as u64 > max)
+        {
+            contents.skipped.push((path, SkipReason::TooLarge));
+        } else {
+            contents.files.push((path, data));
+        }
+    }
+    Ok(contents)
+}
+
+// The outermost directory of `path` whose name is an excluded directory name
+fn excluded_dir(options: &WeaveOptions, path: &Path) -> Option<PathBuf> {
+    let parent = path.parent()?;
+    let mut dir = PathBuf::new();
+    for component in parent.components() {
+        dir.push(component);
+        if options
+            .exclude_dirs()
+            .iter()
+            .any(|name| component.as_os_str() == name.as_str())
+        {
+            return Some(dir);
+        }
+    }
+    None
+}
+
+// Every regular file in the archive, as (raw path, content)
+#[cfg_attr(not(all(feature = "zip", feature = "tar")), allow(unused_variables))]
+fn read_entries(path: &Path, kind: Kind) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let file = File::open(path)?;
+    match kind {
+        #[cfg(feature = "zip")]
+        Kind::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+            let mut entries = Vec::with_capacity(archive.len());
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+                if !entry.is_file() {
+                    continue;
+                }
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data)?;
+                entries.push((entry.name().to_string(), data));
+            }
+            Ok(entries)
+        }
+        #[cfg(feature = "tar")]
+        Kind::Tar => read_tar(file),
+        #[cfg(feature = "tar")]
+        Kind::TarGz => read_tar(flate2::read::GzDecoder::new(file)),
+        #[allow(unreachable_patterns)]
+        kind => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{:?} archives are not supported by this build; rebuild with the '{}' feature",
+                kind,
+                if kind == Kind::Zip { "zip" } else { "tar" }
+            ),
+        )),
+    }
+}
+
+#[cfg(feature = "tar")]
+fn read_tar<R: Read>(reader: R) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        entries.push((name, data));
+    }
+    Ok(entries)
+}
+
+// Only plain relative paths may come out of an archive
+fn is_safe(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+// Drop a top-level directory shared by every entry, e.g. `project-main/`
+fn strip_wrapper(entries: &mut [(PathBuf, Vec<u8>)]) {
+    let Some(first) = entries
+        .first()
+        .and_then(|(path, _)| path.components().next())
+    else {
+        return;
+    };
+    let wrapper = PathBuf::from(first.as_os_str());
+    let wrapped = entries
+        .iter()
+        .all(|(path, _)| path.starts_with(&wrapper) && path.components().count() > 1);
+    if wrapped {
+        for (path, _) in entries.iter_mut() {
+            *path = path.strip_prefix(&wrapper).unwrap_or(path).to_path_buf();
+        }
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+// Include and exclude globs apply to the file and every directory above it
+fn is_excluded(options: &WeaveOptions, path: &Path) -> bool {
+    options.matcher.is_excluded(path, false)
+}
+
+// One matcher per directory containing an ignore file, keyed by that directory
+fn build_ignores(entries: &[(PathBuf, Vec<u8>)]) -> BTreeMap<PathBuf, Gitignore> {
+    let mut builders: BTreeMap<PathBuf, GitignoreBuilder> = BTreeMap::new();
+    for (path, data) in entries {
+        let is_ignore_file = path
+            .file_name()
+            .is_some_and(|name| IGNORE_FILES.iter().any(|f| name == *f));
+        if !is_ignore_file {
+            continue;
+        }
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let builder = builders
+            .entry(dir.clone())
+            .or_insert_with(|| GitignoreBuilder::new(&dir));
+        for line in String::from_utf8_lossy(data).lines() {
+            if let Err(e) = builder.add_line(Some(path.clone()), line) {
+                warn!("Ignoring invalid rule in {}: {}", path.display(), e);
+            }
+        }
+    }
+    builders
+        .into_iter()
+        .filter_map(|(dir, builder)| match builder.build() {
+            Ok(gitignore) => Some((dir, gitignore)),
+            Err(e) => {
+                warn!("Ignoring rules in {}: {}", dir.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Check the rules of every directory above `path`, nearest last so it wins
+fn is_ignored(ignores: &BTreeMap<PathBuf, Gitignore>, path: &Path) -> bool {
+    let mut ignored = false;
+    for (dir, gitignore) in ignores {
+        if !path.starts_with(dir) {
+            continue;
+        }
+        let matched = gitignore.matched_path_or_any_parents(path, false);
+        if matched.is_ignore() {
+            ignored = true;
+        } else if matched.is_whitelist() {
+            ignored = false;
+        }
+    }
+    ignored
+}
@@ -0,0 +1,156 @@
+// src/argfile.rs
+use std::{ffi::OsString, fs};
+
+// Options whose values commonly start with `@`, such as `--owner
+// @org/team`, `--prefix @prompt.md`, or `--package @acme/api`, so the
+// argument after them is never a response file
+const AT_VALUE_OPTIONS: &[&str] = &["--owner", "--prefix", "--suffix", "-p", "--package"];
+
+fn takes_at_value(arg: &OsString) -> bool {
+    AT_VALUE_OPTIONS.iter().any(|option| arg == *option)
+}
+
+/// Expand every `@FILE` argument into the arguments listed in FILE.
+///
+/// Each line of FILE is one argument, taken literally: quotes and
+/// backslashes have no special meaning and nothing is trimmed, so a path
+/// with spaces needs no quoting. Blank lines and lines starting with `#` are
+/// skipped, and both `\n` and `\r\n` line endings work. Arguments after
+/// `--` are never expanded, nor are the values of `--owner`, `--prefix`,
+/// `--suffix`, and `-p`/`--package` (npm scopes start with `@`), and a
+/// response file may not name another one.
+pub fn expand(args: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>, String> {
+    let mut expanded = Vec::new();
+    let mut args = args.into_iter();
+    // The program name is never a response file
+    expanded.extend(args.next());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+        if takes_at_value(&arg) {
+            expanded.push(arg);
+            expanded.extend(args.next());
+            continue;
+        }
+        match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+            Some(path) if !path.is_empty() => expanded.extend(read(path)?),
+            _ => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
+
+// The arguments in the response file at `path`
+fn read(path: &str) -> Result<Vec<OsString>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("can't read response file {}: {}", path, e))?;
+    let mut args: Vec<OsString> = Vec::new();
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('@') && !args.last().is_some_and(takes_at_value) {
+            return Err(format!(
+                "response file {} names another response file ({}); nesting isn't supported",
+                path, line
+            ));
+        }
+        args.push(OsString::from(line));
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // `sourceweaver` followed by `args`, expanded
+    fn run(args: &[&str]) -> Result<Vec<String>, String> {
+        let args = std::iter::once("sourceweaver").chain(args.iter().copied());
+        let expanded = expand(args.map(OsString::from))?;
+        Ok(expanded
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect())
+    }
+
+    fn response_file(dir: &Path, name: &str, content: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        format!("@{}", path.display())
+    }
+
+    #[test]
+    fn lines_are_arguments_taken_literally() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = response_file(
+            dir.path(),
+            "args.txt",
+            "# generated by the build\r\n\
+             --also\r\n\
+             C:\\Program Files\\My App\\config.toml\r\n\
+             \r\n   \n\
+             --include\n\
+             src/my module/*.rs\n\
+             \"quoted\"\n \
+             # indented, so an argument\n",
+        );
+        assert_eq!(
+            run(&["-v", &file, "--stats"]).unwrap(),
+            [
+                "sourceweaver",
+                "-v",
+                "--also",
+                "C:\\Program Files\\My App\\config.toml",
+                "--include",
+                "src/my module/*.rs",
+                "\"quoted\"",
+                " # indented, so an argument",
+                "--stats",
+            ]
+        );
+    }
+
+    #[test]
+    fn at_values_and_arguments_after_dashes_stay_put() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = response_file(dir.path(), "args.txt", "--owner\n@org/team\n");
+        assert_eq!(
+            run(&["--package", "@acme/api", "--", &file, "@"]).unwrap(),
+            ["sourceweaver", "--package", "@acme/api", "--", &file, "@"]
+        );
+        // An @-value inside the file is fine too
+        assert_eq!(
+            run(&[&file]).unwrap(),
+            ["sourceweaver", "--owner", "@org/team"]
+        );
+    }
+
+    #[test]
+    fn nested_and_missing_files_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = response_file(dir.path(), "inner.txt", "--hidden\n");
+        let outer = response_file(dir.path(), "outer.txt", &format!("-v\n{}\n", inner));
+        let error = run(&[&outer]).unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "response file {} names another response file ({}); nesting isn't supported",
+                &outer[1..],
+                inner
+            )
+        );
+
+        let missing = dir.path().join("missing.txt");
+        let error = run(&[&format!("@{}", missing.display())]).unwrap_err();
+        assert!(
+            error.starts_with(&format!("can't read response file {}: ", missing.display())),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,94 @@
+// src/binary.rs
+use crate::{
+    FileOutcome, FileVisitor, Result, SectionOptions, annotations, sentinels, write_section,
+};
+use std::{fs::File, io::Write, path::Path};
+use tracing::debug;
+
+/// Extensions of formats that are always binary, compared ignoring case:
+/// images, fonts, audio and video, archives and packages, compiled code,
+/// documents, and databases. Files with one of them get the binary
+/// placeholder without being read, unless the
+/// [shortcut](crate::WeaveOptionsBuilder::extension_shortcut) is turned
+/// off. Formats that may be text, such as `.svg` or `.ts`, aren't listed.
+pub const BINARY_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "icns", "webp", "tif", "tiff", "psd", "heic",
+    "avif", "woff", "woff2", "ttf", "otf", "eot", "mp3", "m4a", "aac", "flac", "ogg", "wav", "mp4",
+    "m4v", "mov", "avi", "mkv", "webm", "wmv", "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar",
+    "jar", "war", "aar", "apk", "ipa", "whl", "exe", "dll", "so", "dylib", "o", "a", "obj", "lib",
+    "class", "pyc", "wasm", "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "sqlite",
+    "sqlite3",
+];
+
+/// Whether the extension of `path` is one of [`BINARY_EXTENSIONS`].
+///
+/// ```
+/// use sourceweaver::has_binary_extension;
+/// use std::path::Path;
+///
+/// assert!(has_binary_extension(Path::new("assets/logo.png")));
+/// assert!(has_binary_extension(Path::new("fonts/Inter.WOFF2")));
+/// assert!(!has_binary_extension(Path::new("assets/logo.svg")));
+/// assert!(!has_binary_extension(Path::new("png")));
+/// ```
+pub fn has_binary_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            BINARY_EXTENSIONS
+                .iter()
+                .any(|binary| binary.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// Write the placeholder section for the file at `full_path`, whose
+/// extension names a binary format, without reading it. The file is still
+/// opened, so one that can't be read gets the usual error placeholder, and
+/// under grep, which binary files never match, it is left out.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_unread<W: Write + ?Sized>(
+    writer: &mut W,
+    relative_path: &Path,
+    full_path: &Path,
+    heading: &str,
+    notes: Option<&str>,
+    section: SectionOptions<'_>,
+    visitor: &mut dyn FileVisitor,
+) -> Result<FileOutcome> {
+    let size = match File::open(full_path).and_then(|file| file.metadata()) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            return write_section(
+                writer,
+                relative_path,
+                full_path,
+                heading,
+                notes,
+                Err(e),
+                section,
+                visitor,
+            );
+        }
+    };
+    if section.content_filter.is_some_and(|filter| filter.greps()) {
+        debug!("Skipping {}: no grep match", relative_path.display());
+        return Ok(FileOutcome::NoContentMatch);
+    }
+    debug!(
+        "Not reading {}: its extension names a binary format",
+        relative_path.display()
+    );
+    visitor.on_streamed(relative_path, size);
+
+    sentinels::write_begin(writer, section.sentinel)?;
+    writeln!(writer, "\n## {}\n", heading)?;
+    if let Some(notes) = notes {
+        writeln!(writer, "{}\n", notes)?;
+    }
+    if let Some(blame) = section.blame {
+        writeln!(writer, "{}\n", annotations::italic(blame))?;
+    }
+    writeln!(writer, "```\n(Binary file, content omitted)\n```")?;
+    sentinels::write_end(writer, section.sentinel)?;
+    Ok(FileOutcome::Binary)
+}
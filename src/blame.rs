@@ -0,0 +1,82 @@
+// src/blame.rs
+use crate::{Result, WalkedFile, WeaveError, last_commit};
+use content_inspector::ContentType;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{collections::HashMap, path::Path};
+use tracing::debug;
+
+// Authors named in a summary; the rest are counted
+const NAMED_AUTHORS: usize = 2;
+
+/// Who wrote the lines of each file, by `git blame`, for the note under
+/// its heading. Files matching one of the skip globs aren't blamed.
+#[derive(Debug, Clone)]
+pub(crate) struct Blame {
+    skip: Gitignore,
+}
+
+impl Blame {
+    pub(crate) fn new(root: &Path, skip: &[String]) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        for glob in skip {
+            builder.add_line(None, glob).map_err(|e| {
+                WeaveError::InvalidOptions(format!("invalid blame skip glob '{}': {}", glob, e))
+            })?;
+        }
+        let skip = builder
+            .build()
+            .map_err(|e| WeaveError::InvalidOptions(format!("invalid blame skip glob: {}", e)))?;
+        Ok(Blame { skip })
+    }
+
+    /// The summary for `file`, read as `content`, e.g. `authors: alice 62%,
+    /// bob 31%, 3 others`: the authors owning the most lines, most first and
+    /// ties by name, and how many others there are. None for binary, empty,
+    /// and skipped files, and for files git can't blame, such as untracked
+    /// ones.
+    pub(crate) fn summary(&self, file: &WalkedFile, content: &[u8]) -> Option<String> {
+        let skipped = file
+            .relative_path
+            .ancestors()
+            .filter(|level| !level.as_os_str().is_empty())
+            .enumerate()
+            .any(|(depth, level)| !self.skip.matched(level, depth > 0).is_none());
+        if skipped || content_inspector::inspect(content) == ContentType::BINARY {
+            return None;
+        }
+        let dir = file.path.parent()?;
+        let name = file.path.file_name()?.to_str()?;
+        let porcelain = match last_commit::git(dir, &["blame", "--line-porcelain", "--", name]) {
+            Ok(porcelain) => porcelain,
+            Err(e) => {
+                debug!("Not blaming {}: {}", file.relative_path.display(), e);
+                return None;
+            }
+        };
+
+        let mut lines: HashMap<String, u64> = HashMap::new();
+        for line in String::from_utf8_lossy(&porcelain).lines() {
+            if let Some(author) = line.strip_prefix("author ") {
+                *lines.entry(author.to_string()).or_default() += 1;
+            }
+        }
+        let total: u64 = lines.values().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut authors: Vec<(String, u64)> = lines.into_iter().collect();
+        authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut parts: Vec<String> = authors
+            .iter()
+            .take(NAMED_AUTHORS)
+            .map(|(author, count)| format!("{} {}%", author, (count * 100 + total / 2) / total))
+            .collect();
+        match authors.len().saturating_sub(NAMED_AUTHORS) {
+            0 => {}
+            1 => parts.push("1 other".to_string()),
+            others => parts.push(format!("{} others", others)),
+        }
+        Some(format!("authors: {}", parts.join(", ")))
+    }
+}
@@ -0,0 +1,199 @@
+// src/budget.rs
+use crate::{FileOutcome, Result, WeaveError, estimate_tokens};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+
+/// Weights deciding which files a token budget drops first, from globs
+/// matched in order; the last matching glob decides.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Priorities {
+    matchers: Vec<(Gitignore, i64)>,
+}
+
+impl Priorities {
+    pub(crate) fn new(root: &Path, priorities: &[(String, i64)]) -> Result<Self> {
+        let matchers = priorities
+            .iter()
+            .map(|(glob, weight)| {
+                let invalid = |e: ignore::Error| {
+                    WeaveError::InvalidOptions(format!("invalid priority glob '{}': {}", glob, e))
+                };
+                let mut builder = GitignoreBuilder::new(root);
+                builder.add_line(None, glob).map_err(invalid)?;
+                Ok((builder.build().map_err(invalid)?, *weight))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Priorities { matchers })
+    }
+
+    /// The weight of the file at `relative_path`: that of the last glob
+    /// matching it or a directory above it, or 0 when none does.
+    pub(crate) fn weight(&self, relative_path: &Path) -> i64 {
+        self.matchers
+            .iter()
+            .rev()
+            .find(|(matcher, _)| {
+                relative_path
+                    .ancestors()
+                    .filter(|level| !level.as_os_str().is_empty())
+                    .enumerate()
+                    .any(|(depth, level)| !matcher.matched(level, depth > 0).is_none())
+            })
+            .map_or(0, |(_, weight)| *weight)
+    }
+}
+
+/// A section rendered to memory while a budget is in force.
+pub(crate) enum Rendered {
+    /// A placeholder that is always written, such as a submodule note.
+    Fixed(Vec<u8>),
+    /// A file's section, which the budget may drop.
+    Section {
+        path: PathBuf,
+        bytes: Vec<u8>,
+        outcome: Result<FileOutcome>,
+    },
+}
+
+/// The indices of the sections in `rendered` to leave out so the document
+/// fits in `budget` tokens, each with its weight.
+///
+/// Sections with the lowest weight go first and, within a weight, the
+/// largest first, so as many files as possible are kept. Equal sizes drop
+/// the file later in path order first. Sections that weren't written, such
+/// as unreadable or skipped files, are never dropped.
+pub(crate) fn omissions(
+    rendered: &[Rendered],
+    budget: u64,
+    priorities: &Priorities,
+) -> Vec<(usize, i64)> {
+    let mut total: u64 = 0;
+    let mut candidates = Vec::new();
+    for (index, item) in rendered.iter().enumerate() {
+        match item {
+            Rendered::Fixed(bytes) => total += bytes.len() as u64,
+            Rendered::Section {
+                path,
+                bytes,
+                outcome,
+            } => {
+                total += bytes.len() as u64;
+                if matches!(
+                    outcome,
+                    Ok(FileOutcome::Written | FileOutcome::Binary | FileOutcome::Changed)
+                ) {
+                    candidates.push((index, path, bytes.len() as u64, priorities.weight(path)));
+                }
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        a.3.cmp(&b.3)
+            .then_with(|| b.2.cmp(&a.2))
+            .then_with(|| b.1.cmp(a.1))
+    });
+
+    let mut omitted = Vec::new();
+    for (index, _, bytes, weight) in candidates {
+        if estimate_tokens(total) <= budget {
+            break;
+        }
+        total -= bytes;
+        omitted.push((index, weight));
+    }
+    omitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(path: &str, bytes: usize) -> Rendered {
+        Rendered::Section {
+            path: PathBuf::from(path),
+            bytes: vec![b'x'; bytes],
+            outcome: Ok(FileOutcome::Written),
+        }
+    }
+
+    // The paths `omissions` drops from `rendered` under `budget` tokens
+    fn dropped(rendered: &[Rendered], budget: u64, priorities: &[(&str, i64)]) -> Vec<String> {
+        let priorities: Vec<(String, i64)> = priorities
+            .iter()
+            .map(|(glob, weight)| (glob.to_string(), *weight))
+            .collect();
+        let priorities = Priorities::new(Path::new("/project"), &priorities).unwrap();
+        omissions(rendered, budget, &priorities)
+            .into_iter()
+            .map(|(index, _)| match &rendered[index] {
+                Rendered::Section { path, .. } => path.display().to_string(),
+                Rendered::Fixed(_) => panic!("dropped a fixed section"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lowest_weight_goes_first() {
+        let rendered = [section("src/lib.rs", 800), section("notes.md", 400)];
+        let priorities = [("src/**", 100), ("*.md", 50)];
+        assert_eq!(dropped(&rendered, 250, &priorities), ["notes.md"]);
+        // Without weights the larger file goes
+        assert_eq!(dropped(&rendered, 250, &[]), ["src/lib.rs"]);
+    }
+
+    #[test]
+    fn largest_goes_first_within_a_weight() {
+        let rendered = [
+            section("a.rs", 400),
+            section("b.rs", 1200),
+            section("c.rs", 800),
+        ];
+        assert_eq!(dropped(&rendered, 500, &[]), ["b.rs"]);
+        assert_eq!(dropped(&rendered, 200, &[]), ["b.rs", "c.rs"]);
+        assert!(dropped(&rendered, 600, &[]).is_empty());
+    }
+
+    #[test]
+    fn equal_sizes_drop_the_later_path_whatever_the_order() {
+        let forward = [
+            section("a.rs", 400),
+            section("b.rs", 400),
+            section("c.rs", 400),
+        ];
+        let backward = [
+            section("c.rs", 400),
+            section("b.rs", 400),
+            section("a.rs", 400),
+        ];
+        assert_eq!(dropped(&forward, 200, &[]), ["c.rs"]);
+        assert_eq!(dropped(&backward, 200, &[]), ["c.rs"]);
+        assert_eq!(dropped(&backward, 100, &[]), ["c.rs", "b.rs"]);
+    }
+
+    #[test]
+    fn unwritten_sections_are_kept() {
+        let rendered = [
+            Rendered::Fixed(vec![b'x'; 400]),
+            Rendered::Section {
+                path: PathBuf::from("unreadable.rs"),
+                bytes: vec![b'x'; 2000],
+                outcome: Err(WeaveError::InvalidOptions("unreadable".to_string())),
+            },
+            section("small.rs", 40),
+        ];
+        // Over budget even then, but only the written section can go
+        assert_eq!(dropped(&rendered, 10, &[]), ["small.rs"]);
+    }
+
+    #[test]
+    fn last_matching_glob_sets_the_weight() {
+        let priorities = Priorities::new(
+            Path::new("/project"),
+            &[("src/**".to_string(), 100), ("src/gen/".to_string(), 5)],
+        )
+        .unwrap();
+        assert_eq!(priorities.weight(Path::new("src/lib.rs")), 100);
+        assert_eq!(priorities.weight(Path::new("src/gen/schema.rs")), 5);
+        assert_eq!(priorities.weight(Path::new("README.md")), 0);
+    }
+}
@@ -0,0 +1,455 @@
+// src/cache.rs
+use crate::{FileOutcome, WalkedFile};
+use serde_json::{Map, Value, json};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+// Bumped whenever the file layout changes; other versions are discarded
+const CACHE_FORMAT: u64 = 2;
+
+// A file modified this close to when it was read may have changed again
+// within the same timestamp tick, so its mtime isn't trusted
+const MTIME_SLACK: Duration = Duration::from_secs(2);
+
+/// Size and hash of a file's content, taken before the content is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Digest {
+    size: u64,
+    hash: u64,
+}
+
+impl Digest {
+    pub(crate) fn of(content: &[u8]) -> Self {
+        Digest {
+            size: content.len() as u64,
+            hash: hash(content),
+        }
+    }
+}
+
+/// A section rendered by an earlier run, with what it was rendered from.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    content: Digest,
+    mtime: u64,
+    read_at: u64,
+    heading: String,
+    notes: Option<String>,
+    binary: bool,
+    section: String,
+}
+
+impl Entry {
+    /// What the reused section counts as in the report.
+    pub(crate) fn outcome(&self) -> FileOutcome {
+        match self.binary {
+            true => FileOutcome::Binary,
+            false => FileOutcome::Written,
+        }
+    }
+
+    /// Size of the content the section was rendered from, in bytes.
+    pub(crate) fn size(&self) -> u64 {
+        self.content.size
+    }
+
+    /// The rendered section.
+    pub(crate) fn section(&self) -> &[u8] {
+        self.section.as_bytes()
+    }
+}
+
+/// The sections rendered by the previous run, keyed by path relative to the
+/// root, and the ones rendered by this run, which replace them on save.
+///
+/// A file is reused when its size and modification time are unchanged, or
+/// with `trust_mtime` off, when its content hashes the same. The heading and
+/// the notes under it (annotation and last commit) must match too, and the
+/// cache as a whole is discarded when it was written by another version or
+/// under another key.
+#[derive(Debug)]
+pub(crate) struct RenderCache {
+    path: PathBuf,
+    key: String,
+    trust_mtime: bool,
+    root: PathBuf,
+    previous: HashMap<PathBuf, Entry>,
+    // Filled in on the writing thread while readers look up `previous`
+    current: Mutex<HashMap<PathBuf, Entry>>,
+    // Whether any section was rendered afresh
+    changed: AtomicBool,
+}
+
+impl RenderCache {
+    /// Load the cache at `path` for the files below `root`. A missing,
+    /// unreadable, or corrupt cache is treated as empty, so every file is
+    /// rendered afresh.
+    pub(crate) fn load(path: &Path, key: &str, trust_mtime: bool, root: &Path) -> Self {
+        let mut cache = RenderCache {
+            path: path.to_path_buf(),
+            key: key.to_string(),
+            trust_mtime,
+            root: root.to_path_buf(),
+            previous: HashMap::new(),
+            current: Mutex::new(HashMap::new()),
+            changed: AtomicBool::new(false),
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return cache,
+            Err(e) => {
+                warn!("Ignoring cache {}: {}", path.display(), e);
+                return cache;
+            }
+        };
+        match parse(&content, key) {
+            Some(entries) => {
+                debug!(
+                    "Loaded {} cached section(s) from {}",
+                    entries.len(),
+                    path.display()
+                );
+                cache.previous = entries;
+            }
+            None => warn!(
+                "Ignoring cache {}: it is corrupt or was written with other settings",
+                path.display()
+            ),
+        }
+        cache
+    }
+
+    /// The section for `file` when its size and modification time show it
+    /// is unchanged, so it needn't even be read. Always `None` unless
+    /// modification times are trusted.
+    pub(crate) fn unchanged(
+        &self,
+        file: &WalkedFile,
+        heading: &str,
+        notes: Option<&str>,
+    ) -> Option<&Entry> {
+        if !self.trust_mtime {
+            return None;
+        }
+        let entry = self.entry(file, heading, notes)?;
+        let metadata = fs::metadata(&file.path).ok()?;
+        let settled = entry.mtime.saturating_add(MTIME_SLACK.as_nanos() as u64) < entry.read_at;
+        let unchanged = metadata.len() == entry.content.size
+            && metadata.modified().ok().and_then(nanos) == Some(entry.mtime);
+        (settled && unchanged).then_some(entry)
+    }
+
+    /// The section for `file` when its content, just read, is what the
+    /// cached section was rendered from.
+    pub(crate) fn same_content(
+        &self,
+        file: &WalkedFile,
+        heading: &str,
+        notes: Option<&str>,
+        content: Digest,
+    ) -> Option<&Entry> {
+        self.entry(file, heading, notes)
+            .filter(|entry| entry.content == content)
+    }
+
+    fn entry(&self, file: &WalkedFile, heading: &str, notes: Option<&str>) -> Option<&Entry> {
+        self.previous
+            .get(&file.relative_path)
+            .filter(|entry| entry.heading == heading && entry.notes.as_deref() == notes)
+    }
+
+    /// Keep `entry`, reused from the previous run, for the next one.
+    pub(crate) fn keep(&self, file: &WalkedFile, entry: Entry) {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(file.relative_path.clone(), entry);
+    }
+
+    /// Remember the section just rendered for `file` from `content`, which
+    /// was read after the file's modification time was taken as `mtime`.
+    /// Only complete sections are cached: not errors, skips, or files that
+    /// changed while being read.
+    pub(crate) fn store(
+        &self,
+        file: &WalkedFile,
+        heading: &str,
+        notes: Option<&str>,
+        (content, mtime): (Digest, u64),
+        outcome: &FileOutcome,
+        section: &[u8],
+    ) {
+        let binary = match outcome {
+            FileOutcome::Written => false,
+            FileOutcome::Binary => true,
+            FileOutcome::Changed
+            | FileOutcome::Skipped(_)
+            | FileOutcome::ExcludedContent { .. }
+            | FileOutcome::NoContentMatch => return,
+        };
+        let Ok(section) = std::str::from_utf8(section) else {
+            return;
+        };
+        self.changed.store(true, Ordering::Relaxed);
+        self.keep(
+            file,
+            Entry {
+                content,
+                mtime,
+                read_at: nanos(SystemTime::now()).unwrap_or(0),
+                heading: heading.to_string(),
+                notes: notes.map(str::to_string),
+                binary,
+                section: section.to_string(),
+            },
+        );
+    }
+
+    /// Write the sections of this run to the cache file, replacing it. The
+    /// previous run's sections for files this run didn't render are kept
+    /// while the files exist, so runs over parts of the tree, such as the
+    /// documents of `--split-by`, share one cache. A cache that is still
+    /// current isn't rewritten, and one that can't be written is only a
+    /// warning.
+    pub(crate) fn save(self) {
+        let mut current = self.current.into_inner().unwrap_or_else(|e| e.into_inner());
+        let previous_len = self.previous.len();
+        for (path, entry) in self.previous {
+            if !current.contains_key(&path) && self.root.join(&path).is_file() {
+                current.insert(path, entry);
+            }
+        }
+        if !self.changed.into_inner() && current.len() == previous_len {
+            debug!("Cache {} is up to date", self.path.display());
+            return;
+        }
+        let count = current.len();
+        let entries: Map<String, Value> = current
+            .iter()
+            .filter_map(|(path, entry)| {
+                let entry = json!({
+                    "size": entry.content.size,
+                    "mtime": entry.mtime,
+                    "read_at": entry.read_at,
+                    "hash": format!("{:016x}", entry.content.hash),
+                    "heading": entry.heading,
+                    "notes": entry.notes,
+                    "binary": entry.binary,
+                    "section": entry.section,
+                });
+                Some((path.to_str()?.to_string(), entry))
+            })
+            .collect();
+        let document = json!({
+            "format": CACHE_FORMAT,
+            "version": env!("CARGO_PKG_VERSION"),
+            "key": self.key,
+            "entries": entries,
+        });
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let result =
+            fs::write(&temp, document.to_string()).and_then(|()| fs::rename(&temp, &self.path));
+        match result {
+            Ok(()) => debug!("Saved {} section(s) to {}", count, self.path.display()),
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                warn!("Could not write cache {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+// The entries of a cache file, or None when it is corrupt or belongs to
+// another version or key
+fn parse(content: &str, key: &str) -> Option<HashMap<PathBuf, Entry>> {
+    let document: Value = serde_json::from_str(content).ok()?;
+    if document["format"].as_u64()? != CACHE_FORMAT
+        || document["version"].as_str()? != env!("CARGO_PKG_VERSION")
+        || document["key"].as_str()? != key
+    {
+        return None;
+    }
+    let entries = document["entries"]
+        .as_object()?
+        .iter()
+        .map(|(path, entry)| {
+            let notes = match &entry["notes"] {
+                Value::Null => None,
+                value => Some(value.as_str()?.to_string()),
+            };
+            let entry = Entry {
+                content: Digest {
+                    size: entry["size"].as_u64()?,
+                    hash: u64::from_str_radix(entry["hash"].as_str()?, 16).ok()?,
+                },
+                mtime: entry["mtime"].as_u64()?,
+                read_at: entry["read_at"].as_u64()?,
+                heading: entry["heading"].as_str()?.to_string(),
+                notes,
+                binary: entry["binary"].as_bool()?,
+                section: entry["section"].as_str()?.to_string(),
+            };
+            Some((PathBuf::from(path), entry))
+        })
+        .collect::<Option<HashMap<_, _>>>()?;
+    Some(entries)
+}
+
+/// The modification time of the file at `path`, to be taken before it is
+/// read, so a change during the read is caught on the next run.
+pub(crate) fn modified(path: &Path) -> Option<u64> {
+    nanos(fs::metadata(path).ok()?.modified().ok()?)
+}
+
+// Nanoseconds since the epoch
+fn nanos(time: SystemTime) -> Option<u64> {
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64)
+}
+
+// 64-bit FNV-1a, stable across platforms and Rust releases
+fn hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Action, FileContent, FileVisitor, WeaveOptions, generate_markdown_with};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    // Counts the files rendered afresh and the ones reused from the cache
+    #[derive(Default)]
+    struct Counting {
+        rendered: Vec<PathBuf>,
+        reused: Vec<PathBuf>,
+    }
+
+    impl FileVisitor for Counting {
+        fn on_file(&mut self, path: &Path, _content: FileContent<'_>) -> Action {
+            self.rendered.push(path.to_path_buf());
+            Action::Emit
+        }
+
+        fn on_reused(&mut self, path: &Path, _size: u64) {
+            self.reused.push(path.to_path_buf());
+        }
+    }
+
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "sourceweaver-cache-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+            fs::write(dir.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n").unwrap();
+            Fixture { dir }
+        }
+
+        fn cache(&self) -> PathBuf {
+            self.dir.join("cache.json")
+        }
+
+        fn weave(&self, key: &str) -> (String, Counting) {
+            let options = WeaveOptions::new(self.dir.join("src"))
+                .cache(self.cache(), key)
+                .build()
+                .unwrap();
+            let mut visitor = Counting::default();
+            let mut document = Vec::new();
+            generate_markdown_with(&mut document, &options, &mut visitor).unwrap();
+            (String::from_utf8(document).unwrap(), visitor)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn unchanged_files_are_reused() {
+        let fixture = Fixture::new("reuse");
+        let (first, visitor) = fixture.weave("");
+        assert_eq!(visitor.rendered.len(), 2);
+        assert!(visitor.reused.is_empty());
+
+        let (second, visitor) = fixture.weave("");
+        assert_eq!(second, first);
+        assert!(visitor.rendered.is_empty(), "{:?}", visitor.rendered);
+        assert_eq!(visitor.reused.len(), 2);
+    }
+
+    #[test]
+    fn corrupt_cache_is_rendered_afresh_and_rewritten() {
+        let fixture = Fixture::new("corrupt");
+        let (first, _) = fixture.weave("");
+
+        fs::write(fixture.cache(), "{\"format\": 2, \"entries\": [garbage").unwrap();
+        let (second, visitor) = fixture.weave("");
+        assert_eq!(second, first);
+        assert_eq!(visitor.rendered.len(), 2);
+        assert!(visitor.reused.is_empty());
+
+        let rewritten = fs::read_to_string(fixture.cache()).unwrap();
+        assert_eq!(super::parse(&rewritten, "").map(|e| e.len()), Some(2));
+    }
+
+    #[test]
+    fn edited_file_section_is_updated() {
+        let fixture = Fixture::new("edit");
+        let (first, _) = fixture.weave("");
+        assert!(first.contains("{ 42 }"));
+
+        // Same size, so only the content tells the edit apart
+        fs::write(
+            fixture.dir.join("src/lib.rs"),
+            "pub fn answer() -> u32 { 43 }\n",
+        )
+        .unwrap();
+        let (second, visitor) = fixture.weave("");
+        assert!(second.contains("{ 43 }") && !second.contains("{ 42 }"));
+        assert_eq!(visitor.rendered, [PathBuf::from("lib.rs")]);
+        assert_eq!(visitor.reused, [PathBuf::from("main.rs")]);
+    }
+
+    #[test]
+    fn other_key_discards_the_cache() {
+        let fixture = Fixture::new("key");
+        let (first, _) = fixture.weave("one");
+
+        let (second, visitor) = fixture.weave("two");
+        assert_eq!(second, first);
+        assert_eq!(visitor.rendered.len(), 2);
+        assert!(visitor.reused.is_empty());
+
+        // The cache now belongs to the new key
+        let saved = fs::read_to_string(fixture.cache()).unwrap();
+        assert!(super::parse(&saved, "one").is_none());
+        assert!(super::parse(&saved, "two").is_some());
+    }
+}
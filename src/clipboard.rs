@@ -0,0 +1,695 @@
+// src/clipboard.rs
+use crate::html;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use clap::ValueEnum;
+use indicatif::HumanBytes;
+use sourceweaver::WeaveError;
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+    thread,
+};
+use tracing::{info, warn};
+
+/// Default for `--clipboard-limit`: larger payloads are known to be truncated
+/// or dropped by some X11 and Windows clipboard implementations.
+pub const DEFAULT_CLIPBOARD_LIMIT: u64 = 10 << 20;
+
+// Many terminals silently drop OSC 52 payloads beyond roughly this size
+const OSC52_SAFE_BASE64_LEN: usize = 100_000;
+
+// Times the system clipboard is set on Linux before giving up on a copy that
+// doesn't read back
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const SET_ATTEMPTS: u32 = 3;
+
+// Bytes compared at each end of the document when verifying a copy, so
+// multi-megabyte payloads cost no more than a short comparison
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const VERIFY_EDGE_LEN: usize = 4096;
+
+// GNU screen truncates DCS strings, so passthrough is split into pieces
+const SCREEN_CHUNK_LEN: usize = 768;
+
+const OSC52_CAVEATS: &str = "OSC 52 works in iTerm2, kitty, WezTerm, Alacritty, foot, \
+Windows Terminal, and tmux (with `set -g set-clipboard on`); xterm needs \
+`allowWindowOps`, and some terminals ignore it or cap the payload size";
+
+/// Which mechanism `--clipboard` uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackendKind {
+    /// The system clipboard, falling back to a clipboard program on `PATH`,
+    /// then to OSC 52 when a terminal is attached (e.g. over SSH).
+    Auto,
+    /// The system clipboard (X11, Wayland, macOS, Windows).
+    System,
+    /// The OSC 52 terminal escape sequence, written to the controlling terminal.
+    Osc52,
+    /// Pipe the document into an external program such as `wl-copy`.
+    Command,
+}
+
+/// Which selection `--clipboard` sets on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Selection {
+    /// The regular clipboard, pasted with Ctrl+V.
+    Clipboard,
+    /// The X11/Wayland primary selection, pasted with middle-click.
+    Primary,
+    /// Both, with the same content.
+    Both,
+}
+
+#[cfg(target_os = "linux")]
+impl Selection {
+    fn kinds(self) -> &'static [arboard::LinuxClipboardKind] {
+        use arboard::LinuxClipboardKind::{Clipboard, Primary};
+        match self {
+            Selection::Clipboard => &[Clipboard],
+            Selection::Primary => &[Primary],
+            Selection::Both => &[Clipboard, Primary],
+        }
+    }
+}
+
+// Programs tried, in order, when no --clipboard-command is given
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+];
+
+/// Open the clipboard backend selected by `kind`. `command` is the
+/// user-supplied `--clipboard-command`, if any; `selection` and `wait` only
+/// apply to the system clipboard on Linux.
+pub fn open_backend(
+    kind: BackendKind,
+    command: Option<&str>,
+    selection: Selection,
+    wait: bool,
+) -> Result<Box<dyn ClipboardBackend>, WeaveError> {
+    if wait && !cfg!(target_os = "linux") {
+        info!("--clipboard-wait is only needed on Linux; the clipboard keeps the copy here");
+    } else if wait && kind != BackendKind::System && kind != BackendKind::Auto {
+        warn!("--clipboard-wait only applies to the system clipboard; ignoring it");
+    }
+    if selection != Selection::Clipboard && !cfg!(target_os = "linux") {
+        warn!("--selection only applies on Linux; ignoring it");
+    } else if selection != Selection::Clipboard
+        && kind != BackendKind::System
+        && kind != BackendKind::Auto
+    {
+        warn!("--selection only applies to the system clipboard; ignoring it");
+    }
+    match kind {
+        BackendKind::System => Ok(Box::new(SystemClipboard::new(selection, wait)?)),
+        BackendKind::Osc52 => Ok(Box::new(Osc52Clipboard::new()?)),
+        BackendKind::Command => Ok(Box::new(CommandClipboard::new(command)?)),
+        BackendKind::Auto => {
+            let system_err = match SystemClipboard::new(selection, wait) {
+                Ok(clipboard) => return Ok(Box::new(clipboard)),
+                Err(e) => e,
+            };
+            if wait {
+                warn!("--clipboard-wait only applies to the system clipboard; ignoring it");
+            }
+            if let Ok(clipboard) = CommandClipboard::new(command) {
+                warn!(
+                    "{}; falling back to `{}`",
+                    system_err,
+                    clipboard.program.join(" ")
+                );
+                return Ok(Box::new(clipboard));
+            }
+            if io::stderr().is_terminal() || io::stdout().is_terminal() {
+                warn!(
+                    "{}; falling back to the OSC 52 terminal clipboard",
+                    system_err
+                );
+                return Ok(Box::new(Osc52Clipboard::new()?));
+            }
+            Err(system_err)
+        }
+    }
+}
+
+/// Something that can receive the generated document as text.
+pub trait ClipboardBackend {
+    fn set_text(&mut self, text: String) -> Result<(), WeaveError>;
+
+    /// Set `html` as rich text with `alt_text` as the plain-text alternative.
+    /// Backends without rich-text support copy `alt_text` instead.
+    fn set_html(&mut self, html: String, alt_text: String) -> Result<(), WeaveError> {
+        let _ = html;
+        warn!("This clipboard backend can't hold HTML; copying plain text instead");
+        self.set_text(alt_text)
+    }
+}
+
+/// The system clipboard, via arboard.
+pub struct SystemClipboard {
+    clipboard: arboard::Clipboard,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    selection: Selection,
+    // Keep serving the copy until another program takes the clipboard over
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    wait: bool,
+}
+
+impl SystemClipboard {
+    pub fn new(selection: Selection, wait: bool) -> Result<Self, WeaveError> {
+        arboard::Clipboard::new()
+            .map(|clipboard| SystemClipboard {
+                clipboard,
+                selection,
+                wait,
+            })
+            .map_err(|e| WeaveError::Clipboard(format!("failed to initialize: {}", e)))
+    }
+
+    // Apply `set` to every selected clipboard
+    fn each_selection<F>(&mut self, mut set: F) -> Result<(), arboard::Error>
+    where
+        F: FnMut(arboard::Set<'_>) -> Result<(), arboard::Error>,
+    {
+        #[cfg(target_os = "linux")]
+        {
+            use arboard::SetExtLinux;
+            for &kind in self.selection.kinds() {
+                set(self.clipboard.set().clipboard(kind))?;
+            }
+            Ok(())
+        }
+        #[cfg(not(target_os = "linux"))]
+        set(self.clipboard.set())
+    }
+
+    // Whether every selected clipboard reads back as `text`
+    #[cfg(target_os = "linux")]
+    fn holds(&mut self, text: &str) -> bool {
+        use arboard::GetExtLinux;
+        self.selection.kinds().iter().all(|&kind| {
+            match self.clipboard.get().clipboard(kind).text() {
+                Ok(current) => same_content(&current, text),
+                Err(e) => {
+                    tracing::debug!("Could not read the clipboard back: {}", e);
+                    false
+                }
+            }
+        })
+    }
+
+    // Block until another program owns the clipboard. On X11 the content
+    // lives in this process, so without a clipboard manager it would vanish
+    // when we exit. The other selected clipboards are served meanwhile.
+    #[cfg(target_os = "linux")]
+    fn wait_for_owner(&mut self, text: &str) -> Result<(), arboard::Error> {
+        use arboard::SetExtLinux;
+        info!(
+            "Waiting until something else is copied, so the clipboard keeps the document; \
+             press Ctrl+C to stop"
+        );
+        let kind = self.selection.kinds()[0];
+        self.clipboard.set().clipboard(kind).wait().text(text)
+    }
+}
+
+// Cheap equality check: same length and same bytes at both ends
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn same_content(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let edge = VERIFY_EDGE_LEN.min(a.len());
+    a.len() == b.len() && a[..edge] == b[..edge] && a[a.len() - edge..] == b[b.len() - edge..]
+}
+
+// Bytes the clipboard needs for `text`. Windows stores text as UTF-16, so a
+// mostly-ASCII document takes twice its UTF-8 size there.
+fn clipboard_size(text: &str) -> u64 {
+    match cfg!(windows) {
+        true => (text.encode_utf16().count() as u64 + 1) * 2,
+        false => text.len() as u64,
+    }
+}
+
+// Refuse a copy Windows can't hold before touching the clipboard: arboard
+// builds the UTF-16 copy, and the clipboard then copies it again into
+// global memory
+#[cfg(windows)]
+fn check_memory(text: &str) -> Result<(), WeaveError> {
+    let needed = clipboard_size(text) * 2;
+    match available_memory() {
+        Some(available) if needed > available => Err(WeaveError::Clipboard(format!(
+            "copying this document needs about {} of memory, but only {} is available. \
+             Write it to a file with --output instead",
+            HumanBytes(needed),
+            HumanBytes(available)
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// Physical memory currently available, if Windows reports it
+#[cfg(windows)]
+fn available_memory() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+    // SAFETY: MEMORYSTATUSEX is plain data, valid when zeroed, and dwLength
+    // is set to its size as GlobalMemoryStatusEx requires
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    (unsafe { GlobalMemoryStatusEx(&mut status) } != 0).then_some(status.ullAvailPhys)
+}
+
+// The error for a failed copy. On Windows a large copy can fail after the
+// clipboard was emptied or only some formats were set, so it is cleared to
+// leave no partial content behind.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn copy_failed(clipboard: &mut arboard::Clipboard, text: &str, e: arboard::Error) -> WeaveError {
+    #[cfg(windows)]
+    {
+        let _ = clipboard.clear();
+        WeaveError::Clipboard(format!(
+            "failed to copy {} to the Windows clipboard: {}. Large documents are better \
+             written to a file with --output",
+            HumanBytes(clipboard_size(text)),
+            e
+        ))
+    }
+    #[cfg(not(windows))]
+    WeaveError::Clipboard(format!("failed to copy: {}", e))
+}
+
+impl ClipboardBackend for SystemClipboard {
+    /// On Linux the copy is read back and retried when the clipboard doesn't
+    /// hold it, which happens on X11 when the selection is lost without a
+    /// clipboard manager to take it over.
+    fn set_text(&mut self, text: String) -> Result<(), WeaveError> {
+        #[cfg(windows)]
+        check_memory(&text)?;
+        let set = |clipboard: &mut Self| {
+            clipboard
+                .each_selection(|set| set.text(text.as_str()))
+                .map_err(|e| copy_failed(&mut clipboard.clipboard, &text, e))
+        };
+        #[cfg(target_os = "linux")]
+        {
+            for attempt in 1..=SET_ATTEMPTS {
+                set(self)?;
+                if self.holds(&text) {
+                    return match self.wait {
+                        true => self
+                            .wait_for_owner(&text)
+                            .map_err(|e| WeaveError::Clipboard(format!("failed to copy: {}", e))),
+                        false => Ok(()),
+                    };
+                }
+                tracing::debug!(
+                    "Clipboard doesn't hold the document after attempt {} of {}",
+                    attempt,
+                    SET_ATTEMPTS
+                );
+            }
+            Err(WeaveError::Clipboard(
+                "the clipboard was set but doesn't hold the document; no clipboard manager \
+                 seems to be running to keep it. Try --clipboard-wait, start a clipboard \
+                 manager, or use --output instead"
+                    .to_string(),
+            ))
+        }
+        #[cfg(not(target_os = "linux"))]
+        set(self)
+    }
+
+    fn set_html(&mut self, html: String, alt_text: String) -> Result<(), WeaveError> {
+        #[cfg(windows)]
+        check_memory(&html).and_then(|_| check_memory(&alt_text))?;
+        match self.each_selection(|set| set.html(html.as_str(), Some(alt_text.as_str()))) {
+            Ok(()) => Ok(()),
+            Err(
+                e @ (arboard::Error::ClipboardNotSupported | arboard::Error::ConversionFailure),
+            ) => {
+                warn!(
+                    "HTML clipboard unavailable ({}); copying plain text instead",
+                    e
+                );
+                self.set_text(alt_text)
+            }
+            Err(e) => Err(copy_failed(&mut self.clipboard, &alt_text, e)),
+        }
+    }
+}
+
+/// The terminal's clipboard, set with the OSC 52 escape sequence. The
+/// terminal emulator on the user's machine does the copying, so this works
+/// over SSH where no display server is reachable.
+pub struct Osc52Clipboard {
+    tty: Box<dyn Write>,
+}
+
+impl Osc52Clipboard {
+    pub fn new() -> Result<Self, WeaveError> {
+        Ok(Osc52Clipboard {
+            tty: open_terminal()?,
+        })
+    }
+}
+
+// Prefer the controlling terminal so stdout and stderr can stay redirected
+fn open_terminal() -> Result<Box<dyn Write>, WeaveError> {
+    let tty_path = if cfg!(windows) { "CONOUT$" } else { "/dev/tty" };
+    if let Ok(tty) = OpenOptions::new().write(true).open(tty_path) {
+        return Ok(Box::new(tty));
+    }
+    if io::stderr().is_terminal() {
+        return Ok(Box::new(io::stderr()));
+    }
+    Err(WeaveError::Clipboard(format!(
+        "OSC 52 needs a terminal, but none is attached. {}",
+        OSC52_CAVEATS
+    )))
+}
+
+impl ClipboardBackend for Osc52Clipboard {
+    fn set_text(&mut self, text: String) -> Result<(), WeaveError> {
+        let payload = BASE64.encode(text);
+        if payload.len() > OSC52_SAFE_BASE64_LEN {
+            warn!(
+                "OSC 52 payload is {}; some terminals silently drop payloads this large. {}",
+                HumanBytes(payload.len() as u64),
+                OSC52_CAVEATS
+            );
+        }
+        let sequence = osc52_sequence(&payload, env::var_os("TMUX").is_some(), in_screen());
+        self.tty
+            .write_all(sequence.as_bytes())
+            .and_then(|_| self.tty.flush())
+            .map_err(|e| WeaveError::Clipboard(format!("failed to write to terminal: {}", e)))
+    }
+}
+
+fn in_screen() -> bool {
+    env::var("TERM").is_ok_and(|term| term.starts_with("screen")) && env::var_os("STY").is_some()
+}
+
+// Build the escape sequence, wrapped for terminal multiplexers that would
+// otherwise swallow it
+fn osc52_sequence(payload: &str, tmux: bool, screen: bool) -> String {
+    let osc = format!("\x1b]52;c;{}\x07", payload);
+    if tmux {
+        // tmux passthrough: escape inner ESCs by doubling them
+        format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+    } else if screen {
+        osc.as_bytes()
+            .chunks(SCREEN_CHUNK_LEN)
+            .map(|chunk| format!("\x1bP{}\x1b\\", String::from_utf8_lossy(chunk)))
+            .collect()
+    } else {
+        osc
+    }
+}
+
+/// An external program that reads the document on stdin, e.g. `wl-copy`,
+/// `xclip -selection clipboard`, `pbcopy`, or anything else.
+pub struct CommandClipboard {
+    program: Vec<String>,
+}
+
+impl CommandClipboard {
+    /// Use `command` (split with shell quoting rules), or the first of the
+    /// known clipboard programs found on `PATH`.
+    pub fn new(command: Option<&str>) -> Result<Self, WeaveError> {
+        if let Some(command) = command {
+            let program = shlex::split(command)
+                .filter(|words| !words.is_empty())
+                .ok_or_else(|| {
+                    WeaveError::InvalidOptions(format!("invalid clipboard command: {}", command))
+                })?;
+            return Ok(CommandClipboard { program });
+        }
+        CLIPBOARD_COMMANDS
+            .iter()
+            .find(|candidate| find_in_path(candidate[0]))
+            .map(|candidate| CommandClipboard {
+                program: candidate.iter().map(|s| s.to_string()).collect(),
+            })
+            .ok_or_else(|| {
+                WeaveError::Clipboard(
+                    "no clipboard program found on PATH (tried wl-copy, xclip, xsel, pbcopy); \
+                     pass one with --clipboard-command"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+fn find_in_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| {
+            let candidate = dir.join(program);
+            candidate.is_file() || candidate.with_extension("exe").is_file()
+        })
+    })
+}
+
+impl ClipboardBackend for CommandClipboard {
+    fn set_text(&mut self, text: String) -> Result<(), WeaveError> {
+        let display = self.program.join(" ");
+        let mut child = Command::new(&self.program[0])
+            .args(&self.program[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| WeaveError::Clipboard(format!("failed to run `{}`: {}", display, e)))?;
+
+        // Write from a separate thread so a child that fills its stderr pipe
+        // before draining stdin can't deadlock us
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = thread::spawn(move || stdin.write_all(text.as_bytes()));
+        let output = child
+            .wait_with_output()
+            .map_err(|e| WeaveError::Clipboard(format!("failed to run `{}`: {}", display, e)))?;
+        let write_result = writer.join().expect("stdin writer thread panicked");
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WeaveError::Clipboard(format!(
+                "`{}` exited with {}{}",
+                display,
+                output.status,
+                if stderr.trim().is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", stderr.trim())
+                }
+            )));
+        }
+        write_result
+            .map_err(|e| WeaveError::Clipboard(format!("failed to write to `{}`: {}", display, e)))
+    }
+}
+
+/// Size limit applied before anything is handed to the clipboard.
+#[derive(Debug, Clone, Copy)]
+pub struct ClipboardLimit {
+    pub max_bytes: u64,
+    /// Copy even when the document exceeds `max_bytes`.
+    pub force: bool,
+}
+
+/// Copy `text` to the backend returned by `open`, refusing documents over the
+/// limit unless forced. The limit is checked before the backend is opened,
+/// and on Windows applies to the UTF-16 size the clipboard stores, about
+/// twice the document's size. With `as_html`, the document is rendered to HTML and copied as rich text,
+/// with `text` as the plain-text alternative.
+pub fn copy_text<F>(
+    text: String,
+    as_html: bool,
+    limit: ClipboardLimit,
+    open: F,
+) -> Result<(), WeaveError>
+where
+    F: FnOnce() -> Result<Box<dyn ClipboardBackend>, WeaveError>,
+{
+    let size = text.len() as u64;
+    check_limit(clipboard_size(&text), limit)?;
+    let mut backend = open()?;
+    if as_html {
+        backend.set_html(html::render(&text), text)?;
+    } else {
+        backend.set_text(text)?;
+    }
+    info!("Output copied to clipboard ({}).", HumanBytes(size));
+    Ok(())
+}
+
+fn check_limit(size: u64, limit: ClipboardLimit) -> Result<(), WeaveError> {
+    if size <= limit.max_bytes || limit.force {
+        return Ok(());
+    }
+    Err(WeaveError::Clipboard(format!(
+        "output is {}, over the clipboard limit of {}; large payloads are often truncated \
+         by the clipboard. Write it to a file with --output instead, raise --clipboard-limit, \
+         or pass --clipboard-force",
+        HumanBytes(size),
+        HumanBytes(limit.max_bytes)
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[derive(Debug, PartialEq)]
+    enum Copied {
+        Text(String),
+        Html { html: String, alt_text: String },
+    }
+
+    // Records what it is given
+    struct MockClipboard {
+        copied: Rc<RefCell<Vec<Copied>>>,
+    }
+
+    impl ClipboardBackend for MockClipboard {
+        fn set_text(&mut self, text: String) -> Result<(), WeaveError> {
+            self.copied.borrow_mut().push(Copied::Text(text));
+            Ok(())
+        }
+
+        fn set_html(&mut self, html: String, alt_text: String) -> Result<(), WeaveError> {
+            self.copied
+                .borrow_mut()
+                .push(Copied::Html { html, alt_text });
+            Ok(())
+        }
+    }
+
+    // Plain text only, keeping the default `set_html`
+    struct TextOnlyClipboard(MockClipboard);
+
+    impl ClipboardBackend for TextOnlyClipboard {
+        fn set_text(&mut self, text: String) -> Result<(), WeaveError> {
+            self.0.set_text(text)
+        }
+    }
+
+    const LIMIT: ClipboardLimit = ClipboardLimit {
+        max_bytes: 64,
+        force: false,
+    };
+
+    // Copy `text` to a mock, with rich text support when `html` is set,
+    // returning the result, whether the backend was opened, and what it
+    // received
+    fn copy(
+        text: &str,
+        as_html: bool,
+        limit: ClipboardLimit,
+        html: bool,
+    ) -> (Result<(), WeaveError>, bool, Vec<Copied>) {
+        let copied = Rc::new(RefCell::new(Vec::new()));
+        let opened = RefCell::new(false);
+        let result = copy_text(text.to_string(), as_html, limit, || {
+            *opened.borrow_mut() = true;
+            let mock = MockClipboard {
+                copied: Rc::clone(&copied),
+            };
+            Ok(match html {
+                true => Box::new(mock),
+                false => Box::new(TextOnlyClipboard(mock)),
+            })
+        });
+        (result, opened.into_inner(), copied.take())
+    }
+
+    #[test]
+    fn text_is_copied_as_is() {
+        let (result, opened, copied) = copy("# Project\n", false, LIMIT, true);
+        assert!(result.is_ok() && opened);
+        assert_eq!(copied, [Copied::Text("# Project\n".to_string())]);
+    }
+
+    #[test]
+    fn html_carries_the_text_as_alternative() {
+        let (result, _, copied) = copy("# Project\n", true, LIMIT, true);
+        assert!(result.is_ok());
+        match &copied[..] {
+            [Copied::Html { html, alt_text }] => {
+                assert_eq!(html, &html::render("# Project\n"));
+                assert_eq!(alt_text, "# Project\n");
+            }
+            other => panic!("unexpected copies: {:?}", other),
+        }
+
+        // A backend without rich text gets the plain text
+        let (result, _, copied) = copy("# Project\n", true, LIMIT, false);
+        assert!(result.is_ok());
+        assert_eq!(copied, [Copied::Text("# Project\n".to_string())]);
+    }
+
+    #[test]
+    fn over_the_limit_is_refused_before_opening() {
+        let text = "x".repeat(LIMIT.max_bytes as usize + 1);
+        let (result, opened, copied) = copy(&text, false, LIMIT, true);
+        assert!(matches!(result, Err(WeaveError::Clipboard(_))));
+        assert!(!opened);
+        assert!(copied.is_empty());
+
+        let forced = ClipboardLimit {
+            force: true,
+            ..LIMIT
+        };
+        let (result, _, copied) = copy(&text, false, forced, true);
+        assert!(result.is_ok());
+        assert_eq!(copied, [Copied::Text(text)]);
+    }
+
+    #[test]
+    fn open_errors_are_returned() {
+        let result = copy_text("text".to_string(), false, LIMIT, || {
+            Err(WeaveError::Clipboard("no display".to_string()))
+        });
+        assert!(matches!(result, Err(WeaveError::Clipboard(e)) if e == "no display"));
+    }
+
+    #[test]
+    fn osc52_sequence_is_wrapped_for_multiplexers() {
+        assert_eq!(osc52_sequence("aGk=", false, false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            osc52_sequence("aGk=", true, false),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+        let payload = "A".repeat(SCREEN_CHUNK_LEN * 2);
+        let screen = osc52_sequence(&payload, false, true);
+        assert_eq!(screen.matches("\x1bP").count(), 3);
+        assert!(screen.starts_with("\x1bP\x1b]52;c;"));
+    }
+
+    #[test]
+    fn same_content_compares_length_and_edges() {
+        let text = "a".repeat(VERIFY_EDGE_LEN * 3);
+        assert!(same_content(&text, &text.clone()));
+        assert!(!same_content(&text, &text[1..]));
+        assert!(!same_content(&text, &format!("b{}", &text[1..])));
+        assert!(!same_content(&text, &format!("{}b", &text[1..])));
+        assert!(same_content("", ""));
+    }
+
+    #[test]
+    fn clipboard_command_is_split_like_a_shell() {
+        let clipboard = CommandClipboard::new(Some("xclip -selection 'clip board'")).unwrap();
+        assert_eq!(clipboard.program, ["xclip", "-selection", "clip board"]);
+        assert!(matches!(
+            CommandClipboard::new(Some("")),
+            Err(WeaveError::InvalidOptions(_))
+        ));
+        assert!(matches!(
+            CommandClipboard::new(Some("'unterminated")),
+            Err(WeaveError::InvalidOptions(_))
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+// src/codeowners.rs
+use crate::{Result, WeaveError, display};
+use regex::Regex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, warn};
+
+/// Where GitHub looks for the CODEOWNERS file, relative to the repository
+/// root, in the order it looks.
+pub const CODEOWNERS_LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// The rules of a CODEOWNERS file, in GitHub's syntax: a path pattern
+/// followed by its owners on each line, and the last matching line decides.
+///
+/// Patterns mostly follow gitignore, with GitHub's differences:
+///
+/// - A pattern with a `/` at the start or in the middle is anchored at the
+///   repository root; any other pattern matches at any depth.
+/// - A pattern matching a directory owns everything below it, and a
+///   trailing `/` matches only directories.
+/// - `*` stays within one path component, so `docs/*` owns the files
+///   directly in `docs` but not those in its subdirectories; `**` spans any
+///   number of them.
+/// - `!` negation isn't supported, and `[` `]` are taken literally.
+///
+/// A pattern without owners leaves matching files unowned.
+///
+/// ```
+/// use sourceweaver::CodeOwners;
+///
+/// let codeowners = CodeOwners::parse(
+///     "# Everything else
+/// *               @org/core
+/// *.js            @js-owner  # inline comment
+/// /build/logs/    @doctocat
+/// docs/*          docs@example.com
+/// apps/           @octocat
+/// /scripts/**/ci  @org/ci
+/// /vendor/
+/// ",
+/// );
+/// let owners = |path| codeowners.owners(path).join(" ");
+///
+/// assert_eq!(owners("src/main.rs"), "@org/core");
+/// // Unanchored patterns match at any depth; the last match wins
+/// assert_eq!(owners("web/app.js"), "@js-owner");
+/// // A leading or middle `/` anchors at the root
+/// assert_eq!(owners("build/logs/today.txt"), "@doctocat");
+/// assert_eq!(owners("x/build/logs/today.txt"), "@org/core");
+/// // `*` stays within one directory
+/// assert_eq!(owners("docs/guide.md"), "docs@example.com");
+/// assert_eq!(owners("docs/api/guide.md"), "@org/core");
+/// // A trailing `/` owns the directory's contents, at any depth
+/// assert_eq!(owners("apps/web/index.html"), "@octocat");
+/// assert_eq!(owners("src/apps/lib.rs"), "@octocat");
+/// assert_eq!(owners("apps"), "@org/core");
+/// // `**` spans directories, including none
+/// assert_eq!(owners("scripts/ci/run.sh"), "@org/ci");
+/// assert_eq!(owners("scripts/a/b/ci/run.sh"), "@org/ci");
+/// // No owners: unowned
+/// assert_eq!(owners("vendor/lib.c"), "");
+///
+/// // Owners are compared ignoring case, with or without the `@`
+/// assert!(codeowners.is_owned_by("src/main.rs", &["@Org/Core"]));
+/// assert!(codeowners.is_owned_by("web/app.js", &["org/core", "js-owner"]));
+/// assert!(!codeowners.is_owned_by("vendor/lib.c", &["@org/core"]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<(Regex, Vec<String>)>,
+}
+
+impl CodeOwners {
+    /// Parse the content of a CODEOWNERS file. Lines that can't be used,
+    /// such as negated patterns, are skipped with a warning.
+    pub fn parse(text: &str) -> Self {
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let mut tokens = tokens(line).into_iter();
+            let Some(pattern) = tokens.next() else {
+                continue;
+            };
+            // GitLab's section headers, e.g. `[Documentation]` or `^[Docs][2]`
+            let section = pattern.trim_start_matches('^');
+            if section.starts_with('[') && line.trim_end().ends_with(']') {
+                debug!("Ignoring CODEOWNERS section header on line {}", number + 1);
+                continue;
+            }
+            if pattern.starts_with('!') {
+                warn!(
+                    "Ignoring line {} of CODEOWNERS: negated patterns aren't supported",
+                    number + 1
+                );
+                continue;
+            }
+            rules.push((pattern_regex(&pattern), tokens.collect()));
+        }
+        CodeOwners { rules }
+    }
+
+    /// The owners of the file at `path`, relative to the repository root
+    /// with `/` separators, from the last rule matching it. Empty when no
+    /// rule matches or the last one names no owners.
+    pub fn owners(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(regex, _)| regex.is_match(path))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Whether any of `owners` owns the file at `path`. Owners are compared
+    /// ignoring ASCII case, and a leading `@` is optional.
+    pub fn is_owned_by<S: AsRef<str>>(&self, path: &str, owners: &[S]) -> bool {
+        let normalize = |owner: &str| owner.trim_start_matches('@').to_ascii_lowercase();
+        self.owners(path).iter().any(|owner| {
+            let owner = normalize(owner);
+            owners
+                .iter()
+                .any(|wanted| normalize(wanted.as_ref()) == owner)
+        })
+    }
+}
+
+// The whitespace-separated tokens of a line, up to a comment, with
+// backslash escapes (such as `\ ` and `\#`) resolved
+fn tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            '#' if current.is_empty() => break,
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+// The regex matching the paths a CODEOWNERS pattern owns
+fn pattern_regex(pattern: &str) -> Regex {
+    let dir_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.contains('/');
+    let glob = trimmed.trim_start_matches('/');
+    let mut regex = String::from("^");
+    if glob.is_empty() {
+        // `/` on its own owns everything
+        regex.push_str(".*");
+    } else if !anchored {
+        regex.push_str("(?:.*/)?");
+    }
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                if at_start && chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    // `dir/*` owns only the files directly in `dir`; anything else owns a
+    // matching directory's whole subtree
+    let last = glob.rsplit('/').next().unwrap_or(glob);
+    match (dir_only, last) {
+        (_, "") => {}
+        (false, "*") => regex.push('$'),
+        (true, _) => regex.push_str("/.*$"),
+        (false, _) => regex.push_str("(?:/.*)?$"),
+    }
+    Regex::new(&regex).expect("escaped CODEOWNERS pattern is a valid regex")
+}
+
+/// The owners to keep files of, checked against the repository's
+/// CODEOWNERS file.
+#[derive(Debug, Clone)]
+pub(crate) struct OwnerFilter {
+    codeowners: CodeOwners,
+    owners: Vec<String>,
+    // Where the root lies in the repository, as CODEOWNERS paths start there
+    prefix: PathBuf,
+}
+
+impl OwnerFilter {
+    /// Load the CODEOWNERS file of the repository containing `root`, found
+    /// where GitHub looks for it. Without a git repository, `root` is taken
+    /// as the repository root.
+    pub(crate) fn new(root: &Path, owners: &[String]) -> Result<Self> {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let repository = root
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(&root);
+        let Some((path, text)) = CODEOWNERS_LOCATIONS.iter().find_map(|location| {
+            let path = repository.join(location);
+            fs::read_to_string(&path).ok().map(|text| (path, text))
+        }) else {
+            return Err(WeaveError::InvalidOptions(format!(
+                "filtering by owner needs a CODEOWNERS file, but there is none in {} \
+                 (looked for {})",
+                display::strip_verbatim(repository.to_path_buf()).display(),
+                CODEOWNERS_LOCATIONS.join(", ")
+            )));
+        };
+        debug!("Reading code owners from {}", path.display());
+        Ok(OwnerFilter {
+            codeowners: CodeOwners::parse(&text),
+            owners: owners.to_vec(),
+            prefix: root
+                .strip_prefix(repository)
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+        })
+    }
+
+    /// Whether one of the owners owns the file at `relative_path`, relative
+    /// to the root.
+    pub(crate) fn keeps(&self, relative_path: &Path) -> bool {
+        let path = display::to_slash(&self.prefix.join(relative_path));
+        self.codeowners.is_owned_by(&path, &self.owners)
+    }
+}
@@ -0,0 +1,426 @@
+// src/collapse.rs
+use crate::{Action, FileContent, FileVisitor, get_language_tag};
+use std::path::Path;
+
+/// Default for [`CollapseData::min_lines`].
+pub const DEFAULT_COLLAPSE_MIN_LINES: usize = 16;
+
+/// Default for [`CollapseData::min_base64_len`].
+pub const DEFAULT_COLLAPSE_MIN_BASE64_LEN: usize = 64;
+
+/// Visitor replacing embedded data blobs with a one-line placeholder
+/// comment, keeping the code around them.
+///
+/// A blob is a run of at least [`min_lines`](Self::min_lines) consecutive
+/// data lines. A data line holds nothing but:
+///
+/// - comma-separated numeric literals, at least four of them, as in
+///   `0x00, 0x3c, 0x66, 0x7e,` or `1.5, -2.0, 3e8, 4,`;
+/// - one long base64 string, optionally quoted and followed by `,`, `+`,
+///   `;`, or `\`, as in wrapped PEM bodies or string concatenations;
+/// - `\xNN` escapes, at least sixteen of them.
+///
+/// Everything else ends a run, so ordinary code is never touched even when
+/// it is full of numbers.
+///
+/// ```
+/// use sourceweaver::{Action, CollapseData, FileContent, FileVisitor};
+/// use std::path::Path;
+///
+/// let mut font = String::from("const FONT: [u8; 96] = [\n");
+/// for _ in 0..24 {
+///     font.push_str("    0x00, 0x3c, 0x66, 0x7e,\n");
+/// }
+/// font.push_str("];\nfn main() {}\n");
+///
+/// let mut collapse = CollapseData::new();
+/// let Action::EmitModified(text) = collapse.on_file(Path::new("font.rs"), FileContent::Text(&font)) else {
+///     panic!("the font should be collapsed");
+/// };
+/// assert_eq!(
+///     text,
+///     "const FONT: [u8; 96] = [\n    /* 24 lines of embedded data elided */\n];\nfn main() {}\n"
+/// );
+/// ```
+///
+/// Runs shorter than the threshold and ordinary code are left alone:
+///
+/// ```
+/// use sourceweaver::{Action, CollapseData, FileContent, FileVisitor};
+/// use std::path::Path;
+///
+/// let mut collapse = CollapseData::new().min_lines(4);
+/// let mut check = |name: &str, text: &str| collapse.on_file(Path::new(name), FileContent::Text(text));
+///
+/// let short = "let xs = [\n    1, 2, 3, 4,\n    5, 6, 7, 8,\n];\n";
+/// assert_eq!(check("short.rs", short), Action::Emit);
+/// let calls = "draw(1, 2, 3, 4);\ndraw(5, 6, 7, 8);\ndraw(9, 10, 11, 12);\ndraw(13, 14, 15, 16);\n";
+/// assert_eq!(check("calls.rs", calls), Action::Emit);
+/// let matches = "1 => a,\n2 => b,\n3 => c,\n4 => d,\n";
+/// assert_eq!(check("match.rs", matches), Action::Emit);
+/// let dashes = "# ----------------------------------------------------------------------\n".repeat(4);
+/// assert_eq!(check("banner.py", &dashes), Action::Emit);
+/// let idents = "    some_really_long_identifier_name_that_goes_on_and_on_for_a_while,\n".repeat(4);
+/// assert_eq!(check("idents.rs", &idents), Action::Emit);
+///
+/// // Base64 is collapsed with the comment syntax of the file's language
+/// let sprite = "SPRITE = (\n".to_string()
+///     + &"    \"iVBORw0KGgoAAAANSUhEUgAAABAAAAAQCAYAAAAf8/9hAAAAGXRFWHRTb2Z0d2FyZQBBZG9i\"\n".repeat(5)
+///     + ")\n";
+/// assert_eq!(
+///     check("sprite.py", &sprite),
+///     Action::EmitModified("SPRITE = (\n    # 5 lines of embedded data elided\n)\n".to_string())
+/// );
+/// let escapes = "    \"\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a\\x00\\x00\\x00\\x0d\\x49\\x48\\x44\\x52\"\n".repeat(4);
+/// assert!(matches!(check("logo.c", &escapes), Action::EmitModified(_)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CollapseData {
+    min_lines: usize,
+    min_base64_len: usize,
+}
+
+impl Default for CollapseData {
+    fn default() -> Self {
+        CollapseData {
+            min_lines: DEFAULT_COLLAPSE_MIN_LINES,
+            min_base64_len: DEFAULT_COLLAPSE_MIN_BASE64_LEN,
+        }
+    }
+}
+
+impl CollapseData {
+    /// Collapse with the default thresholds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only collapse runs of at least `lines` data lines.
+    pub fn min_lines(mut self, lines: usize) -> Self {
+        self.min_lines = lines.max(1);
+        self
+    }
+
+    /// Only count a base64 string as data when it is at least `len`
+    /// characters long.
+    pub fn min_base64_len(mut self, len: usize) -> Self {
+        self.min_base64_len = len;
+        self
+    }
+
+    /// `text` with every blob collapsed, or `None` when there is none.
+    pub fn collapse(&self, path: &Path, text: &str) -> Option<String> {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let mut out = String::with_capacity(text.len());
+        let mut collapsed = false;
+        let mut i = 0;
+        while i < lines.len() {
+            let run = lines[i..]
+                .iter()
+                .take_while(|line| self.is_data(line))
+                .count();
+            if run >= self.min_lines {
+                let indent = &lines[i][..lines[i].len() - lines[i].trim_start().len()];
+                out.push_str(indent);
+                out.push_str(&placeholder(get_language_tag(path), run));
+                out.push('\n');
+                collapsed = true;
+                i += run;
+            } else {
+                // At least one line, so the scan always moves on
+                for line in &lines[i..i + run.max(1)] {
+                    out.push_str(line);
+                }
+                i += run.max(1);
+            }
+        }
+        collapsed.then_some(out)
+    }
+
+    fn is_data(&self, line: &str) -> bool {
+        let line = line.trim();
+        !line.is_empty()
+            && (is_numeric_list(line)
+                || is_base64(line, self.min_base64_len)
+                || is_byte_escapes(line))
+    }
+}
+
+impl FileVisitor for CollapseData {
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        match content {
+            FileContent::Text(text) => self
+                .collapse(path, text)
+                .map_or(Action::Emit, Action::EmitModified),
+            FileContent::Binary(_) => Action::Emit,
+        }
+    }
+}
+
+// At least four numeric literals and nothing else but commas
+fn is_numeric_list(line: &str) -> bool {
+    let line = line.strip_suffix(',').unwrap_or(line);
+    let mut count = 0;
+    for token in line.split(',') {
+        if !is_number(token.trim()) {
+            return false;
+        }
+        count += 1;
+    }
+    count >= 4
+}
+
+// Decimal, hex, octal, or binary integers and floats, with an optional sign
+// and type suffix such as `u8` or `f32`
+fn is_number(token: &str) -> bool {
+    const INTEGER_SUFFIXES: &[&str] = &[
+        "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize", "ull", "ul", "ll",
+        "u", "l",
+    ];
+    const FLOAT_SUFFIXES: &[&str] = &["f32", "f64", "f"];
+    let strip = |suffixes: &'static [&str], text: &str| -> usize {
+        suffixes
+            .iter()
+            .find(|suffix| text.ends_with(*suffix))
+            .map_or(0, |suffix| suffix.len())
+    };
+    let token = token
+        .strip_prefix(['-', '+'])
+        .unwrap_or(token)
+        .to_ascii_lowercase();
+    let token = &token[..token.len() - strip(INTEGER_SUFFIXES, &token)];
+    if let Some(digits) = ["0x", "0b", "0o"]
+        .iter()
+        .find_map(|prefix| token.strip_prefix(prefix))
+    {
+        return !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit() || c == '_');
+    }
+    let token = &token[..token.len() - strip(FLOAT_SUFFIXES, token)];
+    token.starts_with(|c: char| c.is_ascii_digit())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | '_' | 'e' | '-' | '+'))
+}
+
+// One long base64 string, possibly quoted and continued
+fn is_base64(line: &str, min_len: usize) -> bool {
+    let line = line.trim_end_matches([',', '+', ';', '\\', ' ']);
+    let line = line.trim_matches(['"', '\'', '`']);
+    line.len() >= min_len
+        && line
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_'))
+        // Real base64 mixes cases and digits; identifiers and rules don't
+        && line.chars().any(|c| c.is_ascii_digit())
+        && line.chars().any(|c| c.is_ascii_uppercase())
+        && line.chars().any(|c| c.is_ascii_lowercase())
+}
+
+// A string of `\xNN` escapes, as in C byte arrays
+fn is_byte_escapes(line: &str) -> bool {
+    let line = line.trim_end_matches([',', '+', ';', '\\', ' ']);
+    let line = line.trim_matches(['"', '\'', '`']);
+    let escapes: Vec<&str> = line.split("\\x").collect();
+    escapes.len() > 16
+        && escapes[0].is_empty()
+        && escapes[1..]
+            .iter()
+            .all(|hex| hex.len() == 2 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// The placeholder line, in the comment syntax of `language`
+fn placeholder(language: &str, lines: usize) -> String {
+    let note = format!("{} lines of embedded data elided", thousands(lines));
+    match language {
+        "python" | "ruby" | "perl" | "bash" | "powershell" | "yaml" | "toml" | "nix" | "r"
+        | "elixir" | "terraform" => format!("# {}", note),
+        "sql" | "lua" | "haskell" => format!("-- {}", note),
+        "html" | "xml" | "markdown" | "vue" | "svelte" => format!("<!-- {} -->", note),
+        "erlang" | "latex" | "matlab" | "prolog" => format!("% {}", note),
+        "clojure" => format!(";; {}", note),
+        _ => format!("/* {} */", note),
+    }
+}
+
+// `n` with commas between groups of three digits, e.g. 2,048
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // This crate's own sources: plenty of numbers, tables and long lines,
+    // and not one blob among them
+    const ORDINARY: &[(&str, &str)] = &[
+        ("lib.rs", include_str!("lib.rs")),
+        ("language.rs", include_str!("language.rs")),
+        ("walk.rs", include_str!("walk.rs")),
+        ("display.rs", include_str!("display.rs")),
+        ("collapse.rs", include_str!("collapse.rs")),
+        ("README.md", include_str!("../README.md")),
+        ("Cargo.toml", include_str!("../Cargo.toml")),
+    ];
+
+    #[test]
+    fn ordinary_code_is_never_collapsed() {
+        // Even with a threshold far below the default
+        let collapse = CollapseData::new().min_lines(3);
+        for (name, text) in ORDINARY {
+            assert_eq!(collapse.collapse(Path::new(name), text), None, "{}", name);
+        }
+        let imports =
+            "import { aVeryLongComponentNameForTheDashboardHeaderSection } from './header';\n"
+                .repeat(3);
+        let checksums =
+            "checksum = \"3a8e2e3a7b5b9c0e1e1c1f3c1a6b0e2d7c9d3f2e1a0b9c8d7e6f5a4b3c2d1e0f\"\n"
+                .repeat(3);
+        let samples = [
+            (
+                "matrix.py",
+                "IDENTITY = [\n    [1.0, 0.0, 0.0],\n    [0.0, 1.0, 0.0],\n    [0.0, 0.0, 1.0],\n]\n",
+            ),
+            (
+                "cases.py",
+                "@pytest.mark.parametrize(\"a,b,c,d\", [\n    (1, 2, 3, 4),\n    (5, 6, 7, 8),\n    (9, 10, 11, 12),\n])\n",
+            ),
+            (
+                "colors.go",
+                "var palette = []Color{\n\t{255, 0, 0, 255},\n\t{0, 255, 0, 255},\n\t{0, 0, 255, 255},\n}\n",
+            ),
+            ("imports.ts", &imports),
+            ("Cargo.lock", &checksums),
+            (
+                "versions.txt",
+                "2024-01-01, 2024-02-01\n1.2.3\n4.5.6\n7.8.9\n",
+            ),
+        ];
+        for (name, text) in samples {
+            assert_eq!(collapse.collapse(Path::new(name), text), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn data_lines() {
+        let collapse = CollapseData::new();
+        let table = [
+            ("0x00, 0x3c, 0x66, 0x7e,", true),
+            ("1.5, -2.0, 3e8, 4", true),
+            ("255u8, 0u8, 17u8, 1_000u8,", true),
+            ("0b1010, 0o17, 0xFF_FF, +7", true),
+            ("1, 2, 3", false),
+            ("1, 2, x, 4", false),
+            ("f(1, 2, 3, 4)", false),
+            ("1, 2, 3, 4, // comment", false),
+            (
+                "\"TWFuIGlzIGRpc3Rpbmd1aXNoZWQsIG5vdCBvbmx5IGJ5IGhpcyByZWFzb24sIGJ1dA==\",",
+                true,
+            ),
+            (
+                "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAu1SU1LfVLPHCozMxH2Mo4lgOEePzNm0t",
+                true,
+            ),
+            ("TWFuIGlzIGRpc3Rpbmd1aXNoZWQ=", false),
+            (
+                "abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnop",
+                false,
+            ),
+            (
+                "================================================================",
+                false,
+            ),
+            (
+                "\"\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a\\x00\\x00\\x00\\x0d\\x49\\x48\\x44\\x52\"",
+                true,
+            ),
+            ("\"\\x89\\x50\\x4e\\x47\"", false),
+            ("", false),
+        ];
+        for (line, data) in table {
+            assert_eq!(collapse.is_data(line), data, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn runs_collapse_from_the_threshold_up() {
+        let blob = |lines: usize| {
+            format!(
+                "static DATA: &[u8] = &[\n{}];\n",
+                "    1, 2, 3, 4,\n".repeat(lines)
+            )
+        };
+        let collapse = CollapseData::new().min_lines(8);
+        assert_eq!(collapse.collapse(Path::new("data.rs"), &blob(7)), None);
+        assert_eq!(
+            collapse.collapse(Path::new("data.rs"), &blob(8)).unwrap(),
+            "static DATA: &[u8] = &[\n    /* 8 lines of embedded data elided */\n];\n"
+        );
+        // Each run is judged on its own, and code between runs is kept
+        let two = format!("{}fn between() {{}}\n{}", blob(8), blob(3));
+        assert_eq!(
+            collapse.collapse(Path::new("data.rs"), &two).unwrap(),
+            format!(
+                "static DATA: &[u8] = &[\n    /* 8 lines of embedded data elided */\n];\nfn between() {{}}\n{}",
+                blob(3)
+            )
+        );
+        // A threshold of zero still needs one line
+        let one = CollapseData::new().min_lines(0);
+        assert!(one.collapse(Path::new("data.rs"), &blob(1)).is_some());
+        assert_eq!(one.collapse(Path::new("data.rs"), "fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn base64_length_threshold() {
+        let line = "QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVphYmNkZWZnaGlqa2xtbm9wcXJzdHV2d3h5ejAx\n";
+        let len = line.trim().len();
+        let text = line.repeat(2);
+        let at = CollapseData::new().min_lines(2).min_base64_len(len);
+        assert_eq!(
+            at.collapse(Path::new("key.pem"), &text).unwrap(),
+            "/* 2 lines of embedded data elided */\n"
+        );
+        let above = CollapseData::new().min_lines(2).min_base64_len(len + 1);
+        assert_eq!(above.collapse(Path::new("key.pem"), &text), None);
+    }
+
+    #[test]
+    fn placeholders_use_the_language_comment_syntax() {
+        assert_eq!(
+            placeholder("rust", 2048),
+            "/* 2,048 lines of embedded data elided */"
+        );
+        assert_eq!(
+            placeholder("python", 16),
+            "# 16 lines of embedded data elided"
+        );
+        assert_eq!(
+            placeholder("sql", 16),
+            "-- 16 lines of embedded data elided"
+        );
+        assert_eq!(
+            placeholder("html", 16),
+            "<!-- 16 lines of embedded data elided -->"
+        );
+        assert_eq!(
+            placeholder("clojure", 16),
+            ";; 16 lines of embedded data elided"
+        );
+        assert_eq!(
+            placeholder("", 16),
+            "/* 16 lines of embedded data elided */"
+        );
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1_000), "1,000");
+        assert_eq!(thousands(1_234_567), "1,234,567");
+    }
+}
@@ -0,0 +1,131 @@
+// src/compress.rs
+use clap::ValueEnum;
+use sourceweaver::WeaveError;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Compression applied to the `--output` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// gzip, as produced by `gzip`.
+    Gzip,
+    /// Zstandard, as produced by `zstd`.
+    Zstd,
+}
+
+impl Compression {
+    /// The compression implied by the extension of `path`, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Fail early when this build lacks support for the format.
+    pub fn check_supported(self) -> Result<(), WeaveError> {
+        let (supported, feature) = match self {
+            Compression::Gzip => (cfg!(feature = "gzip"), "gzip"),
+            Compression::Zstd => (cfg!(feature = "zstd"), "zstd"),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(WeaveError::InvalidOptions(format!(
+                "{} compression is not available; rebuild with the '{}' feature",
+                feature, feature
+            )))
+        }
+    }
+}
+
+/// The writer for the output file, compressing when asked to.
+pub enum Encoder {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Encoder {
+    pub fn new(file: File, compression: Option<Compression>) -> io::Result<Self> {
+        let file = BufWriter::new(file);
+        match compression {
+            None => Ok(Encoder::Plain(file)),
+            #[cfg(feature = "gzip")]
+            Some(Compression::Gzip) => Ok(Encoder::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Some(Compression::Zstd) => Ok(Encoder::Zstd(zstd::Encoder::new(file, 0)?)),
+            #[allow(unreachable_patterns)]
+            Some(compression) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{:?} compression is not available in this build",
+                    compression
+                ),
+            )),
+        }
+    }
+
+    /// Write the compressed stream's trailer and flush, returning the file.
+    #[allow(clippy::infallible_destructuring_match)] // Without compression features
+    pub fn finish(self) -> io::Result<File> {
+        let file = match self {
+            Encoder::Plain(file) => file,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        file.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(file) => file.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(file) => file.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Read `file` as written by an [`Encoder`] with `compression`.
+pub fn decoder(file: File, compression: Option<Compression>) -> io::Result<Box<dyn Read>> {
+    match compression {
+        None => Ok(Box::new(file)),
+        #[cfg(feature = "gzip")]
+        Some(Compression::Gzip) => Ok(Box::new(flate2::read::MultiGzDecoder::new(file))),
+        #[cfg(feature = "zstd")]
+        Some(Compression::Zstd) => Ok(Box::new(zstd::Decoder::new(file)?)),
+        #[allow(unreachable_patterns)]
+        Some(compression) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{:?} compression is not available in this build",
+                compression
+            ),
+        )),
+    }
+}
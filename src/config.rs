@@ -0,0 +1,259 @@
+// src/config.rs
+use sourceweaver::{Result, TruncateLimit, WeaveError};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+use toml::{Table, Value};
+
+/// Name of the configuration file read from the root directory.
+pub const CONFIG_FILE: &str = ".sourceweaver.toml";
+
+/// Settings read from the configuration file in the root directory.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Globs and the descriptions shown under matching file headings, from
+    /// the `[annotations]` table, in file order.
+    pub annotations: Vec<(String, String)>,
+    /// Globs and the weights of matching files under `--budget`, from the
+    /// `[priority]` table, in file order.
+    pub priorities: Vec<(String, i64)>,
+    /// Globs and how much of matching files is kept, from the `[truncate]`
+    /// table, in file order.
+    pub truncations: Vec<(String, TruncateLimit)>,
+    /// Extra lock file name patterns, from the `lock_files` array.
+    pub lock_files: Vec<String>,
+    /// Whether lock file names are compared ignoring case, from
+    /// `lock_files_ignore_case`; unset keeps the platform default.
+    pub lock_files_ignore_case: Option<bool>,
+    /// Named sets of globs selected with `--scope`, from the `[scopes]`
+    /// table, in file order.
+    pub scopes: Vec<(String, Vec<String>)>,
+}
+
+impl Config {
+    /// Read the configuration file in `root`. A root without one, or that
+    /// isn't a directory, gets the defaults.
+    pub fn load(root: &Path) -> Result<Config> {
+        let path = root.join(CONFIG_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::NotADirectory
+                ) =>
+            {
+                return Ok(Config::default());
+            }
+            Err(e) => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "can't read {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+        let invalid = |message: String| {
+            WeaveError::InvalidOptions(format!("invalid {}: {}", path.display(), message))
+        };
+        let table: Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| invalid(e.message().to_string()))?;
+
+        let mut config = Config::default();
+        match table.get("annotations") {
+            None => {}
+            Some(Value::Table(annotations)) => {
+                for (glob, description) in annotations {
+                    let Value::String(description) = description else {
+                        return Err(invalid(format!(
+                            "annotation for '{}' must be a string",
+                            glob
+                        )));
+                    };
+                    config.annotations.push((glob.clone(), description.clone()));
+                }
+            }
+            Some(_) => return Err(invalid("'annotations' must be a table".to_string())),
+        }
+        match table.get("priority") {
+            None => {}
+            Some(Value::Table(priorities)) => {
+                for (glob, weight) in priorities {
+                    let Value::Integer(weight) = weight else {
+                        return Err(invalid(format!(
+                            "priority for '{}' must be an integer",
+                            glob
+                        )));
+                    };
+                    config.priorities.push((glob.clone(), *weight));
+                }
+            }
+            Some(_) => return Err(invalid("'priority' must be a table".to_string())),
+        }
+        match table.get("truncate") {
+            None => {}
+            Some(Value::Table(truncations)) => {
+                for (glob, limit) in truncations {
+                    let invalid_limit = || {
+                        invalid(format!(
+                            "truncation for '{}' must be a line count or a table of 'lines' and \
+                             'bytes'",
+                            glob
+                        ))
+                    };
+                    let count = |value: &Value| match value {
+                        Value::Integer(count) if *count >= 0 => Ok(*count as u64),
+                        _ => Err(invalid_limit()),
+                    };
+                    let limit = match limit {
+                        Value::Integer(_) => TruncateLimit {
+                            lines: Some(count(limit)? as usize),
+                            bytes: None,
+                        },
+                        Value::Table(limit) => {
+                            if limit.keys().any(|key| key != "lines" && key != "bytes") {
+                                return Err(invalid_limit());
+                            }
+                            TruncateLimit {
+                                lines: limit
+                                    .get("lines")
+                                    .map(count)
+                                    .transpose()?
+                                    .map(|lines| lines as usize),
+                                bytes: limit.get("bytes").map(count).transpose()?,
+                            }
+                        }
+                        _ => return Err(invalid_limit()),
+                    };
+                    config.truncations.push((glob.clone(), limit));
+                }
+            }
+            Some(_) => return Err(invalid("'truncate' must be a table".to_string())),
+        }
+        match table.get("lock_files") {
+            None => {}
+            Some(Value::Array(patterns)) => {
+                for pattern in patterns {
+                    let Value::String(pattern) = pattern else {
+                        return Err(invalid("'lock_files' entries must be strings".to_string()));
+                    };
+                    config.lock_files.push(pattern.clone());
+                }
+            }
+            Some(_) => return Err(invalid("'lock_files' must be an array".to_string())),
+        }
+        match table.get("lock_files_ignore_case") {
+            None => {}
+            Some(Value::Boolean(ignore_case)) => config.lock_files_ignore_case = Some(*ignore_case),
+            Some(_) => {
+                return Err(invalid(
+                    "'lock_files_ignore_case' must be a boolean".to_string(),
+                ));
+            }
+        }
+        match table.get("scopes") {
+            None => {}
+            Some(Value::Table(scopes)) => {
+                for (name, globs) in scopes {
+                    let invalid_scope =
+                        || invalid(format!("scope '{}' must be an array of strings", name));
+                    let Value::Array(globs) = globs else {
+                        return Err(invalid_scope());
+                    };
+                    let globs = globs
+                        .iter()
+                        .map(|glob| match glob {
+                            Value::String(glob) => Ok(glob.clone()),
+                            _ => Err(invalid_scope()),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    config.scopes.push((name.clone(), globs));
+                }
+            }
+            Some(_) => return Err(invalid("'scopes' must be a table".to_string())),
+        }
+        Ok(config)
+    }
+
+    /// The globs of the scopes named `names`, together. An unknown name is
+    /// an error listing the scopes there are.
+    pub fn scope_globs(&self, names: &[String]) -> Result<Vec<String>> {
+        let mut globs = Vec::new();
+        for name in names {
+            let Some((_, scope)) = self.scopes.iter().find(|(scope, _)| scope == name) else {
+                let defined: Vec<&str> =
+                    self.scopes.iter().map(|(name, _)| name.as_str()).collect();
+                return Err(WeaveError::InvalidOptions(match defined.is_empty() {
+                    true => format!(
+                        "unknown scope '{}': {} defines no [scopes]",
+                        name, CONFIG_FILE
+                    ),
+                    false => format!(
+                        "unknown scope '{}'; {} defines {}",
+                        name,
+                        CONFIG_FILE,
+                        defined.join(", ")
+                    ),
+                }));
+            };
+            globs.extend(scope.iter().cloned());
+        }
+        Ok(globs)
+    }
+
+    /// Print each scope and its globs to stdout, one per line, e.g. `api:
+    /// services/api/** openapi.yaml`, for `--list-scopes`.
+    pub fn print_scopes(&self) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        for (name, globs) in &self.scopes {
+            writeln!(stdout, "{}: {}", name, globs.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(content: &str) -> Result<Config> {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(CONFIG_FILE), content).unwrap();
+        Config::load(dir.path())
+    }
+
+    #[test]
+    fn annotations_keep_file_order() {
+        let config = load(
+            "[annotations]\n\
+             \"src/**\" = \"Library code.\"\n\
+             \"src/net/retry.rs\" = \"Implements the retry policy.\"\n\
+             \"*.md\" = \"Documentation.\"\n",
+        )
+        .unwrap();
+        let globs: Vec<_> = config.annotations.iter().map(|(glob, _)| glob).collect();
+        assert_eq!(globs, ["src/**", "src/net/retry.rs", "*.md"]);
+        assert_eq!(config.annotations[1].1, "Implements the retry policy.");
+    }
+
+    #[test]
+    fn annotations_must_be_a_table_of_strings() {
+        let err = load("[annotations]\n\"*.rs\" = 3\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("annotation for '*.rs' must be a string"),
+            "{}",
+            err
+        );
+        let err = load("annotations = \"everything\"\n").unwrap_err();
+        assert!(
+            err.to_string().ends_with("'annotations' must be a table"),
+            "{}",
+            err
+        );
+        assert!(load("").unwrap().annotations.is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+// src/content_filter.rs
+use crate::{Result, WeaveError};
+use regex::Regex;
+
+/// Regexes choosing files by their content: text files matching an
+/// excluded regex are left out, and with grep regexes, so are files
+/// matching none of them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ContentFilter {
+    excluded: Vec<Regex>,
+    grep: Vec<Regex>,
+    /// Lines of context kept around grep matches, when files are cut down
+    /// to their matches.
+    pub(crate) context: Option<usize>,
+    /// Whether an excluded match fails the run instead of skipping the file.
+    pub(crate) strict: bool,
+}
+
+impl ContentFilter {
+    pub(crate) fn new(
+        excluded: &[String],
+        grep: &[String],
+        context: Option<usize>,
+        strict: bool,
+    ) -> Result<Self> {
+        let compile = |re: &String| {
+            Regex::new(re).map_err(|e| {
+                WeaveError::InvalidOptions(format!("invalid content regex '{}': {}", re, e))
+            })
+        };
+        if context.is_some() && grep.is_empty() {
+            return Err(WeaveError::InvalidOptions(
+                "grep context needs a grep regex to find the lines to keep".to_string(),
+            ));
+        }
+        Ok(ContentFilter {
+            excluded: excluded.iter().map(compile).collect::<Result<_>>()?,
+            grep: grep.iter().map(compile).collect::<Result<_>>()?,
+            context,
+            strict,
+        })
+    }
+
+    /// Whether files have to match a grep regex to be kept. Binary files
+    /// never do.
+    pub(crate) fn greps(&self) -> bool {
+        !self.grep.is_empty()
+    }
+
+    /// The first excluded regex, in the order given, that matches
+    /// somewhere in `text`. Each search stops at its first match.
+    pub(crate) fn excluding(&self, text: &str) -> Option<&str> {
+        self.excluded
+            .iter()
+            .find(|regex| regex.is_match(text))
+            .map(Regex::as_str)
+    }
+
+    /// Whether `text` is kept by the grep regexes: any of them matches, or
+    /// there are none.
+    pub(crate) fn selects(&self, text: &str) -> bool {
+        self.grep.is_empty() || self.grep.iter().any(|regex| regex.is_match(text))
+    }
+
+    /// How many lines of `text` a grep regex matches.
+    pub(crate) fn matching_lines(&self, text: &str) -> usize {
+        text.lines().filter(|line| self.matches_line(line)).count()
+    }
+
+    fn matches_line(&self, line: &str) -> bool {
+        self.grep.iter().any(|regex| regex.is_match(line))
+    }
+
+    /// `text` cut down to the lines a grep regex matches and `context`
+    /// lines either side, each run of left-out lines replaced by a line
+    /// such as `… 12 lines omitted`. A text whose matches all span lines
+    /// is kept whole.
+    pub(crate) fn excerpt(&self, text: &str, context: usize) -> String {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut keep = vec![false; lines.len()];
+        for (index, line) in lines.iter().enumerate() {
+            if self.matches_line(line) {
+                let end = (index + context + 1).min(lines.len());
+                keep[index.saturating_sub(context)..end].fill(true);
+            }
+        }
+        if !keep.contains(&true) {
+            return text.to_string();
+        }
+
+        let mut excerpt = String::new();
+        let mut omitted = 0;
+        for (line, kept) in lines.iter().zip(&keep) {
+            if !kept {
+                omitted += 1;
+                continue;
+            }
+            push_elision(&mut excerpt, omitted);
+            omitted = 0;
+            excerpt.push_str(line);
+            excerpt.push('\n');
+        }
+        push_elision(&mut excerpt, omitted);
+        excerpt
+    }
+}
+
+fn push_elision(excerpt: &mut String, omitted: usize) {
+    match omitted {
+        0 => {}
+        1 => excerpt.push_str("… 1 line omitted\n"),
+        n => excerpt.push_str(&format!("… {} lines omitted\n", n)),
+    }
+}
@@ -0,0 +1,220 @@
+// src/display.rs
+use std::{
+    ffi::OsStr,
+    fmt::Write,
+    path::{Component, MAIN_SEPARATOR, Path, PathBuf},
+};
+
+/// `path` as shown in the document: components joined with `/` on every
+/// platform, so documents look the same wherever they were generated.
+///
+/// Bytes that aren't valid UTF-8 (possible in Unix filenames) are
+/// percent-encoded, e.g. `caf%E9.txt`, so every file keeps a distinct,
+/// stable name instead of collapsing into replacement characters.
+pub(crate) fn to_slash(path: &Path) -> String {
+    join_components(path, '/')
+}
+
+/// Like [`to_slash`], but with the platform's own separator.
+pub(crate) fn to_native(path: &Path) -> String {
+    join_components(path, MAIN_SEPARATOR)
+}
+
+fn join_components(path: &Path, separator: char) -> String {
+    let mut out = String::new();
+    for component in path.components() {
+        match component {
+            // Drive letters and UNC shares, e.g. `C:` or `\\server\share`
+            Component::Prefix(prefix) => {
+                let prefix = prefix.as_os_str().to_string_lossy();
+                out.push_str(&prefix.replace('\\', &separator.to_string()))
+            }
+            Component::RootDir => out.push(separator),
+            other => {
+                if !out.is_empty() && !out.ends_with(separator) {
+                    out.push(separator);
+                }
+                push_encoded(&mut out, other.as_os_str());
+            }
+        }
+    }
+    out
+}
+
+// Append `name`, percent-encoding any bytes that aren't valid UTF-8
+fn push_encoded(out: &mut String, name: &OsStr) {
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        out.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+}
+
+/// `path` for use in a URL: `/` separators, with everything but unreserved
+/// characters percent-encoded.
+pub(crate) fn url_path(path: &Path) -> String {
+    let mut out = String::new();
+    for byte in to_slash(path).bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => {
+                let _ = write!(out, "%{byte:02X}");
+            }
+        }
+    }
+    out
+}
+
+/// Drop the `\\?\` prefix Windows canonicalization adds, which only gets
+/// in the way of reading the path. `\\?\UNC\server\share` becomes
+/// `\\server\share`.
+pub(crate) fn strip_verbatim(path: PathBuf) -> PathBuf {
+    let text = path.to_string_lossy();
+    if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(local) = text.strip_prefix(r"\\?\") {
+        PathBuf::from(local)
+    } else {
+        path
+    }
+}
+
+/// `text` as a Markdown code span. The delimiter is one backtick longer than
+/// the longest run of backticks in `text`, and padded with spaces where
+/// CommonMark would otherwise eat a backtick or a space at either end, so
+/// names like ``weird`name.rs`` survive intact.
+pub(crate) fn code_span(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let padded = text.starts_with('`')
+        || text.ends_with('`')
+        || (text.starts_with(' ') && text.ends_with(' ') && !text.trim().is_empty());
+    let pad = if padded { " " } else { "" };
+    format!("{fence}{pad}{text}{pad}{fence}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WeaveOptions, generate_markdown};
+    use std::fs;
+
+    fn path(components: &[&str]) -> PathBuf {
+        components.iter().collect()
+    }
+
+    #[test]
+    fn components_are_joined_with_slashes() {
+        let nested = path(&["src", "commands", "run.rs"]);
+        assert_eq!(to_slash(&nested), "src/commands/run.rs");
+        let native = ["src", "commands", "run.rs"].join(std::path::MAIN_SEPARATOR_STR);
+        assert_eq!(to_native(&nested), native);
+        assert_eq!(to_slash(Path::new("main.rs")), "main.rs");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_separators_and_prefixes() {
+        assert_eq!(
+            to_slash(Path::new(r"src\commands\run.rs")),
+            "src/commands/run.rs"
+        );
+        assert_eq!(to_slash(Path::new(r"C:\work\main.rs")), "C:/work/main.rs");
+        assert_eq!(
+            to_slash(&strip_verbatim(PathBuf::from(r"\\?\UNC\server\share\a.rs"))),
+            "//server/share/a.rs"
+        );
+        assert_eq!(
+            strip_verbatim(PathBuf::from(r"\\?\C:\work")),
+            PathBuf::from(r"C:\work")
+        );
+    }
+
+    #[test]
+    fn documents_use_slashes_unless_native_paths_is_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = path(&["src", "commands"]);
+        fs::create_dir_all(dir.path().join(&nested)).unwrap();
+        fs::write(dir.path().join(&nested).join("run.rs"), "fn run() {}\n").unwrap();
+
+        let weave = |native: bool| {
+            let options = WeaveOptions::new(dir.path())
+                .native_paths(native)
+                .build()
+                .unwrap();
+            let mut document = Vec::new();
+            generate_markdown(&mut document, &options).unwrap();
+            String::from_utf8(document).unwrap()
+        };
+        assert!(weave(false).contains("\n## `src/commands/run.rs`\n"));
+        let native = format!("src{0}commands{0}run.rs", MAIN_SEPARATOR);
+        assert!(weave(true).contains(&format!("\n## `{}`\n", native)));
+    }
+
+    #[test]
+    fn code_spans_outlast_the_backticks_they_hold() {
+        assert_eq!(code_span("main.rs"), "`main.rs`");
+        assert_eq!(code_span("weird`name.rs"), "``weird`name.rs``");
+        assert_eq!(code_span("a``b`c"), "```a``b`c```");
+        // Padding keeps a leading or trailing backtick out of the delimiter
+        assert_eq!(code_span("`start.rs"), "`` `start.rs ``");
+        assert_eq!(code_span("end.rs`"), "`` end.rs` ``");
+        // and keeps CommonMark from stripping spaces around the name
+        assert_eq!(code_span(" spaced "), "`  spaced  `");
+        assert_eq!(code_span("a|b.rs"), "`a|b.rs`");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hostile_names_make_intact_headings() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = ["weird`name.rs", "`tick.rs", "pipe|name.rs", "two``ticks.rs"];
+        for name in names {
+            fs::write(dir.path().join(name), "// hostile\n").unwrap();
+        }
+        let options = WeaveOptions::new(dir.path()).build().unwrap();
+        let mut document = Vec::new();
+        generate_markdown(&mut document, &options).unwrap();
+        let document = String::from_utf8(document).unwrap();
+        for (name, heading) in [
+            ("weird`name.rs", "## ``weird`name.rs``\n"),
+            ("`tick.rs", "## `` `tick.rs ``\n"),
+            ("pipe|name.rs", "## `pipe|name.rs`\n"),
+            ("two``ticks.rs", "## ```two``ticks.rs```\n"),
+        ] {
+            assert!(document.contains(heading), "{}: {}", name, document);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalid_utf8_bytes_are_percent_encoded() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        // Latin-1 and a stray continuation byte, which lossy display would
+        // both turn into U+FFFD
+        let latin1 = Path::new(OsStr::from_bytes(b"caf\xE9.txt"));
+        let stray = Path::new(OsStr::from_bytes(b"caf\x80.txt"));
+        assert_eq!(to_slash(latin1), "caf%E9.txt");
+        assert_eq!(to_slash(stray), "caf%80.txt");
+        assert_eq!(to_slash(&Path::new("docs").join(latin1)), "docs/caf%E9.txt");
+        // Valid multi-byte characters are left alone
+        assert_eq!(to_slash(Path::new("café.txt")), "café.txt");
+        assert_eq!(url_path(latin1), "caf%25E9.txt");
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(latin1), "menu\n").unwrap();
+        fs::write(dir.path().join(stray), "stray\n").unwrap();
+        let options = WeaveOptions::new(dir.path()).build().unwrap();
+        assert_eq!(options.display_path(latin1), "caf%E9.txt");
+        let mut document = Vec::new();
+        generate_markdown(&mut document, &options).unwrap();
+        let document = String::from_utf8(document).unwrap();
+        assert!(document.contains("\n## `caf%E9.txt`\n"), "{}", document);
+        assert!(document.contains("\n## `caf%80.txt`\n"), "{}", document);
+        assert!(!document.contains('\u{FFFD}'));
+    }
+}
@@ -0,0 +1,204 @@
+// src/editorconfig.rs
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use tracing::{debug, warn};
+
+/// Name of the files EditorConfig settings are read from.
+const EDITORCONFIG_FILE: &str = ".editorconfig";
+
+/// The `.editorconfig` files above each file, read once per directory, for
+/// looking up the settings that apply to a path.
+///
+/// As EditorConfig specifies, files are looked for from the file's own
+/// directory upwards until one says `root = true`; closer files override
+/// farther ones, and later sections override earlier ones.
+#[derive(Debug, Default)]
+pub(crate) struct EditorConfigs {
+    // By directory; None when it has no readable `.editorconfig`
+    files: HashMap<PathBuf, Option<Rc<EditorConfig>>>,
+}
+
+#[derive(Debug)]
+struct EditorConfig {
+    root: bool,
+    // Each section's glob, as a regex over paths relative to the file's
+    // directory, and its properties, lowercased
+    sections: Vec<(Regex, Vec<(String, String)>)>,
+}
+
+impl EditorConfigs {
+    /// The width of a tab for the file at `path`: its `tab_width`, or a
+    /// numeric `indent_size`.
+    pub(crate) fn tab_width(&mut self, path: &Path) -> Option<usize> {
+        let properties = self.properties(path);
+        let number = |key: &str| {
+            properties
+                .get(key)
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|&width| width > 0)
+        };
+        number("tab_width").or_else(|| number("indent_size"))
+    }
+
+    // The properties that apply to the file at `path`
+    fn properties(&mut self, path: &Path) -> HashMap<String, String> {
+        let mut files = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            if let Some(file) = self.load(dir) {
+                let root = file.root;
+                files.push((dir, file));
+                if root {
+                    break;
+                }
+            }
+        }
+        let mut properties = HashMap::new();
+        for (dir, file) in files.iter().rev() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            for (glob, section) in &file.sections {
+                if glob.is_match(&relative) {
+                    properties.extend(section.iter().cloned());
+                }
+            }
+        }
+        properties
+    }
+
+    fn load(&mut self, dir: &Path) -> Option<Rc<EditorConfig>> {
+        self.files
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let path = dir.join(EDITORCONFIG_FILE);
+                let content = fs::read_to_string(&path).ok()?;
+                debug!("Read {}", path.display());
+                Some(Rc::new(EditorConfig::parse(&content, &path)))
+            })
+            .clone()
+    }
+}
+
+impl EditorConfig {
+    // Sections with a glob that can't be translated are left out with a
+    // warning; `path` is only named in it
+    fn parse(content: &str, path: &Path) -> Self {
+        let mut config = EditorConfig {
+            root: false,
+            sections: Vec::new(),
+        };
+        // Properties before the first section only matter for `root`
+        let mut section: Option<usize> = None;
+        let mut skipping = false;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                match glob_regex(glob) {
+                    Some(regex) => {
+                        config.sections.push((regex, Vec::new()));
+                        section = Some(config.sections.len() - 1);
+                        skipping = false;
+                    }
+                    None => {
+                        warn!("Ignoring section [{}] of {}", glob, path.display());
+                        skipping = true;
+                    }
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim().to_ascii_lowercase();
+            match section {
+                _ if skipping => {}
+                Some(index) => config.sections[index].1.push((key, value)),
+                None if key == "root" => config.root = value == "true",
+                None => {}
+            }
+        }
+        config
+    }
+}
+
+// An EditorConfig glob as a regex matching whole paths relative to the
+// directory of its file. A glob without `/` matches a file name at any
+// depth. Numeric ranges such as `{1..3}` match any integer.
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let anchored = glob.contains('/');
+    let glob = glob.strip_prefix('/').unwrap_or(glob);
+    let mut pattern = String::from(match anchored {
+        true => "^",
+        false => "^(?:.*/)?",
+    });
+    let mut chars = glob.chars().peekable();
+    let mut braces = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    pattern.push_str(&regex::escape(&next.to_string()));
+                }
+            }
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => {
+                let mut class = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    class.push(c);
+                }
+                match (closed, class.strip_prefix('!')) {
+                    (false, _) => pattern.push_str(&regex::escape(&format!("[{}", class))),
+                    (true, Some(negated)) => pattern.push_str(&format!("[^{}]", negated)),
+                    (true, None) => pattern.push_str(&format!("[{}]", class)),
+                }
+            }
+            '{' => {
+                let rest: String = chars.clone().collect();
+                if let Some(end) = rest.find('}')
+                    && let Some((from, to)) = rest[..end].split_once("..")
+                    && from.parse::<i64>().is_ok()
+                    && to.parse::<i64>().is_ok()
+                {
+                    pattern.push_str("-?[0-9]+");
+                    for _ in 0..=end {
+                        chars.next();
+                    }
+                } else {
+                    braces += 1;
+                    pattern.push_str("(?:");
+                }
+            }
+            ',' if braces > 0 => pattern.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                pattern.push(')');
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if braces > 0 {
+        return None;
+    }
+    pattern.push('$');
+    Regex::new(&pattern).ok()
+}
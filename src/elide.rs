@@ -0,0 +1,240 @@
+// src/elide.rs
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// What a function body becomes in a brace language.
+const BRACE_MARKER: &str = "{ /* … body elided … */ }";
+
+/// What follows the indentation of an elided Python body.
+const PYTHON_MARKER: &str = "...  # … body elided …\n";
+
+// Where function definitions start, per language. Bodies are found from
+// there: the block after the next `{` for brace languages, the indented
+// lines after the header for Python.
+static FUNCTIONS: LazyLock<Vec<(&'static str, Regex)>> = LazyLock::new(|| {
+    let compile = |pattern: &str| Regex::new(pattern).expect("function pattern is valid");
+    let rust = compile(
+        r#"(?m)^[ \t]*(?:pub(?:[ \t]*\([^)]*\))?[ \t]+)?(?:default[ \t]+)?(?:const[ \t]+)?(?:async[ \t]+)?(?:unsafe[ \t]+)?(?:extern[ \t]+(?:"[^"]*"[ \t]+)?)?fn[ \t]+[A-Za-z_]\w*"#,
+    );
+    // Function declarations, and methods: a name and parameters ending a
+    // line with `{`. Control flow looks the same, and is told apart by name.
+    let js = compile(
+        r"(?m)^[ \t]*(?:export[ \t]+)?(?:default[ \t]+)?(?:async[ \t]+)?function\b|^[ \t]+(?:(?:public|private|protected|static|async|readonly|override|get|set)[ \t]+)*([A-Za-z_$#][\w$]*)[ \t]*(?:<[^>\n]*>)?\([^;{}\n]*\)[^;{}=\n]*\{[ \t]*$",
+    );
+    vec![
+        ("rust", rust),
+        ("go", compile(r"(?m)^func\b")),
+        ("javascript", js.clone()),
+        ("typescript", js.clone()),
+        ("jsx", js.clone()),
+        ("tsx", js),
+        (
+            "python",
+            compile(r"(?m)^[ \t]*(?:async[ \t]+)?def[ \t]+[A-Za-z_]\w*"),
+        ),
+    ]
+});
+
+// Words that open a block the way a method does
+const CONTROL_FLOW: &[&str] = &["if", "for", "while", "switch", "catch", "with", "function"];
+
+/// `text`, in `language`, with function bodies replaced by a marker,
+/// longest first, until `fits` accepts the line count and byte size of
+/// the result or no body is left. Declarations, imports, and type
+/// definitions stay as they are. None when the language isn't supported
+/// or has no function bodies.
+pub(crate) fn elide_bodies(
+    language: &str,
+    text: &str,
+    fits: impl Fn(usize, usize) -> bool,
+) -> Option<String> {
+    let (_, functions) = FUNCTIONS.iter().find(|(tag, _)| *tag == language)?;
+    let python = language == "python";
+    let mut bodies: Vec<(usize, usize, String)> = Vec::new();
+    for found in functions.captures_iter(text) {
+        if let Some(name) = found.get(1)
+            && CONTROL_FLOW.contains(&name.as_str())
+        {
+            continue;
+        }
+        let start = found.get(0).expect("group 0 always matches");
+        // Bodies inside an earlier body go with it
+        if bodies
+            .last()
+            .is_some_and(|(_, end, _)| start.start() < *end)
+        {
+            continue;
+        }
+        // A method's match ends with its opening brace
+        let from = match found.get(1) {
+            Some(_) => start.start() + start.as_str().rfind('{').unwrap_or(0),
+            None => start.end(),
+        };
+        let body = match python {
+            true => python_body(text, start.start()),
+            false => brace_body(text, from, language),
+        };
+        if let Some(body) = body {
+            bodies.push(body);
+        }
+    }
+    if bodies.is_empty() {
+        return None;
+    }
+
+    // Elide the longest bodies until the rest fits
+    let mut order: Vec<usize> = (0..bodies.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(bodies[i].1 - bodies[i].0));
+    let mut lines = text.split_inclusive('\n').count();
+    let mut bytes = text.len();
+    let mut elided = vec![false; bodies.len()];
+    for i in order {
+        if fits(lines, bytes) {
+            break;
+        }
+        let (start, end, marker) = &bodies[i];
+        let body = &text[*start..*end];
+        lines -= body.matches('\n').count() - marker.matches('\n').count();
+        bytes = bytes - body.len() + marker.len();
+        elided[i] = true;
+    }
+
+    let mut out = String::with_capacity(bytes);
+    let mut copied = 0;
+    for ((start, end, marker), _) in bodies.iter().zip(&elided).filter(|(_, elided)| **elided) {
+        out.push_str(&text[copied..*start]);
+        out.push_str(marker);
+        copied = *end;
+    }
+    out.push_str(&text[copied..]);
+    Some(out)
+}
+
+// The braced body of the function whose signature ends at `from`, with its
+// marker, or None for a declaration without one
+fn brace_body(text: &str, from: usize, language: &str) -> Option<(usize, usize, String)> {
+    let open = scan(text, from, language, |c| matches!(c, '{' | ';'))?;
+    if text[open..].starts_with(';') {
+        return None;
+    }
+    let mut depth = 0;
+    let close = scan(text, open, language, |c| {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    Some((open, close + 1, BRACE_MARKER.to_string()))
+}
+
+// The first offset from `from` whose character `stop` accepts, skipping
+// comments and string and character literals
+fn scan(
+    text: &str,
+    from: usize,
+    language: &str,
+    mut stop: impl FnMut(char) -> bool,
+) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = text[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = text[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+                continue;
+            }
+            quote @ (b'"' | b'`') => {
+                i = skip_string(bytes, i, quote);
+                continue;
+            }
+            // In Rust a quote may start a lifetime instead
+            b'\'' if language == "rust" => {
+                match (bytes.get(i + 1), bytes.get(i + 2)) {
+                    (Some(b'\\'), _) => i = skip_string(bytes, i, b'\''),
+                    (Some(_), Some(b'\'')) => i += 3,
+                    _ => i += 1,
+                }
+                continue;
+            }
+            b'\'' => {
+                i = skip_string(bytes, i, b'\'');
+                continue;
+            }
+            _ => {}
+        }
+        let c = text[i..].chars().next()?;
+        if stop(c) {
+            return Some(i);
+        }
+        i += c.len_utf8();
+    }
+    None
+}
+
+// The offset after the string opened by `quote` at `start`
+fn skip_string(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+// The indented body of the Python function defined at `start`, from the
+// line after its header to its last indented line, with its marker
+fn python_body(text: &str, start: usize) -> Option<(usize, usize, String)> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let def_indent = indent(&text[start..]);
+    // The header may span lines; it ends at the first `:` outside brackets
+    let mut depth = 0i32;
+    let colon = text[start..].char_indices().find_map(|(i, c)| {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ':' if depth == 0 => return Some(start + i + 1),
+            _ => {}
+        }
+        None
+    })?;
+    let header_end = text[colon..]
+        .find('\n')
+        .map_or(text.len(), |end| colon + end + 1);
+    // A one-line function keeps its body
+    let rest = text[colon..header_end]
+        .split('#')
+        .next()
+        .unwrap_or_default();
+    if !rest.trim().is_empty() {
+        return None;
+    }
+    let mut offset = header_end;
+    let lines = text[header_end..].split_inclusive('\n');
+    let body_start = offset;
+    let mut body_end = offset;
+    let mut body_indent = None;
+    for line in lines {
+        if !line.trim().is_empty() {
+            if indent(line) <= def_indent {
+                break;
+            }
+            body_indent.get_or_insert(indent(line));
+            body_end = offset + line.len();
+        }
+        offset += line.len();
+    }
+    let body_indent = body_indent?;
+    let marker = format!("{}{}", " ".repeat(body_indent), PYTHON_MARKER);
+    Some((body_start, body_end, marker))
+}
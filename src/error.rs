@@ -0,0 +1,131 @@
+// src/error.rs
+use std::{io, path::PathBuf};
+use thiserror::Error;
+
+/// Errors produced while weaving a codebase.
+#[derive(Debug, Error)]
+pub enum WeaveError {
+    /// The directory walker could not access an entry.
+    #[error("could not access entry: {source}")]
+    Walk {
+        path: Option<PathBuf>,
+        #[source]
+        source: ignore::Error,
+    },
+
+    /// A file was found but could not be read. The document still gets a
+    /// placeholder for it, so this is not fatal.
+    #[error("failed to read file {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The clipboard could not be initialized or written.
+    #[error("clipboard error: {0}")]
+    Clipboard(String),
+
+    /// The root does not exist or cannot be accessed.
+    #[error("can't open root {}: {source}", .path.display())]
+    Root {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The options are invalid or contradict each other.
+    #[error("invalid options: {0}")]
+    InvalidOptions(String),
+
+    /// The walk selected no files and
+    /// [`fail_if_empty`](crate::WeaveOptionsBuilder::fail_if_empty) is set.
+    /// Nothing was written.
+    #[error("no files matched")]
+    NoFiles {
+        /// Directories the walk entered, the root included.
+        dirs_visited: usize,
+    },
+
+    /// The archive given as the root could not be read.
+    #[error("failed to read archive {}: {source}", .path.display())]
+    Archive {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The output file could not be created or written.
+    #[error("failed to write {}: {source}", .path.display())]
+    OutputFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A file's content matches an excluded content regex, and
+    /// [`strict_content`](crate::WeaveOptionsBuilder::strict_content) is
+    /// set.
+    #[error("{} has content matching the excluded pattern '{pattern}'", .path.display())]
+    ExcludedContent { path: PathBuf, pattern: String },
+
+    /// Writing the document to its destination failed.
+    #[error("failed to write output: {0}")]
+    Output(#[from] io::Error),
+}
+
+/// Convenience alias used throughout the library API.
+pub type Result<T> = std::result::Result<T, WeaveError>;
+
+impl WeaveError {
+    /// Whether the error aborts the run. Walk and per-file read errors are
+    /// recorded in the [`WeaveReport`](crate::WeaveReport) instead.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, WeaveError::Walk { .. } | WeaveError::Read { .. })
+    }
+
+    /// The kind of the underlying I/O error, if there is one, e.g. to tell
+    /// permission problems apart from files that vanished mid-walk.
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            WeaveError::Walk { source, .. } => source.io_error().map(io::Error::kind),
+            WeaveError::Read { source, .. }
+            | WeaveError::Root { source, .. }
+            | WeaveError::Archive { source, .. }
+            | WeaveError::OutputFile { source, .. }
+            | WeaveError::Output(source) => Some(source.kind()),
+            _ => None,
+        }
+    }
+
+    /// The file or directory the error concerns, if known.
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            WeaveError::Walk { path, .. } => path.as_deref(),
+            WeaveError::Read { path, .. }
+            | WeaveError::Root { path, .. }
+            | WeaveError::Archive { path, .. }
+            | WeaveError::OutputFile { path, .. }
+            | WeaveError::ExcludedContent { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl From<ignore::Error> for WeaveError {
+    fn from(source: ignore::Error) -> Self {
+        let path = walk_error_path(&source);
+        WeaveError::Walk { path, source }
+    }
+}
+
+// Dig the offending path out of the (possibly nested) ignore error
+fn walk_error_path(err: &ignore::Error) -> Option<PathBuf> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path.clone()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        _ => None,
+    }
+}
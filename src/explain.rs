@@ -0,0 +1,413 @@
+// src/explain.rs
+use crate::{
+    FilterStage, Result, Submodules, WeaveError, WeaveOptions, display, filter_stages,
+    is_lock_file, submodule,
+};
+use ignore::{
+    Match, WalkBuilder,
+    gitignore::{Gitignore, GitignoreBuilder, Glob},
+};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// What one filter stage makes of a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageVerdict {
+    /// The stage.
+    pub stage: FilterStage,
+    /// Whether the stage leaves the path out.
+    pub skips: bool,
+    /// Why, e.g. the ignore rule that matched with its file and line.
+    pub detail: String,
+}
+
+/// Why a path is or isn't in the document, stage by stage, as found by
+/// [`explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// The path, relative to the root.
+    pub path: PathBuf,
+    /// Whether anything exists at the path.
+    pub exists: bool,
+    /// Whether a force-include glob selects the path, which then bypasses
+    /// every stage but the output file, size limit, submodule, and special
+    /// file checks.
+    pub forced: bool,
+    /// The verdict of each stage the run has, in the order they run.
+    pub stages: Vec<StageVerdict>,
+}
+
+impl Explanation {
+    /// Whether the walk selects the file. Content filters, a token budget,
+    /// and an output limit may still leave it out once it is read.
+    pub fn included(&self) -> bool {
+        self.exists && self.deciding().is_none()
+    }
+
+    /// The first stage that leaves the path out, if any.
+    pub fn deciding(&self) -> Option<&StageVerdict> {
+        self.stages.iter().find(|verdict| verdict.skips)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", display::to_slash(&self.path))?;
+        if !self.exists {
+            writeln!(f, "  (does not exist)")?;
+        }
+        if self.forced {
+            writeln!(f, "  (force-included)")?;
+        }
+        let width = self
+            .stages
+            .iter()
+            .map(|verdict| verdict.stage.name().len())
+            .max()
+            .unwrap_or(0);
+        for verdict in &self.stages {
+            let status = if verdict.skips { "skip" } else { "keep" };
+            writeln!(
+                f,
+                "  {:width$}  {}  {}",
+                verdict.stage.name(),
+                status,
+                verdict.detail
+            )?;
+        }
+        match (self.exists, self.deciding()) {
+            (false, _) => write!(f, "=> not included: the path does not exist"),
+            (true, Some(verdict)) => write!(f, "=> excluded by {}", verdict.stage.name()),
+            (true, None) => write!(f, "=> included"),
+        }
+    }
+}
+
+/// Run every filter stage of `options` on `path`, given relative to the
+/// root or as an absolute path below it, and report what each decides,
+/// checking the directories above it too, as the walk never enters a
+/// directory a stage leaves out.
+///
+/// Ignore rules are looked up as the walk applies them: `.ignore` files,
+/// then `.gitignore` files, each from the path's own directory upwards,
+/// then `.git/info/exclude` and the global gitignore. The first rule that
+/// matches is named with its file and line.
+///
+/// ```
+/// use sourceweaver::{FilterStage, WeaveOptions, explain};
+/// use std::path::Path;
+///
+/// let options = WeaveOptions::new(".").exclude("*.md").build()?;
+/// let explanation = explain(&options, Path::new("README.md"));
+/// let deciding = explanation.deciding().unwrap();
+/// assert_eq!(deciding.stage, FilterStage::Patterns);
+/// assert_eq!(deciding.detail, "excluded by '*.md'");
+/// assert!(!explanation.included());
+///
+/// assert!(explain(&options, Path::new("src/lib.rs")).included());
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+pub fn explain(options: &WeaveOptions, path: &Path) -> Explanation {
+    let root = fs::canonicalize(&options.root).unwrap_or_else(|_| options.root.clone());
+    let relative = path
+        .strip_prefix(&root)
+        .or_else(|_| path.strip_prefix(&options.root))
+        .unwrap_or(path)
+        .to_path_buf();
+    let full = root.join(&relative);
+    let metadata = fs::metadata(&full).ok();
+    let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+    // The directories above the path, from the root down, then the path
+    let levels: Vec<(&Path, bool)> = relative
+        .ancestors()
+        .filter(|level| !level.as_os_str().is_empty())
+        .map(|level| (level, level != relative || is_dir))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let forced = options
+        .force_override
+        .as_ref()
+        .is_some_and(|overrides| overrides.matched(&full, is_dir).is_whitelist());
+
+    let mut stages = filter_stages(options);
+    if forced {
+        stages.retain(|stage| {
+            matches!(
+                stage,
+                FilterStage::OutputFile
+                    | FilterStage::SizeLimit
+                    | FilterStage::Submodules
+                    | FilterStage::SpecialFiles
+            )
+        });
+    }
+    let keep = |detail: &str| (false, detail.to_string());
+    let stages = stages
+        .into_iter()
+        .map(|stage| {
+            let (skips, detail) = match stage {
+                FilterStage::IgnoreFiles => match ignore_rule(&root, &levels) {
+                    Some((level, rule)) => (true, format!("{} matches {}", shown(level), rule)),
+                    None => keep("no ignore rule matches"),
+                },
+                FilterStage::Hidden => match levels.iter().find(|(level, _)| is_hidden(level)) {
+                    Some(level) => (true, format!("{} is hidden", shown(*level))),
+                    None => keep("not hidden"),
+                },
+                FilterStage::OutputFile => {
+                    let output = fs::canonicalize(&full).is_ok_and(|canonical| {
+                        options
+                            .exclude_paths
+                            .contains(&display::strip_verbatim(canonical))
+                    });
+                    match output {
+                        true => (true, "is the document being written".to_string()),
+                        false => keep("not the output file"),
+                    }
+                }
+                FilterStage::LockFiles => {
+                    let name = relative.file_name().and_then(|name| name.to_str());
+                    let lock_file = !is_dir
+                        && name.is_some_and(|name| {
+                            is_lock_file(
+                                name,
+                                &options.lock_files,
+                                options.lock_files_case_insensitive,
+                            )
+                        });
+                    match lock_file {
+                        true => (true, "is a lock file".to_string()),
+                        false => keep("not a lock file"),
+                    }
+                }
+                FilterStage::SizeLimit => {
+                    let size = metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len());
+                    match (size, options.max_filesize) {
+                        (Some(size), Some(max)) if size > max => (
+                            true,
+                            format!("{} bytes, over the limit of {} bytes", size, max),
+                        ),
+                        (Some(size), Some(max)) => {
+                            (false, format!("{} bytes, within {} bytes", size, max))
+                        }
+                        _ => keep("no size to check"),
+                    }
+                }
+                FilterStage::Submodules => {
+                    let submodules = match options.submodules {
+                        Submodules::Skip => submodule::submodule_paths(&options.root),
+                        Submodules::Include => Vec::new(),
+                    };
+                    let inside = levels.iter().find(|(level, is_dir)| {
+                        *is_dir && submodules.contains(&options.root.join(level))
+                    });
+                    match inside {
+                        Some(level) => (true, format!("{} is a submodule", shown(*level))),
+                        None => keep("not in a submodule"),
+                    }
+                }
+                FilterStage::SpecialFiles => match &metadata {
+                    Some(m) if !m.is_file() && !m.is_dir() => {
+                        (true, "not a regular file or directory".to_string())
+                    }
+                    _ => keep("a regular file or directory"),
+                },
+                FilterStage::ExcludedDirs => {
+                    let excluded = levels.iter().find(|(level, is_dir)| {
+                        *is_dir
+                            && level.file_name().is_some_and(|name| {
+                                options.exclude_dirs.iter().any(|dir| name == dir.as_str())
+                            })
+                    });
+                    match excluded {
+                        Some(level) => {
+                            (true, format!("{} is an excluded directory", shown(*level)))
+                        }
+                        None => keep("not in an excluded directory"),
+                    }
+                }
+                // The matcher sees paths as the walk does, below the root given
+                FilterStage::Patterns => {
+                    let pruned = levels.iter().find(|(level, is_dir)| {
+                        *is_dir && options.matcher.prunes(&options.root.join(level))
+                    });
+                    let walked = options.root.join(&relative);
+                    match pruned {
+                        Some(&(level, is_dir)) => (
+                            true,
+                            format!(
+                                "{} {}",
+                                shown((level, is_dir)),
+                                options
+                                    .matcher
+                                    .explain(&options.root.join(level), &options.patterns)
+                            ),
+                        ),
+                        None if is_dir => keep("directories are kept for the files inside"),
+                        None => (
+                            options.matcher.is_excluded(&walked, false),
+                            options.matcher.explain(&walked, &options.patterns),
+                        ),
+                    }
+                }
+                FilterStage::Owners => match &options.owner_filter {
+                    Some(owners) if !is_dir && !owners.keeps(&relative) => {
+                        (true, "owned by none of the given owners".to_string())
+                    }
+                    _ => keep("owned by a given owner"),
+                },
+                FilterStage::FileList => match &options.file_list {
+                    Some(list) if !is_dir && !list.keeps_file(&relative) => {
+                        (true, "not on the list of files".to_string())
+                    }
+                    _ => keep("on the list of files"),
+                },
+                FilterStage::Scope => match &options.scope {
+                    Some(scope) if !is_dir && !scope.keeps(&relative) => {
+                        (true, "matches none of the scope's globs".to_string())
+                    }
+                    _ => keep("in scope"),
+                },
+            };
+            StageVerdict {
+                stage,
+                skips,
+                detail,
+            }
+        })
+        .collect();
+
+    Explanation {
+        path: relative.clone(),
+        exists: metadata.is_some(),
+        forced,
+        stages,
+    }
+}
+
+/// [`explain`] every file under the root matching one of `globs`
+/// (gitignore syntax, relative to the root), ignored and hidden ones
+/// included, in path order. `.git` directories aren't searched.
+pub fn explain_matching(options: &WeaveOptions, globs: &[String]) -> Result<Vec<Explanation>> {
+    let mut builder = GitignoreBuilder::new(&options.root);
+    for glob in globs {
+        builder.add_line(None, glob).map_err(|e| {
+            WeaveError::InvalidOptions(format!("invalid filter glob '{}': {}", glob, e))
+        })?;
+    }
+    let matcher = builder
+        .build()
+        .map_err(|e| WeaveError::InvalidOptions(format!("invalid filter globs: {}", e)))?;
+    let walk = WalkBuilder::new(&options.root)
+        .standard_filters(false)
+        .follow_links(options.follow_links)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    Ok(walk
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .filter(|entry| {
+            let relative = entry
+                .path()
+                .strip_prefix(&options.root)
+                .unwrap_or(entry.path());
+            !matcher
+                .matched_path_or_any_parents(relative, false)
+                .is_none()
+        })
+        .map(|entry| explain(options, entry.path()))
+        .collect())
+}
+
+// A level of the path as shown in a verdict, directories with a slash
+fn shown((level, is_dir): (&Path, bool)) -> String {
+    let slash = if is_dir { "/" } else { "" };
+    format!("'{}{}'", display::to_slash(level), slash)
+}
+
+fn is_hidden(level: &Path) -> bool {
+    level
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+// The first level of the path an ignore rule leaves out, with the rule,
+// where it comes from, and its line. As in the walk, `.ignore` files beat
+// `.gitignore` files, deeper files beat shallower ones, and git's exclude
+// file and the global gitignore come last; a whitelist rule keeps the level.
+fn ignore_rule<'a>(root: &Path, levels: &[(&'a Path, bool)]) -> Option<((&'a Path, bool), String)> {
+    // .gitignore rules only count inside a git repository
+    let repository = root.ancestors().find(|dir| dir.join(".git").exists());
+    let mut matchers: Vec<(PathBuf, Option<Gitignore>)> = Vec::new();
+    let mut matcher = |dir: &Path, file: PathBuf| -> Option<Gitignore> {
+        if let Some((_, matcher)) = matchers.iter().find(|(known, _)| *known == file) {
+            return matcher.clone();
+        }
+        let matcher = file.is_file().then(|| {
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add(&file);
+            builder.build().ok()
+        });
+        let matcher = matcher.flatten();
+        matchers.push((file, matcher.clone()));
+        matcher
+    };
+    let global = repository.map(|_| Gitignore::global().0);
+    for &(level, is_dir) in levels {
+        let full = root.join(level);
+        let dirs: Vec<&Path> = full.ancestors().skip(1).collect();
+        let mut files: Vec<(&Path, PathBuf)> =
+            dirs.iter().map(|dir| (*dir, dir.join(".ignore"))).collect();
+        if let Some(repository) = repository {
+            files.extend(
+                dirs.iter()
+                    .filter(|dir| dir.starts_with(repository))
+                    .map(|dir| (*dir, dir.join(".gitignore"))),
+            );
+            files.push((repository, repository.join(".git/info/exclude")));
+        }
+        let mut decided = files
+            .into_iter()
+            .filter_map(|(dir, file)| matcher(dir, file))
+            .map(|matcher| match matcher.matched(&full, is_dir) {
+                Match::Ignore(glob) => Match::Ignore(rule(glob, root)),
+                Match::Whitelist(_) => Match::Whitelist(String::new()),
+                Match::None => Match::None,
+            })
+            .find(|found| !found.is_none());
+        if decided.is_none()
+            && let Some(global) = &global
+            && let Match::Ignore(glob) = global.matched(&full, is_dir)
+        {
+            decided = Some(Match::Ignore(rule(glob, root)));
+        }
+        if let Some(Match::Ignore(rule)) = decided {
+            return Some(((level, is_dir), rule));
+        }
+    }
+    None
+}
+
+// An ignore rule as `'target/' in .gitignore:3`, its file relative to the
+// root when it lies below it
+fn rule(glob: &Glob, root: &Path) -> String {
+    let Some(file) = glob.from() else {
+        return format!("'{}'", glob.original());
+    };
+    let line = fs::read_to_string(file).ok().and_then(|text| {
+        text.lines()
+            .position(|line| line.trim() == glob.original().trim())
+            .map(|index| index + 1)
+    });
+    let file = display::to_slash(file.strip_prefix(root).unwrap_or(file));
+    match line {
+        Some(line) => format!("'{}' in {}:{}", glob.original(), file, line),
+        None => format!("'{}' in {}", glob.original(), file),
+    }
+}
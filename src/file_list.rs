@@ -0,0 +1,38 @@
+// src/file_list.rs
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// The only files the walk may select, by path relative to the root, and
+/// the directories leading to them, so the rest of the tree is pruned.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileList {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileList {
+    pub(crate) fn new(files: &[PathBuf]) -> Self {
+        let dirs = files
+            .iter()
+            .flat_map(|file| file.ancestors().skip(1))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect();
+        FileList {
+            files: files.iter().cloned().collect(),
+            dirs,
+        }
+    }
+
+    /// Whether the file at `relative_path` is listed.
+    pub(crate) fn keeps_file(&self, relative_path: &Path) -> bool {
+        self.files.contains(relative_path)
+    }
+
+    /// Whether the directory at `relative_path` holds a listed file.
+    pub(crate) fn keeps_dir(&self, relative_path: &Path) -> bool {
+        relative_path.as_os_str().is_empty() || self.dirs.contains(relative_path)
+    }
+}
@@ -0,0 +1,168 @@
+// src/git.rs
+use sourceweaver::WeaveError;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Blob URL layout for GitHub and Gitea-compatible hosts.
+const GITHUB_TEMPLATE: &str = "{repo}/blob/{commit}/{path}";
+/// Blob URL layout for GitLab.
+const GITLAB_TEMPLATE: &str = "{repo}/-/blob/{commit}/{path}";
+
+/// Run `git` in `dir` and return its trimmed stdout.
+pub fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let stdout = git_bytes(dir, args)?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+/// Run `git` in `dir` and return its stdout as is, for output that
+/// separates paths with NUL bytes.
+pub fn git_bytes(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            stderr.trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// The files under `root` with uncommitted changes, relative to the root,
+/// as `git status` reports them: modified, added, renamed (by their new
+/// path), and untracked unless `tracked_only` is set. Fails outside a git
+/// repository.
+pub fn dirty_files(root: &Path, tracked_only: bool) -> sourceweaver::Result<Vec<PathBuf>> {
+    let dir = work_dir(root);
+    let invalid =
+        |e: io::Error| WeaveError::InvalidOptions(format!("can't list dirty files: {}", e));
+    // Status paths are relative to the repository, not to `dir`
+    let prefix = git(dir, &["rev-parse", "--show-prefix"]).map_err(invalid)?;
+    let untracked = match tracked_only {
+        true => "--untracked-files=no",
+        false => "--untracked-files=all",
+    };
+    let status = git_bytes(dir, &["status", "--porcelain", "-z", untracked]).map_err(invalid)?;
+
+    let mut files = Vec::new();
+    let mut entries = status
+        .split(|&byte| byte == 0)
+        .map(|entry| String::from_utf8_lossy(entry).into_owned());
+    while let Some(entry) = entries.next() {
+        let Some((status, path)) = entry.split_at_checked(3) else {
+            continue;
+        };
+        // A rename or copy is followed by the path it came from
+        if status.contains(['R', 'C']) {
+            entries.next();
+        }
+        if let Some(path) = path.strip_prefix(&prefix) {
+            files.push(PathBuf::from(path));
+        }
+    }
+    Ok(files)
+}
+
+/// The directory git commands about `root` run in: the root itself, or
+/// its parent for a single-file root.
+pub fn work_dir(root: &Path) -> &Path {
+    match root.parent() {
+        Some(parent) if root.is_file() => parent,
+        _ => root,
+    }
+}
+
+/// Turn a remote URL into the https address of the repository's web page:
+/// `git@github.com:org/repo.git` and `ssh://git@host/org/repo` become
+/// `https://github.com/org/repo` and `https://host/org/repo`.
+pub fn normalize_remote(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let (host, path) = if let Some(rest) = url.strip_prefix("ssh://") {
+        match rest.split_once('/') {
+            Some((host, path)) => (host, path),
+            None => (rest, ""),
+        }
+    } else if let Some(rest) = ["https://", "http://", "git://"]
+        .iter()
+        .find_map(|scheme| url.strip_prefix(scheme))
+    {
+        match rest.split_once('/') {
+            Some((host, path)) => (host, path),
+            None => (rest, ""),
+        }
+    } else if let Some((host, path)) = url.split_once(':') {
+        // scp-like syntax: [user@]host:path
+        (host, path)
+    } else {
+        return url.to_string();
+    };
+    // Drop credentials and ssh ports, which don't belong in a web URL
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = match host.split_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    format!("https://{}/{}", host, path.trim_start_matches('/'))
+}
+
+/// The link template for headings, with everything but `{path}` filled in.
+///
+/// `repo_url` is used as given (after normalization), or read from the
+/// `origin` remote when `auto` is set. `template` overrides the blob URL
+/// layout, which otherwise follows GitHub's, or GitLab's for gitlab hosts.
+/// `{path}` in the result is relative to the root, so the root's position
+/// in the repository is folded into the template here.
+pub fn link_template(
+    root: &Path,
+    repo_url: Option<&str>,
+    auto: bool,
+    template: Option<&str>,
+) -> sourceweaver::Result<String> {
+    let dir = work_dir(root);
+    let invalid =
+        |e: io::Error| WeaveError::InvalidOptions(format!("can't build heading links: {}", e));
+
+    let repo = match repo_url {
+        Some(url) => Some(normalize_remote(url)),
+        None if auto => Some(normalize_remote(
+            &git(dir, &["remote", "get-url", "origin"]).map_err(invalid)?,
+        )),
+        None => None,
+    };
+    let template = match (template, &repo) {
+        (Some(template), _) => template,
+        (None, Some(repo)) if repo.contains("gitlab") => GITLAB_TEMPLATE,
+        (None, _) => GITHUB_TEMPLATE,
+    };
+    if template.contains("{repo}") && repo.is_none() {
+        return Err(WeaveError::InvalidOptions(
+            "the link template uses {repo}, but no --repo-url or --auto-repo-url was given"
+                .to_string(),
+        ));
+    }
+
+    // A template without either is free-form, e.g. an editor URL scheme,
+    // and needs no git checkout
+    let in_repo = template.contains("{repo}") || template.contains("{commit}");
+    let mut filled = template.replace("{repo}", repo.as_deref().unwrap_or_default());
+    if filled.contains("{commit}") {
+        let commit = git(dir, &["rev-parse", "HEAD"]).map_err(invalid)?;
+        filled = filled.replace("{commit}", &commit);
+    }
+    // Paths in the document are relative to the root (or, for a file root,
+    // its directory); links need them relative to the repository
+    let prefix = match in_repo {
+        true => git(dir, &["rev-parse", "--show-prefix"]).map_err(invalid)?,
+        false => String::new(),
+    };
+    let prefix = prefix.trim_end_matches('/');
+    if !prefix.is_empty() {
+        filled = filled.replace("{path}", &format!("{}/{{path}}", prefix));
+    }
+    Ok(filled)
+}
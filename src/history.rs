@@ -0,0 +1,106 @@
+// src/history.rs
+use crate::git;
+use clap::ValueEnum;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// The most commits `--git-log` lists, to keep the section small.
+pub const MAX_COMMITS: u32 = 100;
+
+// Longer subjects are cut, with an ellipsis
+const MAX_SUBJECT_CHARS: usize = 72;
+
+/// Where `--git-log` puts the `## Recent History` section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HistoryPosition {
+    /// Before the first file section, after any --prefix.
+    Start,
+    /// After everything but the --suffix.
+    #[default]
+    End,
+}
+
+/// The last commits of the repository the root is in, from `--git-log`,
+/// as a `## Recent History` section giving the model a sense of what
+/// changed lately. Empty outside of git.
+#[derive(Debug, Default)]
+pub struct History {
+    commits: Vec<String>,
+    position: HistoryPosition,
+}
+
+impl History {
+    /// The last `count` commits, or with `scope`, the last ones touching
+    /// one of those paths. Outside a git repository there is nothing to
+    /// show, which is warned about rather than failing the run.
+    pub fn load(
+        root: &Path,
+        count: Option<u32>,
+        scope: Option<&[PathBuf]>,
+        position: HistoryPosition,
+    ) -> Self {
+        let Some(count) = count else {
+            return History::default();
+        };
+        let count = format!("-{}", count.min(MAX_COMMITS));
+        let mut args = vec![
+            "log",
+            &count,
+            "--no-merges",
+            "--date=short",
+            "--format=%h%x09%ad%x09%an%x09%s",
+        ];
+        let scope: Vec<String> = scope
+            .unwrap_or_default()
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        if !scope.is_empty() {
+            args.push("--");
+            args.extend(scope.iter().map(String::as_str));
+        }
+        let log = match git::git(git::work_dir(root), &args) {
+            Ok(log) => log,
+            Err(e) => {
+                warn!("Leaving out the recent history: {}", e);
+                return History::default();
+            }
+        };
+        History {
+            commits: log.lines().filter_map(entry).collect(),
+            position,
+        }
+    }
+
+    /// Write the section if it goes at `position`. Nothing is written when
+    /// there are no commits.
+    pub fn write_at<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        position: HistoryPosition,
+    ) -> io::Result<()> {
+        if self.commits.is_empty() || self.position != position {
+            return Ok(());
+        }
+        writeln!(writer, "\n## Recent History\n")?;
+        for commit in &self.commits {
+            writeln!(writer, "{}", commit)?;
+        }
+        Ok(())
+    }
+}
+
+// The list item for one line of `git log` output
+fn entry(line: &str) -> Option<String> {
+    let mut fields = line.splitn(4, '\t');
+    let (sha, date, author) = (fields.next()?, fields.next()?, fields.next()?);
+    let subject = fields.next().unwrap_or_default().trim();
+    let subject = match subject.char_indices().nth(MAX_SUBJECT_CHARS) {
+        Some((cut, _)) => format!("{}…", subject[..cut].trim_end()),
+        None => subject.to_string(),
+    };
+    Some(format!("- `{}` {} {}: {}", sha, date, author, subject))
+}
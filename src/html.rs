@@ -0,0 +1,142 @@
+// src/html.rs
+
+/// Render the generated Markdown document as an HTML fragment for rich-text
+/// paste targets. Only the constructs the generator emits are recognised:
+/// `## ` headings (with inline code, possibly wrapped in a link) and fenced
+/// code blocks; any other non-blank line becomes a paragraph.
+pub fn render(markdown: &str) -> String {
+    let mut html = String::with_capacity(markdown.len() + markdown.len() / 8);
+    let mut in_code = false;
+
+    for line in markdown.lines() {
+        if let Some(info) = line.strip_prefix("```") {
+            if in_code {
+                html.push_str("</code></pre>\n");
+            } else if info.trim().is_empty() {
+                html.push_str("<pre><code>");
+            } else {
+                html.push_str("<pre><code class=\"language-");
+                escape_into(&mut html, info.trim());
+                html.push_str("\">");
+            }
+            in_code = !in_code;
+        } else if in_code {
+            escape_into(&mut html, line);
+            html.push('\n');
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            html.push_str("<h2>");
+            match split_link(heading) {
+                Some((text, url)) => {
+                    html.push_str("<a href=\"");
+                    escape_into(&mut html, url);
+                    html.push_str("\">");
+                    inline_into(&mut html, text);
+                    html.push_str("</a>");
+                }
+                None => inline_into(&mut html, heading),
+            }
+            html.push_str("</h2>\n");
+        } else if !line.trim().is_empty() {
+            html.push_str("<p>");
+            inline_into(&mut html, line);
+            html.push_str("</p>\n");
+        }
+    }
+    // An unterminated fence still yields well-formed markup
+    if in_code {
+        html.push_str("</code></pre>\n");
+    }
+    html
+}
+
+// The text and URL of a heading that is entirely a `[text](url)` link
+fn split_link(heading: &str) -> Option<(&str, &str)> {
+    let inner = heading.strip_prefix('[')?.strip_suffix(')')?;
+    inner.rsplit_once("](")
+}
+
+// Escape text, turning backtick code spans into <code> elements. A span
+// closes at the next run of exactly as many backticks as opened it, and one
+// space of padding on both sides is dropped, as in CommonMark.
+fn inline_into(html: &mut String, text: &str) {
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        escape_into(html, &rest[..start]);
+        let run = rest[start..].len() - rest[start..].trim_start_matches('`').len();
+        let after = &rest[start + run..];
+        match closing_run(after, run) {
+            Some(end) => {
+                let mut code = &after[..end];
+                if !code.trim().is_empty() && code.starts_with(' ') && code.ends_with(' ') {
+                    code = &code[1..code.len() - 1];
+                }
+                html.push_str("<code>");
+                escape_into(html, code);
+                html.push_str("</code>");
+                rest = &after[end + run..];
+            }
+            None => {
+                html.push_str(&rest[start..start + run]);
+                rest = after;
+            }
+        }
+    }
+    escape_into(html, rest);
+}
+
+// Offset of the first run of exactly `len` backticks in `text`
+fn closing_run(text: &str, len: usize) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find('`') {
+        let start = offset + start;
+        let run = text[start..].len() - text[start..].trim_start_matches('`').len();
+        if run == len {
+            return Some(start);
+        }
+        offset = start + run;
+    }
+    None
+}
+
+fn escape_into(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_keep_backticks_inside_wider_spans() {
+        assert_eq!(
+            render("## ``weird`name.rs``"),
+            "<h2><code>weird`name.rs</code></h2>\n"
+        );
+        assert_eq!(
+            render("## `` `tick.rs ``"),
+            "<h2><code>`tick.rs</code></h2>\n"
+        );
+        assert_eq!(
+            render("## `a|b<c>.rs`"),
+            "<h2><code>a|b&lt;c&gt;.rs</code></h2>\n"
+        );
+    }
+
+    #[test]
+    fn linked_headings_and_unclosed_spans() {
+        assert_eq!(
+            render("## [``x`y.rs``](https://example.com/x%60y.rs)"),
+            "<h2><a href=\"https://example.com/x%60y.rs\"><code>x`y.rs</code></a></h2>\n"
+        );
+        // A run with no partner of the same length stays literal
+        assert_eq!(render("``open `"), "<p>``open `</p>\n");
+    }
+}
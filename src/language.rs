@@ -0,0 +1,551 @@
+// src/language.rs
+use std::path::Path;
+
+/// Markdown code-fence language tag for `path` with content `text`: the tag
+/// for its extension, or when that gives none, a guess from the content.
+///
+/// The content checks are deliberately conservative, since a wrong tag is
+/// worse than none: JSON objects and arrays that parse, XML with an
+/// `<?xml` declaration, INI files made only of `[section]` headers and
+/// `key = value` lines, and YAML made only of `key: value` and `- item`
+/// lines.
+///
+/// ```
+/// use sourceweaver::detect_language;
+/// use std::path::Path;
+///
+/// let tag = |name: &str, text: &str| detect_language(Path::new(name), text).to_string();
+/// // The extension wins over the content
+/// assert_eq!(tag("notes.md", "{\"a\": 1}"), "markdown");
+/// assert_eq!(tag("data", "{\"name\": \"weaver\", \"tags\": [1, 2]}"), "json");
+/// assert_eq!(tag("list", "[1, 2, 3]\n"), "json");
+/// assert_eq!(tag("broken", "{\"name\": "), "");
+/// assert_eq!(tag("answer", "42"), "");
+/// assert_eq!(tag("pom", "<?xml version=\"1.0\"?>\n<project/>"), "xml");
+/// assert_eq!(tag("page", "<html></html>"), "");
+/// assert_eq!(tag("gitconfig", "[core]\n\tbare = false\n; comment\n[user]\nname = me\n"), "ini");
+/// assert_eq!(tag("settings", "name = me\n[core]\n"), "");
+/// assert_eq!(tag("compose", "---\nservices:\n  web:\n    image: nginx\n    ports:\n      - 80:80\n"), "yaml");
+/// assert_eq!(tag("config", "# comment\nname: weaver\nversion: 2\n"), "yaml");
+/// assert_eq!(tag("CHANGELOG", "Changes:\nFixed the parser.\n"), "");
+/// assert_eq!(tag("NOTE", "Note: read me\n"), "");
+/// assert_eq!(tag("script", "if x: y\nfoo()\n"), "");
+/// assert_eq!(tag("empty", ""), "");
+/// ```
+///
+/// Extensions shared by several languages are settled by the content too:
+/// `.h` is C++ rather than C when it declares classes, templates, or
+/// namespaces, `.m` is Objective-C or MATLAB, and `.pl` is Prolog rather
+/// than Perl when it has `:-` clauses.
+///
+/// ```
+/// use sourceweaver::detect_language;
+/// use std::path::Path;
+///
+/// let tag = |name: &str, text: &str| detect_language(Path::new(name), text).to_string();
+/// let c_header = "#ifndef BUF_H\n#define BUF_H\nstruct buf { char *data; };\nvoid buf_free(struct buf *b);\n#endif\n";
+/// let cpp_header = "#pragma once\nnamespace net {\nclass Socket {\npublic:\n  void close();\n};\n}\n";
+/// let cpp_template = "#pragma once\ntemplate <typename T>\nT max(T a, T b);\n";
+/// assert_eq!(tag("buf.h", c_header), "c");
+/// assert_eq!(tag("socket.h", cpp_header), "cpp");
+/// assert_eq!(tag("max.h", cpp_template), "cpp");
+/// // A C comment mentioning a class doesn't count
+/// assert_eq!(tag("doc.h", "/* the class of errors */\nint err;\n"), "c");
+///
+/// let objc = "#import <Foundation/Foundation.h>\n@implementation Greeter\n- (void)greet {}\n@end\n";
+/// let matlab = "% Solve the system\nfunction x = solve(A, b)\n  x = A \\ b;\nend\n";
+/// assert_eq!(tag("Greeter.m", objc), "objectivec");
+/// assert_eq!(tag("solve.m", matlab), "matlab");
+/// assert_eq!(tag("unknown.m", "x = 1;\n"), "");
+///
+/// assert_eq!(tag("family.pl", "parent(tom, bob).\nancestor(X, Y) :- parent(X, Y).\n"), "prolog");
+/// assert_eq!(tag("hello.pl", "use strict;\nmy $name = 'world';\nprint \"hello $name\\n\";\n"), "perl");
+/// ```
+pub fn detect_language<'a>(path: &'a Path, text: &str) -> &'a str {
+    match lookup(path) {
+        Some(Lookup::Tag(tag)) => tag,
+        Some(Lookup::Ambiguous(ambiguous)) => ambiguous.resolve(text),
+        None => content_language(text).unwrap_or(""),
+    }
+}
+
+// A language tag for `text` from its structure alone, if it is unmistakable
+fn content_language(text: &str) -> Option<&'static str> {
+    let trimmed = text.trim_start_matches('\u{feff}').trim();
+    if trimmed.starts_with("<?xml") {
+        Some("xml")
+    } else if is_json(trimmed) {
+        Some("json")
+    } else if is_ini(trimmed) {
+        Some("ini")
+    } else if is_yaml(trimmed) {
+        Some("yaml")
+    } else {
+        None
+    }
+}
+
+// Only objects and arrays: a bare number or string parses too, but says little
+fn is_json(text: &str) -> bool {
+    (text.starts_with('{') || text.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+// The lines that carry content: no blanks, no comments
+fn content_lines<'t>(text: &'t str, comments: &'t [char]) -> impl Iterator<Item = &'t str> {
+    text.lines()
+        .map(str::trim)
+        .filter(move |line| !line.is_empty() && !line.starts_with(comments))
+}
+
+// A section header first, then only headers and `key = value` lines
+fn is_ini(text: &str) -> bool {
+    let is_header = |line: &str| {
+        line.len() > 2 && line.starts_with('[') && line.ends_with(']') && !line.contains('=')
+    };
+    let is_entry = |line: &str| {
+        line.split_once('=').is_some_and(|(key, _)| {
+            let key = key.trim();
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' '))
+        })
+    };
+    let mut lines = content_lines(text, &[';', '#']);
+    lines.next().is_some_and(is_header) && lines.all(|line| is_header(line) || is_entry(line))
+}
+
+// At least two lines, starting with an unindented key, and nothing but
+// `key: value`, `key:`, and `- item` lines after an optional `---`
+fn is_yaml(text: &str) -> bool {
+    let is_key = |line: &str| {
+        let line = line.strip_prefix("- ").unwrap_or(line).trim_start();
+        line.split_once(':').is_some_and(|(key, value)| {
+            !key.is_empty()
+                && (value.is_empty() || value.starts_with(' '))
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '"' | '\''))
+        })
+    };
+    let is_item = |line: &str| line.trim_start().starts_with("- ") || line.trim() == "-";
+    let text = text.strip_prefix("---").unwrap_or(text);
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect();
+    lines.len() >= 2
+        && lines[0].starts_with(|c: char| !c.is_whitespace())
+        && is_key(lines[0])
+        && lines.iter().all(|line| is_key(line) || is_item(line))
+}
+
+/// Markdown code-fence language tag for `path`, based on its extension.
+///
+/// A compound suffix such as `.d.ts` or `.html.erb` takes precedence over
+/// the final extension when it is known. Returns an empty string when the
+/// extension is unknown or missing. Use [`detect_language`] when the
+/// content is at hand.
+///
+/// ```
+/// use sourceweaver::get_language_tag;
+/// use std::path::Path;
+///
+/// assert_eq!(get_language_tag(Path::new("types.d.ts")), "typescript");
+/// assert_eq!(get_language_tag(Path::new("show.html.erb")), "erb");
+/// assert_eq!(get_language_tag(Path::new("index.blade.php")), "blade");
+/// assert_eq!(get_language_tag(Path::new("main.tf.json")), "json");
+/// // No compound entry: the final extension decides
+/// assert_eq!(get_language_tag(Path::new("component.test.tsx")), "tsx");
+/// assert_eq!(get_language_tag(Path::new("jquery.min.js")), "javascript");
+/// // A dotfile's name isn't a suffix
+/// assert_eq!(get_language_tag(Path::new(".d.ts")), "typescript");
+/// ```
+pub fn get_language_tag(path: &Path) -> &str {
+    match lookup(path) {
+        Some(Lookup::Tag(tag)) => tag,
+        Some(Lookup::Ambiguous(ambiguous)) => ambiguous.default_tag(),
+        None => "",
+    }
+}
+
+/// Whether [`detect_language`] needs the content of the file at `path`:
+/// its extension is unknown, missing, or shared by several languages.
+///
+/// ```
+/// use sourceweaver::language_needs_content;
+/// use std::path::Path;
+///
+/// assert!(!language_needs_content(Path::new("main.rs")));
+/// assert!(language_needs_content(Path::new("CONFIG")));
+/// assert!(language_needs_content(Path::new("socket.h")));
+/// ```
+pub fn language_needs_content(path: &Path) -> bool {
+    !matches!(lookup(path), Some(Lookup::Tag(_)))
+}
+
+// What the extension of `path` says about its language
+enum Lookup {
+    Tag(&'static str),
+    Ambiguous(Ambiguous),
+}
+
+// Extensions used by more than one language, settled by the content
+#[derive(Clone, Copy)]
+enum Ambiguous {
+    // C or C++ header
+    H,
+    // Objective-C or MATLAB
+    M,
+    // Perl or Prolog
+    Pl,
+}
+
+impl Ambiguous {
+    // The tag when the content isn't available
+    fn default_tag(self) -> &'static str {
+        match self {
+            Ambiguous::H => "c",
+            Ambiguous::M => "",
+            Ambiguous::Pl => "perl",
+        }
+    }
+
+    fn resolve(self, text: &str) -> &'static str {
+        let code = || {
+            text.lines()
+                .map(str::trim_start)
+                .filter(|line| !is_comment(line))
+        };
+        match self {
+            Ambiguous::H => {
+                let cpp = code().any(|line| {
+                    line.starts_with("class ")
+                        || line.starts_with("template<")
+                        || line.starts_with("template <")
+                        || line.starts_with("namespace ")
+                });
+                if cpp { "cpp" } else { "c" }
+            }
+            Ambiguous::M => {
+                if code().any(|line| {
+                    line.starts_with("@interface")
+                        || line.starts_with("@implementation")
+                        || line.starts_with("#import ")
+                }) {
+                    "objectivec"
+                } else if text
+                    .lines()
+                    .any(|line| line.trim_start().starts_with("function "))
+                    && text.lines().any(|line| line.trim() == "end")
+                    || text.lines().any(|line| line.trim_start().starts_with('%'))
+                {
+                    "matlab"
+                } else {
+                    ""
+                }
+            }
+            Ambiguous::Pl => {
+                // Perl comments start with '#', Prolog's with '%'
+                let prolog = code().filter(|line| !line.starts_with('#')).any(|line| {
+                    line.starts_with(":-") || line.trim_end().ends_with('.') && line.contains(":-")
+                });
+                if prolog { "prolog" } else { "perl" }
+            }
+        }
+    }
+}
+
+// Comment lines in the C family, skipped when looking for keywords so prose
+// about a "class" doesn't count
+fn is_comment(line: &str) -> bool {
+    line.starts_with("//") || line.starts_with("/*") || line.starts_with('*')
+}
+
+// Tags for suffixes spanning the last two extensions, preferred over the
+// tag for the final extension alone. `.tf.json` is Terraform's JSON syntax,
+// tagged `json` because that is what highlighters can render.
+const COMPOUND_EXTENSIONS: &[(&str, &str)] = &[
+    ("d.ts", "typescript"),
+    ("d.mts", "typescript"),
+    ("d.cts", "typescript"),
+    ("blade.php", "blade"),
+    ("html.erb", "erb"),
+    ("tf.json", "json"),
+];
+
+fn lookup(path: &Path) -> Option<Lookup> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    // Only a file name with a stem before both suffixes has a compound one
+    if let Some(compound) = path
+        .file_stem()
+        .and_then(|stem| Path::new(stem).extension())
+        .and_then(|inner| inner.to_str())
+        .map(|inner| format!("{}.{}", inner.to_lowercase(), ext))
+        && let Some((_, tag)) = COMPOUND_EXTENSIONS
+            .iter()
+            .find(|(suffix, _)| *suffix == compound)
+    {
+        return Some(Lookup::Tag(tag));
+    }
+    let tag = match ext.as_str() {
+        "h" => return Some(Lookup::Ambiguous(Ambiguous::H)),
+        "m" => return Some(Lookup::Ambiguous(Ambiguous::M)),
+        "pl" => return Some(Lookup::Ambiguous(Ambiguous::Pl)),
+        "rs" => "rust",
+        "py" | "pyw" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "mts" | "cts" => "typescript",
+        "jsx" => "jsx",
+        "tsx" => "tsx",
+        "java" => "java",
+        "c" => "c",
+        "cpp" | "hpp" | "cxx" | "hxx" | "cc" | "hh" => "cpp",
+        "cs" => "csharp",
+        "go" => "go",
+        "php" => "php",
+        "rb" => "ruby",
+        "swift" => "swift",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "pm" => "perl",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" => "powershell",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "less" => "less",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "md" | "markdown" => "markdown",
+        "sql" => "sql",
+        "xml" => "xml",
+        "dockerfile" | "containerfile" => "dockerfile",
+        "nix" => "nix",
+        "lua" => "lua",
+        "r" => "r",
+        "dart" => "dart",
+        "ex" | "exs" => "elixir",
+        "erl" | "hrl" => "erlang",
+        "hs" => "haskell",
+        "clj" | "cljs" | "cljc" | "edn" => "clojure",
+        "groovy" | "gradle" => "groovy",
+        "tf" => "terraform",
+        "vue" => "vue",
+        "erb" => "erb",
+        "svelte" => "svelte",
+        "tex" => "latex",
+        "zig" => "zig",
+        _ => return None,
+    };
+    Some(Lookup::Tag(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small extensionless files, each with the tag it should get. Where a
+    // guess could go either way the expectation is no tag at all.
+    const CORPUS: &[(&str, &str, Option<&str>)] = &[
+        // JSON
+        (
+            "object",
+            "{\"name\": \"weaver\", \"version\": 2}\n",
+            Some("json"),
+        ),
+        (
+            "nested",
+            "{\n  \"a\": {\"b\": [true, null]}\n}\n",
+            Some("json"),
+        ),
+        ("strings", "[\"a\", \"b\"]", Some("json")),
+        ("bom", "\u{feff}{\"a\": 1}", Some("json")),
+        ("empty object", "{}", Some("json")),
+        ("scalar", "\"just a string\"", None),
+        ("number", "3.14", None),
+        ("trailing comma", "{\"a\": 1,}", None),
+        ("two documents", "{\"a\": 1}\n{\"b\": 2}\n", None),
+        ("js object", "{ name: 'weaver' }", None),
+        // XML
+        (
+            "declaration",
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<a/>",
+            Some("xml"),
+        ),
+        (
+            "indented",
+            "\n  <?xml version=\"1.0\"?><feed/>",
+            Some("xml"),
+        ),
+        ("bare tags", "<config><a>1</a></config>", None),
+        ("doctype", "<!DOCTYPE html>\n<html></html>", None),
+        // INI
+        (
+            "sections",
+            "[core]\nbare = false\n\n[remote \"origin\"]\nurl = x\n",
+            Some("ini"),
+        ),
+        (
+            "comments",
+            "; settings\n# more\n[server]\nport = 8080\n",
+            Some("ini"),
+        ),
+        ("headers only", "[a]\n[b]\n", Some("ini")),
+        ("no header", "port = 8080\nhost = example.com\n", None),
+        ("markdown link", "[link]\nSee the docs for more.\n", None),
+        ("empty header", "[]\nkey = value\n", None),
+        // YAML
+        ("mapping", "name: weaver\nversion: 2\n", Some("yaml")),
+        ("document", "---\nitems:\n  - one\n  - two\n", Some("yaml")),
+        (
+            "list of maps",
+            "steps:\n  - name: build\n    run: cargo build\n",
+            Some("yaml"),
+        ),
+        ("one line", "name: weaver\n", None),
+        ("leading list", "- one\n- two\n", None),
+        (
+            "uniformly indented",
+            "  name: weaver\n  version: 2\n",
+            Some("yaml"),
+        ),
+        (
+            "prose",
+            "Changes:\nFixed the parser.\nThanks: everyone\n",
+            None,
+        ),
+        ("url", "http://example.com\nhttps://example.org\n", None),
+        ("python", "def main():\n    return 1\n", None),
+        ("makefile", "all: build\n\tcargo build --release\n", None),
+        ("css", "body {\n  color: red;\n}\n", None),
+        // Nothing recognizable
+        ("empty", "", None),
+        ("blank", "\n\n  \n", None),
+        ("shell", "set -e\necho \"hello\"\n", None),
+        ("changelog", "## 1.0.0\n\n- First release\n", None),
+    ];
+
+    #[test]
+    fn content_corpus() {
+        for (name, text, expected) in CORPUS {
+            assert_eq!(content_language(text), *expected, "{}: {:?}", name, text);
+        }
+    }
+
+    #[test]
+    fn content_only_decides_without_a_known_extension() {
+        let (_, json, _) = CORPUS[0];
+        assert_eq!(detect_language(Path::new("data"), json), "json");
+        assert_eq!(detect_language(Path::new("data.md"), json), "markdown");
+        assert_eq!(detect_language(Path::new("data.unknown"), json), "json");
+        assert!(!language_needs_content(Path::new("data.md")));
+        assert!(language_needs_content(Path::new("data")));
+    }
+
+    const C_HEADER: &str = r#"/*
+ * ring.h - a fixed-size ring buffer
+ *
+ * Not a class, just a struct and the functions working on it.
+ */
+#ifndef RING_H
+#define RING_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ring {
+    unsigned char *data;
+    size_t head, tail, cap;
+} ring_t;
+
+int ring_push(ring_t *r, unsigned char byte);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RING_H */
+"#;
+
+    const CPP_HEADER: &str = r#"// socket.h
+#pragma once
+
+#include <string>
+
+namespace net {
+
+class Socket : public Stream {
+public:
+    explicit Socket(std::string host);
+    ~Socket() override;
+};
+
+}  // namespace net
+"#;
+
+    const CPP_TEMPLATE_HEADER: &str = r#"#ifndef MATH_CLAMP_H
+#define MATH_CLAMP_H
+
+template<typename T>
+constexpr T clamp(T value, T low, T high) {
+    return value < low ? low : value > high ? high : value;
+}
+
+#endif
+"#;
+
+    // Only forward-declares a class, nested in an include guard
+    const CPP_FORWARD_HEADER: &str = "#ifndef FWD_H\n#define FWD_H\n  class Widget;\n#endif\n";
+
+    #[test]
+    fn header_samples() {
+        for (sample, expected) in [
+            (C_HEADER, "c"),
+            (CPP_HEADER, "cpp"),
+            (CPP_TEMPLATE_HEADER, "cpp"),
+            (CPP_FORWARD_HEADER, "cpp"),
+            ("", "c"),
+        ] {
+            assert_eq!(
+                detect_language(Path::new("include/lib.h"), sample),
+                expected,
+                "{}",
+                sample
+            );
+        }
+        // The extension is matched without regard to case
+        assert_eq!(detect_language(Path::new("LIB.H"), CPP_HEADER), "cpp");
+        // Unambiguous C++ extensions never look at the content
+        assert_eq!(detect_language(Path::new("ring.hpp"), C_HEADER), "cpp");
+        assert!(!language_needs_content(Path::new("ring.hpp")));
+        assert!(language_needs_content(Path::new("ring.h")));
+    }
+
+    #[test]
+    fn headers_without_content_default_to_c() {
+        assert_eq!(get_language_tag(Path::new("socket.h")), "c");
+        assert_eq!(get_language_tag(Path::new("solve.m")), "");
+        assert_eq!(get_language_tag(Path::new("script.pl")), "perl");
+    }
+
+    #[test]
+    fn objective_c_and_matlab_samples() {
+        let interface = "#import <UIKit/UIKit.h>\n\n@interface AppDelegate : UIResponder\n@end\n";
+        let script = "% Plot a sine wave\nx = linspace(0, 2*pi);\nplot(x, sin(x));\n";
+        let function = "function y = square(x)\n  y = x .^ 2;\nend\n";
+        for (sample, expected) in [
+            (interface, "objectivec"),
+            (script, "matlab"),
+            (function, "matlab"),
+            ("// @interface in a comment\nint x;\n", ""),
+        ] {
+            assert_eq!(detect_language(Path::new("file.m"), sample), expected);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// src/last_commit.rs
+use crate::{Result, WeaveError, display};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, BufReader},
+    path::Path,
+    process::{Command, Stdio},
+};
+use tracing::debug;
+
+/// The most recent commit touching each tracked file under the root, read
+/// once for the whole tree: `git ls-files` names the tracked files, and a
+/// single `git log --name-only` walks history from HEAD until each of them
+/// has turned up, rather than running git once per file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LastCommits {
+    // By path relative to the root, with `/` separators
+    commits: HashMap<String, LastCommit>,
+}
+
+#[derive(Debug, Clone)]
+struct LastCommit {
+    date: String,
+    sha: String,
+    author: String,
+}
+
+impl LastCommits {
+    /// Read the history of the repository containing `root`. Fails when
+    /// `root` isn't in a git repository or git can't be run.
+    pub(crate) fn load(root: &Path) -> Result<Self> {
+        // Paths are relative to the directory git runs in, as the walk's are
+        let dir = match root.parent() {
+            Some(parent) if root.is_file() => parent,
+            _ => root,
+        };
+        let invalid = |e: io::Error| {
+            WeaveError::InvalidOptions(format!(
+                "git info needs a git repository at {}: {}",
+                dir.display(),
+                e
+            ))
+        };
+
+        let tracked = git(dir, &["ls-files", "-z"]).map_err(invalid)?;
+        let mut pending: HashSet<String> = tracked
+            .split(|&byte| byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .collect();
+        let mut commits = HashMap::new();
+        if pending.is_empty() {
+            return Ok(LastCommits { commits });
+        }
+
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "log",
+                "--relative",
+                "--no-renames",
+                "--date=short",
+                "--format=%x01%h%x09%ad%x09%an",
+                "--name-only",
+                "-z",
+                "--",
+                ".",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(invalid)?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut reader = BufReader::new(stdout);
+
+        // Each commit is its header, then the paths it touched, all
+        // NUL-terminated; the first path follows a line break
+        let mut current: Option<LastCommit> = None;
+        let mut record = Vec::new();
+        loop {
+            record.clear();
+            if reader.read_until(0, &mut record).map_err(invalid)? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&record);
+            let text = text.trim_end_matches('\0');
+            if let Some(header) = text.strip_prefix('\u{1}') {
+                let mut fields = header.splitn(3, '\t');
+                current = Some(LastCommit {
+                    sha: fields.next().unwrap_or_default().to_string(),
+                    date: fields.next().unwrap_or_default().to_string(),
+                    author: fields.next().unwrap_or_default().to_string(),
+                });
+                continue;
+            }
+            let path = text.trim_start_matches('\n');
+            if let Some(commit) = &current
+                && pending.remove(path)
+            {
+                commits.insert(path.to_string(), commit.clone());
+                if pending.is_empty() {
+                    break;
+                }
+            }
+        }
+        // The rest of history isn't needed
+        let _ = child.kill();
+        let _ = child.wait();
+        debug!(
+            "Found the last commit of {} files, {} without one",
+            commits.len(),
+            pending.len()
+        );
+        Ok(LastCommits { commits })
+    }
+
+    /// The note for the file at `relative_path`, e.g. `Last commit:
+    /// 2024-05-01, a1b2c3d, Alice`, or `(untracked)` when git doesn't know
+    /// the file.
+    pub(crate) fn note(&self, relative_path: &Path) -> String {
+        match self.commits.get(&display::to_slash(relative_path)) {
+            Some(commit) => format!(
+                "Last commit: {}, {}, {}",
+                commit.date, commit.sha, commit.author
+            ),
+            None => "Last commit: (untracked)".to_string(),
+        }
+    }
+}
+
+/// Run `git` in `dir` and return its stdout.
+pub(crate) fn git(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,1143 @@
+// src/lib.rs
+//! Source Weaver bundles a codebase into a single Markdown document.
+//!
+//! The walk respects `.gitignore` rules, skips lock files, and emits every
+//! remaining file under a heading with its relative path, inside a fenced code
+//! block tagged with its language. The root may also be a zip or tar archive,
+//! which is read in place without extracting it.
+//!
+//! ```
+//! use sourceweaver::{generate_markdown, WeaveOptions};
+//!
+//! let options = WeaveOptions::new("src").build()?;
+//! let mut document = Vec::new();
+//! generate_markdown(&mut document, &options)?;
+//!
+//! let document = String::from_utf8(document).unwrap();
+//! assert!(document.contains("## `lib.rs`"));
+//! # Ok::<(), sourceweaver::WeaveError>(())
+//! ```
+//!
+//! Failures are reported as [`WeaveError`]. Per-entry problems (unreadable
+//! files, inaccessible directories) do not abort the run; they are collected
+//! in the [`WeaveReport`] returned by [`generate_markdown`].
+//!
+//! The library never prints. Warnings are emitted as [`tracing`] events, so
+//! embedding applications can route or silence them with their own subscriber.
+
+mod annotations;
+mod archive;
+mod binary;
+mod blame;
+mod budget;
+mod cache;
+mod codeowners;
+mod collapse;
+mod content_filter;
+mod display;
+mod editorconfig;
+mod elide;
+mod error;
+mod explain;
+mod file_list;
+mod language;
+mod last_commit;
+mod lockfiles;
+mod omitted;
+mod options;
+mod parallel;
+mod patterns;
+mod read;
+mod report;
+mod scope;
+mod sentinels;
+mod stream;
+mod submodule;
+mod symbols;
+mod tabs;
+mod tokens;
+mod truncate;
+mod unicode_tricks;
+mod visitor;
+mod walk;
+
+pub use binary::{BINARY_EXTENSIONS, has_binary_extension};
+pub use codeowners::{CODEOWNERS_LOCATIONS, CodeOwners};
+pub use collapse::{CollapseData, DEFAULT_COLLAPSE_MIN_BASE64_LEN, DEFAULT_COLLAPSE_MIN_LINES};
+pub use error::{Result, WeaveError};
+pub use explain::{Explanation, StageVerdict, explain, explain_matching};
+pub use language::{detect_language, get_language_tag, language_needs_content};
+pub use lockfiles::{LOCK_FILES, LOCK_FILES_CASE_INSENSITIVE, is_lock_file};
+pub use options::{
+    Checksums, DEFAULT_MAX_THREADS, ErrorPlaceholders, OmittedFiles, Submodules, TruncateStrategy,
+    UnicodeTricks, WeaveOptions, WeaveOptionsBuilder,
+};
+pub use patterns::Pattern;
+pub use read::MMAP_THRESHOLD;
+pub use report::{FileOutcome, SkipLog, SkipReason, Timings, WeaveReport};
+pub use sentinels::Sentinel;
+pub use stream::STREAM_THRESHOLD;
+pub use symbols::{Symbol, SymbolIndex, extract_symbols};
+pub use tabs::{DEFAULT_TAB_WIDTH, ExpandTabs, expand_tabs};
+pub use tokens::estimate_tokens;
+pub use truncate::TruncateLimit;
+pub use unicode_tricks::{TrickKind, UnicodeFinding, escape_unicode_tricks, find_unicode_tricks};
+pub use visitor::{Action, FileContent, FileVisitor};
+pub use walk::{
+    CollectedFiles, FilterStage, WalkedFile, build_walker, build_walker_with_log, collect_files,
+    filter_stages,
+};
+
+use budget::Rendered;
+use cache::{Digest, RenderCache};
+use content_filter::ContentFilter;
+use content_inspector::ContentType;
+use read::{FileData, read_file};
+use sha2::{Digest as _, Sha256};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+use truncate::Truncations;
+use unicode_tricks::TrickLog;
+
+/// Walk `options.root` and write the Markdown document to `writer`.
+///
+/// Entries the walker cannot access and files that cannot be read are
+/// logged as `tracing` warnings, skipped, and collected in the returned
+/// [`WeaveReport`], so callers can decide whether a partial run is
+/// acceptable. Only errors writing to `writer` abort the run, unless
+/// [`fail_fast`](WeaveOptionsBuilder::fail_fast) is set, in which case the
+/// first walk or read error is returned and the document is left incomplete.
+pub fn generate_markdown<W: Write + ?Sized>(
+    writer: &mut W,
+    options: &WeaveOptions,
+) -> Result<WeaveReport> {
+    generate_markdown_with(writer, options, &mut EmitAll)
+}
+
+/// Like [`generate_markdown`], but passes every file through `visitor`
+/// between reading it and writing its section.
+pub fn generate_markdown_with<W: Write + ?Sized>(
+    writer: &mut W,
+    options: &WeaveOptions,
+    visitor: &mut dyn FileVisitor,
+) -> Result<WeaveReport> {
+    if archive::is_archive(options.root()) {
+        return write_archive(writer, options, visitor);
+    }
+    write_collected(writer, options, collect_files(options), visitor)
+}
+
+// Write the document for an archive given as the root
+fn write_archive<W: Write + ?Sized>(
+    writer: &mut W,
+    options: &WeaveOptions,
+    visitor: &mut dyn FileVisitor,
+) -> Result<WeaveReport> {
+    let mut report = WeaveReport::default();
+    let started = Instant::now();
+    let contents = archive::read_archive(options)?;
+    report.timings.read = started.elapsed();
+    if options.fail_if_empty && contents.files.is_empty() {
+        return Err(WeaveError::NoFiles { dirs_visited: 0 });
+    }
+    report.skipped.extend(contents.skipped);
+    visitor.on_start(contents.files.len());
+
+    let mut visitor = Timed::new(visitor);
+    let mut rendered = options.budget.map(|_| Vec::new());
+    let mut cap = OutputCap::new(options.max_output);
+    let tricks = TrickLog::of(options);
+    let started = Instant::now();
+    for (path, data) in contents.files {
+        let heading = options.heading(&path);
+        let notes = options.heading_notes(&path);
+        let sentinel = options.sentinels.then(|| options.display_path(&path));
+        emit_section(
+            writer,
+            &mut rendered,
+            &mut cap,
+            &mut report,
+            options,
+            &path,
+            |target| {
+                write_section(
+                    target,
+                    &path,
+                    &path,
+                    &heading,
+                    notes.as_deref(),
+                    Ok(FileData::Owned(data)),
+                    SectionOptions {
+                        tricks: tricks.as_ref(),
+                        ..SectionOptions::of(options, sentinel.as_deref())
+                    },
+                    &mut visitor,
+                )
+            },
+        )?;
+    }
+    report.timings.transform = visitor.elapsed;
+    report.timings.write = started.elapsed().saturating_sub(visitor.elapsed);
+    if let Some(rendered) = rendered {
+        write_within_budget(writer, options, &mut cap, &mut report, rendered)?;
+    }
+    write_truncation_note(writer, &report)?;
+    omitted::write_omitted(writer, options, &report)?;
+    if let Some(tricks) = tricks {
+        report.unicode_tricks = tricks.written(&report);
+    }
+    Ok(report)
+}
+
+/// Write the document for files already selected by [`collect_files`].
+///
+/// Useful for running one walk and writing several documents from it, e.g.
+/// one per subset of `collected.files`. Walk errors and skips in `collected`
+/// end up in the returned report.
+pub fn write_collected<W: Write + ?Sized>(
+    writer: &mut W,
+    options: &WeaveOptions,
+    mut collected: CollectedFiles,
+    visitor: &mut dyn FileVisitor,
+) -> Result<WeaveReport> {
+    let mut report = WeaveReport::default();
+
+    if options.fail_fast && !collected.errors.is_empty() {
+        return Err(collected.errors.swap_remove(0));
+    }
+    if options.fail_if_empty && collected.files.is_empty() {
+        return Err(WeaveError::NoFiles {
+            dirs_visited: collected.dirs_visited,
+        });
+    }
+    report.dirs_visited = collected.dirs_visited;
+    report.timings.walk = collected.walk_time;
+    report.errors.extend(collected.errors);
+    report.skipped.extend(collected.skipped);
+    visitor.on_start(collected.files.len());
+    let mut visitor = Timed::new(visitor);
+
+    // With a token budget, sections are rendered to memory first, so the
+    // files to leave out can be chosen before anything is written
+    let mut rendered = options.budget.map(|_| Vec::new());
+    let mut cap = OutputCap::new(options.max_output);
+
+    // Placeholders for uninitialized submodules are slotted in by path order
+    let mut submodules = collected.uninitialized_submodules.iter().peekable();
+    let tricks = TrickLog::of(options);
+    // Files the cache shows unchanged aren't read at all, so the cache is
+    // off while every file's text is scanned
+    let cache = options.cache.as_ref().filter(|_| tricks.is_none());
+    let cache = cache.map(|(path, key)| {
+        // Checksum, sentinel, and blame lines are part of the cached
+        // sections, and content filters decide which files have one
+        let mut key = match options.checksums {
+            Some(checksums) => format!("{} checksums={:?}", key, checksums),
+            None => key.clone(),
+        };
+        if options.sentinels {
+            key.push_str(" sentinels");
+        }
+        for re in &options.content_regexes {
+            key.push_str(&format!(" exclude-content={:?}", re));
+        }
+        for re in &options.grep_regexes {
+            key.push_str(&format!(" grep={:?}", re));
+        }
+        if let Some(context) = options.grep_context {
+            key.push_str(&format!(" grep-context={}", context));
+        }
+        for (glob, limit) in &options.truncations {
+            key.push_str(&format!(
+                " truncate={:?}={:?},{:?}",
+                glob, limit.lines, limit.bytes
+            ));
+        }
+        if options.truncate_strategy != TruncateStrategy::Tail {
+            key.push_str(&format!(
+                " truncate-strategy={:?}",
+                options.truncate_strategy
+            ));
+        }
+        if options.blame.is_some() {
+            key.push_str(" blame");
+            for glob in &options.blame_skip {
+                key.push_str(&format!(" skip-blame={:?}", glob));
+            }
+        }
+        RenderCache::load(path, &key, options.cache_trust_mtime, &options.root)
+    });
+    let streams = stream::allowed(options);
+    // Checksums hash binary files too, so they need the content
+    let unread = options.extension_shortcut && options.checksums.is_none();
+    // Blamed on the reader threads, and only when the file is read
+    let read = |file: &WalkedFile| {
+        let content = read_file(&file.path);
+        let blame = match (&options.blame, &content) {
+            (Some(blame), Ok(data)) => blame.summary(file, data),
+            _ => None,
+        };
+        (content, blame)
+    };
+    let load_file = |file: &WalkedFile| match &cache {
+        _ if unread && binary::has_binary_extension(&file.relative_path) => Loaded::Binary,
+        Some(cache) => {
+            let heading = options.file_heading(file);
+            let notes = options.heading_notes(&file.relative_path);
+            match cache.unchanged(file, &heading, notes.as_deref()) {
+                Some(entry) => Loaded::Cached(entry.clone()),
+                None => {
+                    let mtime = cache::modified(&file.path);
+                    let (content, blame) = read(file);
+                    Loaded::Read(content, mtime, blame)
+                }
+            }
+        }
+        None if streams && stream::is_large(&file.path) => Loaded::Large,
+        None => {
+            let (content, blame) = read(file);
+            Loaded::Read(content, None, blame)
+        }
+    };
+    // Summed over the reader threads
+    let read_nanos = AtomicU64::new(0);
+    let load = |file: &WalkedFile| {
+        let started = Instant::now();
+        let loaded = load_file(file);
+        read_nanos.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        loaded
+    };
+    let mut emitting = Duration::ZERO;
+
+    let mut emit = |file: &WalkedFile, loaded: Loaded| {
+        while let Some(submodule) = submodules.next_if(|s| **s < file.relative_path) {
+            write_submodule_note(
+                writer,
+                &mut rendered,
+                &mut cap,
+                options,
+                &mut report,
+                submodule,
+            )?;
+        }
+        let heading = options.file_heading(file);
+        let notes = options.heading_notes(&file.relative_path);
+        let sentinel = options.sentinels.then(|| options.display_file(file));
+        let started = Instant::now();
+        let visitor: &mut dyn FileVisitor = &mut visitor;
+        let emitted = emit_section(
+            writer,
+            &mut rendered,
+            &mut cap,
+            &mut report,
+            options,
+            &file.relative_path,
+            |target| {
+                let section = SectionOptions {
+                    tricks: tricks.as_ref(),
+                    ..SectionOptions::of(options, sentinel.as_deref())
+                };
+                let (content, mtime, blame) = match loaded {
+                    Loaded::Cached(entry) => {
+                        return write_cached(target, cache.as_ref(), file, entry, visitor);
+                    }
+                    Loaded::Read(content, mtime, blame) => (content, mtime, blame),
+                    // Visitors that need the content get the file read whole
+                    Loaded::Large if visitor.streams(&file.relative_path) => {
+                        return stream::write_streamed(
+                            target,
+                            &file.relative_path,
+                            &file.path,
+                            &heading,
+                            notes.as_deref(),
+                            section,
+                            visitor,
+                        );
+                    }
+                    Loaded::Binary if visitor.streams(&file.relative_path) => {
+                        return binary::write_unread(
+                            target,
+                            &file.relative_path,
+                            &file.path,
+                            &heading,
+                            notes.as_deref(),
+                            section,
+                            visitor,
+                        );
+                    }
+                    Loaded::Large | Loaded::Binary => {
+                        let mtime = cache.as_ref().and_then(|_| cache::modified(&file.path));
+                        let (content, blame) = read(file);
+                        (content, mtime, blame)
+                    }
+                };
+                let write = |target: &mut dyn Write, content, visitor: &mut dyn FileVisitor| {
+                    write_section(
+                        target,
+                        &file.relative_path,
+                        &file.path,
+                        &heading,
+                        notes.as_deref(),
+                        content,
+                        SectionOptions {
+                            blame: blame.as_deref(),
+                            ..section
+                        },
+                        visitor,
+                    )
+                };
+                let Some(cache) = &cache else {
+                    return write(target, content, visitor);
+                };
+
+                // Same content as last time: reuse the section, visitors and all
+                let digest = content.as_deref().ok().map(Digest::of);
+                if let Some(entry) = digest
+                    .and_then(|digest| cache.same_content(file, &heading, notes.as_deref(), digest))
+                {
+                    return write_cached(target, Some(cache), file, entry.clone(), visitor);
+                }
+                let mut section = Vec::new();
+                let outcome = write(&mut section, content, visitor);
+                if let (Ok(outcome), Some(digest), Some(mtime)) = (&outcome, digest, mtime) {
+                    cache.store(
+                        file,
+                        &heading,
+                        notes.as_deref(),
+                        (digest, mtime),
+                        outcome,
+                        &section,
+                    );
+                }
+                target.write_all(&section)?;
+                outcome
+            },
+        );
+        emitting += started.elapsed();
+        emitted
+    };
+
+    let threads = options.threads();
+    if threads > 1 && collected.files.len() > 1 {
+        // Read ahead on worker threads; sections are still written in order
+        parallel::read_ordered(&collected.files, threads, load, emit)?;
+    } else {
+        for file in &collected.files {
+            emit(file, load(file))?;
+        }
+    }
+    for submodule in submodules {
+        write_submodule_note(
+            writer,
+            &mut rendered,
+            &mut cap,
+            options,
+            &mut report,
+            submodule,
+        )?;
+    }
+    report.timings.read = Duration::from_nanos(read_nanos.into_inner());
+    report.timings.transform = visitor.elapsed;
+    report.timings.write = emitting.saturating_sub(visitor.elapsed);
+    if let Some(rendered) = rendered {
+        write_within_budget(writer, options, &mut cap, &mut report, rendered)?;
+    }
+    write_truncation_note(writer, &report)?;
+    omitted::write_omitted(writer, options, &report)?;
+    if let Some(cache) = cache {
+        cache.save();
+    }
+    if let Some(tricks) = tricks {
+        report.unicode_tricks = tricks.written(&report);
+    }
+
+    Ok(report)
+}
+
+// A file's content, read along with its modification time beforehand, or
+// its section reused from the render cache
+enum Loaded {
+    Cached(cache::Entry),
+    // The content, its modification time when cached, and its blame summary
+    Read(io::Result<FileData>, Option<u64>, Option<String>),
+    // A file large enough to stream, left unread until it is written
+    Large,
+    // A file whose extension names a binary format, never read if it
+    // can be helped
+    Binary,
+}
+
+// Write a section reused from the render cache, and keep it for next time
+fn write_cached(
+    target: &mut dyn Write,
+    cache: Option<&RenderCache>,
+    file: &WalkedFile,
+    entry: cache::Entry,
+    visitor: &mut dyn FileVisitor,
+) -> Result<FileOutcome> {
+    debug!(
+        "Reusing the cached section of {}",
+        file.relative_path.display()
+    );
+    visitor.on_reused(&file.relative_path, entry.size());
+    target.write_all(entry.section())?;
+    let outcome = entry.outcome();
+    if let Some(cache) = cache {
+        cache.keep(file, entry);
+    }
+    Ok(outcome)
+}
+
+// Write one file's section through `write`: straight to `writer`, or into
+// `rendered` while a token budget is in force. Under an output limit the
+// section is rendered to memory first, and only written if it fits.
+fn emit_section<W: Write + ?Sized>(
+    mut writer: &mut W,
+    rendered: &mut Option<Vec<Rendered>>,
+    cap: &mut OutputCap,
+    report: &mut WeaveReport,
+    options: &WeaveOptions,
+    relative_path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<FileOutcome>,
+) -> Result<()> {
+    match rendered {
+        Some(rendered) => {
+            let mut bytes = Vec::new();
+            let outcome = write(&mut bytes);
+            rendered.push(Rendered::Section {
+                path: relative_path.to_path_buf(),
+                bytes,
+                outcome,
+            });
+            Ok(())
+        }
+        None if cap.reached => {
+            report
+                .skipped
+                .push((relative_path.to_path_buf(), SkipReason::OverOutputLimit));
+            Ok(())
+        }
+        None if cap.limit.is_some() => {
+            let mut bytes = Vec::new();
+            let outcome = write(&mut bytes);
+            if !cap.admits(bytes.len() as u64) {
+                debug!(
+                    "Omitting {} and what follows to stay within the output limit",
+                    relative_path.display()
+                );
+                report
+                    .skipped
+                    .push((relative_path.to_path_buf(), SkipReason::OverOutputLimit));
+                return Ok(());
+            }
+            writer.write_all(&bytes)?;
+            record_section(report, options, relative_path, outcome, bytes.len() as u64)
+        }
+        None => {
+            let mut counted = Counted {
+                inner: &mut writer,
+                bytes: 0,
+            };
+            let section = write(&mut counted);
+            let bytes = counted.bytes;
+            record_section(report, options, relative_path, section, bytes)
+        }
+    }
+}
+
+// Write the sections rendered under a token budget, leaving out the files
+// the budget can't hold
+fn write_within_budget<W: Write + ?Sized>(
+    writer: &mut W,
+    options: &WeaveOptions,
+    cap: &mut OutputCap,
+    report: &mut WeaveReport,
+    rendered: Vec<Rendered>,
+) -> Result<()> {
+    let budget = options.budget.unwrap_or(u64::MAX);
+    let started = Instant::now();
+    let omitted: HashMap<usize, i64> =
+        budget::omissions(&rendered, budget, &options.priority_matcher)
+            .into_iter()
+            .collect();
+    report.timings.tokenize += started.elapsed();
+    let started = Instant::now();
+    for (index, item) in rendered.into_iter().enumerate() {
+        match item {
+            Rendered::Fixed(bytes) if cap.admits(bytes.len() as u64) => writer.write_all(&bytes)?,
+            Rendered::Fixed(_) => {}
+            Rendered::Section {
+                path,
+                bytes,
+                outcome,
+            } => match omitted.get(&index) {
+                Some(&priority) => {
+                    debug!("Omitting {} to fit the token budget", path.display());
+                    report
+                        .skipped
+                        .push((path, SkipReason::OverBudget { priority }));
+                }
+                None if !cap.admits(bytes.len() as u64) => {
+                    debug!(
+                        "Omitting {} to stay within the output limit",
+                        path.display()
+                    );
+                    report.skipped.push((path, SkipReason::OverOutputLimit));
+                }
+                None => {
+                    writer.write_all(&bytes)?;
+                    record_section(report, options, &path, outcome, bytes.len() as u64)?;
+                }
+            },
+        }
+    }
+    report.timings.write += started.elapsed();
+    Ok(())
+}
+
+// Write the placeholder section for a submodule that isn't checked out
+fn write_submodule_note<W: Write + ?Sized>(
+    writer: &mut W,
+    rendered: &mut Option<Vec<Rendered>>,
+    cap: &mut OutputCap,
+    options: &WeaveOptions,
+    report: &mut WeaveReport,
+    relative_path: &Path,
+) -> Result<()> {
+    let sentinel = options
+        .sentinels
+        .then(|| options.display_path(relative_path));
+    let mut note = Vec::new();
+    sentinels::write_begin(&mut note, sentinel.as_deref())?;
+    writeln!(
+        note,
+        "\n## {}\n\n```\n(Uninitialized git submodule, content not available)\n```",
+        options.heading(relative_path)
+    )?;
+    sentinels::write_end(&mut note, sentinel.as_deref())?;
+    match rendered {
+        Some(rendered) => rendered.push(Rendered::Fixed(note)),
+        None if cap.admits(note.len() as u64) => writer.write_all(&note)?,
+        None => {}
+    }
+    report.skipped.push((
+        relative_path.to_path_buf(),
+        SkipReason::UninitializedSubmodule,
+    ));
+    Ok(())
+}
+
+// End a document the output limit cut short with how many files it left out
+fn write_truncation_note<W: Write + ?Sized>(writer: &mut W, report: &WeaveReport) -> Result<()> {
+    let omitted = report.truncated_files().count();
+    if omitted > 0 {
+        writeln!(
+            writer,
+            "\n## Output truncated\n\n(Output truncated: {} additional files omitted)",
+            omitted
+        )?;
+    }
+    Ok(())
+}
+
+// Record the outcome of one section of `bytes` bytes, returning the errors
+// that end the run
+fn record_section(
+    report: &mut WeaveReport,
+    options: &WeaveOptions,
+    relative_path: &Path,
+    section: Result<FileOutcome>,
+    bytes: u64,
+) -> Result<()> {
+    if matches!(
+        section,
+        Ok(FileOutcome::Written | FileOutcome::Binary | FileOutcome::Changed)
+    ) {
+        report
+            .section_bytes
+            .push((relative_path.to_path_buf(), bytes));
+    }
+    match section {
+        Ok(FileOutcome::Written) => report.files_written += 1,
+        Ok(FileOutcome::Binary) => {
+            report.files_written += 1;
+            report.binary_files.push(relative_path.to_path_buf());
+        }
+        Ok(FileOutcome::Changed) => {
+            report.files_written += 1;
+            report.changed_files.push(relative_path.to_path_buf());
+        }
+        Ok(FileOutcome::Skipped(reason)) => report
+            .skipped
+            .push((relative_path.to_path_buf(), SkipReason::Visitor(reason))),
+        Ok(FileOutcome::ExcludedContent { pattern }) => report.skipped.push((
+            relative_path.to_path_buf(),
+            SkipReason::ExcludedContent { pattern },
+        )),
+        Ok(FileOutcome::NoContentMatch) => report
+            .skipped
+            .push((relative_path.to_path_buf(), SkipReason::NoContentMatch)),
+        Err(e) if !e.is_fatal() && !options.fail_fast => report.errors.push(e),
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// Write a single file section: a heading with `relative_path` followed by
+/// the content of `full_path` in a fenced code block.
+///
+/// Binary files get a placeholder instead of their content. Unreadable files
+/// get a [note](ErrorPlaceholders::Note) and return [`WeaveError::Read`], which is not
+/// fatal: the section is complete and the caller may carry on.
+///
+/// ```
+/// use std::path::Path;
+///
+/// let mut section = Vec::new();
+/// sourceweaver::process_file(&mut section, Path::new("Cargo.toml"), Path::new("Cargo.toml"))?;
+///
+/// let section = String::from_utf8(section).unwrap();
+/// assert!(section.starts_with("\n## `Cargo.toml`\n\n```toml\n"));
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+pub fn process_file<W: Write + ?Sized>(
+    writer: &mut W,
+    relative_path: &Path,
+    full_path: &Path,
+) -> Result<()> {
+    process_file_with(writer, relative_path, full_path, &mut EmitAll).map(|_| ())
+}
+
+/// Like [`process_file`], but for content already in memory, such as data
+/// read from stdin. The language tag comes from `relative_path`, and binary
+/// content gets the usual placeholder.
+///
+/// ```
+/// use std::path::Path;
+///
+/// let mut section = Vec::new();
+/// sourceweaver::process_bytes(&mut section, Path::new("src/foo.rs"), b"fn foo() {}".to_vec())?;
+///
+/// let section = String::from_utf8(section).unwrap();
+/// assert_eq!(section, "\n## `src/foo.rs`\n\n```rust\nfn foo() {}\n```\n");
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+pub fn process_bytes<W: Write + ?Sized>(
+    writer: &mut W,
+    relative_path: &Path,
+    content: Vec<u8>,
+) -> Result<FileOutcome> {
+    write_section(
+        writer,
+        relative_path,
+        relative_path,
+        &display::code_span(&display::to_slash(relative_path)),
+        None,
+        Ok(FileData::Owned(content)),
+        SectionOptions::default(),
+        &mut EmitAll,
+    )
+}
+
+/// Like [`process_file`], but lets `visitor` rewrite or skip the file after it
+/// has been read. Nothing is written for a skipped file.
+pub fn process_file_with<W: Write + ?Sized>(
+    writer: &mut W,
+    relative_path: &Path,
+    full_path: &Path,
+    visitor: &mut dyn FileVisitor,
+) -> Result<FileOutcome> {
+    write_section(
+        writer,
+        relative_path,
+        full_path,
+        &display::code_span(&display::to_slash(relative_path)),
+        None,
+        read_file(full_path),
+        SectionOptions::default(),
+        visitor,
+    )
+}
+
+// The options shaping each file section
+#[derive(Clone, Copy, Default)]
+struct SectionOptions<'a> {
+    // What an unreadable file gets instead
+    placeholder: ErrorPlaceholders,
+    // Whether the content's SHA-256 goes under the heading
+    checksums: Option<Checksums>,
+    // The path BEGIN and END lines name, when sections get sentinels
+    sentinel: Option<&'a str>,
+    // Regexes leaving out files by content
+    content_filter: Option<&'a ContentFilter>,
+    // Who wrote the file, noted under the heading
+    blame: Option<&'a str>,
+    // Where suspicious Unicode characters are recorded, if text is scanned
+    tricks: Option<&'a TrickLog>,
+    // The rules cutting files down to their first lines
+    truncations: Option<&'a Truncations>,
+}
+
+impl<'a> SectionOptions<'a> {
+    fn of(options: &'a WeaveOptions, sentinel: Option<&'a str>) -> Self {
+        SectionOptions {
+            placeholder: options.error_placeholders,
+            checksums: options.checksums,
+            sentinel,
+            content_filter: Some(&options.content_filter),
+            blame: None,
+            tricks: None,
+            truncations: Some(&options.truncation_matcher),
+        }
+    }
+}
+
+// Write the section for a file whose content has already been read, under
+// `heading` (the Markdown for the displayed path), followed by `notes` (the
+// Markdown for the annotation and last commit) and the rest as `section` says.
+#[allow(clippy::too_many_arguments)]
+fn write_section<W: Write + ?Sized>(
+    writer: &mut W,
+    relative_path: &Path,
+    full_path: &Path,
+    heading: &str,
+    notes: Option<&str>,
+    content: io::Result<FileData>,
+    section: SectionOptions<'_>,
+    visitor: &mut dyn FileVisitor,
+) -> Result<FileOutcome> {
+    let SectionOptions {
+        placeholder,
+        checksums,
+        sentinel,
+        content_filter,
+        blame,
+        tricks,
+        truncations,
+    } = section;
+    debug!("Processing {}", relative_path.display());
+    if relative_path.to_str().is_none() {
+        warn!(
+            path = %relative_path.display(),
+            "{} is not valid UTF-8, showing it percent-encoded as {}",
+            relative_path.display(),
+            display::to_slash(relative_path)
+        );
+    }
+
+    let content = match content {
+        // A mapped file that shrank since mapping must be re-read, not touched
+        Ok(content) if !content.is_intact(full_path) => {
+            debug!(
+                "{} changed size while mapped, re-reading",
+                full_path.display()
+            );
+            fs::read(full_path).map(FileData::Owned)
+        }
+        other => other,
+    };
+    let content = match content {
+        Ok(content) => content,
+        Err(e) => {
+            match placeholder {
+                ErrorPlaceholders::Inline => {
+                    sentinels::write_begin(writer, sentinel)?;
+                    writeln!(writer, "\n## {}\n", heading)?;
+                    writeln!(
+                        writer,
+                        "```\n(Error reading file: {})\n```",
+                        read::error_text(&e)
+                    )?;
+                    sentinels::write_end(writer, sentinel)?;
+                }
+                ErrorPlaceholders::Note => {
+                    sentinels::write_begin(writer, sentinel)?;
+                    writeln!(writer, "\n## {}\n", heading)?;
+                    writeln!(
+                        writer,
+                        "*Could not read this file: {}*",
+                        read::error_text(&e)
+                    )?;
+                    sentinels::write_end(writer, sentinel)?;
+                }
+                ErrorPlaceholders::Omit => {}
+            }
+            let shown = display::strip_verbatim(full_path.to_path_buf());
+            if e.kind() == io::ErrorKind::PermissionDenied {
+                debug!("Failed to read file {}: {}", shown.display(), e);
+            } else {
+                warn!(
+                    path = %relative_path.display(),
+                    "Failed to read file {}: {}",
+                    shown.display(),
+                    e
+                );
+            }
+            return Err(WeaveError::Read {
+                path: full_path.to_path_buf(),
+                source: e,
+            });
+        }
+    };
+
+    let content_type = content_inspector::inspect(&content);
+    let content_str = if content_type == ContentType::BINARY {
+        None
+    } else {
+        Some(String::from_utf8_lossy(&content))
+    };
+
+    if let Some(text) = &content_str
+        && let Some(pattern) = content_filter.and_then(|filter| filter.excluding(text))
+    {
+        if content_filter.is_some_and(|filter| filter.strict) {
+            return Err(WeaveError::ExcludedContent {
+                path: relative_path.to_path_buf(),
+                pattern: pattern.to_string(),
+            });
+        }
+        debug!(
+            "Skipping {}: its content matches '{}'",
+            relative_path.display(),
+            pattern
+        );
+        return Ok(FileOutcome::ExcludedContent {
+            pattern: pattern.to_string(),
+        });
+    }
+
+    // Scanned before grep cuts the text down, so the lines are the file's
+    let findings = match (tricks, &content_str) {
+        (Some(_), Some(text)) => find_unicode_tricks(relative_path, text),
+        _ => Vec::new(),
+    };
+
+    let content_str = match (content_filter, content_str) {
+        (Some(filter), content_str) if filter.greps() => {
+            let Some(text) = content_str.filter(|text| filter.selects(text)) else {
+                debug!("Skipping {}: no grep match", relative_path.display());
+                return Ok(FileOutcome::NoContentMatch);
+            };
+            if tracing::enabled!(tracing::Level::DEBUG) {
+                debug!(
+                    "{}: {} matching lines",
+                    relative_path.display(),
+                    filter.matching_lines(&text)
+                );
+            }
+            match filter.context {
+                Some(context) => Some(Cow::Owned(filter.excerpt(&text, context))),
+                None => Some(text),
+            }
+        }
+        (_, content_str) => content_str,
+    };
+    let content_str = match (tricks, content_str) {
+        (Some(tricks), Some(text)) if tricks.escape && !findings.is_empty() => {
+            Some(Cow::Owned(escape_unicode_tricks(&text).into_owned()))
+        }
+        (_, content_str) => content_str,
+    };
+    let content_str = match (truncations, content_str) {
+        (Some(truncations), Some(text)) => match truncations.apply(relative_path, &text) {
+            Some(head) => {
+                debug!("Truncated {}", relative_path.display());
+                Some(Cow::Owned(head))
+            }
+            None => Some(text),
+        },
+        (_, content_str) => content_str,
+    };
+
+    let file_content = match &content_str {
+        Some(text) => FileContent::Text(text),
+        None => FileContent::Binary(&content),
+    };
+    let modified = match visitor.on_file(relative_path, file_content) {
+        Action::Emit => None,
+        Action::EmitModified(text) => Some(text),
+        Action::Skip(reason) => {
+            debug!("Skipping {}: {}", relative_path.display(), reason);
+            return Ok(FileOutcome::Skipped(reason));
+        }
+    };
+    if let Some(tricks) = tricks {
+        tricks.record(findings);
+    }
+
+    sentinels::write_begin(writer, sentinel)?;
+    writeln!(writer, "\n## {}\n", heading)?;
+    if let Some(notes) = notes {
+        writeln!(writer, "{}\n", notes)?;
+    }
+    if let Some(blame) = blame {
+        writeln!(writer, "{}\n", annotations::italic(blame))?;
+    }
+    if let Some(checksums) = checksums {
+        let digest = match (checksums, modified.as_deref().or(content_str.as_deref())) {
+            (Checksums::PostTransform, Some(text)) => {
+                // The lines as the code block below writes them
+                let mut hasher = Sha256::new();
+                for line in text.lines() {
+                    hasher.update(line);
+                    hasher.update("\n");
+                }
+                hasher.finalize()
+            }
+            _ => Sha256::digest(&content[..]),
+        };
+        writeln!(writer, "`sha256:{}`\n", hex(&digest))?;
+    }
+    let changed = matches!(content, FileData::Changed(_));
+    if changed {
+        warn!(
+            path = %relative_path.display(),
+            "{} kept changing while it was read; its content may be inconsistent",
+            full_path.display()
+        );
+        writeln!(
+            writer,
+            "(File changed during read, content may be inconsistent)\n"
+        )?;
+    }
+
+    match modified.as_deref().or(content_str.as_deref()) {
+        Some(text) => {
+            let lang = detect_language(relative_path, text);
+            writeln!(writer, "```{}", lang)?;
+            for line in text.lines() {
+                writeln!(writer, "{}", line)?;
+            }
+            writeln!(writer, "```")?;
+        }
+        None => {
+            writeln!(writer, "```\n(Binary file, content omitted)\n```")?;
+            sentinels::write_end(writer, sentinel)?;
+            return Ok(FileOutcome::Binary);
+        }
+    }
+    sentinels::write_end(writer, sentinel)?;
+    Ok(match changed {
+        true => FileOutcome::Changed,
+        false => FileOutcome::Written,
+    })
+}
+
+// `bytes` as lowercase hex, the way `sha256sum` prints a digest
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Writer counting what passes through to `inner`
+struct Counted<'a> {
+    inner: &'a mut dyn Write,
+    bytes: u64,
+}
+
+impl Write for Counted<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// How much of the `max_output` limit the document has taken. Once a
+// section doesn't fit, none after it is written either.
+struct OutputCap {
+    limit: Option<u64>,
+    written: u64,
+    reached: bool,
+}
+
+impl OutputCap {
+    fn new(limit: Option<u64>) -> Self {
+        OutputCap {
+            limit,
+            written: 0,
+            reached: false,
+        }
+    }
+
+    // Whether `len` more bytes fit, counting them if they do
+    fn admits(&mut self, len: u64) -> bool {
+        if self.reached {
+            return false;
+        }
+        match self.limit {
+            Some(limit) if self.written + len > limit => {
+                self.reached = true;
+                false
+            }
+            _ => {
+                self.written += len;
+                true
+            }
+        }
+    }
+}
+
+// Visitor measuring the time spent in `inner`
+struct Timed<'a> {
+    inner: &'a mut dyn FileVisitor,
+    elapsed: Duration,
+}
+
+impl<'a> Timed<'a> {
+    fn new(inner: &'a mut dyn FileVisitor) -> Self {
+        Timed {
+            inner,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+impl FileVisitor for Timed<'_> {
+    fn on_start(&mut self, total_files: usize) {
+        self.inner.on_start(total_files);
+    }
+
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        let started = Instant::now();
+        let action = self.inner.on_file(path, content);
+        self.elapsed += started.elapsed();
+        action
+    }
+
+    fn on_reused(&mut self, path: &Path, size: u64) {
+        self.inner.on_reused(path, size);
+    }
+
+    fn streams(&mut self, path: &Path) -> bool {
+        self.inner.streams(path)
+    }
+
+    fn on_streamed(&mut self, path: &Path, size: u64) {
+        self.inner.on_streamed(path, size);
+    }
+}
+
+// Visitor used when the caller doesn't supply one
+struct EmitAll;
+
+impl FileVisitor for EmitAll {
+    fn on_file(&mut self, _path: &Path, _content: FileContent<'_>) -> Action {
+        Action::Emit
+    }
+
+    fn streams(&mut self, _path: &Path) -> bool {
+        true
+    }
+}
@@ -0,0 +1,246 @@
+// src/list.rs
+use clap::ValueEnum;
+use content_inspector::ContentType;
+use indicatif::{HumanBytes, HumanCount};
+use sourceweaver::{
+    CollectedFiles, FileVisitor, WeaveOptions, WeaveReport, collect_files, detect_language,
+    estimate_tokens, get_language_tag, language_needs_content, write_collected,
+};
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// How `list --estimate` arrives at its numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Estimate {
+    /// Render every section as a real run would and count it.
+    Exact,
+    /// Extrapolate from file sizes, without reading anything.
+    Fast,
+}
+
+// One row of the listing
+struct Entry {
+    path: PathBuf,
+    shown: String,
+    size: Option<u64>,
+    language: String,
+    // None when the file would be included
+    skipped: Option<String>,
+}
+
+/// Print the files the document would contain. Only files whose language
+/// tag depends on their content are read, so the tag is the one their
+/// section would get.
+///
+/// With `all`, entries left out by sourceweaver's own filters are listed
+/// too, with the reason. In `porcelain` mode each entry is one line of
+/// tab-separated fields: path, size in bytes (`-` when unknown), language
+/// tag (may be empty), and `included` or the skip reason. Tabs, line breaks,
+/// and backslashes in paths are escaped as `\t`, `\n`, `\r`, and `\\`.
+pub fn run(options: &WeaveOptions, all: bool, porcelain: bool) -> io::Result<WeaveReport> {
+    let collected = collect_files(options);
+    let mut entries: Vec<Entry> = collected
+        .files
+        .iter()
+        .map(|file| Entry {
+            path: file.relative_path.clone(),
+            shown: options.display_file(file),
+            size: fs::metadata(&file.path).ok().map(|m| m.len()),
+            language: language(&file.relative_path, &file.path),
+            skipped: None,
+        })
+        .collect();
+    if all {
+        entries.extend(collected.skipped.iter().map(|(path, reason)| {
+            let metadata = fs::metadata(options.root().join(path)).ok();
+            Entry {
+                path: path.clone(),
+                shown: options.display_path(path),
+                size: metadata.filter(|m| m.is_file()).map(|m| m.len()),
+                language: get_language_tag(path).to_string(),
+                skipped: Some(reason.to_string()),
+            }
+        }));
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if porcelain {
+        for entry in &entries {
+            let size = entry.size.map_or("-".to_string(), |size| size.to_string());
+            let status = entry.skipped.as_deref().unwrap_or("included");
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}",
+                escape(&entry.shown),
+                size,
+                entry.language,
+                status
+            )?;
+        }
+    } else {
+        let width = entries.iter().map(|e| e.shown.chars().count()).max();
+        for entry in &entries {
+            let size = entry
+                .size
+                .map_or("-".to_string(), |s| HumanBytes(s).to_string());
+            let mut line = format!(
+                "{:width$}  {:>10}  {}",
+                entry.shown,
+                size,
+                entry.language,
+                width = width.unwrap_or(0)
+            );
+            if let Some(reason) = &entry.skipped {
+                line.push_str(&format!("  (skipped: {})", reason));
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+    }
+    out.flush()?;
+
+    Ok(WeaveReport {
+        errors: collected.errors,
+        skipped: collected.skipped,
+        ..WeaveReport::default()
+    })
+}
+
+// The language tag of the file at `full_path`, decided the way its section
+// decides it: from the extension, or from the text when that isn't enough
+fn language(relative_path: &Path, full_path: &Path) -> String {
+    let tag = get_language_tag(relative_path);
+    if !language_needs_content(relative_path) {
+        return tag.to_string();
+    }
+    match fs::read(full_path) {
+        Ok(content) if content_inspector::inspect(&content) != ContentType::BINARY => {
+            detect_language(relative_path, &String::from_utf8_lossy(&content)).to_string()
+        }
+        _ => tag.to_string(),
+    }
+}
+
+// One row of the estimate
+struct Cost {
+    shown: String,
+    size: Option<u64>,
+    bytes: u64,
+}
+
+/// Print the size and estimated token count of every file the document
+/// would contain, costliest first, and the totals, without writing it.
+///
+/// In `exact` mode each file's section is rendered the way a real run
+/// renders it, through `visitor`, and only counted, so the total matches the
+/// summary of a real run. In `fast` mode nothing is read and the count is
+/// extrapolated from the file's size. In `porcelain` mode each file is one
+/// line of tab-separated fields: path, size in bytes (`-` when unknown), and
+/// estimated tokens, with no total line.
+pub fn estimate(
+    options: &WeaveOptions,
+    mode: Estimate,
+    porcelain: bool,
+    visitor: &mut dyn FileVisitor,
+) -> sourceweaver::Result<WeaveReport> {
+    let mut collected = collect_files(options);
+    let mut report = WeaveReport {
+        errors: std::mem::take(&mut collected.errors),
+        skipped: std::mem::take(&mut collected.skipped),
+        ..WeaveReport::default()
+    };
+
+    let mut costs = Vec::with_capacity(collected.files.len());
+    for file in collected.files {
+        let size = fs::metadata(&file.path).ok().map(|m| m.len());
+        let shown = options.display_file(&file);
+        let bytes = match mode {
+            Estimate::Fast => size.unwrap_or(0),
+            Estimate::Exact => {
+                let mut counter = Counter(0);
+                let single = CollectedFiles {
+                    files: vec![file],
+                    ..CollectedFiles::default()
+                };
+                report.merge(write_collected(&mut counter, options, single, visitor)?);
+                counter.0
+            }
+        };
+        costs.push(Cost { shown, size, bytes });
+    }
+    // Placeholders for uninitialized submodules count towards the total
+    let mut notes = Counter(0);
+    if mode == Estimate::Exact && !collected.uninitialized_submodules.is_empty() {
+        let submodules = CollectedFiles {
+            uninitialized_submodules: collected.uninitialized_submodules,
+            ..CollectedFiles::default()
+        };
+        report.merge(write_collected(&mut notes, options, submodules, visitor)?);
+    }
+    costs.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.shown.cmp(&b.shown)));
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if porcelain {
+        for cost in &costs {
+            let size = cost.size.map_or("-".to_string(), |size| size.to_string());
+            writeln!(
+                out,
+                "{}\t{}\t{}",
+                escape(&cost.shown),
+                size,
+                estimate_tokens(cost.bytes)
+            )?;
+        }
+    } else {
+        let total_size: u64 = costs.iter().filter_map(|cost| cost.size).sum();
+        let total_bytes = costs.iter().map(|cost| cost.bytes).sum::<u64>() + notes.0;
+        let total = format!("total ({} files)", costs.len());
+        let width = costs
+            .iter()
+            .map(|cost| cost.shown.chars().count())
+            .chain([total.chars().count()])
+            .max()
+            .unwrap_or(0);
+        let row = |shown: &str, size: Option<u64>, bytes: u64| {
+            let size = size.map_or("-".to_string(), |s| HumanBytes(s).to_string());
+            format!(
+                "{:width$}  {:>10}  {:>10} tokens",
+                shown,
+                size,
+                HumanCount(estimate_tokens(bytes)).to_string(),
+                width = width
+            )
+        };
+        for cost in &costs {
+            writeln!(out, "{}", row(&cost.shown, cost.size, cost.bytes))?;
+        }
+        writeln!(out, "{}", row(&total, Some(total_size), total_bytes))?;
+    }
+    out.flush()?;
+    Ok(report)
+}
+
+/// A writer that only counts what it is given.
+pub struct Counter(pub u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Escape the characters that would break a tab-separated line
+fn escape(path: &str) -> String {
+    path.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
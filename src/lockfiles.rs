@@ -0,0 +1,212 @@
+// src/lockfiles.rs
+
+/// Names of the lock files skipped by default. Entries may use `*` for any
+/// run of characters and `?` for a single one.
+pub const LOCK_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "poetry.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "Pipfile.lock",
+    "go.sum",
+    "flake.lock",
+    "bun.lock",
+    "bun.lockb",
+    "pnpm-lock.yaml",
+    "uv.lock",
+    "deno.lock",
+    "mix.lock",
+    "Podfile.lock",
+    "packages.lock.json",
+    "gradle.lockfile",
+    "*.lockfile",
+    "renv.lock",
+];
+
+/// Whether lock file names are compared case-insensitively by default:
+/// on Windows and macOS, whose filesystems usually ignore case.
+pub const LOCK_FILES_CASE_INSENSITIVE: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Whether `file_name` matches one of the lock file `patterns`, where `*`
+/// matches any run of characters and `?` a single one.
+///
+/// ```
+/// use sourceweaver::{LOCK_FILES, is_lock_file};
+///
+/// assert!(is_lock_file("Cargo.lock", LOCK_FILES, false));
+/// assert!(is_lock_file("app.lockfile", LOCK_FILES, false));
+/// assert!(!is_lock_file("lockfile.rs", LOCK_FILES, false));
+/// assert!(!is_lock_file("cargo.lock", LOCK_FILES, false));
+/// assert!(is_lock_file("cargo.lock", LOCK_FILES, true));
+/// assert!(is_lock_file("yarn-v2.lock", &["yarn-v?.lock"], false));
+/// assert!(!is_lock_file("yarn-v10.lock", &["yarn-v?.lock"], false));
+/// ```
+pub fn is_lock_file<S: AsRef<str>>(
+    file_name: &str,
+    patterns: &[S],
+    case_insensitive: bool,
+) -> bool {
+    let fold = |text: &str| match case_insensitive {
+        true => text.to_lowercase(),
+        false => text.to_string(),
+    };
+    let name: Vec<char> = fold(file_name).chars().collect();
+    patterns.iter().any(|pattern| {
+        let pattern: Vec<char> = fold(pattern.as_ref()).chars().collect();
+        wildcard_match(&pattern, &name)
+    })
+}
+
+// Match `name` against `pattern` with `*` and `?` wildcards, backtracking to
+// the last `*` on a mismatch
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SkipReason, WeaveOptions, collect_files};
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn every_built_in_name_matches_itself() {
+        for name in LOCK_FILES.iter().filter(|name| !name.contains(['*', '?'])) {
+            assert!(is_lock_file(name, LOCK_FILES, false), "{}", name);
+        }
+        for name in [
+            "gradle.lockfile",
+            "buildscript-gradle.lockfile",
+            ".lockfile",
+        ] {
+            assert!(is_lock_file(name, LOCK_FILES, false), "{}", name);
+        }
+    }
+
+    #[test]
+    fn look_alikes_are_not_lock_files() {
+        for name in [
+            "Cargo.toml",
+            "Cargo.lock.orig",
+            "my-Cargo.lock",
+            "lockfile",
+            "lockfile.rs",
+            "package.json",
+            "go.mod",
+            "uv.lock.bak",
+            "pnpm-lock.yml",
+        ] {
+            assert!(!is_lock_file(name, LOCK_FILES, false), "{}", name);
+            assert!(!is_lock_file(name, LOCK_FILES, true), "{}", name);
+        }
+    }
+
+    #[test]
+    fn wildcards() {
+        // Pattern, name, and whether they match
+        let table = [
+            ("*", "anything", true),
+            ("*", "", true),
+            ("", "", true),
+            ("", "a", false),
+            ("?", "", false),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "aXXbYYc", true),
+            ("a*b*c", "aXXcYYb", false),
+            ("*.lock", "yarn.lock.lock", true),
+            ("*lock*", "unlocked.txt", true),
+            ("??.sum", "go.sum", true),
+            ("??.sum", "g.sum", false),
+            ("**.lock", "a.lock", true),
+            ("ö*.lock", "ölfarbe.lock", true),
+        ];
+        for (pattern, name, matches) in table {
+            assert_eq!(
+                is_lock_file(name, &[pattern], false),
+                matches,
+                "{} against {}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn case_folding_covers_patterns_and_names() {
+        assert!(!is_lock_file("CARGO.LOCK", LOCK_FILES, false));
+        assert!(is_lock_file("CARGO.LOCK", LOCK_FILES, true));
+        assert!(is_lock_file("podfile.lock", &["PODFILE.*"], true));
+        assert!(is_lock_file("ÄPFEL.LOCK", &["äpfel.lock"], true));
+        assert!(!is_lock_file("ÄPFEL.LOCK", &["äpfel.lock"], false));
+    }
+
+    #[test]
+    fn the_walk_skips_configured_lock_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["main.rs", "Cargo.lock", "YARN.LOCK", "deps.pin", "notes.md"] {
+            fs::write(dir.path().join(name), "x\n").unwrap();
+        }
+        let walk = |case_insensitive: bool| {
+            let options = WeaveOptions::new(dir.path())
+                .lock_file("*.pin")
+                .lock_files_case_insensitive(case_insensitive)
+                .build()
+                .unwrap();
+            let collected = collect_files(&options);
+            let mut files: Vec<_> = collected
+                .files
+                .iter()
+                .map(|file| file.relative_path.clone())
+                .collect();
+            files.sort();
+            let mut locks: Vec<_> = collected
+                .skipped
+                .iter()
+                .filter(|(_, reason)| *reason == SkipReason::LockFile)
+                .map(|(path, _)| path.clone())
+                .collect();
+            locks.sort();
+            (files, locks)
+        };
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            walk(false),
+            (
+                paths(&["YARN.LOCK", "main.rs", "notes.md"]),
+                paths(&["Cargo.lock", "deps.pin"])
+            )
+        );
+        assert_eq!(
+            walk(true),
+            (
+                paths(&["main.rs", "notes.md"]),
+                paths(&["Cargo.lock", "YARN.LOCK", "deps.pin"])
+            )
+        );
+    }
+}
@@ -0,0 +1,317 @@
+// src/logging.rs
+use anstyle::{AnsiColor, Style};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use std::{
+    fmt::{self, Write as _},
+    io::{self, IsTerminal, Write},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{
+    EnvFilter,
+    fmt::{FmtContext, FormatEvent, FormatFields, format::Writer},
+    registry::LookupSpan,
+};
+
+// Progress bar currently drawn on stderr, if any
+static PROGRESS: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+// Whether stderr gets colors, decided once by `init`
+static COLOR: Mutex<anstream::ColorChoice> = Mutex::new(anstream::ColorChoice::Never);
+
+// Whether stderr carries JSON events instead of prose, decided once by `init`
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// How messages on stderr are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Prose for people.
+    Human,
+    /// One JSON object per line, for programs.
+    Json,
+}
+
+// Styles for the message prefixes, in the spirit of cargo's. Only the
+// formatter adds styles: the fmt layer escapes them inside messages.
+const ERROR: Style = AnsiColor::Red.on_default().bold();
+const WARNING: Style = AnsiColor::Yellow.on_default().bold();
+
+/// Whether stderr is treated as an interactive terminal, and colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Detect whether stderr is a terminal.
+    Auto,
+    /// Behave as if stderr is a terminal.
+    Always,
+    /// Behave as if stderr is not a terminal.
+    Never,
+}
+
+impl ColorChoice {
+    /// Resolve `auto` against the actual stderr.
+    pub fn stderr_is_terminal(self) -> bool {
+        match self {
+            ColorChoice::Auto => io::stderr().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    // `auto` also honours NO_COLOR and CLICOLOR_FORCE
+    fn color(self) -> anstream::ColorChoice {
+        match self {
+            ColorChoice::Auto => anstream::ColorChoice::Auto,
+            ColorChoice::Always => anstream::ColorChoice::Always,
+            ColorChoice::Never => anstream::ColorChoice::Never,
+        }
+    }
+}
+
+// `text` in `style`. Styles are written unconditionally; the stderr writer
+// strips them again when colors are off.
+fn paint(style: Style, text: impl fmt::Display) -> String {
+    format!("{style}{text}{style:#}")
+}
+
+/// Install the stderr subscriber used by the CLI.
+///
+/// Status messages are printed as plain lines, warnings and errors get a
+/// `warning:`/`error:` prefix, colored when stderr is a terminal (or per
+/// `color`) and `NO_COLOR` isn't set. The level follows `--quiet`/`--verbose`;
+/// `RUST_LOG` overrides it when set. When stderr isn't a terminal (per
+/// `color`), status messages are dropped by default so pipelines stay quiet,
+/// while warnings and errors still come through.
+///
+/// With [`MessageFormat::Json`], every line is a JSON event instead (see
+/// [`emit_event`]), and status messages are only included with `-v`.
+pub fn init(quiet: bool, verbose: u8, color: ColorChoice, format: MessageFormat) {
+    let json = format == MessageFormat::Json;
+    JSON.store(json, Ordering::Relaxed);
+    let interactive = color.stderr_is_terminal() && !json;
+    *COLOR.lock().unwrap_or_else(|e| e.into_inner()) = match json {
+        true => anstream::ColorChoice::Never,
+        false => color.color(),
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default_directives(quiet, verbose, interactive)));
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| StderrWriter);
+    match json {
+        true => subscriber.event_format(JsonFormat).init(),
+        false => subscriber.event_format(StatusFormat).init(),
+    }
+}
+
+/// Whether stderr carries JSON events (`--message-format json`).
+pub fn json_events() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// A field value of a JSON event.
+pub enum Value<'a> {
+    Str(&'a str),
+    Num(u64),
+}
+
+/// Write one JSON event line to stderr: `{"type":KIND,...fields}`.
+///
+/// The event types, and the fields each one always has, are:
+/// - `file`: `path`, `bytes` — a file was read and is being written;
+/// - `warning`, `error`, `status`: `message`, plus `path` when the message
+///   concerns one file or directory;
+/// - `summary`: `files`, `bytes`, `tokens` — the run finished; `bytes` is
+///   the document size and `tokens` a rough estimate (bytes / 4).
+///
+/// New fields may be added; existing ones keep their meaning.
+pub fn emit_event(kind: &str, fields: &[(&str, Value<'_>)]) {
+    let mut line = String::from("{\"type\":");
+    push_json_string(&mut line, kind);
+    for (name, value) in fields {
+        line.push(',');
+        push_json_string(&mut line, name);
+        line.push(':');
+        match value {
+            Value::Str(text) => push_json_string(&mut line, text),
+            Value::Num(n) => {
+                let _ = write!(line, "{}", n);
+            }
+        }
+    }
+    line.push_str("}\n");
+    let _ = StderrWriter.write_all(line.as_bytes());
+}
+
+fn push_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Register the progress bar so log lines are printed above it instead of
+/// tearing through it. Pass `None` once the bar is cleared.
+pub fn set_progress(bar: Option<ProgressBar>) {
+    *PROGRESS.lock().unwrap_or_else(|e| e.into_inner()) = bar;
+}
+
+// Writes to stderr, suspending the progress bar while doing so. The fmt layer
+// buffers each event, so every write is a whole line. Styles are stripped
+// (or translated for old Windows consoles) according to the color choice.
+struct StderrWriter;
+
+impl Write for StderrWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bar = PROGRESS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let color = *COLOR.lock().unwrap_or_else(|e| e.into_inner());
+        let write = || anstream::AutoStream::new(io::stderr(), color).write(buf);
+        match bar {
+            Some(bar) => bar.suspend(write),
+            None => write(),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+// Map the verbosity flags to a filter. The ignore crate logs the rule behind
+// every skipped entry at debug level, which is exactly what -vv is for.
+fn default_directives(quiet: bool, verbose: u8, interactive: bool) -> &'static str {
+    match (quiet, verbose) {
+        (true, _) => "error",
+        (false, 0) if !interactive => "warn",
+        (false, 0) => "info",
+        (false, 1) => "info,sourceweaver=debug",
+        (false, _) => "info,sourceweaver=trace,ignore=debug",
+    }
+}
+
+// Formats events the way sourceweaver has always printed to stderr
+struct StatusFormat;
+
+impl<S, N> FormatEvent<S, N> for StatusFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "{} ", paint(ERROR, "error:"))?,
+            Level::WARN => write!(writer, "{} ", paint(WARNING, "warning:"))?,
+            _ => {}
+        }
+        let mut fields = MessageFields::default();
+        event.record(&mut fields);
+        writeln!(writer, "{}", fields.message)
+    }
+}
+
+// Formats events as JSON lines for --message-format json
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let kind = match *event.metadata().level() {
+            Level::ERROR => "error",
+            Level::WARN => "warning",
+            _ => "status",
+        };
+        let mut fields = MessageFields::default();
+        event.record(&mut fields);
+
+        let mut line = String::from("{\"type\":");
+        push_json_string(&mut line, kind);
+        line.push_str(",\"message\":");
+        push_json_string(&mut line, &fields.message);
+        if let Some(path) = &fields.path {
+            line.push_str(",\"path\":");
+            push_json_string(&mut line, path);
+        }
+        line.push('}');
+        writeln!(writer, "{}", line)
+    }
+}
+
+// The fields of an event that get printed: the message, and the path it
+// concerns, which only the JSON format shows separately
+#[derive(Default)]
+struct MessageFields {
+    message: String,
+    path: Option<String>,
+}
+
+impl Visit for MessageFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "path" => self.path = Some(format!("{:?}", value)),
+            _ => {}
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            "path" => self.path = Some(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_strings_round_trip_through_a_parser() {
+        for text in [
+            "plain",
+            "say \"hi\"",
+            r"C:\work\main.rs",
+            "line\nbreak\r\ttab",
+            "bell\u{7} escape\u{1b} nul\u{0} del\u{7f}",
+            "café ✓ 🦀",
+        ] {
+            let mut json = String::new();
+            push_json_string(&mut json, text);
+            let parsed: serde_json::Value = serde_json::from_str(&json)
+                .unwrap_or_else(|e| panic!("{:?} became {}: {}", text, json, e));
+            assert_eq!(parsed, text);
+        }
+    }
+}
@@ -1,65 +1,1870 @@
 // src/main.rs
-use arboard::Clipboard;
-use clap::Parser;
-use content_inspector::ContentType;
-use ignore::WalkBuilder;
+mod argfile;
+mod clipboard;
+mod compress;
+mod config;
+mod git;
+mod history;
+mod html;
+mod list;
+mod logging;
+mod output;
+mod packages;
+mod progress;
+mod prompt;
+mod split;
+mod stats;
+mod summary;
+mod verify;
+
+use clap::{
+    ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, error::ErrorKind,
+};
+use clipboard::{BackendKind, ClipboardLimit, DEFAULT_CLIPBOARD_LIMIT, Selection};
+use compress::Compression;
+use config::Config;
+use history::{History, HistoryPosition};
+use list::Estimate;
+use logging::{ColorChoice, MessageFormat};
+use output::{Destinations, OutputFile, Stamp};
+use progress::ProgressVisitor;
+use prompt::Prompt;
+use sourceweaver::{
+    Checksums, CollapseData, DEFAULT_COLLAPSE_MIN_LINES, ErrorPlaceholders, ExpandTabs,
+    FileOutcome, FileVisitor, OmittedFiles, Submodules, SymbolIndex, TruncateLimit,
+    TruncateStrategy, UnicodeTricks, WeaveError, WeaveOptions, WeaveOptionsBuilder, WeaveReport,
+    collect_files, generate_markdown_with, write_collected,
+};
+use split::{DocumentVisitor, SplitBy};
+use stats::StatsFormat;
 use std::{
-    collections::HashSet,
-    fs::{self, File},
-    io::{self, BufWriter, Write},
-    path::{Path, PathBuf},
+    fs,
+    io::{self, BufWriter, IsTerminal, Read, Write},
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
 };
+use tracing::{error, info, warn};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    subcommand_precedence_over_arg = true,
+    about = "Bundle a codebase into a single Markdown document",
+    disable_help_subcommand = true,
+    long_about = "Scans a codebase directory, respects .gitignore rules, and bundles every \
+non-ignored text file into a single Markdown document. Each file is emitted under a heading \
+containing its relative path, inside a fenced code block tagged with its language.\n\n\
+The document is written to standard output unless --output or --clipboard is given; \
+any combination of the three destinations may be used together. \
+Status messages are written to standard error so they never mix with the document.\n\n\
+Arguments can also be read from a response file: @FILE is replaced by the lines of FILE, one \
+argument per line, taken literally (no quoting or escaping, so paths with spaces need nothing \
+special). Blank lines and lines starting with '#' are skipped; response files can't be nested.",
+    after_help = "Exit status:\n  \
+0  success (warnings are reported but tolerated)\n  \
+1  --strict-content found a matching file, or 'verify' found the document out of date\n  \
+2  invalid arguments, options, or .sourceweaver.toml, or a root that doesn't exist\n  \
+3  fatal I/O or walk error: the root or an archive root couldn't be read, writing the \
+output failed, or --fail-fast stopped the run\n  \
+4  clipboard unavailable or copy failed\n  \
+5  completed with warnings under --strict\n  \
+6  the document was cut short by --max-output\n  \
+7  no files matched, under --fail-if-empty or --strict\n\n\
+Examples:\n  \
+sourceweaver | less\n  \
+sourceweaver -o bundle.md\n  \
+sourceweaver -o bundle.md -c\n  \
+sourceweaver src/ Cargo.toml README.md -o context.md\n  \
+sourceweaver --split-by top-dir --output-dir out/\n  \
+sourceweaver --root ../other-project --clipboard\n  \
+sourceweaver --hidden -c\n  \
+git diff | sourceweaver --stdin --stdin-name changes.diff"
+)]
 struct Args {
     /// Optional: The path to the output markdown file. Writes to file instead of stdout.
-    #[arg(short, long, conflicts_with = "clipboard")]
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        long_help = "Write the generated document to FILE instead of standard output. \
+'-' means standard output, e.g. to override an output path set elsewhere. For a directory \
+(an existing one, or any path ending in '/') a name like <root-name>-2025-01-07.md is \
+generated inside it. Missing parent directories are created. When FILE lies inside the scanned directory it is \
+excluded from its own output. The document is written to a temporary file next to FILE \
+and renamed over it once complete, so an interrupted run leaves any previous FILE intact. \
+Can be combined with --clipboard and --stdout."
+    )]
     output: Option<PathBuf>,
 
+    /// Replace the part of FILE between sourceweaver:begin/end markers.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["output", "split_by"],
+        long_help = "Write the document into an existing Markdown file, such as a hand-written \
+prompt, replacing everything between the first line holding only <!-- sourceweaver:begin --> \
+and the last line holding only <!-- sourceweaver:end -->. The rest of FILE, the marker lines \
+included, is kept byte for byte, so rerunning refreshes the snapshot in place, even when woven \
+files quote the markers. Like --output, the new FILE is written to a temporary \
+file and renamed over the old one once complete, and FILE is excluded from its own output. \
+Can be combined with --clipboard and --stdout."
+    )]
+    inject: Option<PathBuf>,
+
+    /// Text to put before the first file, or @FILE to read it (repeatable).
+    #[arg(
+        long,
+        value_name = "TEXT|@FILE",
+        long_help = "Put TEXT before the first file section, e.g. instructions for a language \
+model such as 'You are reviewing the following codebase.' @FILE reads the text from FILE, \
+and @@ stands for a literal leading @. The text is written as is, not as a file section. \
+May be given multiple times; the texts follow one another in order, separated by a blank \
+line. An --output file keeps its generation marker on the first line. Applies to every \
+destination, and to each document of --split-by."
+    )]
+    prefix: Vec<String>,
+
+    /// Text to put after the last file, or @FILE to read it (repeatable).
+    #[arg(
+        long,
+        value_name = "TEXT|@FILE",
+        long_help = "Put TEXT after the last file section (and the symbol index), e.g. 'Answer \
+the questions below.' Works like --prefix otherwise: @FILE reads the text from FILE, and \
+the texts of several --suffix options follow one another in order."
+    )]
+    suffix: Vec<String>,
+
+    /// Append to the --output file instead of overwriting it.
+    #[arg(
+        long,
+        requires = "output",
+        long_help = "Append to the --output file instead of overwriting it, so several runs \
+over different directories can build up one document. Each appended run is preceded by a \
+separator recording the time and the root directory. A missing file is created."
+    )]
+    append: bool,
+
+    /// Overwrite --output even if it wasn't generated by sourceweaver.
+    #[arg(
+        long,
+        requires = "output",
+        long_help = "Overwrite the --output file even if it doesn't look like an earlier \
+sourceweaver document. Without it, an existing file is only replaced when it is empty or \
+starts with the marker comment every output file begins with, so '-o notes.md' can't \
+silently destroy unrelated notes."
+    )]
+    force: bool,
+
+    /// Compress the --output file [default: from its .gz/.zst extension].
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        requires = "output",
+        conflicts_with_all = ["clipboard", "stdout"],
+        long_help = "Compress the --output file with gzip or zstd. Without this flag, an \
+--output path ending in .gz or .zst is compressed accordingly. Only the file is compressed, \
+so the flag can't be combined with --clipboard, --stdout, or --output -."
+    )]
+    compress: Option<Compression>,
+
+    /// Write one document per group instead of a single one.
+    #[arg(
+        long,
+        value_name = "MODE",
+        value_enum,
+        requires = "output_dir",
+        conflicts_with_all = ["output", "clipboard", "stdout"],
+        long_help = "Write one document per group into --output-dir instead of a single \
+document. 'top-dir' groups files by their first path component, so a monorepo yields e.g. \
+backend.md, frontend.md, and infra.md; files directly in the root go into _root.md. All \
+documents come from one walk, so filters behave exactly as for a single document."
+    )]
+    split_by: Option<SplitBy>,
+
+    /// Directory receiving the documents of --split-by.
+    #[arg(long, value_name = "DIR", requires = "split_by")]
+    output_dir: Option<PathBuf>,
+
     /// Optional: Copy the output directly to the system clipboard.
-    #[arg(short, long, conflicts_with = "output")]
+    #[arg(
+        short,
+        long,
+        long_help = "Copy the generated document to the system clipboard instead of writing \
+it to standard output. Can be combined with --output and --stdout; the document is generated \
+once, and a clipboard failure never discards a file that was already written."
+    )]
     clipboard: bool,
 
+    /// Also write the document to stdout when --output, --inject, or --clipboard is used.
+    #[arg(long)]
+    stdout: bool,
+
+    /// Copy to the clipboard as rich HTML, with the Markdown as plain text.
+    #[arg(
+        long,
+        requires = "clipboard",
+        long_help = "Copy the document to the clipboard as rich HTML, with the Markdown as \
+the plain-text alternative, so headings and code blocks keep their structure when pasted \
+into editors such as Google Docs or Notion. Backends or platforms without HTML clipboard \
+support copy plain text instead, with a warning."
+    )]
+    clipboard_html: bool,
+
+    /// Refuse to copy documents larger than SIZE to the clipboard [default: 10M].
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        default_value_t = DEFAULT_CLIPBOARD_LIMIT,
+        hide_default_value = true,
+        requires = "clipboard",
+        long_help = "Refuse to copy documents larger than SIZE to the clipboard, since some \
+X11 and Windows clipboards silently truncate very large payloads. On Windows, where the \
+clipboard holds text as UTF-16, the limit applies to that size, about twice the document's. \
+Accepts the same suffixes as --max-filesize. Defaults to 10M."
+    )]
+    clipboard_limit: u64,
+
+    /// Copy to the clipboard even when over --clipboard-limit.
+    #[arg(long, requires = "clipboard")]
+    clipboard_force: bool,
+
+    /// Keep running until something else is copied, so the copy survives.
+    #[arg(
+        long,
+        requires = "clipboard",
+        long_help = "After copying, keep running until another program takes over the \
+clipboard (or Ctrl+C is pressed). On X11 the copied content is served by the copying process, \
+so without a clipboard manager it disappears as soon as sourceweaver exits. Only the system \
+clipboard backend on Linux needs this; elsewhere the flag does nothing."
+    )]
+    clipboard_wait: bool,
+
+    /// How --clipboard copies: system clipboard, OSC 52, a command, or auto.
+    #[arg(
+        long,
+        value_name = "BACKEND",
+        value_enum,
+        default_value_t = BackendKind::Auto,
+        requires = "clipboard",
+        long_help = "How --clipboard copies the document. 'system' uses the X11, Wayland, \
+macOS, or Windows clipboard. 'osc52' sends the OSC 52 escape sequence to the controlling \
+terminal, so the terminal emulator on your local machine does the copying; this works over \
+SSH but depends on terminal support. 'command' pipes the document into an external \
+program (see --clipboard-command). 'auto' uses the system clipboard, then a clipboard \
+program found on PATH, then OSC 52 when a terminal is attached."
+    )]
+    clipboard_backend: BackendKind,
+
+    /// Which Linux selection to set: clipboard, primary, or both.
+    #[arg(
+        long,
+        value_name = "SELECTION",
+        value_enum,
+        default_value_t = Selection::Clipboard,
+        requires = "clipboard",
+        long_help = "Which selection --clipboard sets on Linux. 'clipboard' is the regular \
+clipboard pasted with Ctrl+V, 'primary' is the selection pasted with middle-click, and 'both' \
+sets the two with the same content. Only the system clipboard backend honours it; elsewhere \
+it is ignored with a warning."
+    )]
+    selection: Selection,
+
+    /// Program that receives the document on stdin, e.g. "wl-copy".
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        requires = "clipboard",
+        long_help = "Program used by --clipboard-backend command (and as the first fallback in \
+auto mode); the document is written to its stdin. Arguments are split with shell quoting \
+rules, e.g. \"xclip -selection clipboard\". Without it, wl-copy, xclip, xsel, and pbcopy \
+are tried in that order. Any program works, making this a general escape hatch."
+    )]
+    clipboard_command: Option<String>,
+
     /// Optional: Specify a root directory instead of the current working directory.
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        value_name = "DIR",
+        long_help = "Scan DIR instead of the current working directory. Headings in the \
+document are relative to DIR. DIR may also be a .zip, .tar, .tar.gz, or .tgz archive, such \
+as a GitHub download, which is read without extracting it: the same filters apply, \
+including .gitignore files inside the archive, and a single top-level wrapper directory is \
+stripped from the headings. When DIR is any other file, just that file is written, under \
+its own name."
+    )]
     root: Option<PathBuf>,
 
-    /// Include hidden files and directories (those starting with '.').
+    /// Files and directories to include instead of the whole working directory.
+    #[arg(
+        value_name = "PATH",
+        conflicts_with_all = ["root", "stdin"],
+        long_help = "Include only these files and directories instead of the whole working \
+directory. Directories are walked with the usual filters; files are included as named, even \
+when an ignore rule or built-in filter would skip them. Headings are relative to the current \
+directory, or to the closest common ancestor of the paths when some lie outside it. A file \
+reached through more than one path is written once."
+    )]
+    paths: Vec<PathBuf>,
+
+    /// Weave only the workspace package NAME and the workspace manifest (repeatable).
+    #[arg(
+        short = 'p',
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["paths", "stdin"],
+        long_help = "Weave only the package NAME of the workspace at the root, along with the \
+workspace's root manifests. Cargo packages are found through the members globs of the root \
+Cargo.toml; JavaScript packages, e.g. @acme/api, through the workspaces globs of the root \
+package.json or the packages of pnpm-workspace.yaml. Headings stay relative to the root. An \
+unknown NAME is an error naming the manifests read and the packages found. May be given \
+multiple times."
+    )]
+    package: Vec<String>,
+
+    /// Also weave the workspace packages --package depends on, transitively.
+    #[arg(
+        long,
+        visible_alias = "with-deps",
+        requires = "package",
+        long_help = "Also weave the workspace packages the --package packages depend on, and the \
+ones those depend on. For Cargo these are path dependencies, including those inherited from \
+[workspace.dependencies]; for JavaScript, dependencies of any kind in package.json that are \
+packages of the workspace. Path dependencies outside the root are left out with a warning."
+    )]
+    with_path_deps: bool,
+
+    /// Read one file's content from stdin instead of scanning a directory.
+    #[arg(
+        long,
+        conflicts_with_all = ["root", "split_by"],
+        long_help = "Read content from standard input and format it as a single file section \
+instead of scanning a directory. Use --stdin-name to give it a path for the heading and \
+language tag. Binary content still gets a placeholder."
+    )]
+    stdin: bool,
+
+    /// Heading and language for --stdin content, e.g. src/foo.rs.
+    #[arg(long, value_name = "PATH", requires = "stdin")]
+    stdin_name: Option<PathBuf>,
+
+    /// Prepend PREFIX to every path shown in the document.
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        long_help = "Prepend PREFIX to every path shown in the document, e.g. \
+--root backend/service --path-prefix backend/service so headings reflect the monorepo \
+layout. Only displayed paths change; filtering and reading use the real root."
+    )]
+    path_prefix: Option<PathBuf>,
+
+    /// Show paths relative to DIR, an ancestor of the root.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "path_prefix",
+        long_help = "Show paths relative to DIR instead of the root. DIR must be the root or \
+one of its ancestors, e.g. --root backend/service --relative-to . shows \
+backend/service/src/main.rs. Only displayed paths change."
+    )]
+    relative_to: Option<PathBuf>,
+
+    /// Show canonical absolute paths in headings.
+    #[arg(
+        long,
+        conflicts_with_all = ["path_prefix", "relative_to"],
+        long_help = "Show canonical absolute paths in headings instead of paths relative to \
+the root, e.g. for tools that jump from the document back to the editor. The \\\\?\\ prefix \
+Windows adds to canonical paths is left out. Filtering is unaffected."
+    )]
+    absolute_paths: bool,
+
+    /// Show paths with the platform's separators instead of '/'.
+    #[arg(
+        long,
+        long_help = "Show paths in the document with the platform's own separators. By \
+default paths use '/' everywhere, including on Windows, so documents are identical across \
+platforms and read like the POSIX paths most tools expect."
+    )]
+    native_paths: bool,
+
+    /// Make the document identical wherever and whenever it is generated.
+    #[arg(
+        long,
+        conflicts_with_all = ["native_paths", "absolute_paths"],
+        long_help = "Produce byte-identical output for the same files on any machine, so \
+snapshots can be diffed. The generation marker names only the root's directory, not where it \
+lies, and carries no time unless SOURCE_DATE_EPOCH is set; a generated --output file name \
+drops its date the same way. Lock file names are matched ignoring case on every platform. \
+Everything else already is deterministic: files are always in sorted path order whatever \
+--threads says, paths use '/' (so --native-paths and --absolute-paths are refused), and \
+nothing in the document depends on the locale."
+    )]
+    reproducible: bool,
+
+    /// Link each heading to the file in this repository at the current commit.
+    #[arg(
+        long,
+        value_name = "URL",
+        conflicts_with = "auto_repo_url",
+        long_help = "Turn each heading into a link to the file at the HEAD commit, e.g. \
+--repo-url https://github.com/org/repo links to https://github.com/org/repo/blob/<sha>/<path>. \
+SSH remotes such as git@github.com:org/repo.git are accepted and converted to https. GitLab \
+hosts get GitLab's blob URLs; for other layouts use --link-template."
+    )]
+    repo_url: Option<String>,
+
+    /// Like --repo-url, using the URL of the 'origin' remote.
     #[arg(long)]
+    auto_repo_url: bool,
+
+    /// URL template for heading links, with {repo}, {commit}, and {path}.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        long_help = "URL template for heading links. {path} is the file's path in the \
+repository, {commit} the HEAD commit, and {repo} the --repo-url or origin URL. E.g. for Gitea: \
+--auto-repo-url --link-template '{repo}/src/commit/{commit}/{path}'. A template without {repo} \
+or {commit}, such as an editor URL scheme, works outside a git checkout."
+    )]
+    link_template: Option<String>,
+
+    /// Follow symlinks to directories.
+    #[arg(
+        short = 'L',
+        long,
+        long_help = "Descend into symlinked directories. Symlinks to files are always included, \
+and links to sockets, FIFOs, and devices are always skipped. Link cycles are detected and \
+reported as warnings."
+    )]
+    follow: bool,
+
+    /// Include hidden files and directories (those starting with '.').
+    #[arg(
+        long,
+        long_help = "Include hidden files and directories (those starting with '.') that are \
+not otherwise excluded by .gitignore, .ignore, or git exclude rules."
+    )]
     hidden: bool,
+
+    /// Exclude paths matching GLOB (repeatable).
+    #[arg(
+        short = 'x',
+        long,
+        value_name = "GLOB",
+        long_help = "Exclude paths matching GLOB, using .gitignore syntax relative to the root \
+directory (e.g. 'tests/fixtures/**' or '*.snap'). May be given multiple times. --exclude, \
+--include, and --patterns-from are evaluated in the order given, and the last pattern \
+matching a path or a directory above it decides."
+    )]
+    exclude: Vec<String>,
+
+    /// Include paths matching GLOB, overriding earlier excludes (repeatable).
+    #[arg(
+        short = 'i',
+        long,
+        value_name = "GLOB",
+        long_help = "Include paths matching GLOB, using .gitignore syntax relative to the root \
+directory. After an --exclude, it re-includes what it matches, e.g. --exclude 'tests/**' \
+--include 'tests/helpers/**' keeps the helpers and everything outside tests. When the first \
+pattern is an --include, only files some --include selects are woven. Includes never \
+select files that .gitignore rules or the built-in filters skip. May be given multiple times."
+    )]
+    include: Vec<String>,
+
+    /// Turn off the built-in filters: ignore files, hidden files, lock files.
+    #[arg(
+        long,
+        long_help = "Turn off every built-in filter, for a complete inventory: .gitignore, \
+.ignore, and git exclude rules, the hidden-file rule, and the lock file filter. Explicit \
+options such as --exclude, --include, --exclude-dir, and --max-filesize still apply, as do \
+the exclusion of the output file and the skipping of sockets and FIFOs, which could hang the \
+run. The .git directory is included too unless excluded, e.g. with --exclude-dir .git. -v \
+prints the filter stages that are active."
+    )]
+    no_default_filters: bool,
+
+    /// Also skip files whose name matches PATTERN as lock files (repeatable).
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        long_help = "Treat files whose name matches PATTERN as lock files and skip them, in \
+addition to the built-in list (Cargo.lock, package-lock.json, yarn.lock, pnpm-lock.yaml, \
+uv.lock, *.lockfile, ...). '*' matches any run of characters and '?' a single one, e.g. \
+'*.lock'. Names are compared ignoring case on Windows and macOS. Patterns from the \
+lock_files array in .sourceweaver.toml are added too. Repeatable."
+    )]
+    lock_file: Vec<String>,
+
+    /// Include paths matching GLOB even if ignored or filtered (repeatable).
+    #[arg(
+        long,
+        value_name = "GLOB",
+        long_help = "Include files matching GLOB even when .gitignore, .ignore, or git \
+exclude rules, the hidden-file rule, the lock file filter, --exclude, or --exclude-dir would \
+skip them, e.g. a generated schema.graphql or a local docker-compose.override.yml. The \
+globs are used as whitelist overrides in a walk starting from the directories they spell \
+out, so 'generated/**' reaches into an ignored generated/ directory, while '*.graphql' \
+only finds files in directories that aren't ignored themselves. --max-filesize, binary \
+detection, and --collapse-data still apply. With -v each force-included file is logged. \
+Repeatable."
+    )]
+    force_include: Vec<String>,
+
+    /// Read exclude globs, and '!'-prefixed includes, from FILE (repeatable).
+    #[arg(
+        long,
+        value_name = "FILE",
+        long_help = "Read patterns from FILE, one per line, as if each were given at this \
+point on the command line: a line is an --exclude glob, or an --include when it starts \
+with '!'. Blank lines and lines starting with '#' are ignored; '\\!' and '\\#' escape a \
+literal first character. May be given multiple times."
+    )]
+    patterns_from: Vec<PathBuf>,
+
+    /// Only include files whose relative path matches RE (repeatable).
+    #[arg(
+        long,
+        value_name = "RE",
+        long_help = "Only include files whose path relative to the root, with '/' separators \
+on every platform, matches the regular expression RE somewhere in it; anchor it with ^ and $ \
+to match the whole path. Given several times, a file matching any of them is included. \
+Regexes apply after --include and --exclude, and only to files, so a file the globs leave \
+out stays out."
+    )]
+    path_regex: Vec<String>,
+
+    /// Exclude files whose relative path matches RE (repeatable).
+    #[arg(
+        long,
+        value_name = "RE",
+        long_help = "Exclude files whose path relative to the root, with '/' separators on \
+every platform, matches the regular expression RE somewhere in it, e.g. '/v[0-9]+/'. Like \
+--path-regex, it applies after --include and --exclude. May be given multiple times."
+    )]
+    path_regex_not: Vec<String>,
+
+    /// Skip text files whose content matches RE (repeatable).
+    #[arg(
+        long,
+        value_name = "RE",
+        long_help = "Skip text files whose content matches the regular expression RE anywhere, \
+e.g. 'DO NOT SHARE' or '(?i)confidential'. Files are checked after they are read, before \
+content options such as --collapse-data; binary files aren't checked. The summary counts \
+the skipped files under the first pattern that matched, and -v lists them. May be given \
+multiple times."
+    )]
+    exclude_content: Vec<String>,
+
+    /// Fail the run when a file matches --exclude-content.
+    #[arg(
+        long,
+        requires = "exclude_content",
+        long_help = "Treat a file matching --exclude-content as a fatal error instead of \
+skipping it: the run stops with exit status 1, naming the file and the pattern, and an \
+--output file is left as it was."
+    )]
+    strict_content: bool,
+
+    /// Keep only text files whose content matches RE (repeatable).
+    #[arg(
+        long,
+        value_name = "RE",
+        long_help = "Keep only text files whose content matches the regular expression RE \
+somewhere, e.g. 'PaymentIntent' to weave the files that mention a symbol. Given multiple \
+times, a file matching any of them is kept. Binary files never match. Files are checked \
+after they are read, before content options such as --collapse-data; -v logs how many \
+lines of each kept file match."
+    )]
+    grep: Vec<String>,
+
+    /// Keep only the lines matching --grep and N lines around them.
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "grep",
+        long_help = "Cut each file section down to the lines matching --grep, with N lines of \
+context before and after each. Every run of lines left out becomes a line such as \
+'… 12 lines omitted' inside the code block. A file whose matches all span several lines \
+is kept whole."
+    )]
+    grep_context: Option<usize>,
+
+    // --exclude, --include, and --patterns-from in command-line order
+    #[arg(skip)]
+    patterns: Vec<PatternArg>,
+
+    /// Skip every directory named NAME, at any depth (repeatable).
+    #[arg(
+        long,
+        value_name = "NAME",
+        long_help = "Skip every directory named NAME wherever it occurs, without descending \
+into it, e.g. --exclude-dir node_modules --exclude-dir __snapshots__. NAME is a directory \
+name, not a path or glob. May be given multiple times."
+    )]
+    exclude_dir: Vec<String>,
+
+    /// Only include files OWNER owns per CODEOWNERS (repeatable).
+    #[arg(
+        long,
+        value_name = "OWNER",
+        long_help = "Only include files owned by OWNER according to the repository's CODEOWNERS \
+file, e.g. --owner @org/payments-team. The file is looked for where GitHub looks: \
+.github/CODEOWNERS, CODEOWNERS, then docs/CODEOWNERS at the repository root, and it is an \
+error when there is none. Patterns follow GitHub's rules, with the last matching line \
+deciding. Owners are compared ignoring case, and the @ is optional. May be given multiple \
+times; a file owned by any of them is included."
+    )]
+    owner: Vec<String>,
+
+    /// Only include files in the named scope from the config (repeatable).
+    #[arg(
+        long = "scope",
+        value_name = "NAME",
+        long_help = "Only include files matching one of the globs of the scope NAME, defined in \
+the [scopes] table of .sourceweaver.toml, e.g. --scope api with api = [\"services/api/**\", \
+\"openapi.yaml\"]. The scope applies on top of every other filter, so it only narrows what \
+they select. May be given multiple times; a file in any of the scopes is included. An unknown \
+NAME is an error listing the scopes there are."
+    )]
+    scopes: Vec<String>,
+
+    /// Print the scopes defined in the config and exit.
+    #[arg(long, conflicts_with = "scopes")]
+    list_scopes: bool,
+
+    /// Only include files with uncommitted changes.
+    #[arg(
+        long,
+        long_help = "Only include the files 'git status' reports as changed in the working tree: \
+modified, added, renamed (under their new name), and untracked files, with their current \
+content on disk, e.g. to review your own work before committing it. Every other filter still \
+applies. It is an error outside a git repository."
+    )]
+    dirty: bool,
+
+    /// Leave untracked files out of --dirty.
+    #[arg(long, requires = "dirty")]
+    dirty_tracked_only: bool,
+
+    /// Also include FILE, even from outside the root (repeatable).
+    #[arg(
+        long,
+        value_name = "FILE",
+        long_help = "Include FILE in addition to the scanned files, e.g. a shared \
+../common/proto/defs.proto. FILE is added as named, bypassing ignore rules, but the size \
+limit and binary detection still apply. Files outside the root are shown as \
+external/<file name>, or by their absolute path with --absolute-paths. A missing FILE is \
+reported as a warning, and fails --strict. May be given multiple times."
+    )]
+    also: Vec<PathBuf>,
+
+    /// Skip files larger than SIZE (e.g. 512K, 1M).
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        long_help = "Skip files larger than SIZE. Accepts a plain byte count or a number with \
+a K, M, or G suffix (powers of 1024), e.g. 512K or 1M."
+    )]
+    max_filesize: Option<u64>,
+
+    /// Stop adding files once the document would pass SIZE (e.g. 50M).
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        long_help = "Stop adding file sections once the next one would take the document past \
+SIZE bytes, as a safety valve independent of --budget. Accepts a plain byte count or a \
+number with a K, M, or G suffix (powers of 1024), e.g. 50M. Sections are never cut in half: \
+each is rendered in full and left out if it doesn't fit, along with every file after it. The \
+document ends with an 'Output truncated' section giving the number of files omitted, and \
+the exit status is 6. With --split-by the limit applies to each document."
+    )]
+    max_output: Option<u64>,
+
+    /// Warn when the document is larger than SIZE (default 2M; 0 turns it off).
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        default_value = "2M",
+        long_help = "Warn on stderr when the document written is larger than SIZE bytes, with \
+its size, the estimated tokens, and hints for cutting it down. Accepts a plain byte count or \
+a number with a K, M, or G suffix (powers of 1024). The default of 2M is about 500k tokens; \
+0 turns the warning off. Checked for every destination, stdout included, and for each \
+document of --split-by. Silenced by --quiet."
+    )]
+    warn_size: u64,
+
+    /// Leave out files until the document fits in N tokens (e.g. 100k).
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        value_parser = parse_tokens,
+        long_help = "Keep the document within TOKENS estimated tokens (bytes / 4) by leaving \
+out whole files. Accepts a plain count or a number with a k or M suffix (powers of 1000), \
+e.g. 100k. Files are dropped lowest --priority first and, within a priority, largest first, \
+so as many files as possible are kept; ties go to the file later in path order. Dropped \
+files are counted in the summary by priority. The document is only written once every file \
+has been read."
+    )]
+    budget: Option<u64>,
+
+    /// Weight of files matching GLOB under --budget, e.g. 'src/**=100' (repeatable).
+    #[arg(
+        long,
+        value_name = "GLOB=WEIGHT",
+        value_parser = parse_priority,
+        requires = "budget",
+        long_help = "Give files matching GLOB the weight WEIGHT when --budget has to drop \
+files; lower weights are dropped first. Files no glob matches weigh 0, and when several \
+globs match, the last one decides. Priorities from the [priority] table of \
+.sourceweaver.toml come first, so flags override them. Repeatable."
+    )]
+    priority: Vec<(String, i64)>,
+
+    /// Keep only the first lines of files matching GLOB, e.g. 'data/**=20' (repeatable).
+    #[arg(
+        long,
+        value_name = "GLOB=LINES[,SIZE]",
+        value_parser = parse_truncate,
+        long_help = "Keep only the first LINES lines of text files matching GLOB, and with \
+SIZE, at most that many bytes, whichever cuts more, e.g. 'data/**=20', '**/*.snap=5', or \
+'logs/**=,64K' for a size alone. SIZE accepts a K, M, or G suffix (powers of 1024). A cut \
+file ends with the line '… [truncated by rule GLOB] …' inside its code block. When several \
+globs match, the last one decides. Rules from the [truncate] table of .sourceweaver.toml come \
+first, so flags override them. Repeatable."
+    )]
+    truncate: Vec<(String, TruncateLimit)>,
+
+    /// How files over a --truncate rule are cut down.
+    #[arg(
+        long,
+        value_enum,
+        default_value = "tail",
+        value_name = "STRATEGY",
+        long_help = "How files over a --truncate rule are cut down. 'tail' keeps the first \
+lines. 'smart' first replaces function bodies in Rust, Go, JavaScript, TypeScript, and Python \
+with '{ /* … body elided … */ }' (an indented '...' line in Python), longest first, until the \
+file fits, so every declaration, import, and type stays; what still doesn't fit, and files in \
+other languages, lose their tail as with 'tail'."
+    )]
+    truncate_strategy: TruncateStrategyMode,
+
+    /// End the document with a list of the files left out, and why.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHICH",
+        num_args = 0..=1,
+        default_missing_value = "filtered",
+        conflicts_with = "split_by",
+        long_help = "End the document with an '## Omitted Files' section listing every file \
+under the root that isn't in it, with a one-word reason: excluded (by --include, --exclude, \
+--owner, --scope, and other filters), lockfile, size (--max-filesize or --max-output), or \
+budget. A directory whose files are all left out for the same reason is listed once, as \
+'docs/'. This tells a model reading the document what exists beyond it. Files hidden or \
+matched by ignore files are too many to list by default; '--list-omitted all' adds them, \
+with the reason 'ignored'."
+    )]
+    list_omitted: Option<OmittedMode>,
+
+    /// After the run, list the N files taking the most space.
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "stdin",
+        value_parser = clap::value_parser!(u32).range(1..),
+        long_help = "After the run, print the N files whose sections take the most of the \
+document to stderr, largest first: path, size, estimated tokens (bytes / 4), and share of \
+everything written. Sizes are those of the sections as written, heading and fences \
+included, so a budget-busting file stands out at once. Printed even with --quiet; with \
+--message-format json each row is a largest_file event instead. With 'stats' the table goes \
+to stdout, after the totals."
+    )]
+    top: Option<u32>,
+
+    /// After the run, print where the time went, phase by phase.
+    #[arg(
+        long,
+        long_help = "After the run, print to stderr how long each phase took: walk (traversing \
+and filtering the tree), read (reading files, summed over the reader threads, so it can \
+exceed the wall time), transform (content transforms such as --collapse-data), tokenize \
+(fitting --budget), and write (rendering sections and writing the document out), with the \
+total wall time. Printed even with --quiet; with --message-format json it is a timings \
+event instead."
+    )]
+    timings: bool,
+
+    /// Replace long runs of embedded data, like byte arrays, with a comment.
+    #[arg(
+        long,
+        long_help = "Replace embedded data blobs inside text files with a one-line comment \
+such as '/* 2,048 lines of embedded data elided */', keeping the code around them. A blob \
+is a run of lines holding only numeric literals (0x3c, 0x66, ...), a long base64 string, or \
+\\xNN escapes; any other line ends it."
+    )]
+    collapse_data: bool,
+
+    /// Only collapse runs of at least N data lines.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_COLLAPSE_MIN_LINES,
+        requires = "collapse_data"
+    )]
+    collapse_min_lines: usize,
+
+    /// Replace tabs with spaces to tab stops every WIDTH columns [default: 4].
+    #[arg(
+        long,
+        value_name = "WIDTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "4",
+        value_parser = clap::value_parser!(u32).range(1..=64),
+        long_help = "Replace each tab in text files with spaces up to the next tab stop, every \
+WIDTH columns (4 when only --expand-tabs is given), so columns line up the way an editor \
+shows them and token counts don't depend on how tabs are rendered. Tabs anywhere in a line \
+are expanded, keeping alignment. Makefiles (Makefile, GNUmakefile, *.mk, *.mak) are left \
+alone, as their recipes need their tabs."
+    )]
+    expand_tabs: Option<u32>,
+
+    /// With --expand-tabs, take each file's tab width from .editorconfig.
+    #[arg(
+        long,
+        requires = "expand_tabs",
+        long_help = "With --expand-tabs, use the tab_width, or else a numeric indent_size, that \
+the .editorconfig files above each file set for it, read from the file's directory upwards \
+until one has root = true. Files no section sets a width for use --expand-tabs' WIDTH. \
+Sections cached with --cache aren't redone when only an .editorconfig changes."
+    )]
+    respect_editorconfig: bool,
+
+    /// Append an index of each file's top-level symbols.
+    #[arg(
+        long,
+        conflicts_with = "cache",
+        long_help = "End the document with a '## Symbol Index' section listing, per file, the \
+top-level definitions found by lightweight pattern matching, each file linked to its heading: \
+fn, struct, enum, trait, and impl for Rust; def and class for Python; function, class, and \
+export const for JavaScript and TypeScript. Files in other languages are left out of the \
+index. Can't be combined with --cache, since reused sections aren't read."
+    )]
+    symbol_index: bool,
+
+    /// Add a '## Recent History' section listing the last N commits.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..=history::MAX_COMMITS as i64),
+        long_help = "Add a '## Recent History' section listing the last N commits of the \
+repository the root is in, one line each with the short SHA, date, author, and subject, so \
+the model knows what changed lately. Subjects are cut at 72 characters and N is at most 100, \
+to keep the section small. Outside a git repository the section is left out with a warning."
+    )]
+    git_log: Option<u32>,
+
+    /// List only commits touching the woven paths in --git-log.
+    #[arg(
+        long,
+        requires = "git_log",
+        long_help = "Restrict --git-log to commits touching the root, or the PATH arguments \
+when given, instead of the whole repository; useful for one service of a monorepo."
+    )]
+    git_log_scoped: bool,
+
+    /// Where --git-log puts its section.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHERE",
+        default_value_t,
+        requires = "git_log"
+    )]
+    git_log_at: HistoryPosition,
+
+    /// Reuse unchanged files' sections from the cache FILE.
+    #[arg(
+        long,
+        value_name = "FILE",
+        long_help = "Keep every rendered file section in FILE, e.g. .sourceweaver-cache.json, \
+and on later runs reuse the sections of files whose size and modification time are \
+unchanged, without reading them again. Changed files are rendered afresh and the cache \
+updated. The cache is discarded when it was written by another version or with different \
+content options such as --collapse-data; a corrupt cache only costs a full run. FILE is \
+never part of the document."
+    )]
+    cache: Option<PathBuf>,
+
+    /// Read every file and reuse only sections whose content hash matches.
+    #[arg(
+        long,
+        requires = "cache",
+        long_help = "Don't trust size and modification time: read every file and reuse its \
+cached section only when the content hashes the same. Slower, but immune to tools that \
+restore modification times."
+    )]
+    no_cache_trust_mtime: bool,
+
+    /// Only print errors to stderr.
+    #[arg(
+        short,
+        long,
+        conflicts_with = "verbose",
+        long_help = "Suppress status messages and warnings on stderr; only errors are printed. \
+The document itself is unaffected."
+    )]
+    quiet: bool,
+
+    /// How to handle git submodules: include or skip.
+    #[arg(
+        long,
+        value_name = "MODE",
+        value_enum,
+        default_value_t = SubmoduleMode::Include,
+        long_help = "How to handle git submodules, as listed in the repository's .gitmodules \
+file. 'include' walks initialized submodules, honouring each submodule's own .gitignore, \
+and writes a placeholder section for each uninitialized one. 'skip' leaves submodule \
+directories out entirely."
+    )]
+    submodules: SubmoduleMode,
+
+    /// Whether stderr is treated as a terminal: auto, always, or never.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        default_value_t = ColorChoice::Auto,
+        long_help = "Whether stderr is treated as an interactive terminal. With 'auto', status \
+messages such as 'Scanning directory' are only printed when stderr is a terminal, so \
+pipelines stay quiet; warnings and errors are always printed. Warnings and errors are \
+colored on a terminal unless NO_COLOR is set. 'always' prints status messages and colors \
+regardless, 'never' suppresses both even on a terminal. -v always prints status messages. \
+The document itself never depends on this setting."
+    )]
+    color: ColorChoice,
+
+    /// Format of messages on stderr: human or json.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t = MessageFormat::Human,
+        long_help = "Format of messages on stderr. 'json' writes one JSON object per line \
+instead of prose, for tools wrapping sourceweaver: {\"type\":\"file\",\"path\",\"bytes\"} per \
+file, {\"type\":\"warning\"|\"error\",\"message\",\"path\"?} for problems, and \
+{\"type\":\"summary\",\"files\",\"bytes\",\"tokens\"} at the end. The document itself is \
+unaffected."
+    )]
+    message_format: MessageFormat,
+
+    /// Print more detail to stderr (-v per file, -vv per filter decision).
+    #[arg(
+        short,
+        long,
+        action = clap::ArgAction::Count,
+        long_help = "Print more detail to stderr. -v adds a line per processed file; -vv also \
+explains why entries are skipped, including the matching ignore rule. RUST_LOG, when set, \
+takes precedence. The document itself is unaffected."
+    )]
+    verbose: u8,
+
+    /// Log each filter stage's verdict on the files matching GLOB (repeatable).
+    #[arg(
+        long,
+        value_name = "GLOB",
+        long_help = "Before the run, log what each filter stage decides about every file \
+matching GLOB (gitignore syntax, relative to the root), ignored and hidden files included, \
+as 'sourceweaver explain' prints it for one path. Useful to find out why files under a \
+directory are missing without listing them one by one. The decisions are status messages, \
+so when stderr isn't a terminal they need -v. Repeatable."
+    )]
+    debug_filter: Vec<String>,
+
+    /// Never show a progress bar.
+    #[arg(
+        long,
+        long_help = "Never show a progress bar. By default a bar with files processed, bytes \
+read, and an ETA is drawn on stderr for large trees when stderr is a terminal."
+    )]
+    no_progress: bool,
+
+    /// Exit with status 5 if any file or directory could not be read.
+    #[arg(
+        long,
+        long_help = "Treat unreadable files and inaccessible directories as errors. The \
+document is still produced, but the process exits with status 5 instead of 0 so problems \
+don't go unnoticed in CI. Implies --fail-if-empty."
+    )]
+    strict: bool,
+
+    /// Exit with status 7, writing nothing, if no files matched.
+    #[arg(
+        long,
+        long_help = "Treat a walk that selects no files, e.g. because of a mistyped --root or an \
+exclude that matches everything, as an error: nothing is written or copied and the process \
+exits with status 7. Without it an empty document is produced with a warning listing the \
+directories visited and the active filters. Implied by --strict."
+    )]
+    fail_if_empty: bool,
+
+    /// Abort on the first unreadable file or directory.
+    #[arg(
+        long,
+        long_help = "Abort on the first file or directory that cannot be read, instead of \
+warning and carrying on. Output already written to stdout is flushed as is; the partial \
+--output document is discarded and any previous file left in place."
+    )]
+    fail_fast: bool,
+
+    /// What the document shows for files that can't be read.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        default_value_t = PlaceholderMode::Note,
+        long_help = "What the document shows for a file that can't be read. 'note' (the default) \
+writes the heading followed by a one-line italic note, outside any code block so it isn't \
+mistaken for the file's content. 'inline' writes the error inside the code block instead. \
+'omit' leaves the file out entirely, heading included. Either way the error is logged, \
+counted in the summary, and fails --strict."
+    )]
+    error_placeholders: PlaceholderMode,
+
+    /// Put the SHA-256 of each file's content under its heading.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "OVER",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "original",
+        long_help = "Put a line such as `sha256:1f2a…` under each file heading, so you can later \
+check whether a file still matches what the document shows (compare with sha256sum). \
+'original' (the default) hashes the exact bytes read from disk, text and binary files alike. \
+'post-transform' hashes the code block's text as the document shows it, after content options \
+such as --collapse-data rewrote it and with \\n line endings; binary files still hash their \
+bytes on disk. Unreadable files and \
+content read with --stdin get no checksum."
+    )]
+    checksums: Option<ChecksumMode>,
+
+    /// Check the content of files with binary extensions such as .png too.
+    #[arg(
+        long,
+        long_help = "Read files with extensions of formats that are always binary, such as .png, \
+.jar, .woff2, and .mp4, to check their content like any other file's. By default they get the \
+binary placeholder without being read, which saves reading every asset in a tree only to leave \
+it out. Use this when such names may hold text, e.g. Git LFS pointer files. --checksums reads \
+them anyway."
+    )]
+    no_extension_shortcut: bool,
+
+    /// Enclose each file section in BEGIN/END FILE sentinel lines.
+    #[arg(
+        long,
+        long_help = "Enclose each file section in sentinel lines, '===== BEGIN FILE: src/main.rs \
+=====' before the heading and '===== END FILE: src/main.rs =====' after the code fence, for \
+pipelines that split the document without parsing Markdown. The lines can't be mistaken for \
+code fences, so files containing backticks can't confuse them, and they name the path shown \
+in the heading. 'verify' splits such documents at their sentinels."
+    )]
+    sentinels: bool,
+
+    /// Report bidi controls, zero-width characters, and lookalike letters.
+    #[arg(
+        long,
+        long_help = "Scan the text of every file for characters that can make code read \
+differently than it runs: bidi controls (the 'Trojan Source' attack), zero-width and other \
+invisible format characters (Unicode category Cf), and Cyrillic or Greek letters that look \
+Latin inside otherwise ASCII words. Each one is reported after the summary with its file, \
+line, and code point. The content is left as it is; see --escape-unicode-tricks. Every file \
+is read, so --cache isn't used."
+    )]
+    flag_unicode_tricks: bool,
+
+    /// Like --flag-unicode-tricks, also writing them as visible escapes.
+    #[arg(
+        long,
+        long_help = "Like --flag-unicode-tricks, and also write each character found as a \
+visible escape such as \\u{202E} in the document, so the code reads the way it compiles."
+    )]
+    escape_unicode_tricks: bool,
+
+    /// Note each file's last commit under its heading.
+    #[arg(
+        long,
+        long_help = "Put a line such as 'Last commit: 2024-05-01, a1b2c3d, Alice' under each \
+file heading: the date, short SHA, and author of the most recent commit touching the file. \
+Files git doesn't track show '(untracked)'. History is read once for the whole tree with a \
+single git log, so the cost doesn't grow with a git call per file. The root must be in a git \
+repository."
+    )]
+    git_info: bool,
+
+    /// Note each file's main authors, by git blame, under its heading.
+    #[arg(
+        long,
+        long_help = "Put a line such as 'authors: alice 62%, bob 31%, 3 others' under each file \
+heading, from git blame: the two authors owning the most lines with their share, then how \
+many others there are. Blame is slow, so it runs on the reader threads, and with --cache \
+unchanged files reuse their cached line without being blamed again. Binary and untracked \
+files get no line."
+    )]
+    blame_summary: bool,
+
+    /// Leave files matching GLOB without a blame summary (repeatable).
+    #[arg(
+        long,
+        value_name = "GLOB",
+        requires = "blame_summary",
+        long_help = "Don't blame files matching the gitignore-style GLOB, e.g. generated code \
+or vendored files whose authors say nothing. A glob matching a directory covers everything \
+below it. May be given multiple times."
+    )]
+    skip_blame: Vec<String>,
+
+    /// Walk and read on up to N threads [default: CPUs, at most 8].
+    #[arg(
+        short = 'j',
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        long_help = "Walk the tree and read files on up to N threads; this one setting \
+controls every parallel stage. The document is identical for any N: sections are always \
+written in sorted path order, and at most 64 MiB of content is read ahead of the writer. \
+--threads 1 runs everything sequentially on one thread, which is useful on constrained \
+machines and when debugging. Defaults to the number of CPUs, capped at 8."
+    )]
+    threads: Option<u32>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the manual page for sourceweaver in roff format.
+    #[command(
+        hide = true,
+        long_about = "Render the manual page for sourceweaver and its subcommands in roff \
+format. Pages are printed to standard output unless --out-dir is given, in which case one \
+file per command is written to that directory (e.g. sourceweaver.1)."
+    )]
+    Man {
+        /// Write one man page per command into DIR instead of printing to stdout.
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+
+    /// List the files the document would contain, without writing it.
+    #[command(
+        long_about = "List the files the document would contain, with their size and \
+language, without writing a document; only files whose language depends on their content \
+are read. --estimate adds what each would cost \
+in tokens. The filters are the same as for a \
+normal run, so options such as --root, --exclude, and --hidden go before 'list', e.g. \
+'sourceweaver --exclude \"*.snap\" list'."
+    )]
+    List {
+        /// Also list entries left out by the built-in filters, with the reason.
+        #[arg(
+            long,
+            long_help = "Also list entries left out by sourceweaver's own filters (lock files, \
+size limit, submodules, special files, ...), with the reason. Entries hidden by .gitignore or \
+as dotfiles aren't listed."
+        )]
+        all: bool,
+
+        /// One tab-separated record per line, for scripts.
+        #[arg(
+            long,
+            long_help = "Print one record per line with tab-separated fields, for scripts: \
+path, size in bytes ('-' when unknown), language tag (possibly empty), and 'included' or the \
+skip reason. No header, no alignment, and the field order never changes. Tabs, line breaks, \
+and backslashes in paths are escaped as \\t, \\n, \\r, and \\\\."
+        )]
+        porcelain: bool,
+
+        /// Show each file's size and estimated tokens, costliest first.
+        #[arg(
+            long,
+            value_name = "MODE",
+            value_enum,
+            num_args = 0..=1,
+            default_missing_value = "exact",
+            conflicts_with = "all",
+            long_help = "Show each file's size and estimated token count, costliest first, \
+followed by the totals, without writing a document. 'exact' (the default) renders every \
+section as a real run would, with the same transformations such as --collapse-data, and \
+counts it, so the total matches the summary of a real run. 'fast' doesn't read anything and \
+extrapolates from file sizes. With --porcelain each line holds the path, size in bytes, and \
+tokens, separated by tabs, and there is no total line."
+        )]
+        estimate: Option<Estimate>,
+    },
+
+    /// Show why a path is or isn't in the document, filter by filter.
+    #[command(
+        long_about = "Run every filter stage of a normal run on PATH alone and print what each \
+decides, then whether the file is included: the ignore rule that matched, with its file and \
+line, the hidden-file rule, the lock file filter, the size limit, the include and exclude \
+globs, and so on. Stages also check the directories above PATH, as the walk never enters one \
+a stage leaves out. PATH is taken relative to the working directory when it exists there, \
+and otherwise relative to the root. The filters are those of a normal run, so options go \
+before 'explain', e.g. 'sourceweaver --exclude \"src/gen/**\" explain src/gen/schema.rs'. \
+Content filters, --budget, and --max-output apply once files are read, so a file shown as \
+included may still be left out by them."
+    )]
+    Explain {
+        /// The file or directory to explain.
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Print per-language file, line, byte, and token counts, without writing a document.
+    #[command(
+        long_about = "Read every file the document would contain, exactly as a real run \
+would, but print only numbers: files, lines, bytes, and estimated tokens per language, the \
+totals, and how many files were left out for each reason. Bytes are those of the file \
+sections, so the tokens add up to what a real run reports. The filters and content options \
+are those of a normal run and go before 'stats', e.g. 'sourceweaver --collapse-data stats'; \
+with --top, the largest files are listed after the totals."
+    )]
+    Stats {
+        /// Print an aligned table or one JSON object.
+        #[arg(
+            long = "stats-format",
+            value_name = "FORMAT",
+            value_enum,
+            default_value_t = StatsFormat::Table,
+            long_help = "'table' (the default) prints aligned columns for people. 'json' \
+prints one object with a 'languages' array of {language, files, lines, bytes, tokens}, a \
+'total' object with the same fields, an 'excluded' object counting files by reason, and with \
+--top a 'largest' array of {path, bytes, tokens}."
+        )]
+        format: StatsFormat,
+    },
+
+    /// Check that the --output document is up to date, for CI.
+    #[command(
+        long_about = "Regenerate the --output document in memory, with the same options, and \
+compare it with the file on disk, ignoring the generation marker line and its timestamp. \
+Exits with status 0 when they match and 1 when the file is missing or out of date, listing \
+the file sections that changed, were added, or were removed. Options go before 'verify', \
+e.g. 'sourceweaver --exclude \"*.snap\" -o CONTEXT.md verify'."
+    )]
+    Verify {
+        /// Rewrite the document when it is out of date, instead of failing.
+        #[arg(
+            long,
+            long_help = "Rewrite the document when it is out of date and exit with status 0, \
+like rustfmt without --check. An up-to-date file is left untouched."
+        )]
+        write: bool,
+    },
+}
+
+/// Values of `--error-placeholders`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PlaceholderMode {
+    /// The error in a code block under the heading.
+    Inline,
+    /// Nothing at all.
+    Omit,
+    /// The heading and an italic note.
+    Note,
+}
+
+impl From<PlaceholderMode> for ErrorPlaceholders {
+    fn from(mode: PlaceholderMode) -> Self {
+        match mode {
+            PlaceholderMode::Inline => ErrorPlaceholders::Inline,
+            PlaceholderMode::Omit => ErrorPlaceholders::Omit,
+            PlaceholderMode::Note => ErrorPlaceholders::Note,
+        }
+    }
+}
+
+/// Values of `--checksums`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ChecksumMode {
+    /// The bytes read from disk.
+    Original,
+    /// The content as written to the document.
+    PostTransform,
+}
+
+impl From<ChecksumMode> for Checksums {
+    fn from(mode: ChecksumMode) -> Self {
+        match mode {
+            ChecksumMode::Original => Checksums::Original,
+            ChecksumMode::PostTransform => Checksums::PostTransform,
+        }
+    }
+}
+
+/// Values of `--truncate-strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TruncateStrategyMode {
+    /// Keep the first lines.
+    Tail,
+    /// Elide function bodies first, longest first.
+    Smart,
+}
+
+impl From<TruncateStrategyMode> for TruncateStrategy {
+    fn from(mode: TruncateStrategyMode) -> Self {
+        match mode {
+            TruncateStrategyMode::Tail => TruncateStrategy::Tail,
+            TruncateStrategyMode::Smart => TruncateStrategy::Smart,
+        }
+    }
+}
+
+/// Values of `--list-omitted`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OmittedMode {
+    /// Files the filters, size limits, and budget left out.
+    Filtered,
+    /// Those, and hidden and ignored files.
+    All,
+}
+
+impl From<OmittedMode> for OmittedFiles {
+    fn from(mode: OmittedMode) -> Self {
+        match mode {
+            OmittedMode::Filtered => OmittedFiles::Filtered,
+            OmittedMode::All => OmittedFiles::All,
+        }
+    }
+}
+
+/// Values of `--submodules`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SubmoduleMode {
+    /// Walk initialized submodules and note uninitialized ones.
+    Include,
+    /// Leave submodules out.
+    Skip,
+}
+
+impl From<SubmoduleMode> for Submodules {
+    fn from(mode: SubmoduleMode) -> Self {
+        match mode {
+            SubmoduleMode::Include => Submodules::Include,
+            SubmoduleMode::Skip => Submodules::Skip,
+        }
+    }
+}
+
+/// One of `--exclude`, `--include`, or `--patterns-from`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternArg {
+    Exclude(String),
+    Include(String),
+    File(PathBuf),
+}
+
+/// Process exit statuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    /// The document was produced (possibly with warnings, outside strict mode).
+    Success = 0,
+    /// `--strict-content` found a file matching an excluded content regex,
+    /// or `verify` found the document out of date.
+    Failure = 1,
+    /// The arguments, options, or configuration file were invalid, or the
+    /// root doesn't exist. Matches the status clap uses for usage errors,
+    /// including unreadable response files.
+    Usage = 2,
+    /// A fatal I/O or walk error stopped the run: the root or an archive
+    /// root couldn't be read, the output couldn't be written, or
+    /// `--fail-fast` hit an unreadable file or directory.
+    Io = 3,
+    /// The clipboard could not be used.
+    Clipboard = 4,
+    /// The run completed, but with warnings, and `--strict` was given.
+    StrictWarnings = 5,
+    /// The document reached `--max-output` and files were left out.
+    Truncated = 6,
+    /// No files matched, and `--fail-if-empty` or `--strict` was given.
+    Empty = 7,
+}
+
+impl Exit {
+    // Map a fatal error onto the process exit status. Every variant is
+    // listed, so a new one has to be given a status deliberately.
+    fn from_error(err: &WeaveError) -> Self {
+        match err {
+            WeaveError::InvalidOptions(_) => Exit::Usage,
+            // A mistyped root is a usage error; one that exists but can't
+            // be read is an I/O error
+            WeaveError::Root { source, .. } if source.kind() == io::ErrorKind::NotFound => {
+                Exit::Usage
+            }
+            WeaveError::NoFiles { .. } => Exit::Empty,
+            WeaveError::Clipboard(_) => Exit::Clipboard,
+            WeaveError::ExcludedContent { .. } => Exit::Failure,
+            // Walk and read errors are only fatal under --fail-fast
+            WeaveError::Root { .. }
+            | WeaveError::Walk { .. }
+            | WeaveError::Read { .. }
+            | WeaveError::Archive { .. }
+            | WeaveError::OutputFile { .. }
+            | WeaveError::Output(_) => Exit::Io,
+        }
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = argfile::expand(std::env::args_os())
+        .unwrap_or_else(|e| Args::command().error(ErrorKind::Io, e).exit());
+    let matches = Args::command().get_matches_from(args);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    args.patterns = ordered_patterns(&args, &matches);
+    logging::init(args.quiet, args.verbose, args.color, args.message_format);
+    let strict = args.strict;
+    let timings = args.timings;
+
+    let started = Instant::now();
+    let result = run(args);
+    if timings && let Ok(Outcome::Report(report)) = &result {
+        summary::print_timings(&report.timings, started.elapsed());
+    }
+    let exit = match result {
+        Ok(Outcome::Report(report)) if report.is_truncated() => {
+            warn!(
+                "Output truncated at --max-output: {} file(s) omitted.",
+                report.truncated_files().count()
+            );
+            Exit::Truncated
+        }
+        Ok(Outcome::Report(report)) if report.is_clean() => Exit::Success,
+        Ok(Outcome::Report(report)) => {
+            warn!(
+                "Completed with {} warning(s); {} file(s) written.",
+                report.errors.len(),
+                report.files_written
+            );
+            if strict {
+                error!("Treating warnings as errors because of --strict.");
+                Exit::StrictWarnings
+            } else {
+                Exit::Success
+            }
+        }
+        Ok(Outcome::Stale) => Exit::Failure,
+        Err(e) => {
+            error!("{}", e);
+            Exit::from_error(&e)
+        }
+    };
+    exit.into()
+}
+
+/// What a run that didn't fail came to.
+enum Outcome {
+    /// The run completed, with this report.
+    Report(Box<WeaveReport>),
+    /// `verify` found the document out of date.
+    Stale,
 }
 
-// Define common lock file names
-const LOCK_FILES: &[&str] = &[
-    "Cargo.lock",
-    "package-lock.json",
-    "yarn.lock",
-    "poetry.lock",
-    "Gemfile.lock",
-    "composer.lock",
-    "Pipfile.lock",
-    "go.sum",
-    "flake.lock",
-    "bun.lock",
-    "pnpm-lock.yaml",
-];
-
-fn main() -> io::Result<()> {
-    let args = Args::parse();
-
-    let root_dir = args
-        .root
-        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
-
-    // Use stderr for status messages to avoid polluting stdout
-    eprintln!("Scanning directory: {}", root_dir.display());
-
-    if args.clipboard {
-        // Write to an in-memory byte vector first
-        let mut buffer: Vec<u8> = Vec::new();
-        generate_markdown(&mut buffer, &root_dir, args.hidden, None)?;
+fn run(args: Args) -> sourceweaver::Result<Outcome> {
+    if let Some(Command::Man { out_dir }) = args.command {
+        render_man_pages(out_dir.as_deref())?;
+        return Ok(Outcome::Report(Box::default()));
+    }
+
+    let cwd = std::env::current_dir().map_err(|source| WeaveError::Root {
+        path: PathBuf::from("."),
+        source,
+    })?;
+    let paths: Vec<PathBuf> = args.paths.iter().map(|p| absolute(&cwd, p)).collect();
+    let root_dir = match (&args.root, paths.is_empty()) {
+        (Some(root), _) => root.clone(),
+        (None, true) => cwd.clone(),
+        (None, false) => common_base(&cwd, &paths),
+    };
+
+    let config = Config::load(&root_dir)?;
+    if args.list_scopes {
+        if config.scopes.is_empty() {
+            warn!("{} defines no [scopes]", config::CONFIG_FILE);
+        }
+        config.print_scopes()?;
+        return Ok(Outcome::Report(Box::default()));
+    }
 
+    // Status messages go to stderr via the subscriber to avoid polluting stdout
+    if !args.stdin {
+        info!("Scanning directory: {}", root_dir.display());
+    }
+
+    let stamp = Stamp::new(&root_dir, args.reproducible)?;
+    let prompt = Prompt::new(&args.prefix, &args.suffix)?;
+    let scope = match paths.is_empty() {
+        true => vec![root_dir.clone()],
+        false => paths.clone(),
+    };
+    let history = History::load(
+        &root_dir,
+        args.git_log.filter(|_| !args.stdin),
+        args.git_log_scoped.then_some(scope.as_slice()),
+        args.git_log_at,
+    );
+
+    let mut builder = WeaveOptions::new(&root_dir)
+        .hidden(args.hidden)
+        .default_filters(!args.no_default_filters)
+        .fail_fast(args.fail_fast)
+        .fail_if_empty(args.fail_if_empty || args.strict)
+        .error_placeholders(args.error_placeholders.into())
+        .follow_links(args.follow)
+        .submodules(args.submodules.into());
+    for path in &paths {
+        builder = builder.path(path);
+    }
+    if !args.package.is_empty() {
+        for path in packages::resolve(&root_dir, &args.package, args.with_path_deps)? {
+            builder = builder.path(root_dir.join(path));
+        }
+    }
+    for path in &args.also {
+        builder = builder.also(absolute(&cwd, path));
+    }
+    for pattern in &args.patterns {
+        builder = match pattern {
+            PatternArg::Exclude(glob) => builder.exclude(glob),
+            PatternArg::Include(glob) => builder.include(glob),
+            PatternArg::File(path) => builder.patterns_from(path),
+        };
+    }
+    for glob in &args.force_include {
+        builder = builder.force_include(glob);
+    }
+    for re in &args.path_regex {
+        builder = builder.path_regex(re);
+    }
+    for re in &args.path_regex_not {
+        builder = builder.path_regex_not(re);
+    }
+    for re in &args.exclude_content {
+        builder = builder.exclude_content(re);
+    }
+    builder = builder.strict_content(args.strict_content);
+    for re in &args.grep {
+        builder = builder.grep(re);
+    }
+    if let Some(lines) = args.grep_context {
+        builder = builder.grep_context(lines);
+    }
+    for name in &args.exclude_dir {
+        builder = builder.exclude_dir(name);
+    }
+    if args.dirty {
+        builder = builder.only_files(git::dirty_files(&root_dir, args.dirty_tracked_only)?);
+    }
+    for owner in &args.owner {
+        builder = builder.owner(owner);
+    }
+    if !args.scopes.is_empty() {
+        builder = builder.scope(config.scope_globs(&args.scopes)?);
+    }
+    for (glob, description) in config.annotations {
+        builder = builder.annotate(glob, description);
+    }
+    for (glob, weight) in config.priorities {
+        builder = builder.priority(glob, weight);
+    }
+    for (glob, limit) in config
+        .truncations
+        .into_iter()
+        .chain(args.truncate.iter().cloned())
+    {
+        builder = builder.truncate(glob, limit);
+    }
+    builder = builder.truncate_strategy(args.truncate_strategy.into());
+    if let Some(mode) = args.list_omitted {
+        builder = builder.list_omitted(mode.into());
+    }
+    for pattern in config
+        .lock_files
+        .into_iter()
+        .chain(args.lock_file.iter().cloned())
+    {
+        builder = builder.lock_file(pattern);
+    }
+    // The platform default would make the selection differ between systems
+    if args.reproducible {
+        builder = builder.lock_files_case_insensitive(true);
+    }
+    if let Some(ignore_case) = config.lock_files_ignore_case {
+        builder = builder.lock_files_case_insensitive(ignore_case);
+    }
+    if let Some(mode) = args.checksums {
+        builder = builder.checksums(mode.into());
+    }
+    builder = builder.sentinels(args.sentinels);
+    builder = builder.extension_shortcut(!args.no_extension_shortcut);
+    if args.escape_unicode_tricks {
+        builder = builder.unicode_tricks(UnicodeTricks::Escape);
+    } else if args.flag_unicode_tricks {
+        builder = builder.unicode_tricks(UnicodeTricks::Flag);
+    }
+    builder = builder.git_info(args.git_info);
+    builder = builder.blame_summary(args.blame_summary);
+    for glob in &args.skip_blame {
+        builder = builder.skip_blame(glob);
+    }
+    if let Some(max_filesize) = args.max_filesize {
+        builder = builder.max_filesize(max_filesize);
+    }
+    if let Some(max_output) = args.max_output {
+        builder = builder.max_output(max_output);
+    }
+    if let Some(budget) = args.budget {
+        builder = builder.budget(budget);
+    }
+    for (glob, weight) in &args.priority {
+        builder = builder.priority(glob, *weight);
+    }
+    if let Some(threads) = args.threads {
+        builder = builder.threads(threads as usize);
+    }
+    if args.absolute_paths {
+        builder = builder.absolute_paths(true);
+    }
+    if args.native_paths {
+        builder = builder.native_paths(true);
+    }
+    if let Some(prefix) = &args.path_prefix {
+        builder = builder.path_prefix(prefix);
+    }
+    if args.repo_url.is_some() || args.auto_repo_url || args.link_template.is_some() {
+        builder = builder.link_template(git::link_template(
+            &root_dir,
+            args.repo_url.as_deref(),
+            args.auto_repo_url,
+            args.link_template.as_deref(),
+        )?);
+    }
+    if let Some(dir) = &args.relative_to {
+        builder = builder.relative_to(dir);
+    }
+    if let Some(cache) = &args.cache {
+        // stats counts the lines of every file, so it can't skip reading any
+        if !matches!(args.command, Some(Command::Stats { .. })) {
+            builder = builder
+                .cache(cache, content_key(&args))
+                .cache_trust_mtime(!args.no_cache_trust_mtime);
+        }
+        let mut temp = cache.clone().into_os_string();
+        temp.push(".tmp");
+        for path in [cache.clone(), PathBuf::from(temp)] {
+            if let Some(canonical_path) = output::canonical_target(&path) {
+                builder = builder.exclude_path(canonical_path);
+            }
+        }
+    }
+
+    if let Some(Command::List {
+        all,
+        porcelain,
+        estimate,
+    }) = args.command
+    {
+        let options = builder.build()?;
+        let report = match estimate {
+            Some(mode) => list::estimate(
+                &options,
+                mode,
+                porcelain,
+                &mut content_visitors(&args, &root_dir),
+            )?,
+            None => list::run(&options, all, porcelain)?,
+        };
+        return Ok(Outcome::Report(Box::new(report)));
+    }
+    if let Some(Command::Stats { format }) = args.command {
+        let options = builder.build()?;
+        let top = args.top.map(|top| top as usize);
+        let report = stats::run(
+            &options,
+            format,
+            top,
+            &mut content_visitors(&args, &root_dir),
+        )?;
+        return Ok(Outcome::Report(Box::new(report)));
+    }
+    if !args.debug_filter.is_empty() {
+        let options = builder.clone().build()?;
+        for explanation in sourceweaver::explain_matching(&options, &args.debug_filter)? {
+            info!("Filter decisions for {}", explanation);
+        }
+    }
+    if let Some(Command::Explain { path }) = &args.command {
+        let options = builder.build()?;
+        // A path the working directory has but the root doesn't is resolved
+        // from the working directory
+        let path = match fs::canonicalize(path) {
+            Ok(canonical) if !root_dir.join(path).exists() => canonical,
+            _ => path.clone(),
+        };
+        println!("{}", sourceweaver::explain(&options, &path));
+        return Ok(Outcome::Report(Box::default()));
+    }
+    if let Some(split_by) = args.split_by {
+        return run_split(
+            &args, builder, &root_dir, &stamp, &prompt, &history, split_by,
+        )
+        .map(|report| Outcome::Report(Box::new(report)));
+    }
+
+    // `--output -` is stdout; a directory gets a generated file name
+    let output_path = args
+        .output
+        .as_deref()
+        .and_then(|path| output::resolve_target(path, &root_dir, &stamp));
+    if output_path.is_none() && args.compress.is_some() {
+        return Err(WeaveError::InvalidOptions(
+            "--compress only applies to --output files, and --output - writes to stdout"
+                .to_string(),
+        ));
+    }
+    // Without an explicit destination the document goes to stdout
+    let to_stdout = args.stdout
+        || (output_path.is_none()
+            && args.inject.is_none()
+            && (args.output.is_some() || !args.clipboard));
+
+    // Decide everything before the output file is touched, so an invalid
+    // option or a refused overwrite never destroys an existing file
+    if let Some(output_path) = output_path.as_ref().or(args.inject.as_ref()) {
+        info!("Outputting to: {}", output_path.display());
+        output::check_target(output_path, &root_dir)?;
+        // Exclude both the output file and the temporary file it is written to
+        for path in [output_path.clone(), output::temp_path(output_path)] {
+            match output::canonical_target(&path) {
+                Some(canonical_path) => builder = builder.exclude_path(canonical_path),
+                None => warn!(
+                    "Could not canonicalize output path {}. It might be included if inside the scanned directory.",
+                    path.display()
+                ),
+            }
+        }
+    }
+    let options = builder.build()?;
+
+    if let Some(Command::Verify { write }) = args.command {
+        let Some(output_path) = &output_path else {
+            return Err(WeaveError::InvalidOptions(
+                "verify needs the document to check, given with --output FILE".to_string(),
+            ));
+        };
+        if args.append || args.inject.is_some() {
+            return Err(WeaveError::InvalidOptions(
+                "verify can't check --append or --inject documents".to_string(),
+            ));
+        }
+        return run_verify(
+            &args,
+            &options,
+            &stamp,
+            &prompt,
+            &history,
+            output_path,
+            write,
+        );
+    }
+
+    let output_file = match &output_path {
+        Some(output_path) => {
+            let compression = args
+                .compress
+                .or_else(|| Compression::from_path(output_path));
+            if let Some(compression) = compression {
+                compression.check_supported()?;
+            }
+            let mut output_file =
+                OutputFile::open(output_path, args.append, args.force, compression)?;
+            output_file.begin_run(&stamp)?;
+            Some(output_file)
+        }
+        None => match &args.inject {
+            Some(inject_path) => Some(OutputFile::inject(inject_path)?),
+            None => None,
+        },
+    };
+
+    // The bar lives on stderr; keep it off a terminal that is also showing the document
+    let document_on_terminal = to_stdout && io::stdout().is_terminal();
+    let progress = ProgressVisitor::new(
+        !args.no_progress
+            && !args.quiet
+            && io::stderr().is_terminal()
+            && !document_on_terminal
+            && !logging::json_events(),
+    );
+    let symbols = SymbolIndex::new();
+    let mut visitors: Vec<Box<dyn FileVisitor>> = vec![Box::new(progress)];
+    visitors.extend(content_visitors(&args, &root_dir));
+    if args.symbol_index {
+        visitors.push(Box::new(symbols.clone()));
+    }
+
+    let mut destinations = Destinations::new(output_file, to_stdout, args.clipboard);
+    let result = (|| {
+        prompt.write_prefix(&mut destinations)?;
+        history.write_at(&mut destinations, HistoryPosition::Start)?;
+        let report = if args.stdin {
+            weave_stdin(&mut destinations, args.stdin_name.as_deref())?
+        } else {
+            let report = generate_markdown_with(&mut destinations, &options, &mut visitors)?;
+            symbols.write_markdown(&mut destinations, &options)?;
+            report
+        };
+        history.write_at(&mut destinations, HistoryPosition::End)?;
+        prompt.write_suffix(&mut destinations)?;
+        Ok(report)
+    })();
+    // Dropping the progress visitor removes the bar
+    drop(visitors);
+    let mut report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            destinations.abandon();
+            if let WeaveError::NoFiles { dirs_visited } = e {
+                summary::log_no_files(&options, dirs_visited);
+            }
+            return Err(e);
+        }
+    };
+    if report.files_written == 0 && !args.stdin {
+        summary::log_no_files(&options, report.dirs_visited);
+    }
+
+    // The file and stdout are complete before the clipboard is tried, so a
+    // clipboard failure never costs the written document
+    let bytes = destinations.bytes_written();
+    let finishing = Instant::now();
+    let buffer = destinations.finish()?;
+    report.timings.write += finishing.elapsed();
+    if let Some(buffer) = buffer {
         // Convert the byte vector to a String
         let output_string = String::from_utf8(buffer).map_err(|e| {
             io::Error::new(
@@ -68,225 +1873,462 @@ fn main() -> io::Result<()> {
             )
         })?;
 
-        match Clipboard::new() {
-            Ok(mut clipboard) => {
-                // Use the converted string
-                if let Err(e) = clipboard.set_text(output_string) {
-                    eprintln!("Error copying to clipboard: {}", e);
-                    // Convert arboard error to io::Error for consistent return type
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        format!("Clipboard error: {}", e),
-                    ));
-                } else {
-                    eprintln!("Output copied to clipboard.");
-                }
+        let limit = ClipboardLimit {
+            max_bytes: args.clipboard_limit,
+            force: args.clipboard_force,
+        };
+        clipboard::copy_text(output_string, args.clipboard_html, limit, || {
+            clipboard::open_backend(
+                args.clipboard_backend,
+                args.clipboard_command.as_deref(),
+                args.selection,
+                args.clipboard_wait,
+            )
+        })?;
+    }
+
+    summary::log_summary(&report, &root_dir);
+    summary::warn_if_large(None, bytes, args.warn_size);
+    if let Some(top) = args.top {
+        summary::print_largest(&report, &options, top as usize, bytes);
+    }
+    if logging::json_events() {
+        summary::emit_summary_event(&report, bytes);
+    }
+    Ok(Outcome::Report(Box::new(report)))
+}
+
+// Regenerate the document at `path` in memory and compare it with the file,
+// rewriting the file when `write` is set and it is out of date
+fn run_verify(
+    args: &Args,
+    options: &WeaveOptions,
+    stamp: &Stamp,
+    prompt: &Prompt,
+    history: &History,
+    path: &Path,
+    write: bool,
+) -> sourceweaver::Result<Outcome> {
+    let compression = args.compress.or_else(|| Compression::from_path(path));
+    if let Some(compression) = compression {
+        compression.check_supported()?;
+    }
+    let symbols = SymbolIndex::new();
+    let mut visitors = content_visitors(args, options.root());
+    if args.symbol_index {
+        visitors.push(Box::new(symbols.clone()));
+    }
+    let mut document = Vec::new();
+    prompt.write_prefix(&mut document)?;
+    history.write_at(&mut document, HistoryPosition::Start)?;
+    let report = generate_markdown_with(&mut document, options, &mut visitors)?;
+    symbols.write_markdown(&mut document, options)?;
+    history.write_at(&mut document, HistoryPosition::End)?;
+    prompt.write_suffix(&mut document)?;
+    let document = String::from_utf8(document).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Generated content is not valid UTF-8: {}", e),
+        )
+    })?;
+
+    let staleness = match verify::read_document(path, compression)? {
+        Some(on_disk) => verify::compare(&on_disk, &document),
+        None => {
+            warn!("{} doesn't exist yet.", path.display());
+            Some(verify::Staleness::default())
+        }
+    };
+    match staleness {
+        None => {
+            info!("{} is up to date.", path.display());
+            Ok(Outcome::Report(Box::new(report)))
+        }
+        Some(staleness) if write => {
+            if path.exists() {
+                staleness.log(path);
             }
-            Err(e) => {
-                eprintln!("Error initializing clipboard: {}", e);
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Clipboard init error: {}", e),
-                ));
+            let mut output_file = OutputFile::open(path, false, args.force, compression)?;
+            output_file.begin_run(stamp)?;
+            let mut destinations = Destinations::new(Some(output_file), false, false);
+            destinations.write_all(document.as_bytes())?;
+            destinations.finish()?;
+            Ok(Outcome::Report(Box::new(report)))
+        }
+        Some(staleness) => {
+            if path.exists() {
+                staleness.log(path);
             }
+            error!(
+                "{} is out of date; run 'sourceweaver verify --write' with the same options, \
+                 or a normal run, to update it.",
+                path.display()
+            );
+            Ok(Outcome::Stale)
         }
-    } else if let Some(output_path) = args.output {
-        eprintln!("Outputting to: {}", output_path.display());
+    }
+}
 
-        // Canonicalization logic for filtering the output file itself
-        let canonical_output_path = if let Some(parent) = output_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)?;
+// `path` made absolute against `cwd`, with `.` and `..` resolved lexically
+// so that the result can be compared with other paths
+fn absolute(cwd: &Path, path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
             }
-            // Create file first to allow canonicalization
-            File::create(&output_path)?;
-            fs::canonicalize(&output_path).ok() // ok() converts Result to Option
-        } else {
-            // Handle case where output path has no parent (e.g., just "file.md")
-            File::create(&output_path)?;
-            fs::canonicalize(&output_path).ok()
-        };
-
-        if canonical_output_path.is_none() {
-            eprintln!(
-                "Warning: Could not canonicalize output path {}. It might be included if inside the scanned directory.",
-                output_path.display()
-            );
+            other => resolved.push(other),
         }
+    }
+    resolved
+}
+
+// The directory headings for positional paths are relative to: the working
+// directory when it contains all of them, else their closest common ancestor
+fn common_base(cwd: &Path, paths: &[PathBuf]) -> PathBuf {
+    if paths.iter().all(|path| path.starts_with(cwd)) {
+        return cwd.to_path_buf();
+    }
+    let dir_of = |path: &PathBuf| match path.parent() {
+        Some(parent) if !path.is_dir() => parent.to_path_buf(),
+        _ => path.clone(),
+    };
+    let mut base = dir_of(&paths[0]);
+    for path in &paths[1..] {
+        while !path.starts_with(&base) && base.pop() {}
+    }
+    base
+}
+
+// The pattern flags merged in the order they appear on the command line,
+// since the last matching pattern decides
+fn ordered_patterns(args: &Args, matches: &ArgMatches) -> Vec<PatternArg> {
+    let indices = |id: &str| matches.indices_of(id).into_iter().flatten();
+    let mut patterns: Vec<(usize, PatternArg)> = indices("exclude")
+        .zip(&args.exclude)
+        .map(|(i, glob)| (i, PatternArg::Exclude(glob.clone())))
+        .chain(
+            indices("include")
+                .zip(&args.include)
+                .map(|(i, glob)| (i, PatternArg::Include(glob.clone()))),
+        )
+        .chain(
+            indices("patterns_from")
+                .zip(&args.patterns_from)
+                .map(|(i, path)| (i, PatternArg::File(path.clone()))),
+        )
+        .collect();
+    patterns.sort_by_key(|(i, _)| *i);
+    patterns.into_iter().map(|(_, pattern)| pattern).collect()
+}
 
-        let output_file_handle = File::create(&output_path)?; // Re-open for writing
-        let mut writer = BufWriter::new(output_file_handle);
-        generate_markdown(&mut writer, &root_dir, args.hidden, canonical_output_path)?;
-        eprintln!("Successfully wrote codebase to {}", output_path.display());
-    } else {
-        // Default to stdout
-        let stdout = io::stdout();
-        let mut handle = BufWriter::new(stdout.lock()); // Lock stdout for buffered writing
-        generate_markdown(&mut handle, &root_dir, args.hidden, None)?;
-        handle.flush()?; // Ensure buffer is flushed before program exits
+// Format standard input as a single file section named `name`
+fn weave_stdin(
+    destinations: &mut Destinations,
+    name: Option<&Path>,
+) -> sourceweaver::Result<WeaveReport> {
+    let name = name.unwrap_or(Path::new("stdin"));
+    let mut content = Vec::new();
+    io::stdin().lock().read_to_end(&mut content)?;
+
+    let mut report = WeaveReport::default();
+    if sourceweaver::process_bytes(destinations, name, content)? == FileOutcome::Binary {
+        report.binary_files.push(name.to_path_buf());
+    }
+    report.files_written = 1;
+    Ok(report)
+}
+
+// The visitors transforming file content, in the order they apply
+fn content_visitors(args: &Args, root: &Path) -> Vec<Box<dyn FileVisitor>> {
+    let mut visitors: Vec<Box<dyn FileVisitor>> = Vec::new();
+    if args.collapse_data {
+        visitors.push(Box::new(
+            CollapseData::new().min_lines(args.collapse_min_lines),
+        ));
+    }
+    if let Some(width) = args.expand_tabs {
+        let expand = ExpandTabs::new(width as usize);
+        visitors.push(Box::new(match args.respect_editorconfig {
+            true => expand.editorconfig(root),
+            false => expand,
+        }));
     }
+    visitors
+}
 
-    Ok(())
+// The settings of the content visitors, which cached sections depend on
+fn content_key(args: &Args) -> String {
+    let mut key = Vec::new();
+    if args.collapse_data {
+        key.push(format!("collapse-data={}", args.collapse_min_lines));
+    }
+    if let Some(width) = args.expand_tabs {
+        key.push(format!("expand-tabs={}", width));
+    }
+    if args.respect_editorconfig {
+        key.push("respect-editorconfig".to_string());
+    }
+    key.join(" ")
 }
 
-// Centralized function to generate the markdown content
-fn generate_markdown<W: Write>(
-    writer: &mut W,
+// Write one document per group into --output-dir, all from a single walk
+fn run_split(
+    args: &Args,
+    mut builder: WeaveOptionsBuilder,
     root_dir: &Path,
-    hidden: bool,
-    output_path_for_filter: Option<PathBuf>, // Pass canonicalized path if writing to file
-) -> io::Result<()> {
-    // Create a HashSet for efficient lock file checking
-    let lock_file_set: HashSet<&str> = LOCK_FILES.iter().cloned().collect();
-
-    // Use WalkBuilder to respect .gitignore, .ignore, etc.
-    let walker = WalkBuilder::new(&root_dir)
-        .hidden(!hidden) // Use the passed 'hidden' flag
-        .parents(true)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .ignore(true)
-        // Add a filter predicate to explicitly ignore the output file and lock files
-        .filter_entry(move |entry| {
-            // --- Filter 1: Output File ---
-            if let Some(output_path_to_check) = &output_path_for_filter {
-                // Attempt canonicalization for comparison, proceed if it fails
-                if let Ok(entry_path_canonical) = fs::canonicalize(entry.path()) {
-                    if entry_path_canonical == *output_path_to_check {
-                        return false; // Skip output file
-                    }
-                }
-                // If canonicalization fails, don't skip based on this check
-            }
+    stamp: &Stamp,
+    prompt: &Prompt,
+    history: &History,
+    split_by: SplitBy,
+) -> sourceweaver::Result<WeaveReport> {
+    let output_dir = args
+        .output_dir
+        .as_deref()
+        .expect("clap requires --output-dir with --split-by");
+    info!("Outputting to directory: {}", output_dir.display());
+    // The output directory is pruned from the walk when it lies inside the root
+    if let Some(canonical_dir) = output::canonical_target(output_dir) {
+        builder = builder.exclude_path(canonical_dir);
+    }
+    let options = builder.build()?;
 
-            // --- Filter 2: Lock Files ---
-            // Check only if it's a file to avoid matching directory names
-            if entry.file_type().map_or(false, |ft| ft.is_file()) {
-                // Check if the filename exists in our lock file set
-                if let Some(file_name) = entry.file_name().to_str() {
-                    if lock_file_set.contains(file_name) {
-                        return false; // Skip lock file
-                    }
-                }
-            }
+    let mut collected = collect_files(&options);
+    if options.fail_fast() && !collected.errors.is_empty() {
+        return Err(collected.errors.swap_remove(0));
+    }
+    if collected.files.is_empty() {
+        summary::log_no_files(&options, collected.dirs_visited);
+        if options.fail_if_empty() {
+            return Err(WeaveError::NoFiles {
+                dirs_visited: collected.dirs_visited,
+            });
+        }
+    }
+    let dirs_visited = collected.dirs_visited;
+    let walk_time = collected.walk_time;
+
+    let mut progress = ProgressVisitor::new(
+        !args.no_progress && !args.quiet && io::stderr().is_terminal() && !logging::json_events(),
+    );
+    progress.on_start(collected.files.len());
+    let symbols = SymbolIndex::new();
+    let mut visitors = content_visitors(args, root_dir);
+    if args.symbol_index {
+        visitors.push(Box::new(symbols.clone()));
+    }
 
-            // --- Default: Include ---
-            // If neither filter matched, include the entry
-            true
-        })
-        .build();
-
-    for result in walker {
-        match result {
-            Ok(entry) => {
-                let path = entry.path();
-                if path == root_dir {
-                    continue;
-                } // Skip root dir itself
-                if path.is_file() {
-                    if let Ok(relative_path) = path.strip_prefix(&root_dir) {
-                        if relative_path.as_os_str().is_empty() {
-                            continue;
-                        }
-                        process_file(writer, relative_path, path)?;
-                    } else {
-                        eprintln!(
-                            "Warning: Could not get relative path for {}",
-                            path.display()
-                        );
-                    }
-                }
+    let mut report = WeaveReport::default();
+    let mut bytes = 0;
+    for (name, files) in split::group(collected, root_dir, split_by) {
+        let path = output_dir.join(format!("{}.md", name));
+        let mut output_file = OutputFile::open(&path, false, args.force, None)?;
+        output_file.begin_run(stamp)?;
+        let mut destinations = Destinations::new(Some(output_file), false, false);
+
+        let mut visitor = DocumentVisitor {
+            progress: &mut progress,
+            visitors: &mut visitors,
+        };
+        let written = (|| {
+            prompt.write_prefix(&mut destinations)?;
+            history.write_at(&mut destinations, HistoryPosition::Start)?;
+            let document = write_collected(&mut destinations, &options, files, &mut visitor)?;
+            symbols.write_markdown(&mut destinations, &options)?;
+            symbols.clear();
+            history.write_at(&mut destinations, HistoryPosition::End)?;
+            prompt.write_suffix(&mut destinations)?;
+            Ok(document)
+        })();
+        let mut document = match written {
+            Ok(document) => document,
+            Err(e) => {
+                destinations.abandon();
+                return Err(e);
             }
-            Err(err) => eprintln!("Error accessing entry: {}", err),
-        }
+        };
+        summary::warn_if_large(Some(&name), destinations.bytes_written(), args.warn_size);
+        bytes += destinations.bytes_written();
+        let finishing = Instant::now();
+        destinations.finish()?;
+        document.timings.write += finishing.elapsed();
+        summary::log_document_summary(&name, &document);
+        report.merge(document);
     }
+    progress.finish();
+    report.dirs_visited = dirs_visited;
+    report.timings.walk = walk_time;
 
-    Ok(())
+    summary::log_summary(&report, root_dir);
+    if let Some(top) = args.top {
+        summary::print_largest(&report, &options, top as usize, bytes);
+    }
+    if logging::json_events() {
+        summary::emit_summary_event(&report, bytes);
+    }
+    Ok(report)
 }
 
-fn process_file<W: Write>(
-    writer: &mut W,
-    relative_path: &Path,
-    full_path: &Path,
-) -> io::Result<()> {
-    writeln!(writer, "\n## `{}`\n", relative_path.display())?;
+// Parse a size such as "4096", "512K", or "1M" into bytes
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size '{}' (expected e.g. 4096, 512K, 1M)", s))
+}
 
-    match fs::read(full_path) {
-        Ok(content) => {
-            let content_type = content_inspector::inspect(&content);
+fn parse_tokens(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1_000),
+        Some((i, 'm' | 'M')) => (&s[..i], 1_000_000),
+        _ => (s, 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid token count '{}' (expected e.g. 8000, 100k, 1M)", s))
+}
 
-            if content_type == ContentType::BINARY {
-                writeln!(writer, "```\n(Binary file, content omitted)\n```")?;
-            } else {
-                let content_str = String::from_utf8_lossy(&content);
-                let lang = get_language_tag(relative_path);
-                writeln!(writer, "```{}", lang)?;
-                for line in content_str.lines() {
-                    writeln!(writer, "{}", line)?;
-                }
-                writeln!(writer, "```")?;
-            }
-        }
-        Err(e) => {
-            writeln!(writer, "```\n(Error reading file: {})\n```", e)?;
-            eprintln!(
-                "Warning: Failed to read file {}: {}",
-                full_path.display(),
-                e
-            );
+fn parse_priority(s: &str) -> Result<(String, i64), String> {
+    let invalid = || {
+        format!(
+            "invalid priority '{}' (expected GLOB=WEIGHT, e.g. 'src/**=100')",
+            s
+        )
+    };
+    let (glob, weight) = s.rsplit_once('=').ok_or_else(invalid)?;
+    let weight = weight.trim().parse::<i64>().map_err(|_| invalid())?;
+    if glob.is_empty() {
+        return Err(invalid());
+    }
+    Ok((glob.to_string(), weight))
+}
+
+fn parse_truncate(s: &str) -> Result<(String, TruncateLimit), String> {
+    let invalid = || {
+        format!(
+            "invalid truncation '{}' (expected GLOB=LINES[,SIZE], e.g. 'data/**=20')",
+            s
+        )
+    };
+    let (glob, limit) = s.rsplit_once('=').ok_or_else(invalid)?;
+    let (lines, size) = match limit.split_once(',') {
+        Some((lines, size)) => (lines.trim(), Some(size)),
+        None => (limit.trim(), None),
+    };
+    let lines = match lines {
+        "" => None,
+        lines => Some(lines.parse::<usize>().map_err(|_| invalid())?),
+    };
+    let bytes = size.map(parse_size).transpose()?;
+    if glob.is_empty() || (lines.is_none() && bytes.is_none()) {
+        return Err(invalid());
+    }
+    Ok((glob.to_string(), TruncateLimit { lines, bytes }))
+}
+
+// Render the man page for the top-level command followed by one page per visible subcommand
+fn render_man_pages(out_dir: Option<&Path>) -> io::Result<()> {
+    let command = Args::command();
+
+    if let Some(dir) = out_dir {
+        fs::create_dir_all(dir)?;
+        clap_mangen::generate_to(command, dir)?;
+        info!("Man pages written to {}", dir.display());
+        return Ok(());
+    }
+
+    let stdout = io::stdout();
+    let mut handle = BufWriter::new(stdout.lock());
+    clap_mangen::Man::new(command.clone()).render(&mut handle)?;
+    for subcommand in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let name = format!("{}-{}", command.get_name(), subcommand.get_name());
+        let subcommand = subcommand.clone().display_name(name);
+        clap_mangen::Man::new(subcommand).render(&mut handle)?;
+    }
+    handle.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    // Run the command line `args`, as `main` does
+    fn run_args(args: &[&OsStr]) -> sourceweaver::Result<Outcome> {
+        let matches = Args::command().get_matches_from(args);
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        args.patterns = ordered_patterns(&args, &matches);
+        run(args)
+    }
+
+    // Create the same tree under `root`, writing its files in `order`
+    fn write_fixture(root: &Path, order: &[usize]) {
+        let files = [
+            ("src/main.rs", "fn main() {}\n"),
+            ("src/Lib.rs", "pub fn lib() {}\n"),
+            ("src/lib/mod.rs", "mod inner;\n"),
+            ("README.md", "# Fixture\n"),
+            ("a_b.txt", "underscore\n"),
+            ("a-b.txt", "dash\n"),
+            ("Cargo.LOCK", "# left out on every platform\n"),
+        ];
+        for &index in order {
+            let (path, content) = files[index];
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
         }
     }
-    Ok(())
-}
-
-fn get_language_tag(path: &Path) -> &str {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| match ext.to_lowercase().as_str() {
-            "rs" => "rust",
-            "py" | "pyw" => "python",
-            "js" | "mjs" | "cjs" => "javascript",
-            "ts" | "mts" | "cts" => "typescript",
-            "java" => "java",
-            "c" | "h" => "c",
-            "cpp" | "hpp" | "cxx" | "hxx" | "cc" | "hh" => "cpp",
-            "cs" => "csharp",
-            "go" => "go",
-            "php" => "php",
-            "rb" => "ruby",
-            "swift" => "swift",
-            "kt" | "kts" => "kotlin",
-            "scala" => "scala",
-            "pl" => "perl",
-            "sh" | "bash" | "zsh" => "bash",
-            "ps1" => "powershell",
-            "html" | "htm" => "html",
-            "css" => "css",
-            "scss" | "sass" => "scss",
-            "less" => "less",
-            "json" => "json",
-            "yaml" | "yml" => "yaml",
-            "toml" => "toml",
-            "md" | "markdown" => "markdown",
-            "sql" => "sql",
-            "xml" => "xml",
-            "dockerfile" | "containerfile" => "dockerfile",
-            "nix" => "nix",
-            "lua" => "lua",
-            "r" => "r",
-            "dart" => "dart",
-            "ex" | "exs" => "elixir",
-            "erl" | "hrl" => "erlang",
-            "hs" => "haskell",
-            "clj" | "cljs" | "cljc" | "edn" => "clojure",
-            "groovy" | "gradle" => "groovy",
-            "tf" => "terraform",
-            "vue" => "vue",
-            "svelte" => "svelte",
-            "tex" => "latex",
-            "zig" => "zig",
-            _ => "", // Default to no language tag
-        })
-        .unwrap_or("") // Handle cases with no extension
+
+    #[test]
+    fn reproducible_runs_are_byte_identical() {
+        let base = std::env::temp_dir().join(format!("sourceweaver-repro-{}", std::process::id()));
+        let runs = [
+            (base.join("one"), vec![0, 1, 2, 3, 4, 5, 6], "1"),
+            (base.join("elsewhere/two"), vec![6, 5, 4, 3, 2, 1, 0], "8"),
+        ];
+        let documents: Vec<Vec<u8>> = runs
+            .iter()
+            .map(|(dir, order, threads)| {
+                let root = dir.join("project");
+                write_fixture(&root, order);
+                let output = dir.join("out.md");
+                run_args(&[
+                    "sourceweaver".as_ref(),
+                    "--reproducible".as_ref(),
+                    "--no-progress".as_ref(),
+                    "--threads".as_ref(),
+                    threads.as_ref(),
+                    "--root".as_ref(),
+                    root.as_os_str(),
+                    "--output".as_ref(),
+                    output.as_os_str(),
+                ])
+                .unwrap();
+                fs::read(output).unwrap()
+            })
+            .collect();
+        fs::remove_dir_all(&base).unwrap();
+
+        let document = String::from_utf8_lossy(&documents[0]);
+        assert!(document.contains(" from project"), "{}", document);
+        assert!(!document.contains("Cargo.LOCK"));
+        assert!(documents[0] == documents[1], "{}", document);
+    }
 }
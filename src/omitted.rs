@@ -0,0 +1,346 @@
+// src/omitted.rs
+use crate::{OmittedFiles, Result, SkipReason, WeaveOptions, WeaveReport, archive};
+use ignore::WalkBuilder;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+/// The reason word for a file omitted by an ignore file or for being hidden.
+const IGNORED: &str = "ignored";
+
+/// The reason word for a file dropped to fit the token budget.
+const BUDGET: &str = "budget";
+
+/// The reason word for a file left out by the include and exclude globs or
+/// another selection filter.
+const EXCLUDED: &str = "excluded";
+
+// The one-word reason the omitted list gives for a skip, or None for
+// entries that aren't files the document could have shown
+fn reason_word(reason: &SkipReason) -> Option<&'static str> {
+    match reason {
+        SkipReason::LockFile => Some("lockfile"),
+        SkipReason::TooLarge | SkipReason::OverOutputLimit => Some("size"),
+        SkipReason::OverBudget { .. } => Some(BUDGET),
+        SkipReason::ExcludedDir
+        | SkipReason::Submodule
+        | SkipReason::ExcludedContent { .. }
+        | SkipReason::NoContentMatch
+        | SkipReason::Visitor(_) => Some(EXCLUDED),
+        SkipReason::OutputFile
+        | SkipReason::UnsafePath
+        | SkipReason::UninitializedSubmodule
+        | SkipReason::SpecialFile => None,
+    }
+}
+
+/// End the document with the files under the root it doesn't show, each
+/// with a one-word reason, when `options` ask for the list. A directory
+/// whose every entry is left out for the same reason is listed once, with
+/// a trailing `/`. Nothing is written when no file was left out.
+pub(crate) fn write_omitted<W: Write + ?Sized>(
+    writer: &mut W,
+    options: &WeaveOptions,
+    report: &WeaveReport,
+) -> Result<()> {
+    let Some(mode) = options.list_omitted else {
+        return Ok(());
+    };
+    let omitted = omitted(options, report, mode);
+    if omitted.is_empty() {
+        return Ok(());
+    }
+    writeln!(writer, "\n## Omitted Files\n")?;
+    if omitted.iter().any(|(_, _, reason)| *reason == BUDGET) {
+        writeln!(writer, "{}\n", budget_policy(options))?;
+    }
+    for (path, is_dir, reason) in omitted {
+        let slash = if is_dir { "/" } else { "" };
+        writeln!(
+            writer,
+            "- {}{} ({})",
+            options.display_path(&path),
+            slash,
+            reason
+        )?;
+    }
+    Ok(())
+}
+
+// The note saying how the files marked `budget` were chosen, the way
+// `budget::omissions` chooses them
+fn budget_policy(options: &WeaveOptions) -> &'static str {
+    match options.priorities.is_empty() {
+        true => "*Files marked `budget` were dropped to fit the token budget, largest first.*",
+        false => {
+            "*Files marked `budget` were dropped to fit the token budget, lowest priority \
+             first and largest first within a priority.*"
+        }
+    }
+}
+
+// The entries to list, in path order, with whether each is a directory
+fn omitted(
+    options: &WeaveOptions,
+    report: &WeaveReport,
+    mode: OmittedFiles,
+) -> Vec<(PathBuf, bool, &'static str)> {
+    let written: HashSet<&Path> = report
+        .section_bytes
+        .iter()
+        .map(|(path, _)| path.as_path())
+        .collect();
+    let skipped: HashMap<&Path, Option<&'static str>> = report
+        .skipped
+        .iter()
+        .map(|(path, reason)| (path.as_path(), reason_word(reason)))
+        .collect();
+
+    // Every entry that could have been shown, with its reason if it wasn't;
+    // directories the walk doesn't enter stand for everything inside
+    let mut universe: BTreeMap<PathBuf, (bool, Option<&'static str>)> = BTreeMap::new();
+    for (path, reason) in &skipped {
+        if let Some(reason) = reason {
+            universe.insert(path.to_path_buf(), (false, Some(reason)));
+        }
+    }
+    let walkable = options.root.is_dir() && !archive::is_archive(&options.root);
+    if walkable {
+        let skipped_dirs: HashSet<PathBuf> =
+            skipped.keys().map(|path| path.to_path_buf()).collect();
+        let shown = walk(options, true, move |relative| {
+            skipped_dirs.contains(relative)
+        });
+        for path in &shown.pruned {
+            if let Some(Some(reason)) = skipped.get(path.as_path()) {
+                universe.insert(path.clone(), (true, Some(reason)));
+            }
+        }
+        for path in &shown.files {
+            let reason = match written.contains(path.as_path()) {
+                true => None,
+                false => skipped
+                    .get(path.as_path())
+                    .copied()
+                    .unwrap_or(Some(EXCLUDED)),
+            };
+            // Skips that aren't files, such as the output file, aren't listed
+            if written.contains(path.as_path()) || reason.is_some() {
+                universe.insert(path.clone(), (false, reason));
+            } else {
+                universe.remove(path);
+            }
+        }
+        if mode == OmittedFiles::All {
+            // Directories holding a force-included file are entered
+            let mut known = shown.dirs;
+            known.extend(
+                written
+                    .iter()
+                    .flat_map(|path| path.ancestors().skip(1))
+                    .map(Path::to_path_buf),
+            );
+            let everything = walk(options, false, move |relative| {
+                !known.contains(relative) || relative.file_name() == Some(".git".as_ref())
+            });
+            for path in everything.pruned {
+                if path.file_name() != Some(".git".as_ref()) && !universe.contains_key(&path) {
+                    universe.insert(path, (true, Some(IGNORED)));
+                }
+            }
+            for path in everything.files {
+                if written.contains(path.as_path()) {
+                    continue;
+                }
+                universe.entry(path).or_insert((false, Some(IGNORED)));
+            }
+        }
+    }
+
+    collapse(&universe)
+}
+
+// The omitted entries of `universe`, with each directory whose entries are
+// all omitted for one reason standing in for them
+fn collapse(
+    universe: &BTreeMap<PathBuf, (bool, Option<&'static str>)>,
+) -> Vec<(PathBuf, bool, &'static str)> {
+    // For each directory: how many entries are below it, how many of them
+    // are omitted, and their reason if they share one
+    let mut dirs: HashMap<&Path, (usize, usize, Option<&'static str>)> = HashMap::new();
+    for (path, (_, reason)) in universe {
+        for dir in path.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            let (total, omitted, shared) = dirs.entry(dir).or_insert((0, 0, *reason));
+            *total += 1;
+            if reason.is_some() {
+                *omitted += 1;
+            }
+            if *shared != *reason {
+                *shared = None;
+            }
+        }
+    }
+
+    let mut listed = Vec::new();
+    let mut collapsed: HashSet<&Path> = HashSet::new();
+    for (path, (is_dir, reason)) in universe {
+        let Some(reason) = reason else {
+            continue;
+        };
+        // The outermost directory holding nothing else
+        let outermost = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| !dir.as_os_str().is_empty())
+            .filter(|dir| {
+                dirs.get(dir)
+                    .is_some_and(|(total, omitted, shared)| total == omitted && shared.is_some())
+            })
+            .last();
+        match outermost {
+            Some(dir) => {
+                if collapsed.insert(dir) {
+                    listed.push((dir.to_path_buf(), true, *reason));
+                }
+            }
+            None => listed.push((path.clone(), *is_dir, *reason)),
+        }
+    }
+    listed
+}
+
+// What a walk of the root found, relative to it
+#[derive(Debug, Default)]
+struct Walked {
+    files: Vec<PathBuf>,
+    dirs: HashSet<PathBuf>,
+    // Directories `prune` kept the walk out of
+    pruned: Vec<PathBuf>,
+}
+
+// Walk the whole root, honouring ignore files and hidden entries as the
+// run does when `filtered`, and not entering directories `prune` accepts
+fn walk(
+    options: &WeaveOptions,
+    filtered: bool,
+    prune: impl Fn(&Path) -> bool + Send + Sync + 'static,
+) -> Walked {
+    let ignore_files = filtered && options.default_filters;
+    let pruned = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&pruned);
+    let root = options.root.clone();
+    let mut walked = Walked::default();
+    let walk = WalkBuilder::new(&options.root)
+        .hidden(ignore_files && !options.hidden)
+        .parents(ignore_files)
+        .git_ignore(ignore_files)
+        .git_global(ignore_files)
+        .git_exclude(ignore_files)
+        .ignore(ignore_files)
+        .follow_links(options.follow_links)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            if entry.depth() > 0 && is_dir && prune(relative) {
+                log.lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(relative.to_path_buf());
+                return false;
+            }
+            true
+        })
+        .build();
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) if entry.depth() > 0 => entry,
+            Ok(_) => continue,
+            Err(e) => {
+                debug!("Could not list an entry for the omitted files: {}", e);
+                continue;
+            }
+        };
+        let Ok(relative) = entry.path().strip_prefix(&options.root) else {
+            continue;
+        };
+        match entry.file_type() {
+            Some(ft) if ft.is_dir() => {
+                walked.dirs.insert(relative.to_path_buf());
+            }
+            Some(ft) if ft.is_file() => walked.files.push(relative.to_path_buf()),
+            // Symlinks the walk doesn't follow
+            _ if entry.path().is_file() => walked.files.push(relative.to_path_buf()),
+            _ => {}
+        }
+    }
+    walked.pruned = std::mem::take(&mut *pruned.lock().unwrap_or_else(|e| e.into_inner()));
+    walked
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OmittedFiles, WeaveOptions, generate_markdown};
+    use std::fs;
+
+    // The omitted files section of a budgeted run over three 4 KB files
+    fn omitted_section(priorities: &[(&str, i64)]) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(dir.path().join(name), "x".repeat(4000)).unwrap();
+        }
+        let mut builder = WeaveOptions::new(dir.path())
+            .budget(2500)
+            .list_omitted(OmittedFiles::Filtered);
+        for (glob, weight) in priorities {
+            builder = builder.priority(*glob, *weight);
+        }
+        let mut document = Vec::new();
+        generate_markdown(&mut document, &builder.build().unwrap()).unwrap();
+        let document = String::from_utf8(document).unwrap();
+        let start = document.find("## Omitted Files").unwrap();
+        document[start..].to_string()
+    }
+
+    #[test]
+    fn budget_omissions_state_the_policy() {
+        assert_eq!(
+            omitted_section(&[]),
+            "## Omitted Files\n\n\
+             *Files marked `budget` were dropped to fit the token budget, largest first.*\n\n\
+             - c.txt (budget)\n"
+        );
+        let section = omitted_section(&[("c.txt", 10)]);
+        assert!(
+            section.contains("lowest priority first and largest first within a priority"),
+            "{}",
+            section
+        );
+        assert!(section.ends_with("- b.txt (budget)\n"), "{}", section);
+    }
+
+    #[test]
+    fn other_omissions_have_no_policy_note() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("notes.md"), "notes\n").unwrap();
+        let options = WeaveOptions::new(dir.path())
+            .exclude("*.md")
+            .list_omitted(OmittedFiles::Filtered)
+            .build()
+            .unwrap();
+        let mut document = Vec::new();
+        generate_markdown(&mut document, &options).unwrap();
+        let document = String::from_utf8(document).unwrap();
+        assert!(
+            document.ends_with("## Omitted Files\n\n- notes.md (excluded)\n"),
+            "{}",
+            document
+        );
+    }
+}
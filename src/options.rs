@@ -0,0 +1,1835 @@
+// src/options.rs
+use crate::{
+    LOCK_FILES, LOCK_FILES_CASE_INSENSITIVE, Result, WalkedFile, WeaveError,
+    annotations::{self, Annotations},
+    blame::Blame,
+    budget::Priorities,
+    codeowners::OwnerFilter,
+    content_filter::ContentFilter,
+    display,
+    file_list::FileList,
+    last_commit::LastCommits,
+    patterns::{self, Pattern, Patterns},
+    scope::Scope,
+    truncate::{TruncateLimit, Truncations},
+};
+use ignore::overrides::{Override, OverrideBuilder};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Settings controlling which files are woven and how.
+///
+/// Construct with [`WeaveOptions::new`], which returns a builder:
+///
+/// ```
+/// use sourceweaver::WeaveOptions;
+///
+/// let options = WeaveOptions::new("src")
+///     .hidden(true)
+///     .exclude("*.snap")
+///     .max_filesize(1 << 20)
+///     .build()?;
+/// assert!(options.hidden());
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WeaveOptions {
+    pub(crate) root: PathBuf,
+    pub(crate) hidden: bool,
+    pub(crate) patterns: Vec<Pattern>,
+    pub(crate) exclude_dirs: Vec<String>,
+    pub(crate) path_regexes: Vec<String>,
+    pub(crate) path_regexes_not: Vec<String>,
+    pub(crate) content_regexes: Vec<String>,
+    pub(crate) grep_regexes: Vec<String>,
+    pub(crate) grep_context: Option<usize>,
+    pub(crate) content_filter: ContentFilter,
+    pub(crate) matcher: Patterns,
+    pub(crate) max_filesize: Option<u64>,
+    pub(crate) exclude_paths: Vec<PathBuf>,
+    pub(crate) fail_fast: bool,
+    pub(crate) fail_if_empty: bool,
+    pub(crate) threads: Option<usize>,
+    pub(crate) submodules: Submodules,
+    pub(crate) path_prefix: Option<PathBuf>,
+    pub(crate) absolute_base: Option<PathBuf>,
+    pub(crate) native_paths: bool,
+    pub(crate) error_placeholders: ErrorPlaceholders,
+    pub(crate) checksums: Option<Checksums>,
+    pub(crate) unicode_tricks: Option<UnicodeTricks>,
+    pub(crate) sentinels: bool,
+    pub(crate) extension_shortcut: bool,
+    pub(crate) last_commits: Option<LastCommits>,
+    pub(crate) blame_skip: Vec<String>,
+    pub(crate) blame: Option<Blame>,
+    pub(crate) follow_links: bool,
+    pub(crate) link_template: Option<String>,
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) also: Vec<PathBuf>,
+    pub(crate) annotations: Vec<(String, String)>,
+    pub(crate) annotation_matcher: Annotations,
+    pub(crate) budget: Option<u64>,
+    pub(crate) max_output: Option<u64>,
+    pub(crate) priorities: Vec<(String, i64)>,
+    pub(crate) priority_matcher: Priorities,
+    pub(crate) truncations: Vec<(String, TruncateLimit)>,
+    pub(crate) truncate_strategy: TruncateStrategy,
+    pub(crate) list_omitted: Option<OmittedFiles>,
+    pub(crate) truncation_matcher: Truncations,
+    pub(crate) force_includes: Vec<String>,
+    pub(crate) force_override: Option<Override>,
+    pub(crate) owners: Vec<String>,
+    pub(crate) owner_filter: Option<OwnerFilter>,
+    pub(crate) file_list: Option<FileList>,
+    pub(crate) scope_globs: Option<Vec<String>>,
+    pub(crate) scope: Option<Scope>,
+    pub(crate) default_filters: bool,
+    pub(crate) lock_files: Vec<String>,
+    pub(crate) lock_files_case_insensitive: bool,
+    pub(crate) cache: Option<(PathBuf, String)>,
+    pub(crate) cache_trust_mtime: bool,
+}
+
+/// How git submodules under the root are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Submodules {
+    /// Walk initialized submodules, honouring their own ignore files, and
+    /// write a placeholder section for each uninitialized one.
+    #[default]
+    Include,
+    /// Leave submodule directories out of the walk entirely.
+    Skip,
+}
+
+/// What the document shows for a file that can't be read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPlaceholders {
+    /// The heading, then the error in a code block where the content would
+    /// be. Readers may mistake it for the file's content.
+    Inline,
+    /// Nothing: neither heading nor note.
+    Omit,
+    /// The heading, then a one-line italic note outside any code block.
+    #[default]
+    Note,
+}
+
+/// What the checksum line under each file heading is computed over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Checksums {
+    /// The exact bytes read from disk, so the hash can be checked against
+    /// the file later with `sha256sum`.
+    #[default]
+    Original,
+    /// The text of the code block as the document shows it, after visitors
+    /// rewrote it and with `\n` line endings. Binary files still hash their
+    /// bytes on disk.
+    PostTransform,
+}
+
+/// What is done about Unicode characters that can hide what code does,
+/// such as bidi controls, zero-width characters, and Latin lookalikes.
+/// See [`find_unicode_tricks`](crate::find_unicode_tricks).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnicodeTricks {
+    /// Record each one in the report, leaving the content as it is.
+    #[default]
+    Flag,
+    /// Record each one, and write it as a visible escape such as
+    /// `\u{202E}` in the document.
+    Escape,
+}
+
+/// How a file over its [truncation rule](WeaveOptionsBuilder::truncate)
+/// is cut down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncateStrategy {
+    /// Keep the first lines, dropping the rest.
+    #[default]
+    Tail,
+    /// In Rust, Go, JavaScript, TypeScript, and Python, replace function
+    /// bodies with a marker, longest first, until the file fits, keeping
+    /// every declaration; what still doesn't fit, and files in other
+    /// languages, lose their tail.
+    Smart,
+}
+
+/// Which files the [omitted files list](WeaveOptionsBuilder::list_omitted)
+/// names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OmittedFiles {
+    /// Files the walk saw but left out: by the include and exclude globs
+    /// and other selection filters, as lock files, by size, or to fit the
+    /// token budget.
+    #[default]
+    Filtered,
+    /// Those, and files hidden or matched by ignore files, which can be
+    /// many (`target/`, `node_modules/`).
+    All,
+}
+
+/// Default upper bound on worker threads when none is configured.
+pub const DEFAULT_MAX_THREADS: usize = 8;
+
+impl WeaveOptions {
+    /// Start building options for scanning `root` with the default filters.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(root: impl Into<PathBuf>) -> WeaveOptionsBuilder {
+        WeaveOptionsBuilder {
+            root: root.into(),
+            hidden: false,
+            patterns: Vec::new(),
+            exclude_dirs: Vec::new(),
+            path_regexes: Vec::new(),
+            path_regexes_not: Vec::new(),
+            content_regexes: Vec::new(),
+            strict_content: false,
+            grep_regexes: Vec::new(),
+            grep_context: None,
+            max_filesize: None,
+            exclude_paths: Vec::new(),
+            fail_fast: false,
+            fail_if_empty: false,
+            threads: None,
+            submodules: Submodules::default(),
+            path_prefix: None,
+            relative_to: None,
+            absolute_paths: false,
+            native_paths: false,
+            error_placeholders: ErrorPlaceholders::default(),
+            checksums: None,
+            unicode_tricks: None,
+            sentinels: false,
+            extension_shortcut: true,
+            git_info: false,
+            blame_summary: false,
+            blame_skip: Vec::new(),
+            follow_links: false,
+            link_template: None,
+            paths: Vec::new(),
+            also: Vec::new(),
+            annotations: Vec::new(),
+            budget: None,
+            max_output: None,
+            priorities: Vec::new(),
+            truncations: Vec::new(),
+            truncate_strategy: TruncateStrategy::default(),
+            list_omitted: None,
+            force_includes: Vec::new(),
+            owners: Vec::new(),
+            only_files: None,
+            scope: None,
+            default_filters: true,
+            lock_files: LOCK_FILES.iter().map(|name| name.to_string()).collect(),
+            lock_files_case_insensitive: LOCK_FILES_CASE_INSENSITIVE,
+            cache: None,
+            cache_trust_mtime: true,
+        }
+    }
+
+    /// Root directory of the codebase. Headings are relative to it.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether hidden files and directories are included.
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Include and exclude globs, relative to the root, in the order they
+    /// are evaluated. Pattern files are expanded in place.
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// Regexes one of which every file's relative path must match.
+    pub fn path_regexes(&self) -> &[String] {
+        &self.path_regexes
+    }
+
+    /// Regexes excluding files whose relative path matches.
+    pub fn path_regexes_not(&self) -> &[String] {
+        &self.path_regexes_not
+    }
+
+    /// Regexes leaving out text files whose content matches, in the order
+    /// added.
+    pub fn content_regexes(&self) -> &[String] {
+        &self.content_regexes
+    }
+
+    /// Whether content matching a content regex fails the run.
+    pub fn strict_content(&self) -> bool {
+        self.content_filter.strict
+    }
+
+    /// Regexes of which text files' content has to match one, in the order
+    /// added. Empty keeps every file.
+    pub fn grep_regexes(&self) -> &[String] {
+        &self.grep_regexes
+    }
+
+    /// Lines of context kept around grep matches, when sections are cut
+    /// down to their matching lines.
+    pub fn grep_context(&self) -> Option<usize> {
+        self.grep_context
+    }
+
+    /// Names of directories pruned wherever they occur.
+    pub fn exclude_dirs(&self) -> &[String] {
+        &self.exclude_dirs
+    }
+
+    /// Files larger than this many bytes are skipped.
+    pub fn max_filesize(&self) -> Option<u64> {
+        self.max_filesize
+    }
+
+    /// Paths excluded from the walk, such as the output file.
+    pub fn exclude_paths(&self) -> &[PathBuf] {
+        &self.exclude_paths
+    }
+
+    /// Whether the first walk or read error aborts the run.
+    pub fn fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
+    /// Whether a walk selecting no files is an error.
+    pub fn fail_if_empty(&self) -> bool {
+        self.fail_if_empty
+    }
+
+    /// How git submodules are handled.
+    pub fn submodules(&self) -> Submodules {
+        self.submodules
+    }
+
+    /// Prefix prepended to every path shown in the document.
+    pub fn path_prefix(&self) -> Option<&Path> {
+        self.path_prefix.as_deref()
+    }
+
+    /// The path shown in the document for a file at `relative_path` below
+    /// the root, with `/` separators unless native paths were requested.
+    /// Filtering and reading always use the real path.
+    ///
+    /// ```
+    /// use sourceweaver::WeaveOptions;
+    /// use std::path::Path;
+    ///
+    /// let options = WeaveOptions::new("src").path_prefix("crates/weaver").build()?;
+    /// let shown = options.display_path(&Path::new("bin").join("main.rs"));
+    /// assert_eq!(shown, "crates/weaver/bin/main.rs");
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn display_path(&self, relative_path: &Path) -> String {
+        let path = match (&self.absolute_base, &self.path_prefix) {
+            (Some(base), _) => base.join(relative_path),
+            (None, Some(prefix)) => prefix.join(relative_path),
+            (None, None) => relative_path.to_path_buf(),
+        };
+        if self.native_paths {
+            display::to_native(&path)
+        } else {
+            display::to_slash(&path)
+        }
+    }
+
+    /// How `file` is shown in the document: its [display path](Self::display_path),
+    /// or for a file from outside the root, `external/<file name>` or its
+    /// absolute path.
+    pub fn display_file(&self, file: &WalkedFile) -> String {
+        if !file.external {
+            return self.display_path(&file.relative_path);
+        }
+        let path = match self.absolute_base {
+            Some(_) => &file.path,
+            None => &file.relative_path,
+        };
+        if self.native_paths {
+            display::to_native(path)
+        } else {
+            display::to_slash(path)
+        }
+    }
+
+    /// The Markdown for the heading of `file`. Files from outside the root
+    /// are never linked, since the template can't address them.
+    pub(crate) fn file_heading(&self, file: &WalkedFile) -> String {
+        match file.external {
+            true => display::code_span(&self.display_file(file)),
+            false => self.heading(&file.relative_path),
+        }
+    }
+
+    /// The Markdown for the heading of the file at `relative_path`: its
+    /// display path as inline code, linked when a link template is set.
+    pub(crate) fn heading(&self, relative_path: &Path) -> String {
+        let span = display::code_span(&self.display_path(relative_path));
+        match &self.link_template {
+            Some(template) => format!(
+                "[{}]({})",
+                span,
+                template.replace("{path}", &display::url_path(relative_path))
+            ),
+            None => span,
+        }
+    }
+
+    /// The files and directories the document is limited to, joined to the
+    /// root. Empty when the whole root is woven.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Files to include in addition to the walk, as given.
+    pub fn also_files(&self) -> &[PathBuf] {
+        &self.also
+    }
+
+    /// Globs and the descriptions shown under the headings of matching
+    /// files, in the order they were added.
+    pub fn annotations(&self) -> &[(String, String)] {
+        &self.annotations
+    }
+
+    /// The description shown under the heading of the file at
+    /// `relative_path`, if any annotation matches it.
+    pub fn annotation(&self, relative_path: &Path) -> Option<String> {
+        self.annotation_matcher.describe(relative_path)
+    }
+
+    /// The Markdown paragraphs under the heading of the file at
+    /// `relative_path`: its annotation and its last commit, if there are
+    /// any.
+    pub(crate) fn heading_notes(&self, relative_path: &Path) -> Option<String> {
+        let notes: Vec<String> = self
+            .annotation(relative_path)
+            .into_iter()
+            .chain(self.last_commits.as_ref().map(|c| c.note(relative_path)))
+            .map(|note| annotations::italic(&note))
+            .collect();
+        (!notes.is_empty()).then(|| notes.join("\n\n"))
+    }
+
+    /// The most tokens the document may take, if limited.
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// The most bytes of file sections the document may hold, if limited.
+    pub fn max_output(&self) -> Option<u64> {
+        self.max_output
+    }
+
+    /// Globs and the weights of matching files under a token budget, in
+    /// the order they were added.
+    pub fn priorities(&self) -> &[(String, i64)] {
+        &self.priorities
+    }
+
+    /// Globs and how much of matching files is kept, in the order they
+    /// were added.
+    pub fn truncations(&self) -> &[(String, TruncateLimit)] {
+        &self.truncations
+    }
+
+    /// How files over a truncation rule are cut down.
+    pub fn truncate_strategy(&self) -> TruncateStrategy {
+        self.truncate_strategy
+    }
+
+    /// Which files the document ends by listing as left out, if any.
+    pub fn list_omitted(&self) -> Option<OmittedFiles> {
+        self.list_omitted
+    }
+
+    /// Whether the built-in default filters run: ignore files, hidden
+    /// entries, and lock files.
+    pub fn default_filters(&self) -> bool {
+        self.default_filters
+    }
+
+    /// File name patterns of the lock files that are skipped.
+    pub fn lock_files(&self) -> &[String] {
+        &self.lock_files
+    }
+
+    /// Whether lock file names are compared case-insensitively.
+    pub fn lock_files_case_insensitive(&self) -> bool {
+        self.lock_files_case_insensitive
+    }
+
+    /// The render cache file, if sections are cached.
+    pub fn cache_path(&self) -> Option<&Path> {
+        self.cache.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// Globs whose matches are included despite ignore rules and the
+    /// built-in filters.
+    pub fn force_includes(&self) -> &[String] {
+        &self.force_includes
+    }
+
+    /// The code owners whose files are kept, if filtering by owner.
+    pub fn owners(&self) -> &[String] {
+        &self.owners
+    }
+
+    /// Whether the walk is limited to a list of files.
+    pub fn has_file_list(&self) -> bool {
+        self.file_list.is_some()
+    }
+
+    /// The globs a file must match one of, if the walk is scoped.
+    pub fn scope(&self) -> Option<&[String]> {
+        self.scope_globs.as_deref()
+    }
+
+    /// The template headings link to, if any.
+    pub fn link_template(&self) -> Option<&str> {
+        self.link_template.as_deref()
+    }
+
+    /// Whether the document shows canonical absolute paths.
+    pub fn absolute_paths(&self) -> bool {
+        self.absolute_base.is_some()
+    }
+
+    /// Whether symlinked directories are walked.
+    pub fn follow_links(&self) -> bool {
+        self.follow_links
+    }
+
+    /// What unreadable files get in the document.
+    pub fn error_placeholders(&self) -> ErrorPlaceholders {
+        self.error_placeholders
+    }
+
+    /// What each file's checksum line is computed over, if sections carry
+    /// one.
+    pub fn checksums(&self) -> Option<Checksums> {
+        self.checksums
+    }
+
+    /// What is done about suspicious Unicode characters, if text is scanned
+    /// for them.
+    pub fn unicode_tricks(&self) -> Option<UnicodeTricks> {
+        self.unicode_tricks
+    }
+
+    /// Whether each file section is enclosed in BEGIN and END sentinel lines.
+    pub fn sentinels(&self) -> bool {
+        self.sentinels
+    }
+
+    /// Whether files with one of the [`BINARY_EXTENSIONS`](crate::BINARY_EXTENSIONS)
+    /// get the binary placeholder without being read.
+    pub fn extension_shortcut(&self) -> bool {
+        self.extension_shortcut
+    }
+
+    /// Whether each file's last commit is noted under its heading.
+    pub fn git_info(&self) -> bool {
+        self.last_commits.is_some()
+    }
+
+    /// Whether each file's authors, by `git blame`, are noted under its
+    /// heading.
+    pub fn blame_summary(&self) -> bool {
+        self.blame.is_some()
+    }
+
+    /// Globs of files left without a blame summary, in the order added.
+    pub fn blame_skip(&self) -> &[String] {
+        &self.blame_skip
+    }
+
+    /// Number of threads used by every parallel stage (walking and reading).
+    /// Defaults to the available parallelism, capped at
+    /// [`DEFAULT_MAX_THREADS`]. One thread means a strictly sequential run.
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map_or(1, |n| n.get())
+                .min(DEFAULT_MAX_THREADS)
+        })
+    }
+}
+
+/// Builder for [`WeaveOptions`], created by [`WeaveOptions::new`].
+#[derive(Debug, Clone)]
+pub struct WeaveOptionsBuilder {
+    root: PathBuf,
+    hidden: bool,
+    patterns: Vec<PatternSource>,
+    exclude_dirs: Vec<String>,
+    path_regexes: Vec<String>,
+    path_regexes_not: Vec<String>,
+    content_regexes: Vec<String>,
+    strict_content: bool,
+    grep_regexes: Vec<String>,
+    grep_context: Option<usize>,
+    max_filesize: Option<u64>,
+    exclude_paths: Vec<PathBuf>,
+    fail_fast: bool,
+    fail_if_empty: bool,
+    threads: Option<usize>,
+    submodules: Submodules,
+    path_prefix: Option<PathBuf>,
+    relative_to: Option<PathBuf>,
+    absolute_paths: bool,
+    native_paths: bool,
+    error_placeholders: ErrorPlaceholders,
+    checksums: Option<Checksums>,
+    unicode_tricks: Option<UnicodeTricks>,
+    sentinels: bool,
+    extension_shortcut: bool,
+    git_info: bool,
+    blame_summary: bool,
+    blame_skip: Vec<String>,
+    follow_links: bool,
+    link_template: Option<String>,
+    paths: Vec<PathBuf>,
+    also: Vec<PathBuf>,
+    annotations: Vec<(String, String)>,
+    budget: Option<u64>,
+    max_output: Option<u64>,
+    priorities: Vec<(String, i64)>,
+    truncations: Vec<(String, TruncateLimit)>,
+    truncate_strategy: TruncateStrategy,
+    list_omitted: Option<OmittedFiles>,
+    force_includes: Vec<String>,
+    owners: Vec<String>,
+    only_files: Option<Vec<PathBuf>>,
+    scope: Option<Vec<String>>,
+    default_filters: bool,
+    lock_files: Vec<String>,
+    lock_files_case_insensitive: bool,
+    cache: Option<(PathBuf, String)>,
+    cache_trust_mtime: bool,
+}
+
+impl WeaveOptionsBuilder {
+    /// Include hidden files and directories (those starting with `.`).
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Exclude paths matching `glob` (gitignore syntax, relative to the root).
+    /// May be called repeatedly.
+    ///
+    /// Includes and excludes are evaluated in the order they are added, and
+    /// the last one matching a path (or a directory above it) decides.
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.patterns
+            .push(PatternSource::Pattern(Pattern::Exclude(glob.into())));
+        self
+    }
+
+    /// Include paths matching `glob` (gitignore syntax, relative to the root).
+    /// May be called repeatedly.
+    ///
+    /// After an exclude, an include re-includes what it matches. When the
+    /// first pattern is an include, paths no pattern matches are left out,
+    /// so only included files are woven. Includes never select files that
+    /// `.gitignore` rules or the built-in filters skip.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, collect_files};
+    /// use std::path::Path;
+    ///
+    /// // Every Rust file except lib.rs, and nothing else
+    /// let options = WeaveOptions::new("src")
+    ///     .include("*.rs")
+    ///     .exclude("lib.rs")
+    ///     .build()?;
+    /// let files = collect_files(&options).files;
+    /// assert!(files.iter().any(|f| f.relative_path == Path::new("main.rs")));
+    /// assert!(files.iter().all(|f| f.relative_path != Path::new("lib.rs")));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.patterns
+            .push(PatternSource::Pattern(Pattern::Include(glob.into())));
+        self
+    }
+
+    /// Add the patterns in the file at `path` at this point in the order.
+    /// Each line is an exclude glob, or an include when it starts with `!`;
+    /// blank lines and lines starting with `#` are ignored. The file is
+    /// read by [`build`](Self::build). May be called repeatedly.
+    pub fn patterns_from(mut self, path: impl Into<PathBuf>) -> Self {
+        self.patterns.push(PatternSource::File(path.into()));
+        self
+    }
+
+    /// Only include files whose path relative to the root, with `/`
+    /// separators, matches the regex `re` somewhere (anchor it with `^` and
+    /// `$` to match the whole path). With several, a file matching any of
+    /// them is included. May be called repeatedly.
+    ///
+    /// Regexes apply after the include and exclude globs, and only to files:
+    /// a file the globs leave out stays out, and directories are always
+    /// walked.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, collect_files};
+    /// use std::path::Path;
+    ///
+    /// let options = WeaveOptions::new("src")
+    ///     .exclude("main.rs")
+    ///     .path_regex(r"^(lib|main)\.rs$")
+    ///     .build()?;
+    /// let files = collect_files(&options).files;
+    /// assert_eq!(files.len(), 1);
+    /// assert_eq!(files[0].relative_path, Path::new("lib.rs"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn path_regex(mut self, re: impl Into<String>) -> Self {
+        self.path_regexes.push(re.into());
+        self
+    }
+
+    /// Exclude files whose path relative to the root, with `/` separators,
+    /// matches the regex `re` somewhere, e.g. `/v[0-9]+/`. Like
+    /// [`path_regex`](Self::path_regex), it applies after the globs and only
+    /// to files. May be called repeatedly.
+    pub fn path_regex_not(mut self, re: impl Into<String>) -> Self {
+        self.path_regexes_not.push(re.into());
+        self
+    }
+
+    /// Skip text files whose content matches the regex `re` anywhere, e.g.
+    /// `DO NOT SHARE`. Files are checked after they are read and before
+    /// any [`FileVisitor`](crate::FileVisitor) sees them; binary files
+    /// aren't checked. Skipped files are reported as
+    /// [`SkipReason::ExcludedContent`](crate::SkipReason::ExcludedContent),
+    /// naming the first regex that matched. May be called repeatedly.
+    ///
+    /// ```
+    /// use sourceweaver::{SkipReason, WeaveOptions, generate_markdown};
+    /// use std::path::Path;
+    ///
+    /// let options = WeaveOptions::new("src")
+    ///     .path("lib.rs")
+    ///     .path("tokens.rs")
+    ///     .exclude_content(r"(?i)do not share")
+    ///     .exclude_content(r"mod \w+;")
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// let report = generate_markdown(&mut document, &options)?;
+    ///
+    /// assert_eq!(report.files_written, 1);
+    /// assert_eq!(
+    ///     report.skipped,
+    ///     [(
+    ///         Path::new("lib.rs").to_path_buf(),
+    ///         SkipReason::ExcludedContent { pattern: r"mod \w+;".to_string() }
+    ///     )]
+    /// );
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn exclude_content(mut self, re: impl Into<String>) -> Self {
+        self.content_regexes.push(re.into());
+        self
+    }
+
+    /// Fail the run with [`WeaveError::ExcludedContent`] when a file matches
+    /// an [`exclude_content`](Self::exclude_content) regex, instead of
+    /// skipping the file, for workflows where such a file must never come
+    /// near a document.
+    pub fn strict_content(mut self, strict: bool) -> Self {
+        self.strict_content = strict;
+        self
+    }
+
+    /// Keep only files whose content matches the regex `re` somewhere, e.g.
+    /// `PaymentIntent`. May be called repeatedly, and a file matching any
+    /// of the regexes is kept. Binary files never match. Files are checked
+    /// after they are read and before any
+    /// [`FileVisitor`](crate::FileVisitor) sees them; the rest are reported
+    /// as [`SkipReason::NoContentMatch`](crate::SkipReason::NoContentMatch).
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new("src")
+    ///     .path("lib.rs")
+    ///     .path("tokens.rs")
+    ///     .path("sentinels.rs")
+    ///     .grep(r"fn estimate_tokens")
+    ///     .grep("===== BEGIN FILE: ")
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// let report = generate_markdown(&mut document, &options)?;
+    ///
+    /// assert_eq!(report.files_written, 2);
+    /// assert_eq!(report.skipped.len(), 1);
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn grep(mut self, re: impl Into<String>) -> Self {
+        self.grep_regexes.push(re.into());
+        self
+    }
+
+    /// Cut each section down to the lines a [`grep`](Self::grep) regex
+    /// matches, with `lines` lines of context before and after. Each run of
+    /// lines left out becomes a line such as `… 12 lines omitted` inside
+    /// the code block. A file whose matches all span several lines is kept
+    /// whole. Needs a grep regex.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new("src")
+    ///     .path("tokens.rs")
+    ///     .grep(r"pub fn estimate_tokens")
+    ///     .grep_context(1)
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    /// let document = String::from_utf8(document).unwrap();
+    ///
+    /// assert!(document.contains("pub fn estimate_tokens"));
+    /// assert!(document.contains(" lines omitted\n"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn grep_context(mut self, lines: usize) -> Self {
+        self.grep_context = Some(lines);
+        self
+    }
+
+    /// Skip every directory named `name` (e.g. `node_modules`), at any depth.
+    /// `name` is matched against the directory's file name, not its path.
+    /// May be called repeatedly.
+    pub fn exclude_dir(mut self, name: impl Into<String>) -> Self {
+        self.exclude_dirs.push(name.into());
+        self
+    }
+
+    /// Skip files larger than `bytes`.
+    pub fn max_filesize(mut self, bytes: u64) -> Self {
+        self.max_filesize = Some(bytes);
+        self
+    }
+
+    /// Exclude the file at `path` from the walk, typically the output file.
+    /// The path should be canonicalized; a Windows `\\?\` prefix is
+    /// optional. May be called repeatedly.
+    pub fn exclude_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.exclude_paths
+            .push(display::strip_verbatim(path.into()));
+        self
+    }
+
+    /// Abort on the first entry that cannot be walked or read, instead of
+    /// recording it in the report and carrying on.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Fail with [`WeaveError::NoFiles`] before anything is written when
+    /// the walk selects no files, e.g. because the root is the wrong
+    /// directory or an exclude matches everything.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveError, WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new("src")
+    ///     .exclude("*")
+    ///     .fail_if_empty(true)
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// let result = generate_markdown(&mut document, &options);
+    /// assert!(matches!(result, Err(WeaveError::NoFiles { .. })));
+    /// assert!(document.is_empty());
+    /// # Ok::<(), WeaveError>(())
+    /// ```
+    pub fn fail_if_empty(mut self, fail_if_empty: bool) -> Self {
+        self.fail_if_empty = fail_if_empty;
+        self
+    }
+
+    /// Walk and read on up to `threads` threads. Output order is unaffected;
+    /// `1` runs everything sequentially on the calling thread.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Choose how git submodules under the root are handled. Submodules are
+    /// found through the repository's `.gitmodules` file.
+    pub fn submodules(mut self, submodules: Submodules) -> Self {
+        self.submodules = submodules;
+        self
+    }
+
+    /// Prepend `prefix` to every path shown in the document, e.g. to show
+    /// where a deep root sits in a monorepo.
+    pub fn path_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Show paths relative to `dir`, which must be the root or one of its
+    /// ancestors, instead of relative to the root.
+    pub fn relative_to(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.relative_to = Some(dir.into());
+        self
+    }
+
+    /// Show canonical absolute paths in the document instead of paths
+    /// relative to the root, e.g. for tools that jump back to the editor.
+    /// Can't be combined with a path prefix or relative-to directory.
+    pub fn absolute_paths(mut self, absolute_paths: bool) -> Self {
+        self.absolute_paths = absolute_paths;
+        self
+    }
+
+    /// Show paths with the platform's own separators (`\\` on Windows)
+    /// instead of `/`.
+    pub fn native_paths(mut self, native_paths: bool) -> Self {
+        self.native_paths = native_paths;
+        self
+    }
+
+    /// What the document shows for a file that can't be read. The error is
+    /// recorded in the report whatever the choice.
+    pub fn error_placeholders(mut self, error_placeholders: ErrorPlaceholders) -> Self {
+        self.error_placeholders = error_placeholders;
+        self
+    }
+
+    /// Put a line with the SHA-256 of the file's content under each file
+    /// heading, e.g. `` `sha256:1f2a…` ``, computed over what `checksums`
+    /// says. Binary files get one too. Unreadable files don't.
+    ///
+    /// ```
+    /// use sourceweaver::{Checksums, WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .path("Cargo.toml")
+    ///     .checksums(Checksums::Original)
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    ///
+    /// let document = String::from_utf8(document).unwrap();
+    /// assert!(document.contains("## `Cargo.toml`\n\n`sha256:"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn checksums(mut self, checksums: Checksums) -> Self {
+        self.checksums = Some(checksums);
+        self
+    }
+
+    /// Scan the text of every file for characters that can make code read
+    /// differently than it runs: bidi controls ("Trojan Source"),
+    /// zero-width and other invisible format characters, and Cyrillic or
+    /// Greek lookalikes inside ASCII words. Each one is recorded in
+    /// [`WeaveReport::unicode_tricks`](crate::WeaveReport::unicode_tricks)
+    /// with its line, and with [`UnicodeTricks::Escape`] also written as a
+    /// visible escape. Every file is read, so the
+    /// [render cache](Self::cache) isn't used.
+    ///
+    /// ```
+    /// use sourceweaver::{UnicodeTricks, WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .path("src/main.rs")
+    ///     .unicode_tricks(UnicodeTricks::Flag)
+    ///     .build()?;
+    /// let report = generate_markdown(&mut Vec::new(), &options)?;
+    /// assert!(report.unicode_tricks.is_empty());
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn unicode_tricks(mut self, unicode_tricks: UnicodeTricks) -> Self {
+        self.unicode_tricks = Some(unicode_tricks);
+        self
+    }
+
+    /// Enclose each file section in [sentinel](crate::Sentinel) lines,
+    /// `===== BEGIN FILE: <path> =====` before the heading and
+    /// `===== END FILE: <path> =====` after the code fence, each after a
+    /// blank line, for tools that split the document without parsing
+    /// Markdown. The lines can't be mistaken for fences, and the path is
+    /// the one shown in the heading.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".").path("Cargo.toml").sentinels(true).build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    ///
+    /// let document = String::from_utf8(document).unwrap();
+    /// assert!(document.starts_with("\n===== BEGIN FILE: Cargo.toml =====\n\n## `Cargo.toml`\n"));
+    /// assert!(document.ends_with("```\n\n===== END FILE: Cargo.toml =====\n"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn sentinels(mut self, sentinels: bool) -> Self {
+        self.sentinels = sentinels;
+        self
+    }
+
+    /// Give files whose extension is one of
+    /// [`BINARY_EXTENSIONS`](crate::BINARY_EXTENSIONS), such as `.png` or
+    /// `.jar`, the binary placeholder without reading them. On by default;
+    /// turn it off to check every file's content, e.g. for Git LFS pointers,
+    /// which are text files under the name of the binary they stand for.
+    /// [Checksums](Self::checksums) need the content, so they turn the
+    /// shortcut off too.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    /// use std::fs;
+    ///
+    /// let root = std::env::temp_dir().join(format!("sourceweaver-lfs-{}", std::process::id()));
+    /// fs::create_dir_all(&root)?;
+    /// fs::write(root.join("logo.png"), "version https://git-lfs.github.com/spec/v1\n")?;
+    ///
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &WeaveOptions::new(&root).build()?)?;
+    /// assert!(String::from_utf8_lossy(&document).contains("(Binary file, content omitted)"));
+    ///
+    /// let options = WeaveOptions::new(&root).extension_shortcut(false).build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    /// fs::remove_dir_all(&root)?;
+    /// assert!(String::from_utf8_lossy(&document).contains("git-lfs.github.com"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn extension_shortcut(mut self, extension_shortcut: bool) -> Self {
+        self.extension_shortcut = extension_shortcut;
+        self
+    }
+
+    /// Note each file's most recent commit under its heading, as an italic
+    /// line such as `Last commit: 2024-05-01, a1b2c3d, Alice` (date, short
+    /// SHA, and author), or `Last commit: (untracked)` for files git
+    /// doesn't track. History is read once at build time, with one `git
+    /// log` for the whole tree, so the root must be in a git repository.
+    pub fn git_info(mut self, git_info: bool) -> Self {
+        self.git_info = git_info;
+        self
+    }
+
+    /// Note who wrote each file under its heading, as an italic line such
+    /// as `authors: alice 62%, bob 31%, 3 others`, from `git blame`: the
+    /// two authors owning the most lines with their share, most first and
+    /// ties by name, and how many others there are. Binary files and files
+    /// git can't blame, such as untracked ones, get no line.
+    ///
+    /// Blame is slow, so files are blamed on the reader threads as they
+    /// are read, and not at all when a [`cache`](Self::cache)d section is
+    /// reused; the line is cached with the section.
+    pub fn blame_summary(mut self, blame_summary: bool) -> Self {
+        self.blame_summary = blame_summary;
+        self
+    }
+
+    /// Leave files matching the gitignore-style `glob` without a blame
+    /// summary, e.g. generated code whose authors say nothing. A glob
+    /// matching a directory covers everything below it. May be called
+    /// repeatedly.
+    pub fn skip_blame(mut self, glob: impl Into<String>) -> Self {
+        self.blame_skip.push(glob.into());
+        self
+    }
+
+    /// Descend into symlinked directories. Links to files are always read;
+    /// links to sockets, FIFOs, and devices are always skipped.
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Turn each heading into a link. `{path}` in `template` is replaced
+    /// with the file's URL-encoded path relative to the root, e.g.
+    /// `https://github.com/org/repo/blob/<sha>/{path}`.
+    pub fn link_template(mut self, template: impl Into<String>) -> Self {
+        self.link_template = Some(template.into());
+        self
+    }
+
+    /// Limit the document to `path`, a file or directory below the root
+    /// (relative paths are taken from the root). May be called repeatedly.
+    ///
+    /// Directories are walked with the usual filters. Files are included
+    /// as named, bypassing ignore rules and the built-in filters. A file
+    /// reachable through several paths is written once, and headings stay
+    /// relative to the root.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Also include the file at `path`, which may lie outside the root.
+    /// May be called repeatedly.
+    ///
+    /// The file is added after the walk, bypassing ignore rules, but still
+    /// subject to the size limit, binary detection, and visitors. Outside
+    /// the root it is shown as `external/<file name>`, or by its absolute
+    /// path with [`absolute_paths`](Self::absolute_paths). A missing file is
+    /// reported as a read error rather than failing the build.
+    pub fn also(mut self, path: impl Into<PathBuf>) -> Self {
+        self.also.push(path.into());
+        self
+    }
+
+    /// Show `description` as an italic paragraph under the heading of every
+    /// file matching `glob` (gitignore syntax, relative to the root), e.g.
+    /// "Implements the retry policy". A file matching several globs gets
+    /// their descriptions joined in the order they were added. May be
+    /// called repeatedly.
+    ///
+    /// ```
+    /// use sourceweaver::WeaveOptions;
+    /// use std::path::Path;
+    ///
+    /// let options = WeaveOptions::new("src")
+    ///     .annotate("*.rs", "Rust source.")
+    ///     .annotate("lib.rs", "The library's entry point.")
+    ///     .build()?;
+    /// assert_eq!(
+    ///     options.annotation(Path::new("lib.rs")).as_deref(),
+    ///     Some("Rust source. The library's entry point.")
+    /// );
+    /// assert_eq!(options.annotation(Path::new("README.md")), None);
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn annotate(mut self, glob: impl Into<String>, description: impl Into<String>) -> Self {
+        self.annotations.push((glob.into(), description.into()));
+        self
+    }
+
+    /// Keep the document within `tokens` tokens, as counted by
+    /// [`estimate_tokens`](crate::estimate_tokens), by leaving out whole
+    /// files. Files are dropped by ascending [`priority`](Self::priority)
+    /// and, within a priority, largest first, so as many files as possible
+    /// are kept. Dropped files are reported as
+    /// [`SkipReason::OverBudget`](crate::SkipReason::OverBudget).
+    ///
+    /// Every section is rendered to memory before any is written, so the
+    /// first byte of the document only arrives once all files are read.
+    pub fn budget(mut self, tokens: u64) -> Self {
+        self.budget = Some(tokens);
+        self
+    }
+
+    /// Stop adding file sections once the next one would take what the
+    /// document holds past `bytes`, as a safety valve independent of the
+    /// [`budget`](Self::budget). Each section is rendered in full before
+    /// it is counted, so none is ever cut in half. The files left are
+    /// reported as [`SkipReason::OverOutputLimit`](crate::SkipReason::OverOutputLimit),
+    /// without being rendered, and a closing section says how many there
+    /// were.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new("src").max_output(4096).build()?;
+    /// let mut document = Vec::new();
+    /// let report = generate_markdown(&mut document, &options)?;
+    ///
+    /// let document = String::from_utf8(document).unwrap();
+    /// assert!(report.is_truncated());
+    /// assert!(document.ends_with(&format!(
+    ///     "(Output truncated: {} additional files omitted)\n",
+    ///     report.truncated_files().count()
+    /// )));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn max_output(mut self, bytes: u64) -> Self {
+        self.max_output = Some(bytes);
+        self
+    }
+
+    /// Give files matching `glob` (gitignore syntax, relative to the root)
+    /// the weight `weight` under a [`budget`](Self::budget); files no glob
+    /// matches weigh 0. When several globs match, the last one added
+    /// decides. May be called repeatedly.
+    ///
+    /// Among files of equal weight the largest goes first, and among those
+    /// of equal size the one later in path order, so the choice never
+    /// depends on the order files were read in.
+    ///
+    /// ```
+    /// use sourceweaver::{SkipReason, WeaveOptions, generate_markdown};
+    /// use std::{fs, path::Path};
+    ///
+    /// let root = std::env::temp_dir().join(format!("sourceweaver-priority-{}", std::process::id()));
+    /// fs::create_dir_all(root.join("src"))?;
+    /// fs::write(root.join("src/a.rs"), "a".repeat(400))?;
+    /// fs::write(root.join("src/b.rs"), "b".repeat(400))?;
+    /// fs::write(root.join("notes.md"), "n".repeat(40))?;
+    /// fs::write(root.join("tests.rs"), "t".repeat(40))?;
+    ///
+    /// // Room for one of the sources and little else
+    /// let options = WeaveOptions::new(&root)
+    ///     .budget(120)
+    ///     .priority("src/**", 100)
+    ///     .priority("*.md", 50)
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// let report = generate_markdown(&mut document, &options)?;
+    /// fs::remove_dir_all(&root)?;
+    ///
+    /// // tests.rs weighs 0 and goes first, then notes.md, then the source
+    /// // later in path order
+    /// let omitted: Vec<_> = report
+    ///     .skipped
+    ///     .iter()
+    ///     .map(|(path, reason)| (path.as_path(), reason.clone()))
+    ///     .collect();
+    /// assert_eq!(
+    ///     omitted,
+    ///     [
+    ///         (Path::new("notes.md"), SkipReason::OverBudget { priority: 50 }),
+    ///         (Path::new("src/b.rs"), SkipReason::OverBudget { priority: 100 }),
+    ///         (Path::new("tests.rs"), SkipReason::OverBudget { priority: 0 }),
+    ///     ]
+    /// );
+    /// assert_eq!(report.files_written, 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn priority(mut self, glob: impl Into<String>, weight: i64) -> Self {
+        self.priorities.push((glob.into(), weight));
+        self
+    }
+
+    /// Keep only the start of text files matching `glob` (gitignore syntax,
+    /// relative to the root), as `limit` says, e.g. the first 20 lines of
+    /// `data/**`. The kept text ends with a line `… [truncated by rule
+    /// GLOB] …` inside the code block, naming the glob. When several globs
+    /// match, the last one added decides, so general rules go first and
+    /// exceptions after. May be called repeatedly.
+    ///
+    /// ```
+    /// use sourceweaver::{TruncateLimit, WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .path("src/lib.rs")
+    ///     .path("src/tokens.rs")
+    ///     .truncate("src/*.rs", TruncateLimit { lines: Some(1), bytes: None })
+    ///     .truncate("lib.rs", TruncateLimit { lines: Some(2), bytes: None })
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    ///
+    /// let document = String::from_utf8(document).unwrap();
+    /// // The later rule wins for lib.rs
+    /// assert!(document.contains("\n… [truncated by rule lib.rs] …\n```"));
+    /// assert!(document.contains("```rust\n// src/tokens.rs\n… [truncated by rule src/*.rs] …\n```"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn truncate(mut self, glob: impl Into<String>, limit: TruncateLimit) -> Self {
+        self.truncations.push((glob.into(), limit));
+        self
+    }
+
+    /// How files over their [truncation rule](Self::truncate) are cut
+    /// down: by default, the tail goes. With [`TruncateStrategy::Smart`],
+    /// function bodies in supported languages are elided first, longest
+    /// first, each replaced by `{ /* … body elided … */ }` (an indented
+    /// `...` line in Python), so the declarations of the whole file stay.
+    ///
+    /// ```
+    /// use sourceweaver::{TruncateLimit, TruncateStrategy, WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .path("src/tabs.rs")
+    ///     .truncate("*.rs", TruncateLimit { lines: Some(100), bytes: None })
+    ///     .truncate_strategy(TruncateStrategy::Smart)
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    ///
+    /// let document = String::from_utf8(document).unwrap();
+    /// assert!(document.contains("pub fn expand_tabs(text: &str, width: usize) -> Cow<'_, str> { /* … body elided … */ }"));
+    /// assert!(document.contains("fn is_makefile(path: &Path) -> bool {\n"));
+    /// assert!(!document.contains("[truncated by rule"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn truncate_strategy(mut self, strategy: TruncateStrategy) -> Self {
+        self.truncate_strategy = strategy;
+        self
+    }
+
+    /// End the document with an `## Omitted Files` section listing the
+    /// files under the root it doesn't show, each with a one-word reason:
+    /// `excluded`, `lockfile`, `size`, `budget`, or with
+    /// [`OmittedFiles::All`], `ignored`. A directory whose every file is
+    /// left out for the same reason is listed once, with a trailing `/`.
+    /// The section is left out when nothing was.
+    ///
+    /// ```
+    /// use sourceweaver::{OmittedFiles, WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .include("src/lib.rs")
+    ///     .list_omitted(OmittedFiles::Filtered)
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// generate_markdown(&mut document, &options)?;
+    ///
+    /// let document = String::from_utf8(document).unwrap();
+    /// assert!(document.contains("\n## Omitted Files\n\n"));
+    /// assert!(document.contains("\n- flake.lock (lockfile)\n"));
+    /// assert!(document.contains("\n- src/main.rs (excluded)\n"));
+    /// assert!(!document.contains("- src/lib.rs"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn list_omitted(mut self, mode: OmittedFiles) -> Self {
+        self.list_omitted = Some(mode);
+        self
+    }
+
+    /// Include files matching `glob` (gitignore syntax, relative to the
+    /// root) even when `.gitignore`, `.ignore`, and git exclude rules, the
+    /// hidden-file rule, the lock file filter, or the exclude globs would
+    /// skip them, e.g. a generated `schema.graphql`. May be called
+    /// repeatedly.
+    ///
+    /// Matches are found by a walk that uses the globs as whitelist
+    /// overrides, starting from the directories the globs spell out, so
+    /// `generated/**` reaches into an ignored `generated` directory while
+    /// `*.graphql` finds ignored files only in directories that aren't
+    /// ignored themselves. The size limit, binary detection, and visitors
+    /// still apply.
+    pub fn force_include(mut self, glob: impl Into<String>) -> Self {
+        self.force_includes.push(glob.into());
+        self
+    }
+
+    /// Keep only files that `owner`, e.g. `@org/payments-team`, owns by the
+    /// repository's [`CodeOwners`](crate::CodeOwners) file. May be called
+    /// repeatedly; a file owned by any of the owners is kept. Owners are
+    /// compared ignoring case, and the `@` is optional.
+    ///
+    /// The CODEOWNERS file is looked for where GitHub looks for it
+    /// ([`CODEOWNERS_LOCATIONS`](crate::CODEOWNERS_LOCATIONS)) in the git
+    /// repository containing the root, or in the root itself outside of
+    /// one. Files named with [`path`](Self::path) or force-included are kept
+    /// whoever owns them.
+    pub fn owner(mut self, owner: impl Into<String>) -> Self {
+        self.owners.push(owner.into());
+        self
+    }
+
+    /// Keep only the files at `paths`, relative to the root, e.g. the
+    /// files git reports as modified. Unlike [`path`](Self::path), the
+    /// listed files still go through every other filter, and directories
+    /// without a listed file are never walked. Listed paths that aren't
+    /// files are ignored. May be called repeatedly; an empty list keeps
+    /// nothing.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .only_files(["src/lib.rs", "src/tokens.rs", "Cargo.lock"])
+    ///     .build()?;
+    /// let mut document = Vec::new();
+    /// let report = generate_markdown(&mut document, &options)?;
+    ///
+    /// // The lock file filter still applies
+    /// assert_eq!(report.files_written, 2);
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn only_files<P: Into<PathBuf>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.only_files
+            .get_or_insert_with(Vec::new)
+            .extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Keep only files matching one of `globs` (gitignore syntax, relative
+    /// to the root), e.g. a named set of paths from a configuration file.
+    /// The scope is layered on top of every other filter, including the
+    /// include and exclude globs, so it can only narrow what they select.
+    /// May be called repeatedly; a file matching any of the globs given is
+    /// kept, and an empty list keeps nothing. Files named with
+    /// [`path`](Self::path) or force-included are kept regardless.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, collect_files};
+    /// use std::path::Path;
+    ///
+    /// let options = WeaveOptions::new(".")
+    ///     .scope(["src/walk.rs"])
+    ///     .scope(["README.md", "src/options.rs"])
+    ///     .build()?;
+    /// let files = collect_files(&options).files;
+    /// assert_eq!(files.len(), 3);
+    /// assert_eq!(files[0].relative_path, Path::new("README.md"));
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn scope<S: Into<String>>(mut self, globs: impl IntoIterator<Item = S>) -> Self {
+        self.scope
+            .get_or_insert_with(Vec::new)
+            .extend(globs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Run the built-in default [filter stages](crate::FilterStage): ignore
+    /// files, hidden entries, and lock files. Turning them off leaves only
+    /// what is configured explicitly, such as globs, excluded directory
+    /// names, and the size limit, plus the output file and special file
+    /// stages a run can't do without. Note that `.git` is then walked too.
+    pub fn default_filters(mut self, default_filters: bool) -> Self {
+        self.default_filters = default_filters;
+        self
+    }
+
+    /// Also skip files whose name matches `pattern`, where `*` matches any
+    /// run of characters and `?` a single one, e.g. `*.lock`. Adds to
+    /// [`LOCK_FILES`](crate::LOCK_FILES). May be called repeatedly.
+    pub fn lock_file(mut self, pattern: impl Into<String>) -> Self {
+        self.lock_files.push(pattern.into());
+        self
+    }
+
+    /// Compare lock file names ignoring case. Defaults to
+    /// [`LOCK_FILES_CASE_INSENSITIVE`](crate::LOCK_FILES_CASE_INSENSITIVE).
+    pub fn lock_files_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.lock_files_case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Keep each rendered file section in the JSON cache file at `path`, and
+    /// reuse it on later runs while the file is unchanged, without reading
+    /// the file again. A missing or corrupt cache just means every file is
+    /// rendered, and the cache is rewritten at the end of the run.
+    ///
+    /// Sections are reused together with whatever the
+    /// [`FileVisitor`](crate::FileVisitor) made of them, so `key` must
+    /// describe the visitor's settings: a cache written under another key
+    /// is discarded. Visitors get [`on_reused`](crate::FileVisitor::on_reused)
+    /// instead of `on_file` for reused sections. Archive roots aren't cached.
+    ///
+    /// ```
+    /// use sourceweaver::{WeaveOptions, generate_markdown};
+    ///
+    /// let cache = std::env::temp_dir().join(format!("sourceweaver-doc-{}.json", std::process::id()));
+    /// let options = WeaveOptions::new("src").cache(&cache, "").build()?;
+    ///
+    /// let (mut first, mut second) = (Vec::new(), Vec::new());
+    /// generate_markdown(&mut first, &options)?;
+    /// generate_markdown(&mut second, &options)?;
+    /// assert_eq!(first, second);
+    /// assert!(cache.exists());
+    /// # std::fs::remove_file(&cache).unwrap();
+    /// # Ok::<(), sourceweaver::WeaveError>(())
+    /// ```
+    pub fn cache(mut self, path: impl Into<PathBuf>, key: impl Into<String>) -> Self {
+        self.cache = Some((path.into(), key.into()));
+        self
+    }
+
+    /// Whether an unchanged size and modification time are enough to reuse
+    /// a cached section (the default). With `false` every file is read, and
+    /// its section reused only when the content hashes the same.
+    pub fn cache_trust_mtime(mut self, trust_mtime: bool) -> Self {
+        self.cache_trust_mtime = trust_mtime;
+        self
+    }
+
+    /// Validate the settings and produce the final [`WeaveOptions`].
+    ///
+    /// Fails with [`WeaveError::Root`] when the root doesn't exist, and
+    /// otherwise when a glob or regex does not compile, when a pattern file
+    /// can't be read, when the size limit is zero (which would exclude every
+    /// file), when an excluded path is the root itself, when the relative-to
+    /// directory isn't an ancestor of the root, when absolute paths are
+    /// combined with a path prefix, when owners are given and there is no
+    /// CODEOWNERS file or the root is an archive, when a file list is given
+    /// for an archive, or when git info is asked for outside a git
+    /// repository.
+    pub fn build(self) -> Result<WeaveOptions> {
+        if self.root.as_os_str().is_empty() {
+            return Err(WeaveError::InvalidOptions(
+                "root path must not be empty".to_string(),
+            ));
+        }
+        // Fail now rather than with an empty walk. Symlinks are followed, so
+        // a linked root is scanned as the directory it points to.
+        let metadata = fs::metadata(&self.root).map_err(|source| WeaveError::Root {
+            path: self.root.clone(),
+            source,
+        })?;
+        if !metadata.is_dir() && !metadata.is_file() {
+            return Err(WeaveError::InvalidOptions(format!(
+                "root {} is not a directory, file, or archive",
+                self.root.display()
+            )));
+        }
+        if self.max_filesize == Some(0) {
+            return Err(WeaveError::InvalidOptions(
+                "max file size of 0 would exclude every file".to_string(),
+            ));
+        }
+        if self.threads == Some(0) {
+            return Err(WeaveError::InvalidOptions(
+                "thread count must be at least 1".to_string(),
+            ));
+        }
+        if let Some(exclude_path) = self.exclude_paths.iter().find(|p| **p == self.root) {
+            return Err(WeaveError::InvalidOptions(format!(
+                "excluded path {} is the root directory",
+                exclude_path.display()
+            )));
+        }
+
+        let path_prefix = match (self.path_prefix, &self.relative_to) {
+            (Some(_), Some(_)) => {
+                return Err(WeaveError::InvalidOptions(
+                    "a path prefix and a relative-to directory can't be combined".to_string(),
+                ));
+            }
+            (Some(prefix), None) => Some(prefix),
+            (None, Some(dir)) => Some(prefix_relative_to(&self.root, dir)?),
+            (None, None) => None,
+        };
+        let absolute_base = match self.absolute_paths {
+            true if path_prefix.is_some() => {
+                return Err(WeaveError::InvalidOptions(
+                    "absolute paths can't be combined with a path prefix".to_string(),
+                ));
+            }
+            true => Some(absolute_base(&self.root)?),
+            false => None,
+        };
+
+        if let Some(template) = &self.link_template
+            && !template.contains("{path}")
+        {
+            return Err(WeaveError::InvalidOptions(format!(
+                "link template '{}' has no {{path}} placeholder",
+                template
+            )));
+        }
+
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                let path = self.root.join(path);
+                if let Err(e) = fs::metadata(&path) {
+                    return Err(WeaveError::InvalidOptions(format!(
+                        "can't open {}: {}",
+                        path.display(),
+                        e
+                    )));
+                }
+                if !path.starts_with(&self.root) {
+                    return Err(WeaveError::InvalidOptions(format!(
+                        "{} is not below the root {}",
+                        path.display(),
+                        self.root.display()
+                    )));
+                }
+                Ok(path)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(name) = self
+            .exclude_dirs
+            .iter()
+            .find(|name| name.is_empty() || name.contains(['/', '\\']))
+        {
+            return Err(WeaveError::InvalidOptions(format!(
+                "excluded directory '{}' must be a name, not a path",
+                name
+            )));
+        }
+
+        let mut pattern_list = Vec::new();
+        for source in self.patterns {
+            match source {
+                PatternSource::Pattern(pattern) => pattern_list.push(pattern),
+                PatternSource::File(path) => pattern_list.extend(patterns::read_patterns(&path)?),
+            }
+        }
+        let matcher = Patterns::new(&self.root, &pattern_list)?
+            .with_regexes(&self.path_regexes, &self.path_regexes_not)?;
+        let content_filter = ContentFilter::new(
+            &self.content_regexes,
+            &self.grep_regexes,
+            self.grep_context,
+            self.strict_content,
+        )?;
+        let annotation_matcher = Annotations::new(&self.root, &self.annotations)?;
+        let priority_matcher = Priorities::new(&self.root, &self.priorities)?;
+        let truncation_matcher =
+            Truncations::new(&self.root, &self.truncations, self.truncate_strategy)?;
+        let force_override = force_override(&self.root, &self.force_includes)?;
+        let owner_filter = match self.owners.is_empty() {
+            true => None,
+            false if crate::archive::is_archive(&self.root) => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "filtering by owner needs a directory root, and {} is an archive",
+                    self.root.display()
+                )));
+            }
+            false => Some(OwnerFilter::new(&self.root, &self.owners)?),
+        };
+        let file_list = match &self.only_files {
+            Some(_) if crate::archive::is_archive(&self.root) => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "a file list needs a directory root, and {} is an archive",
+                    self.root.display()
+                )));
+            }
+            Some(files) => Some(FileList::new(files)),
+            None => None,
+        };
+        let scope = match &self.scope {
+            Some(_) if crate::archive::is_archive(&self.root) => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "a scope needs a directory root, and {} is an archive",
+                    self.root.display()
+                )));
+            }
+            Some(globs) => Some(Scope::new(&self.root, globs)?),
+            None => None,
+        };
+        let last_commits = match self.git_info {
+            false => None,
+            true if crate::archive::is_archive(&self.root) => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "git info needs a directory root, and {} is an archive",
+                    self.root.display()
+                )));
+            }
+            true => Some(LastCommits::load(&self.root)?),
+        };
+        let blame = match self.blame_summary {
+            false => None,
+            true if crate::archive::is_archive(&self.root) => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "blame summaries need a directory root, and {} is an archive",
+                    self.root.display()
+                )));
+            }
+            true => Some(Blame::new(&self.root, &self.blame_skip)?),
+        };
+
+        Ok(WeaveOptions {
+            root: self.root,
+            hidden: self.hidden,
+            patterns: pattern_list,
+            exclude_dirs: self.exclude_dirs,
+            path_regexes: self.path_regexes,
+            path_regexes_not: self.path_regexes_not,
+            content_regexes: self.content_regexes,
+            grep_regexes: self.grep_regexes,
+            grep_context: self.grep_context,
+            content_filter,
+            matcher,
+            max_filesize: self.max_filesize,
+            exclude_paths: self.exclude_paths,
+            fail_fast: self.fail_fast,
+            fail_if_empty: self.fail_if_empty,
+            threads: self.threads,
+            submodules: self.submodules,
+            path_prefix,
+            absolute_base,
+            native_paths: self.native_paths,
+            error_placeholders: self.error_placeholders,
+            checksums: self.checksums,
+            unicode_tricks: self.unicode_tricks,
+            sentinels: self.sentinels,
+            extension_shortcut: self.extension_shortcut,
+            last_commits,
+            blame_skip: self.blame_skip,
+            blame,
+            follow_links: self.follow_links,
+            link_template: self.link_template,
+            paths,
+            also: self.also,
+            annotations: self.annotations,
+            annotation_matcher,
+            budget: self.budget,
+            max_output: self.max_output,
+            priorities: self.priorities,
+            priority_matcher,
+            truncations: self.truncations,
+            truncate_strategy: self.truncate_strategy,
+            list_omitted: self.list_omitted,
+            truncation_matcher,
+            force_includes: self.force_includes,
+            force_override,
+            owners: self.owners,
+            owner_filter,
+            file_list,
+            scope_globs: self.scope,
+            scope,
+            default_filters: self.default_filters,
+            lock_files: self.lock_files,
+            lock_files_case_insensitive: self.lock_files_case_insensitive,
+            cache: self.cache,
+            cache_trust_mtime: self.cache_trust_mtime,
+        })
+    }
+}
+
+// A pattern given directly, or a file of patterns read at build time
+#[derive(Debug, Clone)]
+enum PatternSource {
+    Pattern(Pattern),
+    File(PathBuf),
+}
+
+// The whitelist overrides for the force-include globs, if there are any
+fn force_override(root: &Path, globs: &[String]) -> Result<Option<Override>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = OverrideBuilder::new(root);
+    for glob in globs {
+        let invalid = |message: String| {
+            WeaveError::InvalidOptions(format!(
+                "invalid force-include glob '{}': {}",
+                glob, message
+            ))
+        };
+        // A leading '!' would turn the override into an ignore rule
+        if glob.starts_with('!') {
+            return Err(invalid("must not start with '!'".to_string()));
+        }
+        builder.add(glob).map_err(|e| invalid(e.to_string()))?;
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| WeaveError::InvalidOptions(format!("invalid force-include globs: {}", e)))
+}
+
+// The directory that relative paths are joined to for absolute display: the
+// canonical root, or its parent when the root is a single file
+fn absolute_base(root: &Path) -> Result<PathBuf> {
+    let canonical = fs::canonicalize(root).map_err(|e| {
+        WeaveError::InvalidOptions(format!("can't resolve {}: {}", root.display(), e))
+    })?;
+    let base = match canonical.parent() {
+        Some(parent) if canonical.is_file() && !crate::archive::is_archive(&canonical) => {
+            parent.to_path_buf()
+        }
+        _ => canonical,
+    };
+    Ok(display::strip_verbatim(base))
+}
+
+// The path of `root` below its ancestor `dir`
+fn prefix_relative_to(root: &Path, dir: &Path) -> Result<PathBuf> {
+    let canonical = |path: &Path| {
+        fs::canonicalize(path).map_err(|e| {
+            WeaveError::InvalidOptions(format!("can't resolve {}: {}", path.display(), e))
+        })
+    };
+    let (root_canonical, dir_canonical) = (canonical(root)?, canonical(dir)?);
+    root_canonical
+        .strip_prefix(&dir_canonical)
+        .map(Path::to_path_buf)
+        .map_err(|_| {
+            WeaveError::InvalidOptions(format!(
+                "{} is not an ancestor of the root {}",
+                dir.display(),
+                root.display()
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_markdown;
+    use std::io::ErrorKind;
+
+    fn build_error(root: &Path) -> WeaveError {
+        WeaveOptions::new(root).build().unwrap_err()
+    }
+
+    #[test]
+    fn missing_root_names_the_attempted_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("no-such-dir");
+        let err = build_error(&missing);
+        assert!(matches!(err, WeaveError::Root { .. }), "{:?}", err);
+        assert_eq!(err.io_error_kind(), Some(ErrorKind::NotFound));
+        assert_eq!(err.path(), Some(missing.as_path()));
+        let message = err.to_string();
+        assert!(
+            message.starts_with(&format!("can't open root {}: ", missing.display())),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn root_below_a_file_is_not_found_as_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let err = build_error(&dir.path().join("notes.txt/src"));
+        assert!(matches!(err, WeaveError::Root { .. }), "{:?}", err);
+        assert_ne!(err.io_error_kind(), Some(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn empty_and_special_roots_are_invalid_options() {
+        let err = build_error(Path::new(""));
+        assert_eq!(
+            err.to_string(),
+            "invalid options: root path must not be empty"
+        );
+        #[cfg(unix)]
+        {
+            let dir = tempfile::tempdir().unwrap();
+            let fifo = dir.path().join("pipe");
+            let made = std::process::Command::new("mkfifo").arg(&fifo).status();
+            assert!(made.unwrap().success());
+            let err = build_error(&fifo);
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "invalid options: root {} is not a directory, file, or archive",
+                    fifo.display()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn trailing_slash_and_symlinked_roots_weave_alike() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+
+        let weave = |root: PathBuf| {
+            let options = WeaveOptions::new(root).build().unwrap();
+            let mut document = Vec::new();
+            generate_markdown(&mut document, &options).unwrap();
+            String::from_utf8(document).unwrap()
+        };
+        let plain = weave(project.clone());
+        assert!(plain.contains("\n## `src/lib.rs`\n"), "{}", plain);
+        let mut slashed = project.clone().into_os_string();
+        slashed.push("/");
+        assert_eq!(weave(slashed.into()), plain);
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link");
+            std::os::unix::fs::symlink(&project, &link).unwrap();
+            assert_eq!(weave(link), plain);
+        }
+    }
+}
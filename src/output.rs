@@ -0,0 +1,690 @@
+// src/output.rs
+use crate::compress::{Compression, Encoder, decoder};
+use sourceweaver::WeaveError;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Read, StdoutLock, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+// Every file written by --output starts with this, so a later run can tell
+// its own output apart from a file it must not clobber
+const MARKER_PREFIX: &str = "<!-- Generated by sourceweaver";
+
+/// Whether `line` is the generation marker a run writes at the top of its
+/// document. It names the version, root, and time of the run.
+pub fn is_marker(line: &str) -> bool {
+    line.starts_with(MARKER_PREFIX)
+}
+
+/// Marker after which `--inject` places the document.
+pub const INJECT_BEGIN: &str = "<!-- sourceweaver:begin -->";
+
+/// Marker before which `--inject` ends the document.
+pub const INJECT_END: &str = "<!-- sourceweaver:end -->";
+
+/// The root and time a run names in its generation marker and generated
+/// file names.
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    root: PathBuf,
+    time: Option<SystemTime>,
+}
+
+impl Stamp {
+    /// The stamp for a run over `root`. The time is `SOURCE_DATE_EPOCH` when
+    /// set, otherwise now; an invalid `SOURCE_DATE_EPOCH` fails a
+    /// `reproducible` run and is otherwise ignored with a warning. A
+    /// `reproducible` run names only the root's directory, not where it
+    /// lies, and without `SOURCE_DATE_EPOCH` has no time at all.
+    pub fn new(root: &Path, reproducible: bool) -> sourceweaver::Result<Self> {
+        let epoch = std::env::var("SOURCE_DATE_EPOCH").ok().map(|epoch| {
+            match epoch.trim().parse::<u64>() {
+                Ok(seconds) => Ok(UNIX_EPOCH + Duration::from_secs(seconds)),
+                Err(_) => Err(epoch),
+            }
+        });
+        let time = match epoch {
+            Some(Ok(time)) => Some(time),
+            // Only a reproducible run depends on it
+            Some(Err(epoch)) if reproducible => {
+                return Err(WeaveError::InvalidOptions(format!(
+                    "SOURCE_DATE_EPOCH must be a number of seconds, not '{}'",
+                    epoch
+                )));
+            }
+            Some(Err(epoch)) => {
+                warn!(
+                    "Ignoring SOURCE_DATE_EPOCH '{}', which isn't a number of seconds.",
+                    epoch
+                );
+                Some(SystemTime::now())
+            }
+            None if reproducible => None,
+            None => Some(SystemTime::now()),
+        };
+        let root = match reproducible {
+            true => {
+                let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+                root.file_name().map_or(PathBuf::from("."), PathBuf::from)
+            }
+            false => root.to_path_buf(),
+        };
+        Ok(Stamp { root, time })
+    }
+
+    // The date part of an RFC 3339 timestamp, in UTC
+    fn date(&self) -> Option<String> {
+        let time = humantime::format_rfc3339_seconds(self.time?).to_string();
+        Some(time[..10].to_string())
+    }
+}
+
+/// The canonical form of `path`, whether or not it exists yet, so the walk
+/// can exclude the output file from its own output. The deepest existing
+/// ancestor is canonicalized and the remaining components re-attached.
+pub fn canonical_target(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return Some(
+                rest.iter()
+                    .rev()
+                    .fold(canonical, |acc, name| acc.join(name)),
+            );
+        }
+        rest.push(existing.file_name()?);
+        existing = match existing.parent()? {
+            parent if parent.as_os_str().is_empty() => Path::new("."),
+            parent => parent,
+        };
+    }
+}
+
+/// The file `--output PATH` writes to, or None for `-`, which means stdout.
+///
+/// A directory, whether it exists or is spelled with a trailing separator,
+/// gets a generated name inside it: `<root-name>-<date>.md`, e.g.
+/// `out/myproject-2025-01-07.md`, or `<root-name>.md` when the stamp has no
+/// time.
+pub fn resolve_target(path: &Path, root: &Path, stamp: &Stamp) -> Option<PathBuf> {
+    if path.as_os_str() == "-" {
+        return None;
+    }
+    let spelled_as_dir = path
+        .as_os_str()
+        .to_string_lossy()
+        .ends_with(['/', std::path::MAIN_SEPARATOR]);
+    if !spelled_as_dir && !path.is_dir() {
+        return Some(path.to_path_buf());
+    }
+
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let name = match root.is_file() {
+        true => root.file_stem(),
+        false => root.file_name(),
+    };
+    let name = name.map_or("sourceweaver".into(), |name| name.to_string_lossy());
+    let target = match stamp.date() {
+        Some(date) => path.join(format!("{}-{}.md", name, date)),
+        None => path.join(format!("{}.md", name)),
+    };
+    info!("Writing the document to {}", target.display());
+    Some(target)
+}
+
+/// Reject an output path that can't hold the document before anything is
+/// created: an existing directory, or, for a single-file or archive root,
+/// the root itself.
+pub fn check_target(path: &Path, root: &Path) -> sourceweaver::Result<()> {
+    if path.is_dir() {
+        return Err(WeaveError::InvalidOptions(format!(
+            "output path {} is a directory",
+            path.display()
+        )));
+    }
+    if let (Some(target), Ok(root)) = (canonical_target(path), fs::canonicalize(root))
+        && target == root
+    {
+        return Err(WeaveError::InvalidOptions(format!(
+            "output path {} is the root being scanned",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+// Whether `path` is missing, empty, or a document we wrote earlier
+fn safe_to_overwrite(path: &Path, compression: Option<Compression>) -> io::Result<bool> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    if !file.metadata()?.is_file() {
+        return Ok(true); // Let the open for writing report it
+    }
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    let mut head = Vec::with_capacity(MARKER_PREFIX.len());
+    // Anything that doesn't decompress isn't ours
+    if decoder(file, compression)?
+        .take(MARKER_PREFIX.len() as u64)
+        .read_to_end(&mut head)
+        .is_err()
+    {
+        return Ok(false);
+    }
+    // Documents from before the marker existed start with a file heading
+    Ok(head.is_empty() || head == MARKER_PREFIX.as_bytes() || head.starts_with(b"\n## `"))
+}
+
+/// Where the document for `path` is written until it is complete: a hidden
+/// file next to it, so the final rename stays on the same filesystem.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    path.with_file_name(format!(".{}.tmp-{}", name, process::id()))
+}
+
+/// The `--output` file, opened for writing. The document goes to a
+/// temporary file that only replaces `path` once it is complete, so an
+/// interrupted run never leaves a truncated document behind.
+pub struct OutputFile {
+    path: PathBuf,
+    // Declared before `temp` so the handle is closed before the file is removed
+    file: Encoder,
+    temp: TempFile,
+    append: bool,
+    // Length of the earlier document being appended to
+    start_len: u64,
+    // What follows the document, for --inject
+    tail: Vec<u8>,
+}
+
+// A temporary file that is removed on drop unless it was renamed into place
+struct TempFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted
+            && let Err(e) = fs::remove_file(&self.path)
+        {
+            warn!(
+                "Could not remove temporary file {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+impl OutputFile {
+    /// Open `path` for writing, creating missing parent directories. With
+    /// `append`, the existing document is carried over and extended;
+    /// appending to a missing file creates it. Without `force`, an existing
+    /// file that doesn't look like earlier sourceweaver output is left
+    /// untouched and an error returned. With `compression`, appended runs
+    /// become further gzip members or zstd frames, which decompress as one.
+    pub fn open(
+        path: &Path,
+        append: bool,
+        force: bool,
+        compression: Option<Compression>,
+    ) -> Result<Self, WeaveError> {
+        let output_error = |source| WeaveError::OutputFile {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if !append && !force && !safe_to_overwrite(path, compression).map_err(output_error)? {
+            return Err(output_error(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "refusing to overwrite a file that wasn't generated by sourceweaver; \
+                 pass --force to overwrite it or --append to add to it",
+            )));
+        }
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent).map_err(output_error)?;
+        }
+        let (mut file, temp) = create_temp(path).map_err(output_error)?;
+
+        let mut start_len = 0;
+        if append {
+            match File::open(path) {
+                Ok(mut existing) => {
+                    start_len = io::copy(&mut existing, &mut file).map_err(output_error)?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(output_error(e)),
+            }
+        }
+
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            file: Encoder::new(file, compression).map_err(output_error)?,
+            temp,
+            append,
+            start_len,
+            tail: Vec::new(),
+        })
+    }
+
+    /// Open the existing Markdown file at `path` for replacing what lies
+    /// between its [`INJECT_BEGIN`] and [`INJECT_END`] lines with the
+    /// document. Everything else, the marker lines included, is kept byte
+    /// for byte. Only lines holding nothing but a marker count, and the
+    /// document goes between the first begin line and the last end line,
+    /// so marker lines in a woven file, which end up in between, don't
+    /// confuse the next run.
+    pub fn inject(path: &Path) -> Result<Self, WeaveError> {
+        let output_error = |source| WeaveError::OutputFile {
+            path: path.to_path_buf(),
+            source,
+        };
+        let existing = fs::read(path).map_err(output_error)?;
+        let (head, tail) = split_at_markers(&existing).map_err(|message| {
+            WeaveError::InvalidOptions(format!("can't inject into {}: {}", path.display(), message))
+        })?;
+
+        let (mut file, temp) = create_temp(path).map_err(output_error)?;
+        file.write_all(head).map_err(output_error)?;
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            file: Encoder::new(file, None).map_err(output_error)?,
+            temp,
+            append: false,
+            start_len: 0,
+            tail: tail.to_vec(),
+        })
+    }
+
+    /// Write the generation marker, preceded by a separator when appending
+    /// to a non-empty file.
+    pub fn begin_run(&mut self, stamp: &Stamp) -> Result<(), WeaveError> {
+        let separator = if self.append && self.start_len > 0 {
+            "\n---\n\n"
+        } else {
+            ""
+        };
+        let time = match stamp.time {
+            Some(time) => format!(" at {}", humantime::format_rfc3339_seconds(time)),
+            None => String::new(),
+        };
+        writeln!(
+            self.file,
+            "{}{} {} from {}{} -->",
+            separator,
+            MARKER_PREFIX,
+            env!("CARGO_PKG_VERSION"),
+            stamp.root.display(),
+            time
+        )
+        .map_err(|source| self.error(source))
+    }
+
+    /// Finish and sync the document, then move it over `path`.
+    fn persist(mut self) -> Result<(), WeaveError> {
+        let output_error = |source| WeaveError::OutputFile {
+            path: self.path.clone(),
+            source,
+        };
+        let tail = std::mem::take(&mut self.tail);
+        self.file
+            .write_all(&tail)
+            .and_then(|()| self.file.finish())
+            .and_then(|file| file.sync_all())
+            .map_err(output_error)?;
+        replace(&self.temp.path, &self.path).map_err(output_error)?;
+        self.temp.persisted = true;
+        info!("Successfully wrote codebase to {}", self.path.display());
+        Ok(())
+    }
+
+    fn error(&self, source: io::Error) -> WeaveError {
+        WeaveError::OutputFile {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+// Create the temporary file for `path`, with the permissions of the file it
+// will replace, if there is one
+fn create_temp(path: &Path) -> io::Result<(File, TempFile)> {
+    let temp_path = temp_path(path);
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)?;
+    let temp = TempFile {
+        path: temp_path,
+        persisted: false,
+    };
+    match fs::metadata(path) {
+        Ok(metadata) => fs::set_permissions(&temp.path, metadata.permissions())?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok((file, temp))
+}
+
+// Split `content` into what comes up to the end of the first begin marker
+// line and what comes from the start of the last end marker line
+fn split_at_markers(content: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    let guidance = format!(
+        "add a line with {} and a later line with {} where the document should go",
+        INJECT_BEGIN, INJECT_END
+    );
+    let begin = marker_lines(content, INJECT_BEGIN).next();
+    let end = marker_lines(content, INJECT_END).last();
+    let (Some((_, head_end)), Some((tail_start, _))) = (begin, end) else {
+        return Err(format!("markers not found; {}", guidance));
+    };
+    if tail_start < head_end {
+        return Err(format!(
+            "the begin marker must be on a line before the end marker; {}",
+            guidance
+        ));
+    }
+    Ok((&content[..head_end], &content[tail_start..]))
+}
+
+// The start and end, past its line break, of every line of `content` that
+// holds only `marker`, give or take surrounding whitespace
+fn marker_lines<'a>(
+    content: &'a [u8],
+    marker: &'a str,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    content
+        .split_inclusive(|&b| b == b'\n')
+        .scan(0, |start, line| {
+            let span = (*start, *start + line.len());
+            *start = span.1;
+            Some((span, line))
+        })
+        .filter(move |(_, line)| line.trim_ascii() == marker.as_bytes())
+        .map(|(span, _)| span)
+}
+
+// Rename `from` over `to`. Windows refuses to rename over a file that is
+// open elsewhere, so fall back to removing the target first.
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if cfg!(windows) && to.exists() => {
+            fs::remove_file(to).map_err(|_| e)?;
+            fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+/// Every place the document is written to. Any combination of a file,
+/// stdout, and an in-memory copy for the clipboard may be active; the
+/// document is generated once and written to all of them.
+pub struct Destinations {
+    file: Option<OutputFile>,
+    stdout: Option<BufWriter<StdoutLock<'static>>>,
+    buffer: Option<Vec<u8>>,
+    written: u64,
+}
+
+impl Destinations {
+    pub fn new(file: Option<OutputFile>, stdout: bool, buffer: bool) -> Self {
+        Destinations {
+            file,
+            // Lock stdout for buffered writing
+            stdout: stdout.then(|| BufWriter::new(io::stdout().lock())),
+            buffer: buffer.then(Vec::new),
+            written: 0,
+        }
+    }
+
+    /// Size of the document so far, in bytes (before any compression).
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Flush everything and hand back the in-memory copy, if one was kept.
+    pub fn finish(mut self) -> Result<Option<Vec<u8>>, WeaveError> {
+        if let Some(stdout) = &mut self.stdout {
+            stdout.flush()?; // Ensure buffer is flushed before program exits
+        }
+        if let Some(output) = self.file.take() {
+            output.persist()?;
+        }
+        Ok(self.buffer.take())
+    }
+
+    /// Give up after a failed run. Whatever reached stdout is flushed as is,
+    /// but the partial document is discarded and any earlier output file
+    /// left intact.
+    pub fn abandon(mut self) {
+        if let Some(stdout) = &mut self.stdout {
+            let _ = stdout.flush();
+        }
+        // Dropping the output file removes its temporary file
+        self.file.take();
+    }
+}
+
+impl Write for Destinations {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(output) = &mut self.file {
+            output.file.write_all(buf)?;
+        }
+        if let Some(stdout) = &mut self.stdout {
+            stdout.write_all(buf)?;
+        }
+        if let Some(buffer) = &mut self.buffer {
+            buffer.extend_from_slice(buf);
+        }
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(output) = &mut self.file {
+            output.file.flush()?;
+        }
+        if let Some(stdout) = &mut self.stdout {
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Inject `document` into the file at `path`, as a run with --inject does
+    fn inject(path: &Path, document: &str) -> Result<(), WeaveError> {
+        let mut destinations = Destinations::new(Some(OutputFile::inject(path)?), false, false);
+        destinations.write_all(document.as_bytes())?;
+        destinations.finish().map(|_| ())
+    }
+
+    #[test]
+    fn inject_ignores_markers_inside_the_document() {
+        let dir = std::env::temp_dir().join(format!("sourceweaver-inject-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("README.md");
+        fs::write(
+            &path,
+            format!(
+                "# Intro\n\n{}\nstale\n{}\n\nOutro\n",
+                INJECT_BEGIN, INJECT_END
+            ),
+        )
+        .unwrap();
+        // A woven file quoting the markers, once on lines of their own
+        let document = format!(
+            "\n## `src/output.rs`\n\n```rust\nconst BEGIN: &str = \"{}\";\n```\n\n\
+             ## `docs/inject.md`\n\n```markdown\n{}\n{}\n```\n",
+            INJECT_BEGIN, INJECT_BEGIN, INJECT_END
+        );
+
+        inject(&path, &document).unwrap();
+        let first = fs::read_to_string(&path).unwrap();
+        inject(&path, &document).unwrap();
+        let second = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            first,
+            format!(
+                "# Intro\n\n{}\n{}{}\n\nOutro\n",
+                INJECT_BEGIN, document, INJECT_END
+            )
+        );
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn markers_must_be_whole_lines_in_order() {
+        let quoted = format!("see `{}` and `{}`\n", INJECT_BEGIN, INJECT_END);
+        assert!(split_at_markers(quoted.as_bytes()).is_err());
+
+        let reversed = format!("{}\n{}\n", INJECT_END, INJECT_BEGIN);
+        assert!(split_at_markers(reversed.as_bytes()).is_err());
+
+        let crlf = format!("a\r\n  {}\r\nold\r\n{}\r\nb\r\n", INJECT_BEGIN, INJECT_END);
+        let (head, tail) = split_at_markers(crlf.as_bytes()).unwrap();
+        assert_eq!(head, format!("a\r\n  {}\r\n", INJECT_BEGIN).as_bytes());
+        assert_eq!(tail, format!("{}\r\nb\r\n", INJECT_END).as_bytes());
+    }
+
+    // Write a one-section document to `path`, as a run with --output does
+    fn weave_to(path: &Path, force: bool) -> Result<(), WeaveError> {
+        let mut file = OutputFile::open(path, false, force, Compression::from_path(path))?;
+        file.begin_run(&Stamp::new(Path::new("project"), true)?)?;
+        let mut destinations = Destinations::new(Some(file), false, false);
+        destinations.write_all(b"\n## `main.rs`\n\n```rust\nfn main() {}\n```\n")?;
+        destinations.finish().map(|_| ())
+    }
+
+    #[test]
+    fn earlier_documents_are_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.md");
+        weave_to(&path, false).unwrap();
+        let first = fs::read(&path).unwrap();
+        assert!(first.starts_with(MARKER_PREFIX.as_bytes()));
+        weave_to(&path, false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), first);
+
+        // An empty file, and a document from before the generation marker
+        fs::write(&path, "").unwrap();
+        weave_to(&path, false).unwrap();
+        fs::write(&path, "\n## `old.rs`\n\n```rust\n```\n").unwrap();
+        weave_to(&path, false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), first);
+    }
+
+    #[test]
+    fn unrelated_files_need_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        fs::write(&path, "# My notes\n\nNot generated.\n").unwrap();
+
+        let err = weave_to(&path, false).unwrap_err();
+        assert_eq!(err.io_error_kind(), Some(io::ErrorKind::AlreadyExists));
+        assert!(err.to_string().contains("--force"), "{}", err);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# My notes\n\nNot generated.\n"
+        );
+        // Nothing is left behind next to it either
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        weave_to(&path, true).unwrap();
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with(MARKER_PREFIX)
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_documents_are_recognized() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.md.gz");
+        weave_to(&path, false).unwrap();
+        weave_to(&path, false).unwrap();
+
+        // Plain text under a .gz name doesn't decompress, so it isn't ours
+        fs::write(&path, "\n## `main.rs`\n").unwrap();
+        assert!(weave_to(&path, false).is_err());
+    }
+
+    #[test]
+    fn directories_and_the_root_are_refused_as_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(root.join("src")).unwrap();
+
+        // The root itself, a directory inside it, and its parent
+        for target in [root.clone(), root.join("src"), dir.path().to_path_buf()] {
+            let err = check_target(&target, &root).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "invalid options: output path {} is a directory",
+                    target.display()
+                )
+            );
+        }
+        // A single-file root can't be its own output
+        let single = root.join("notes.md");
+        fs::write(&single, "# Notes\n").unwrap();
+        let dotted = root.join("src/../notes.md");
+        let err = check_target(&dotted, &single).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "invalid options: output path {} is the root being scanned",
+                dotted.display()
+            )
+        );
+        // Files that don't exist yet, even in missing directories, are fine
+        assert!(check_target(&root.join("bundle.md"), &root).is_ok());
+        assert!(check_target(&root.join("out/bundle.md"), &root).is_ok());
+    }
+
+    #[test]
+    fn targets_resolve_through_dots_and_missing_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(base.join("project")).unwrap();
+
+        let resolved = |path: PathBuf| canonical_target(&path).unwrap();
+        assert_eq!(
+            resolved(base.join("project/./out.md")),
+            base.join("project/out.md")
+        );
+        assert_eq!(
+            resolved(base.join("project/../out.md")),
+            base.join("out.md")
+        );
+        assert_eq!(
+            resolved(base.join("project/docs/out.md")),
+            base.join("project/docs/out.md")
+        );
+    }
+}
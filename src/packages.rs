@@ -0,0 +1,662 @@
+// src/packages.rs
+use serde_json::Value as Json;
+use sourceweaver::{Result, WeaveError};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use toml::{Table, Value};
+use tracing::{debug, warn};
+
+/// The dependency tables of a Cargo manifest that can hold path dependencies.
+const CARGO_DEPENDENCY_TABLES: &[&str] =
+    &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The dependency fields of a `package.json`.
+const JS_DEPENDENCY_FIELDS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+// Directories never searched for workspace packages
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Cargo,
+    Js,
+}
+
+// The packages of one workspace at the root, by name, with the manifests
+// describing it, all relative to the root
+struct Workspace {
+    kind: Kind,
+    manifests: Vec<PathBuf>,
+    members: BTreeMap<String, PathBuf>,
+    // The Cargo `[workspace]` table, for inherited dependencies
+    cargo_workspace: Option<Table>,
+}
+
+/// What `--package` limits the document to for the workspaces at `root`,
+/// relative to it: each package in `names` by its directory, with
+/// `with_deps` the workspace packages it depends on, transitively, and the
+/// root manifests of the workspaces involved.
+///
+/// A root `Cargo.toml` makes a Cargo workspace of its `members` globs, and
+/// a root `package.json` with `workspaces` or a `pnpm-workspace.yaml` a
+/// JavaScript one; a root with both can select from either. Fails when
+/// there is neither, or a name is in none of them, naming the manifests
+/// read and the packages found.
+pub fn resolve(root: &Path, names: &[String], with_deps: bool) -> Result<Vec<PathBuf>> {
+    let mut workspaces = Vec::new();
+    if root.join("Cargo.toml").is_file() {
+        workspaces.push(cargo_workspace(root)?);
+    }
+    if root.join("package.json").is_file() || root.join("pnpm-workspace.yaml").is_file() {
+        workspaces.push(js_workspace(root)?);
+    }
+    if workspaces.is_empty() {
+        return Err(WeaveError::InvalidOptions(format!(
+            "selecting packages needs a Cargo.toml, package.json, or pnpm-workspace.yaml in {}",
+            root.display()
+        )));
+    }
+
+    // Each selected directory, with the workspace it is a package of
+    let mut dirs: Vec<(PathBuf, usize)> = Vec::new();
+    for name in names {
+        let Some((dir, workspace)) = workspaces
+            .iter()
+            .enumerate()
+            .find_map(|(index, workspace)| workspace.members.get(name).map(|dir| (dir, index)))
+        else {
+            return Err(unknown_package(root, name, &workspaces));
+        };
+        debug!("Package {} is in {}", name, shown(dir));
+        if !dirs.iter().any(|(known, _)| known == dir) {
+            dirs.push((dir.clone(), workspace));
+        }
+    }
+
+    if with_deps {
+        // Breadth-first over the dependencies of each directory added
+        let mut next = 0;
+        while next < dirs.len() {
+            let (dir, index) = dirs[next].clone();
+            next += 1;
+            let workspace = &workspaces[index];
+            let dependencies = match workspace.kind {
+                Kind::Cargo => cargo_dependencies(root, &dir, workspace)?,
+                Kind::Js => js_dependencies(root, &dir, workspace)?,
+            };
+            for dependency in dependencies {
+                if dependency.starts_with("..") {
+                    warn!(
+                        "Leaving out dependency {} of {}: it lies outside the root",
+                        dependency.display(),
+                        shown(&dir)
+                    );
+                    continue;
+                }
+                if !dirs.iter().any(|(known, _)| *known == dependency) {
+                    debug!("{} depends on {}", shown(&dir), shown(&dependency));
+                    dirs.push((dependency, index));
+                }
+            }
+        }
+    }
+
+    // The root package's directory holds the manifests already
+    let whole_root = dirs.iter().any(|(dir, _)| dir.as_os_str().is_empty());
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for (index, workspace) in workspaces.iter().enumerate() {
+        if !whole_root && dirs.iter().any(|(_, used)| *used == index) {
+            paths.extend(workspace.manifests.iter().cloned());
+        }
+    }
+    paths.extend(dirs.into_iter().map(|(dir, _)| dir));
+    Ok(paths)
+}
+
+fn unknown_package(root: &Path, name: &str, workspaces: &[Workspace]) -> WeaveError {
+    let manifests: Vec<String> = workspaces
+        .iter()
+        .flat_map(|workspace| &workspace.manifests)
+        .map(|manifest| root.join(manifest).display().to_string())
+        .collect();
+    let mut known: Vec<&str> = workspaces
+        .iter()
+        .flat_map(|workspace| workspace.members.keys())
+        .map(String::as_str)
+        .collect();
+    known.sort_unstable();
+    known.dedup();
+    WeaveError::InvalidOptions(format!(
+        "no package '{}' in {}; the packages are: {}",
+        name,
+        manifests.join(", "),
+        match known.is_empty() {
+            true => "(none)".to_string(),
+            false => known.join(", "),
+        }
+    ))
+}
+
+// The Cargo workspace of the root `Cargo.toml`, whose packages are the
+// root's own and those of its `members` globs, minus `exclude`
+fn cargo_workspace(root: &Path) -> Result<Workspace> {
+    let manifest = read_toml(&root.join("Cargo.toml"))?;
+    let cargo_workspace = manifest.get("workspace").and_then(Value::as_table).cloned();
+
+    let mut members = BTreeMap::new();
+    if let Some(name) = cargo_package_name(&manifest) {
+        members.insert(name.to_string(), PathBuf::new());
+    }
+    if let Some(workspace) = &cargo_workspace {
+        let globs: Vec<&str> = toml_strings(workspace.get("members")).collect();
+        let excluded: Vec<&str> = toml_strings(workspace.get("exclude")).collect();
+        for dir in expand_all(root, &globs, &excluded) {
+            let Ok(member) = read_toml(&root.join(&dir).join("Cargo.toml")) else {
+                warn!(
+                    "Workspace member {} has no readable Cargo.toml",
+                    dir.display()
+                );
+                continue;
+            };
+            if let Some(name) = cargo_package_name(&member) {
+                members.insert(name.to_string(), dir);
+            }
+        }
+    }
+    Ok(Workspace {
+        kind: Kind::Cargo,
+        manifests: vec![PathBuf::from("Cargo.toml")],
+        members,
+        cargo_workspace,
+    })
+}
+
+// The directories of the packages the Cargo package in `dir` depends on by
+// `path`, including dependencies inherited from the workspace with
+// `workspace = true` and target-specific ones
+fn cargo_dependencies(root: &Path, dir: &Path, workspace: &Workspace) -> Result<Vec<PathBuf>> {
+    let manifest = read_toml(&root.join(dir).join("Cargo.toml"))?;
+    let inherited = workspace
+        .cargo_workspace
+        .as_ref()
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Value::as_table);
+    let targets = manifest
+        .get("target")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values().filter_map(Value::as_table));
+    let mut found = Vec::new();
+    for table in std::iter::once(&manifest).chain(targets) {
+        let tables = CARGO_DEPENDENCY_TABLES
+            .iter()
+            .filter_map(|name| table.get(*name).and_then(Value::as_table));
+        for (name, dependency) in tables.flatten() {
+            let Some(dependency) = dependency.as_table() else {
+                continue;
+            };
+            if let Some(path) = dependency.get("path").and_then(Value::as_str) {
+                found.push(normalize(&dir.join(path)));
+            } else if dependency.get("workspace").and_then(Value::as_bool) == Some(true)
+                && let Some(path) = inherited
+                    .and_then(|inherited| inherited.get(name))
+                    .and_then(|dependency| dependency.get("path"))
+                    .and_then(Value::as_str)
+            {
+                // Relative to the workspace root
+                found.push(normalize(Path::new(path)));
+            }
+        }
+    }
+    Ok(found)
+}
+
+// The JavaScript workspace of the root `package.json` `workspaces` globs
+// (an array, or yarn's `{ "packages": [...] }`) and of
+// `pnpm-workspace.yaml`'s `packages` list. Globs starting with `!` exclude.
+fn js_workspace(root: &Path) -> Result<Workspace> {
+    let mut manifests = Vec::new();
+    let mut globs: Vec<String> = Vec::new();
+    let mut members = BTreeMap::new();
+
+    if root.join("package.json").is_file() {
+        manifests.push(PathBuf::from("package.json"));
+        let package = read_json(&root.join("package.json"))?;
+        if let Some(name) = package["name"].as_str() {
+            members.insert(name.to_string(), PathBuf::new());
+        }
+        let workspaces = match &package["workspaces"] {
+            Json::Object(workspaces) => workspaces.get("packages").unwrap_or(&Json::Null),
+            workspaces => workspaces,
+        };
+        globs.extend(
+            workspaces
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Json::as_str)
+                .map(str::to_string),
+        );
+    }
+    if root.join("pnpm-workspace.yaml").is_file() {
+        manifests.push(PathBuf::from("pnpm-workspace.yaml"));
+        let path = root.join("pnpm-workspace.yaml");
+        let text = fs::read_to_string(&path).map_err(|e| {
+            WeaveError::InvalidOptions(format!("can't read {}: {}", path.display(), e))
+        })?;
+        globs.extend(pnpm_packages(&text));
+    }
+
+    let (excluded, included): (Vec<&str>, Vec<&str>) = globs
+        .iter()
+        .map(String::as_str)
+        .partition(|glob| glob.starts_with('!'));
+    let excluded: Vec<&str> = excluded.iter().map(|glob| &glob[1..]).collect();
+    for dir in expand_all(root, &included, &excluded) {
+        let Ok(package) = read_json(&root.join(&dir).join("package.json")) else {
+            continue;
+        };
+        if let Some(name) = package["name"].as_str() {
+            members.insert(name.to_string(), dir);
+        }
+    }
+    Ok(Workspace {
+        kind: Kind::Js,
+        manifests,
+        members,
+        cargo_workspace: None,
+    })
+}
+
+// The directories of the workspace packages the JavaScript package in `dir`
+// lists among its dependencies of any kind
+fn js_dependencies(root: &Path, dir: &Path, workspace: &Workspace) -> Result<Vec<PathBuf>> {
+    let package = read_json(&root.join(dir).join("package.json"))?;
+    Ok(JS_DEPENDENCY_FIELDS
+        .iter()
+        .filter_map(|field| package[*field].as_object())
+        .flat_map(|dependencies| dependencies.keys())
+        .filter_map(|name| workspace.members.get(name).cloned())
+        .collect())
+}
+
+// The globs of the `packages` list in a `pnpm-workspace.yaml`, read line by
+// line: the file is small and only this one list matters
+fn pnpm_packages(text: &str) -> Vec<String> {
+    let mut globs = Vec::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        let content = line.split(" #").next().unwrap_or_default().trim_end();
+        if content.trim().is_empty() || content.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = content.trim() == "packages:";
+            continue;
+        }
+        if in_packages && let Some(item) = content.trim().strip_prefix('-') {
+            let item = item.trim().trim_matches(['\'', '"']);
+            if !item.is_empty() {
+                globs.push(item.to_string());
+            }
+        }
+    }
+    globs
+}
+
+fn read_toml(path: &Path) -> Result<Table> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| WeaveError::InvalidOptions(format!("can't read {}: {}", path.display(), e)))?;
+    text.parse().map_err(|e: toml::de::Error| {
+        WeaveError::InvalidOptions(format!("invalid {}: {}", path.display(), e.message()))
+    })
+}
+
+fn read_json(path: &Path) -> Result<Json> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| WeaveError::InvalidOptions(format!("can't read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&text)
+        .map_err(|e| WeaveError::InvalidOptions(format!("invalid {}: {}", path.display(), e)))
+}
+
+fn cargo_package_name(manifest: &Table) -> Option<&str> {
+    manifest.get("package")?.get("name")?.as_str()
+}
+
+// The strings of a TOML array, skipping anything else
+fn toml_strings(value: Option<&Value>) -> impl Iterator<Item = &str> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+// A directory relative to the root as messages show it
+fn shown(dir: &Path) -> String {
+    match dir.as_os_str().is_empty() {
+        true => ".".to_string(),
+        false => dir.display().to_string(),
+    }
+}
+
+// `path` with `.` components dropped and `..` applied where it can be,
+// without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// The directories matching any of `globs` and none of `excluded`, sorted
+fn expand_all(root: &Path, globs: &[&str], excluded: &[&str]) -> Vec<PathBuf> {
+    let excluded: Vec<PathBuf> = excluded
+        .iter()
+        .flat_map(|glob| expand(root, glob))
+        .collect();
+    let mut dirs: Vec<PathBuf> = globs
+        .iter()
+        .flat_map(|glob| expand(root, glob))
+        .filter(|dir| !excluded.contains(dir))
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+// The directories below `root` matching the workspace glob `pattern`,
+// relative to the root and sorted: `*` and `?` match within one path
+// component, `**` any number of directories, and anything else literally.
+// Hidden directories, `node_modules`, and `target` are never searched.
+fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut found = vec![PathBuf::new()];
+    for component in normalize(Path::new(pattern)).iter() {
+        let component = component.to_string_lossy();
+        let mut next = Vec::new();
+        for dir in found {
+            if component == "**" {
+                descendants(root, dir, &mut next);
+            } else if !component.contains(['*', '?']) {
+                let path = dir.join(&*component);
+                if root.join(&path).is_dir() {
+                    next.push(path);
+                }
+            } else {
+                next.extend(
+                    subdirs(root, &dir)
+                        .into_iter()
+                        .filter(|(_, name)| wildcard(&component, name))
+                        .map(|(path, _)| path),
+                );
+            }
+        }
+        found = next;
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+// `dir` and every directory below it
+fn descendants(root: &Path, dir: PathBuf, found: &mut Vec<PathBuf>) {
+    for (path, _) in subdirs(root, &dir) {
+        descendants(root, path, found);
+    }
+    found.push(dir);
+}
+
+// The directories directly in `dir` that packages may be in, with their names
+fn subdirs(root: &Path, dir: &Path) -> Vec<(PathBuf, String)> {
+    let Ok(entries) = fs::read_dir(root.join(dir)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()))
+        .map(|name| (dir.join(&name), name))
+        .collect()
+}
+
+// Whether `name` matches `pattern`, where `*` matches any run of characters
+// and `?` any one
+fn wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it had taken
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // A directory tree written file by file
+    struct Fixture(TempDir);
+
+    impl Fixture {
+        fn new() -> Self {
+            Fixture(tempfile::tempdir().unwrap())
+        }
+
+        fn file(self, path: &str, content: &str) -> Self {
+            let path = self.0.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+            self
+        }
+
+        fn resolve(&self, names: &[&str], with_deps: bool) -> Result<Vec<String>> {
+            let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+            let paths = resolve(self.0.path(), &names, with_deps)?;
+            Ok(paths.iter().map(|path| shown(path)).collect())
+        }
+    }
+
+    fn cargo_workspace() -> Fixture {
+        Fixture::new()
+            .file(
+                "Cargo.toml",
+                "[workspace]\nmembers = [\"crates/*\", \"tools/cli\"]\nexclude = [\"crates/old\"]\n\n\
+                 [workspace.dependencies]\nshared = { path = \"libs/shared\" }\n",
+            )
+            .file("crates/core/Cargo.toml", "[package]\nname = \"weaver-core\"\n")
+            .file(
+                "crates/api/Cargo.toml",
+                "[package]\nname = \"weaver-api\"\n\n[dependencies]\n\
+                 weaver-core = { path = \"../core\" }\nshared = { workspace = true }\nserde = \"1\"\n",
+            )
+            .file("crates/old/Cargo.toml", "[package]\nname = \"weaver-old\"\n")
+            .file(
+                "tools/cli/Cargo.toml",
+                "[package]\nname = \"weaver-cli\"\n\n\
+                 [target.'cfg(unix)'.dev-dependencies]\nweaver-api = { path = \"../../crates/api\" }\n",
+            )
+            .file("libs/shared/Cargo.toml", "[package]\nname = \"shared\"\n")
+    }
+
+    #[test]
+    fn a_member_comes_with_the_workspace_manifest() {
+        let workspace = cargo_workspace();
+        assert_eq!(
+            workspace.resolve(&["weaver-api"], false).unwrap(),
+            ["Cargo.toml", "crates/api"]
+        );
+        assert_eq!(
+            workspace
+                .resolve(&["weaver-core", "weaver-api", "weaver-core"], false)
+                .unwrap(),
+            ["Cargo.toml", "crates/core", "crates/api"]
+        );
+    }
+
+    #[test]
+    fn path_dependencies_are_followed_transitively() {
+        let workspace = cargo_workspace();
+        // Through a target-specific dev-dependency, then a direct path and
+        // an inherited workspace dependency
+        assert_eq!(
+            workspace.resolve(&["weaver-cli"], true).unwrap(),
+            [
+                "Cargo.toml",
+                "tools/cli",
+                "crates/api",
+                "crates/core",
+                "libs/shared"
+            ]
+        );
+        assert_eq!(
+            workspace.resolve(&["weaver-core"], true).unwrap(),
+            ["Cargo.toml", "crates/core"]
+        );
+    }
+
+    #[test]
+    fn unknown_packages_list_the_members() {
+        let workspace = cargo_workspace();
+        let err = workspace.resolve(&["weaver-old"], false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "invalid options: no package 'weaver-old' in {}; the packages are: \
+                 weaver-api, weaver-cli, weaver-core",
+                workspace.0.path().join("Cargo.toml").display()
+            )
+        );
+
+        let empty = Fixture::new().file("Cargo.toml", "[workspace]\nmembers = []\n");
+        let err = empty.resolve(&["anything"], false).unwrap_err();
+        assert!(
+            err.to_string().ends_with("the packages are: (none)"),
+            "{}",
+            err
+        );
+
+        let bare = Fixture::new().file("README.md", "# Not a workspace\n");
+        let err = bare.resolve(&["anything"], false).unwrap_err();
+        assert!(
+            err.to_string().starts_with(
+                "invalid options: selecting packages needs a Cargo.toml, package.json, or \
+                 pnpm-workspace.yaml in "
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn dependencies_outside_the_root_are_left_out() {
+        let workspace = Fixture::new()
+            .file("Cargo.toml", "[workspace]\nmembers = [\"app\"]\n")
+            .file(
+                "app/Cargo.toml",
+                "[package]\nname = \"app\"\n\n[dependencies]\nvendored = { path = \"../../vendored\" }\n",
+            );
+        assert_eq!(
+            workspace.resolve(&["app"], true).unwrap(),
+            ["Cargo.toml", "app"]
+        );
+    }
+
+    #[test]
+    fn the_root_package_is_the_whole_root() {
+        let workspace = cargo_workspace().file(
+            "Cargo.toml",
+            "[package]\nname = \"weaver\"\n\n[dependencies]\nweaver-api = { path = \"crates/api\" }\n\n\
+             [workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        assert_eq!(workspace.resolve(&["weaver"], false).unwrap(), ["."]);
+        assert_eq!(
+            workspace.resolve(&["weaver"], true).unwrap(),
+            [".", "crates/api", "crates/core"]
+        );
+    }
+
+    #[test]
+    fn javascript_workspaces_resolve_by_package_name() {
+        let workspace = Fixture::new()
+            .file(
+                "package.json",
+                r#"{"name": "monorepo", "workspaces": {"packages": ["packages/*", "!packages/private"]}}"#,
+            )
+            .file(
+                "pnpm-workspace.yaml",
+                "# extra packages\npackages:\n  - 'apps/**' # every app\n  - \"!apps/legacy\"\nonlyBuiltDependencies:\n  - esbuild\n",
+            )
+            .file("packages/ui/package.json", r#"{"name": "@acme/ui"}"#)
+            .file(
+                "packages/api/package.json",
+                r#"{"name": "@acme/api", "peerDependencies": {"@acme/ui": "*", "react": "^18"}}"#,
+            )
+            .file("packages/private/package.json", r#"{"name": "@acme/private"}"#)
+            .file(
+                "apps/web/package.json",
+                r#"{"name": "web", "dependencies": {"@acme/api": "workspace:*"}}"#,
+            )
+            .file("apps/legacy/package.json", r#"{"name": "legacy"}"#)
+            .file("apps/web/node_modules/dep/package.json", r#"{"name": "dep"}"#);
+
+        assert_eq!(
+            workspace.resolve(&["web"], true).unwrap(),
+            [
+                "package.json",
+                "pnpm-workspace.yaml",
+                "apps/web",
+                "packages/api",
+                "packages/ui"
+            ]
+        );
+        let err = workspace.resolve(&["dep"], false).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("the packages are: @acme/api, @acme/ui, monorepo, web"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,254 @@
+// src/parallel.rs
+use crate::{Result, WalkedFile};
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{
+        Condvar, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+/// Upper bound on file content read ahead of the writer, in bytes.
+pub(crate) const MAX_IN_FLIGHT_BYTES: u64 = 64 << 20;
+
+// Read-ahead accounting shared between the readers and the writer
+struct Budget {
+    in_flight: u64,
+    next_to_write: usize,
+}
+
+/// Load `files` with `read` on up to `threads` worker threads and hand each
+/// result to `emit` on the calling thread, strictly in the order of `files`.
+///
+/// Readers stop claiming new files once `MAX_IN_FLIGHT_BYTES` of content is
+/// waiting to be written. The file the writer needs next is always allowed
+/// through, so one huge file cannot stall the pipeline. If `emit` fails, the
+/// readers are stopped and the error is returned.
+pub(crate) fn read_ordered<T, R, F>(
+    files: &[WalkedFile],
+    threads: usize,
+    read: R,
+    mut emit: F,
+) -> Result<()>
+where
+    T: Send,
+    R: Fn(&WalkedFile) -> T + Sync,
+    F: FnMut(&WalkedFile, T) -> Result<()>,
+{
+    let next_index = AtomicUsize::new(0);
+    let abort = AtomicBool::new(false);
+    let budget = Mutex::new(Budget {
+        in_flight: 0,
+        next_to_write: 0,
+    });
+    let budget_changed = Condvar::new();
+    let (sender, receiver) = mpsc::channel::<(usize, u64, T)>();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (next_index, abort, budget, budget_changed, read) =
+                (&next_index, &abort, &budget, &budget_changed, &read);
+            scope.spawn(move || {
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    let Some(file) = files.get(index) else { break };
+                    let size = fs::metadata(&file.path).map(|m| m.len()).unwrap_or(0);
+
+                    // Wait for room in the read-ahead budget
+                    let mut state = budget.lock().unwrap_or_else(|e| e.into_inner());
+                    while !abort.load(Ordering::SeqCst)
+                        && index != state.next_to_write
+                        && state.in_flight > 0
+                        && state.in_flight + size > MAX_IN_FLIGHT_BYTES
+                    {
+                        state = budget_changed
+                            .wait(state)
+                            .unwrap_or_else(|e| e.into_inner());
+                    }
+                    if abort.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    state.in_flight += size;
+                    drop(state);
+
+                    if sender.send((index, size, read(file))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let result = (|| {
+            let mut pending = BTreeMap::new();
+            for (index, file) in files.iter().enumerate() {
+                let (size, content) = loop {
+                    if let Some(ready) = pending.remove(&index) {
+                        break ready;
+                    }
+                    // All readers exited without producing this file; can't happen
+                    // unless a reader panicked, which the scope will propagate
+                    let Ok((done, size, content)) = receiver.recv() else {
+                        return Ok(());
+                    };
+                    pending.insert(done, (size, content));
+                };
+
+                emit(file, content)?;
+
+                let mut state = budget.lock().unwrap_or_else(|e| e.into_inner());
+                state.in_flight = state.in_flight.saturating_sub(size);
+                state.next_to_write = index + 1;
+                drop(state);
+                budget_changed.notify_all();
+            }
+            Ok(())
+        })();
+
+        if result.is_err() {
+            abort.store(true, Ordering::SeqCst);
+            budget_changed.notify_all();
+        }
+        // Dropping the receiver makes any reader still sending give up
+        drop(receiver);
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WeaveError, WeaveOptions, generate_markdown};
+    use std::{path::Path, time::Duration};
+    use tempfile::TempDir;
+
+    // `count` files of `len` bytes each, sparse where the file system allows
+    fn files(count: usize, len: u64) -> (TempDir, Vec<WalkedFile>) {
+        let dir = tempfile::tempdir().unwrap();
+        let files = (0..count)
+            .map(|index| {
+                let relative_path = format!("{:03}.txt", index).into();
+                let path = dir.path().join(&relative_path);
+                fs::File::create(&path).unwrap().set_len(len).unwrap();
+                WalkedFile {
+                    path,
+                    relative_path,
+                    external: false,
+                }
+            })
+            .collect();
+        (dir, files)
+    }
+
+    #[test]
+    fn results_are_emitted_in_file_order() {
+        let (_dir, files) = files(64, 0);
+        let mut emitted = Vec::new();
+        read_ordered(
+            &files,
+            8,
+            |file| {
+                // Early files take longest, so they finish last
+                let index: u64 = file.relative_path.to_str().unwrap()[..3].parse().unwrap();
+                thread::sleep(Duration::from_micros((64 - index) * 50));
+                file.relative_path.clone()
+            },
+            |file, read| {
+                assert_eq!(file.relative_path, read);
+                emitted.push(read);
+                Ok(())
+            },
+        )
+        .unwrap();
+        let expected: Vec<_> = files.iter().map(|f| f.relative_path.clone()).collect();
+        assert_eq!(emitted, expected);
+    }
+
+    #[test]
+    fn emit_error_stops_the_readers() {
+        let (_dir, files) = files(200, 0);
+        let reads = AtomicUsize::new(0);
+        let mut emits = 0;
+        let result = read_ordered(
+            &files,
+            2,
+            |_| {
+                reads.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(1));
+            },
+            |_, ()| {
+                emits += 1;
+                match emits {
+                    3 => Err(WeaveError::InvalidOptions("disk full".to_string())),
+                    _ => Ok(()),
+                }
+            },
+        );
+        assert!(matches!(result, Err(WeaveError::InvalidOptions(e)) if e == "disk full"));
+        assert_eq!(emits, 3);
+        assert!(reads.load(Ordering::SeqCst) < files.len());
+    }
+
+    #[test]
+    fn read_ahead_stays_within_the_limit() {
+        // Each file alone is under the limit, but no two fit in it together
+        let len = MAX_IN_FLIGHT_BYTES * 5 / 8;
+        let (_dir, files) = files(6, len);
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        read_ordered(
+            &files,
+            4,
+            |_| {
+                let now = in_flight.fetch_add(len as usize, Ordering::SeqCst) + len as usize;
+                peak.fetch_max(now, Ordering::SeqCst);
+            },
+            |_, ()| {
+                // A slow writer, so the readers would run ahead if they could
+                thread::sleep(Duration::from_millis(5));
+                in_flight.fetch_sub(len as usize, Ordering::SeqCst);
+                Ok(())
+            },
+        )
+        .unwrap();
+        // Four readers left alone would hold four files; the file the
+        // writer needs next is always let through, so at most one more than
+        // the limit allows is held
+        let peak = peak.load(Ordering::SeqCst) as u64;
+        assert!(
+            peak <= MAX_IN_FLIGHT_BYTES + len,
+            "{} bytes in flight",
+            peak
+        );
+    }
+
+    #[test]
+    fn documents_match_across_thread_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..120 {
+            let sub = dir.path().join(format!("module_{}", index % 7));
+            fs::create_dir_all(&sub).unwrap();
+            let body = format!("// file {}\n", index).repeat(index % 13 + 1);
+            fs::write(sub.join(format!("file_{}.rs", index)), body).unwrap();
+        }
+        let document = |root: &Path, threads: usize| {
+            let options = WeaveOptions::new(root).threads(threads).build().unwrap();
+            let mut document = Vec::new();
+            generate_markdown(&mut document, &options).unwrap();
+            document
+        };
+        let sequential = document(dir.path(), 1);
+        assert!(sequential.len() > 120 * 20);
+        for threads in [2, 8] {
+            assert!(
+                document(dir.path(), threads) == sequential,
+                "{} threads",
+                threads
+            );
+        }
+    }
+}
@@ -0,0 +1,436 @@
+// src/patterns.rs
+use crate::{Result, WeaveError, display};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use regex::Regex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// An include or exclude glob, in gitignore syntax relative to the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// Select matching paths, re-including them after an earlier exclude.
+    Include(String),
+    /// Leave matching paths out.
+    Exclude(String),
+}
+
+impl Pattern {
+    /// The glob itself.
+    pub fn glob(&self) -> &str {
+        match self {
+            Pattern::Include(glob) | Pattern::Exclude(glob) => glob,
+        }
+    }
+
+    /// Parse one line of a pattern file: an exclude, or an include when it
+    /// starts with `!`. Blank lines and `#` comments yield `None`; `\!` and
+    /// `\#` escape a literal first character.
+    pub fn from_line(line: &str) -> Option<Pattern> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        Some(match line.strip_prefix('!') {
+            Some(glob) => Pattern::Include(glob.to_string()),
+            None => Pattern::Exclude(line.to_string()),
+        })
+    }
+}
+
+/// Read the patterns in the file at `path`, one per line, in order.
+pub(crate) fn read_patterns(path: &Path) -> Result<Vec<Pattern>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        WeaveError::InvalidOptions(format!(
+            "can't read patterns from {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(content.lines().filter_map(Pattern::from_line).collect())
+}
+
+/// The compiled include and exclude patterns, evaluated in order.
+///
+/// A pattern matches a path when it matches the path itself or one of the
+/// directories above it, and the last matching pattern decides. A path no
+/// pattern matches is excluded only when the list starts with an include,
+/// so `--include` alone selects, while `--exclude 'tests/**' --include
+/// 'tests/helpers/**'` re-includes the helpers and keeps everything else.
+///
+/// Path regexes apply to files the globs keep: a file must match one of the
+/// include regexes, if there are any, and none of the exclude regexes.
+#[derive(Debug, Clone)]
+pub(crate) struct Patterns {
+    root: PathBuf,
+    matchers: Vec<(Gitignore, bool)>,
+    path_regexes: Vec<Regex>,
+    path_regexes_not: Vec<Regex>,
+}
+
+impl Patterns {
+    pub(crate) fn new(root: &Path, patterns: &[Pattern]) -> Result<Self> {
+        let matchers = patterns
+            .iter()
+            .map(|pattern| {
+                let (kind, include, opposite) = match pattern {
+                    Pattern::Include(_) => ("include", true, "exclude"),
+                    Pattern::Exclude(_) => ("exclude", false, "include"),
+                };
+                // The matcher would take it for a whitelist rule and turn the
+                // pattern around
+                if let Some(glob) = pattern.glob().strip_prefix('!') {
+                    return Err(WeaveError::InvalidOptions(format!(
+                        "invalid {} glob '{}': a leading '!' isn't supported; {} '{}' instead, \
+                         or write '\\!' for a literal '!'",
+                        kind,
+                        pattern.glob(),
+                        opposite,
+                        glob
+                    )));
+                }
+                let invalid = |e: ignore::Error| {
+                    WeaveError::InvalidOptions(format!(
+                        "invalid {} glob '{}': {}",
+                        kind,
+                        pattern.glob(),
+                        e
+                    ))
+                };
+                let mut builder = GitignoreBuilder::new(root);
+                builder.add_line(None, pattern.glob()).map_err(invalid)?;
+                Ok((builder.build().map_err(invalid)?, include))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Patterns {
+            root: root.to_path_buf(),
+            matchers,
+            path_regexes: Vec::new(),
+            path_regexes_not: Vec::new(),
+        })
+    }
+
+    /// Also require files to match one of `include` (when not empty) and
+    /// none of `exclude`.
+    pub(crate) fn with_regexes(mut self, include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |re: &String| {
+            Regex::new(re).map_err(|e| {
+                WeaveError::InvalidOptions(format!("invalid path regex '{}': {}", re, e))
+            })
+        };
+        self.path_regexes = include.iter().map(compile).collect::<Result<_>>()?;
+        self.path_regexes_not = exclude.iter().map(compile).collect::<Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Whether the file or directory at `path` (below the root, or relative
+    /// to it) is left out. Directories no pattern matches are kept, so the
+    /// walk can reach files inside them.
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let excluded = match self.last_match(path, is_dir) {
+            Some(index) => !self.matchers[index].1,
+            None => !is_dir && self.matchers.first().is_some_and(|(_, include)| *include),
+        };
+        excluded || !is_dir && self.is_excluded_by_regex(path)
+    }
+
+    // Regexes see the relative path with '/' separators on every platform
+    fn is_excluded_by_regex(&self, path: &Path) -> bool {
+        if self.path_regexes.is_empty() && self.path_regexes_not.is_empty() {
+            return false;
+        }
+        let relative = display::to_slash(path.strip_prefix(&self.root).unwrap_or(path));
+        !self.path_regexes.is_empty() && !self.path_regexes.iter().any(|re| re.is_match(&relative))
+            || self
+                .path_regexes_not
+                .iter()
+                .any(|re| re.is_match(&relative))
+    }
+
+    /// Whether the walk may skip the directory at `path` without looking
+    /// inside: it is excluded and no later include could select anything
+    /// below it.
+    pub(crate) fn prunes(&self, dir: &Path) -> bool {
+        match self.last_match(dir, true) {
+            Some(index) => {
+                !self.matchers[index].1
+                    && !self.matchers[index + 1..]
+                        .iter()
+                        .any(|(_, include)| *include)
+            }
+            None => false,
+        }
+    }
+
+    /// Why the file at `path` is kept or left out, naming the deciding glob
+    /// of `patterns`, the list the matcher was built from, or path regex.
+    pub(crate) fn explain(&self, path: &Path, patterns: &[Pattern]) -> String {
+        let relative = display::to_slash(path.strip_prefix(&self.root).unwrap_or(path));
+        let by_glob = match self.last_match(path, false) {
+            Some(index) => {
+                let glob = patterns.get(index).map_or("", Pattern::glob);
+                match self.matchers[index].1 {
+                    true => format!("included by '{}'", glob),
+                    false => return format!("excluded by '{}'", glob),
+                }
+            }
+            None if self.matchers.first().is_some_and(|(_, include)| *include) => {
+                return "matches no include glob".to_string();
+            }
+            None => "no glob matches".to_string(),
+        };
+        if !self.path_regexes.is_empty()
+            && !self.path_regexes.iter().any(|re| re.is_match(&relative))
+        {
+            return "matches no --path-regex".to_string();
+        }
+        if let Some(re) = self
+            .path_regexes_not
+            .iter()
+            .find(|re| re.is_match(&relative))
+        {
+            return format!("matches --path-regex-not '{}'", re);
+        }
+        by_glob
+    }
+
+    // The index of the last pattern matching `path` or a directory above it
+    fn last_match(&self, path: &Path, is_dir: bool) -> Option<usize> {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let levels: Vec<(&Path, bool)> = std::iter::once((relative, is_dir))
+            .chain(relative.ancestors().skip(1).map(|dir| (dir, true)))
+            .filter(|(level, _)| !level.as_os_str().is_empty())
+            .collect();
+        self.matchers.iter().rposition(|(matcher, _)| {
+            levels
+                .iter()
+                .any(|(level, is_dir)| !matcher.matched(level, *is_dir).is_none())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[Pattern]) -> Patterns {
+        Patterns::new(Path::new("/root"), list).unwrap()
+    }
+
+    fn include(glob: &str) -> Pattern {
+        Pattern::Include(glob.to_string())
+    }
+
+    fn exclude(glob: &str) -> Pattern {
+        Pattern::Exclude(glob.to_string())
+    }
+
+    #[test]
+    fn last_matching_pattern_decides() {
+        // Patterns, a file below the root, and whether it is excluded
+        let table: &[(&[Pattern], &str, bool)] = &[
+            (&[], "src/lib.rs", false),
+            (&[exclude("*.md")], "README.md", true),
+            (&[exclude("*.md")], "src/lib.rs", false),
+            (&[include("*.rs")], "src/lib.rs", false),
+            // A leading include selects: what it doesn't match is left out
+            (&[include("*.rs")], "README.md", true),
+            (&[include("*.rs"), exclude("lib.rs")], "src/lib.rs", true),
+            (&[include("*.rs"), exclude("lib.rs")], "src/main.rs", false),
+            (&[exclude("lib.rs"), include("*.rs")], "src/lib.rs", false),
+            // A leading exclude keeps what no pattern matches
+            (
+                &[exclude("tests/"), include("tests/helpers/")],
+                "src/lib.rs",
+                false,
+            ),
+            // Directories above a file match as well
+            (&[exclude("tests/")], "tests/unit/a.rs", true),
+            (&[exclude("tests")], "tests/unit/a.rs", true),
+            (
+                &[exclude("tests/"), include("tests/helpers/")],
+                "tests/a.rs",
+                true,
+            ),
+            (
+                &[exclude("tests/"), include("tests/helpers/")],
+                "tests/helpers/a.rs",
+                false,
+            ),
+            (
+                &[include("src/"), exclude("src/gen/")],
+                "src/gen/a.rs",
+                true,
+            ),
+            (&[include("src/"), exclude("src/gen/")], "src/a.rs", false),
+            (&[include("src/"), exclude("src/gen/")], "docs/a.md", true),
+            // Anchored globs only match from the root
+            (&[exclude("/lib.rs")], "lib.rs", true),
+            (&[exclude("/lib.rs")], "src/lib.rs", false),
+            // A directory-only glob doesn't match a file of that name
+            (&[exclude("build/")], "build", false),
+        ];
+        for (list, path, excluded) in table {
+            let path = Path::new("/root").join(path);
+            assert_eq!(
+                patterns(list).is_excluded(&path, false),
+                *excluded,
+                "{:?} on {}",
+                list,
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn directories_no_pattern_matches_are_kept() {
+        let list = patterns(&[include("*.rs")]);
+        assert!(!list.is_excluded(Path::new("/root/src"), true));
+        assert!(list.is_excluded(Path::new("/root/src/a.md"), false));
+    }
+
+    #[test]
+    fn pruning_waits_for_later_includes() {
+        // Patterns, a directory below the root, and whether the walk may skip it
+        let table: &[(&[Pattern], &str, bool)] = &[
+            (&[], "tests", false),
+            (&[exclude("tests/")], "tests", true),
+            (&[exclude("tests/")], "src", false),
+            (
+                &[exclude("tests/"), include("tests/helpers/")],
+                "tests",
+                false,
+            ),
+            (&[exclude("tests/"), include("*.rs")], "tests", false),
+            (&[include("*.rs"), exclude("tests/")], "tests", true),
+            (&[exclude("tests/")], "tests/unit", true),
+            // An include that doesn't select anything yet still keeps it open
+            (&[include("src/")], "docs", false),
+        ];
+        for (list, dir, prunes) in table {
+            let dir = Path::new("/root").join(dir);
+            assert_eq!(
+                patterns(list).prunes(&dir),
+                *prunes,
+                "{:?} on {}",
+                list,
+                dir.display()
+            );
+        }
+    }
+
+    #[test]
+    fn negated_globs_are_rejected() {
+        for pattern in [exclude("!README.md"), include("!*.rs")] {
+            let error = Patterns::new(Path::new("/root"), &[pattern]).unwrap_err();
+            assert!(matches!(error, WeaveError::InvalidOptions(_)), "{}", error);
+        }
+        // Escaped, the '!' is part of the name
+        let list = patterns(&[exclude("\\!important.md")]);
+        assert!(list.is_excluded(Path::new("/root/!important.md"), false));
+        assert!(!list.is_excluded(Path::new("/root/important.md"), false));
+    }
+
+    #[test]
+    fn pattern_lines() {
+        let table = [
+            ("src/", Some(exclude("src/"))),
+            ("!src/keep.rs", Some(include("src/keep.rs"))),
+            ("  *.md  ", Some(exclude("*.md"))),
+            ("\\!literal", Some(exclude("\\!literal"))),
+            ("# comment", None),
+            ("", None),
+        ];
+        for (line, pattern) in table {
+            assert_eq!(Pattern::from_line(line), pattern, "{:?}", line);
+        }
+    }
+
+    fn regexes(list: &[Pattern], include: &[&str], exclude: &[&str]) -> Patterns {
+        let owned = |res: &[&str]| res.iter().map(|re| re.to_string()).collect::<Vec<_>>();
+        patterns(list)
+            .with_regexes(&owned(include), &owned(exclude))
+            .unwrap()
+    }
+
+    #[test]
+    fn path_regexes_apply_after_globs() {
+        // Globs, --path-regex, --path-regex-not, a file below the root, and
+        // whether it is excluded
+        type Case<'a> = (&'a [Pattern], &'a [&'a str], &'a [&'a str], &'a str, bool);
+        let table: &[Case] = &[
+            (&[], &[r"/v[0-9]+/"], &[], "api/v2/users.rs", false),
+            (&[], &[r"/v[0-9]+/"], &[], "api/users.rs", true),
+            (&[], &[], &[r"/v[0-9]+/"], "api/v2/users.rs", true),
+            (&[], &[], &[r"/v[0-9]+/"], "api/vN/users.rs", false),
+            // Any one include regex is enough, any one exclude is too many
+            (&[], &[r"^docs/", r"\.rs$"], &[], "src/lib.rs", false),
+            (&[], &[], &[r"^docs/", r"\.rs$"], "src/lib.rs", true),
+            // A glob exclusion stands whatever the regexes say
+            (&[exclude("gen/")], &[r"\.rs$"], &[], "gen/v1/api.rs", true),
+            // A glob inclusion is narrowed further by the regexes
+            (
+                &[include("*.rs")],
+                &[r"/v[0-9]+/"],
+                &[],
+                "api/v1/a.rs",
+                false,
+            ),
+            (&[include("*.rs")], &[r"/v[0-9]+/"], &[], "api/a.rs", true),
+            (&[include("*.rs")], &[], &[r"_test\.rs$"], "a_test.rs", true),
+            // Paths are relative to the root, so anchors work
+            (&[], &[r"^src/"], &[], "src/lib.rs", false),
+            (&[], &[r"^lib\.rs$"], &[], "src/lib.rs", true),
+        ];
+        for (list, include, exclude, path, excluded) in table {
+            let path = Path::new("/root").join(path);
+            assert_eq!(
+                regexes(list, include, exclude).is_excluded(&path, false),
+                *excluded,
+                "{:?} {:?} {:?} on {}",
+                list,
+                include,
+                exclude,
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn path_regexes_never_exclude_directories() {
+        let list = regexes(&[], &[r"\.rs$"], &[r"^api"]);
+        assert!(!list.is_excluded(Path::new("/root/src"), true));
+        assert!(!list.is_excluded(Path::new("/root/api"), true));
+        assert!(!list.prunes(Path::new("/root/api")));
+    }
+
+    #[test]
+    fn explanations_name_the_deciding_regex() {
+        let globs = [include("*.rs")];
+        let list = regexes(&globs, &[r"^src/"], &[r"_test\.rs$"]);
+        let explain = |path: &str| list.explain(&Path::new("/root").join(path), &globs);
+        assert_eq!(explain("README.md"), "matches no include glob");
+        assert_eq!(explain("build.rs"), "matches no --path-regex");
+        assert_eq!(
+            explain("src/io_test.rs"),
+            r"matches --path-regex-not '_test\.rs$'"
+        );
+        assert_eq!(explain("src/io.rs"), "included by '*.rs'");
+    }
+
+    #[test]
+    fn invalid_regexes_report_the_regex_error() {
+        let error = patterns(&[])
+            .with_regexes(&[], &["v[0-9".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("invalid options: invalid path regex 'v[0-9': "),
+            "{}",
+            error
+        );
+        // The regex crate's own description of the problem
+        assert!(error.contains("unclosed character class"), "{}", error);
+    }
+}
@@ -0,0 +1,90 @@
+// src/progress.rs
+use crate::logging::{self, Value};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use sourceweaver::{Action, FileContent, FileVisitor};
+use std::path::Path;
+
+// Smaller trees finish before a bar would be useful
+const PROGRESS_THRESHOLD: usize = 500;
+
+/// Visitor drawing a progress bar on stderr with files processed, bytes read,
+/// and an ETA. Only shown when enabled and the walk found enough files.
+/// With `--message-format json` it emits a `file` event per file instead.
+pub struct ProgressVisitor {
+    enabled: bool,
+    bar: Option<ProgressBar>,
+    bytes: u64,
+}
+
+impl ProgressVisitor {
+    pub fn new(enabled: bool) -> Self {
+        ProgressVisitor {
+            enabled,
+            bar: None,
+            bytes: 0,
+        }
+    }
+
+    /// Remove the bar from the terminal.
+    pub fn finish(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+            logging::set_progress(None);
+        }
+    }
+}
+
+impl FileVisitor for ProgressVisitor {
+    fn on_start(&mut self, total_files: usize) {
+        if !self.enabled || total_files < PROGRESS_THRESHOLD {
+            return;
+        }
+        let bar = ProgressBar::new(total_files as u64).with_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} files, {msg} (ETA {eta})")
+                .expect("progress template is valid"),
+        );
+        // Log lines are printed above the bar instead of through it
+        logging::set_progress(Some(bar.clone()));
+        self.bar = Some(bar);
+    }
+
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        let size = match content {
+            FileContent::Text(text) => text.len(),
+            FileContent::Binary(bytes) => bytes.len(),
+        } as u64;
+        self.on_reused(path, size);
+        Action::Emit
+    }
+
+    fn streams(&mut self, _path: &Path) -> bool {
+        true
+    }
+
+    fn on_streamed(&mut self, path: &Path, size: u64) {
+        self.on_reused(path, size);
+    }
+
+    fn on_reused(&mut self, path: &Path, size: u64) {
+        if logging::json_events() {
+            logging::emit_event(
+                "file",
+                &[
+                    ("path", Value::Str(&path.to_string_lossy())),
+                    ("bytes", Value::Num(size)),
+                ],
+            );
+        }
+        if let Some(bar) = &self.bar {
+            self.bytes += size;
+            bar.set_message(format!("{} read", HumanBytes(self.bytes)));
+            bar.inc(1);
+        }
+    }
+}
+
+impl Drop for ProgressVisitor {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
@@ -0,0 +1,59 @@
+// src/prompt.rs
+use sourceweaver::WeaveError;
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+/// Instructions framing the document, from `--prefix` and `--suffix`. The
+/// text is written as is, never as a file section, so a prompt for a
+/// language model can open and close the codebase it is about.
+#[derive(Debug, Default)]
+pub struct Prompt {
+    prefix: Option<String>,
+    suffix: Option<String>,
+}
+
+impl Prompt {
+    /// Resolve each argument to its text, in order: `@FILE` is the content
+    /// of FILE, `@@` stands for a literal leading `@`, and anything else is
+    /// the text itself. Texts are separated by a blank line.
+    pub fn new(prefix: &[String], suffix: &[String]) -> Result<Self, WeaveError> {
+        Ok(Prompt {
+            prefix: join("--prefix", prefix)?,
+            suffix: join("--suffix", suffix)?,
+        })
+    }
+
+    /// Write the prefix, which goes before the first section.
+    pub fn write_prefix<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        match &self.prefix {
+            Some(text) => writeln!(writer, "{}", text),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the suffix, which goes after everything else.
+    pub fn write_suffix<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        match &self.suffix {
+            Some(text) => writeln!(writer, "\n{}", text),
+            None => Ok(()),
+        }
+    }
+}
+
+// The texts of `args` given with `option`, as one block
+fn join(option: &str, args: &[String]) -> Result<Option<String>, WeaveError> {
+    let mut texts = Vec::new();
+    for arg in args {
+        let text = match arg.strip_prefix('@') {
+            Some(literal) if literal.starts_with('@') => literal.to_string(),
+            Some(path) => fs::read_to_string(path).map_err(|e| {
+                WeaveError::InvalidOptions(format!("can't read {} file {}: {}", option, path, e))
+            })?,
+            None => arg.clone(),
+        };
+        texts.push(text.trim_end_matches(['\n', '\r']).to_string());
+    }
+    Ok((!texts.is_empty()).then(|| texts.join("\n\n")))
+}
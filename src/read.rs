@@ -0,0 +1,229 @@
+// src/read.rs
+use memmap2::Mmap;
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read},
+    ops::Deref,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+use tracing::debug;
+
+/// Files at least this large are memory-mapped instead of copied to the heap.
+pub const MMAP_THRESHOLD: u64 = 1 << 20;
+
+/// Attempts at reading a file another process holds open without sharing it,
+/// as editors and virus scanners briefly do on Windows.
+const SHARING_ATTEMPTS: u32 = 3;
+
+/// Wait before the second attempt; doubled before each one after it.
+const SHARING_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Content of a file, either read into memory or mapped from disk.
+pub(crate) enum FileData {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+    /// The file kept changing while it was read, even on a second attempt;
+    /// the bytes may mix old and new content.
+    Changed(Vec<u8>),
+}
+
+impl Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileData::Owned(bytes) | FileData::Changed(bytes) => bytes,
+            FileData::Mapped(map) => map,
+        }
+    }
+}
+
+/// Read the file at `path`, mapping it when it is at least
+/// [`MMAP_THRESHOLD`] bytes and falling back to a plain read when mapping
+/// isn't possible (special filesystems, platforms without mmap, ...).
+///
+/// The file's size and modification time are compared before and after the
+/// read. A file that changed in between is read once more, and if it changed
+/// again the content is returned as [`FileData::Changed`].
+///
+/// On Windows, a file locked by another process (a sharing or lock
+/// violation) is retried a few times with a short backoff before the error
+/// is returned. Other errors, including denied permissions, fail at once.
+pub(crate) fn read_file(path: &Path) -> io::Result<FileData> {
+    read_file_with(path, &mut |_| {})
+}
+
+// `read_file`, calling `between` after each attempt's first stat and before
+// its read, where another process's write would land
+fn read_file_with(path: &Path, between: &mut dyn FnMut(&Path)) -> io::Result<FileData> {
+    if let Some(data) = read_unlocked(path, between)? {
+        return Ok(data);
+    }
+    debug!("{} changed while being read, retrying", path.display());
+    match read_unlocked(path, between)? {
+        Some(data) => Ok(data),
+        None => fs::read(path).map(FileData::Changed),
+    }
+}
+
+// Read `path`, waiting out another process's exclusive lock on it
+fn read_unlocked(path: &Path, between: &mut dyn FnMut(&Path)) -> io::Result<Option<FileData>> {
+    let mut backoff = SHARING_BACKOFF;
+    for _ in 1..SHARING_ATTEMPTS {
+        match read_stable(path, between) {
+            Err(e) if is_sharing_violation(&e) => {
+                debug!(
+                    "{} is locked by another process, retrying: {}",
+                    path.display(),
+                    e
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+    read_stable(path, between)
+}
+
+/// How a read error is shown in the document. A lock held by another
+/// process gets a fixed message, so documents don't differ run to run in
+/// the OS's wording.
+pub(crate) fn error_text(e: &io::Error) -> String {
+    match is_sharing_violation(e) {
+        true => "the file is in use by another process".to_string(),
+        false => e.to_string(),
+    }
+}
+
+// ERROR_SHARING_VIOLATION or ERROR_LOCK_VIOLATION. Access denied is a
+// different code, so genuine permission errors are never retried.
+fn is_sharing_violation(e: &io::Error) -> bool {
+    cfg!(windows) && matches!(e.raw_os_error(), Some(32 | 33))
+}
+
+// One attempt at reading `path`; None when it changed during the read
+fn read_stable(path: &Path, between: &mut dyn FnMut(&Path)) -> io::Result<Option<FileData>> {
+    // Paths beyond MAX_PATH need no special handling on Windows: std adds the
+    // `\\?\` prefix itself when a path is too long for the plain API
+    let mut file = File::open(path)?;
+    let before = Stamp::of(&file.metadata()?);
+    between(path);
+    let data = match before.len >= MMAP_THRESHOLD {
+        true => map_file(&file, before.len).map(FileData::Mapped),
+        false => None,
+    };
+    let data = match data {
+        Some(map) => map,
+        None => {
+            if before.len >= MMAP_THRESHOLD {
+                debug!("Could not map {}, reading it instead", path.display());
+            }
+            let mut bytes = Vec::with_capacity(before.len as usize);
+            file.read_to_end(&mut bytes)?;
+            FileData::Owned(bytes)
+        }
+    };
+    // Stat by path, not handle, so a file replaced by rename counts as changed
+    let after = Stamp::of(&fs::metadata(path)?);
+    Ok((before == after && data.len() as u64 == after.len).then_some(data))
+}
+
+// The parts of a file's metadata that change when it is written
+#[derive(PartialEq, Eq)]
+pub(crate) struct Stamp {
+    pub(crate) len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Stamp {
+    pub(crate) fn of(metadata: &Metadata) -> Self {
+        Stamp {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+fn map_file(file: &File, expected_len: u64) -> Option<Mmap> {
+    // SAFETY: the mapping is read-only, but another process truncating the file
+    // while it is mapped would fault on access. We can't rule that out, so we
+    // narrow the window: the length is re-checked after mapping, and callers
+    // re-check it with `is_intact` right before the bytes are used.
+    let map = unsafe { Mmap::map(file) }.ok()?;
+    (map.len() as u64 == expected_len && is_intact(file, map.len())).then_some(map)
+}
+
+impl FileData {
+    /// Whether a mapped file still has the length it was mapped with. A file
+    /// that shrank must not be touched through its mapping.
+    pub(crate) fn is_intact(&self, path: &Path) -> bool {
+        match self {
+            FileData::Owned(_) | FileData::Changed(_) => true,
+            FileData::Mapped(map) => File::open(path).is_ok_and(|f| is_intact(&f, map.len())),
+        }
+    }
+}
+
+fn is_intact(file: &File, len: usize) -> bool {
+    file.metadata().is_ok_and(|m| m.len() == len as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rewrite `path` with one more line than it has, so its size changes
+    // even where modification times are coarse
+    fn grow(path: &Path) {
+        let mut content = fs::read_to_string(path).unwrap();
+        content.push_str("appended\n");
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn a_file_changed_once_is_read_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watched.txt");
+        fs::write(&path, "original\n").unwrap();
+
+        let mut writes = 0;
+        let data = read_file_with(&path, &mut |path| {
+            if writes == 0 {
+                grow(path);
+            }
+            writes += 1;
+        })
+        .unwrap();
+        assert_eq!(writes, 2);
+        assert!(matches!(data, FileData::Owned(_)));
+        assert_eq!(&data[..], b"original\nappended\n");
+    }
+
+    #[test]
+    fn a_file_that_keeps_changing_is_marked_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        fs::write(&path, "start\n").unwrap();
+
+        let data = read_file_with(&path, &mut grow).unwrap();
+        assert!(matches!(data, FileData::Changed(_)));
+        // The final read sees both rewrites
+        assert_eq!(&data[..], b"start\nappended\nappended\n");
+        assert!(data.is_intact(&path));
+    }
+
+    #[test]
+    fn mapped_files_are_checked_too() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.txt");
+        fs::write(&path, "x".repeat(MMAP_THRESHOLD as usize)).unwrap();
+
+        assert!(matches!(read_file(&path).unwrap(), FileData::Mapped(_)));
+        let data = read_file_with(&path, &mut grow).unwrap();
+        assert!(matches!(data, FileData::Changed(_)));
+        assert_eq!(data.len(), MMAP_THRESHOLD as usize + 2 * "appended\n".len());
+    }
+}
@@ -0,0 +1,235 @@
+// src/report.rs
+use crate::{UnicodeFinding, WeaveError};
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Outcome of a weaving run that completed without a fatal error.
+#[derive(Debug, Default)]
+pub struct WeaveReport {
+    /// Number of file sections written to the document.
+    pub files_written: usize,
+    /// Files written with a placeholder because their content is binary.
+    /// Paths are relative to the root.
+    pub binary_files: Vec<PathBuf>,
+    /// Files that kept changing while they were read; their sections carry
+    /// a note. Paths are relative to the root.
+    pub changed_files: Vec<PathBuf>,
+    /// Non-fatal errors encountered along the way: entries the walker could
+    /// not access and files that could not be read.
+    pub errors: Vec<WeaveError>,
+    /// Files left out of the document by the built-in filters or a
+    /// [`FileVisitor`](crate::FileVisitor), with the reason. Paths are
+    /// relative to the root.
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// Directories the walk entered, the root included. Zero for archives
+    /// and standard input.
+    pub dirs_visited: usize,
+    /// Size in bytes of the section written for each file, heading and
+    /// fences included, in document order. Paths are relative to the root.
+    pub section_bytes: Vec<(PathBuf, u64)>,
+    /// Suspicious Unicode characters in the files written, when
+    /// [scanning for them](crate::WeaveOptionsBuilder::unicode_tricks), in
+    /// document order.
+    pub unicode_tricks: Vec<UnicodeFinding>,
+    /// Where the run spent its time.
+    pub timings: Timings,
+}
+
+impl WeaveReport {
+    /// Whether every entry was walked and read without error.
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Fold the report of another run into this one.
+    pub fn merge(&mut self, other: WeaveReport) {
+        self.files_written += other.files_written;
+        self.binary_files.extend(other.binary_files);
+        self.changed_files.extend(other.changed_files);
+        self.errors.extend(other.errors);
+        self.skipped.extend(other.skipped);
+        self.dirs_visited += other.dirs_visited;
+        self.section_bytes.extend(other.section_bytes);
+        self.unicode_tricks.extend(other.unicode_tricks);
+        self.timings.merge(&other.timings);
+    }
+
+    /// The `n` files with the largest sections, largest first, with their
+    /// size in bytes. Equal sizes are in path order.
+    pub fn largest(&self, n: usize) -> Vec<(&PathBuf, u64)> {
+        let mut sections: Vec<_> = self
+            .section_bytes
+            .iter()
+            .map(|(path, bytes)| (path, *bytes))
+            .collect();
+        sections.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        sections.truncate(n);
+        sections
+    }
+
+    /// Whether the [output limit](crate::WeaveOptionsBuilder::max_output)
+    /// cut the document short.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated_files().next().is_some()
+    }
+
+    /// Files left out because the document reached its
+    /// [output limit](crate::WeaveOptionsBuilder::max_output).
+    pub fn truncated_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.skipped_for(&SkipReason::OverOutputLimit)
+    }
+
+    /// Skipped paths for one reason, in walk order.
+    pub fn skipped_for<'a>(
+        &'a self,
+        reason: &'a SkipReason,
+    ) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        self.skipped
+            .iter()
+            .filter(move |(_, r)| r == reason)
+            .map(|(path, _)| path)
+    }
+}
+
+/// Time a run spent in each phase. Always collected: taking it costs a few
+/// clock reads per file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Traversing the directory tree and filtering its entries.
+    pub walk: Duration,
+    /// Reading files, or the archive. With several threads this is summed
+    /// over them, so it can exceed the run's wall time.
+    pub read: Duration,
+    /// In [`FileVisitor`](crate::FileVisitor)s, which transform content.
+    pub transform: Duration,
+    /// Counting tokens to fit a [budget](crate::WeaveOptionsBuilder::budget).
+    pub tokenize: Duration,
+    /// Rendering sections and writing them out.
+    pub write: Duration,
+}
+
+impl Timings {
+    /// Add the time of another run to this one's.
+    pub fn merge(&mut self, other: &Timings) {
+        self.walk += other.walk;
+        self.read += other.read;
+        self.transform += other.transform;
+        self.tokenize += other.tokenize;
+        self.write += other.write;
+    }
+
+    /// The phases with their names, in pipeline order.
+    pub fn phases(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("walk", self.walk),
+            ("read", self.read),
+            ("transform", self.transform),
+            ("tokenize", self.tokenize),
+            ("write", self.write),
+        ]
+    }
+}
+
+/// Why a file was left out of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SkipReason {
+    /// The file is a known lock file.
+    LockFile,
+    /// The file is the document being written.
+    OutputFile,
+    /// The file exceeds the configured size limit.
+    TooLarge,
+    /// The archive entry's path is absolute or escapes the archive.
+    UnsafePath,
+    /// The directory is a git submodule and submodules are skipped.
+    Submodule,
+    /// The directory is a git submodule that isn't checked out; a
+    /// placeholder section was written instead.
+    UninitializedSubmodule,
+    /// The entry is a socket, FIFO, or device rather than a regular file.
+    SpecialFile,
+    /// The directory's name is one of the excluded directory names.
+    ExcludedDir,
+    /// The document would exceed the token budget with it. The priority
+    /// is the file's weight, and lower weights are dropped first.
+    OverBudget { priority: i64 },
+    /// The document had reached its output limit.
+    OverOutputLimit,
+    /// The file's content matches an excluded content regex, the first
+    /// of which is given.
+    ExcludedContent { pattern: String },
+    /// None of the grep regexes matches the file's content, or the file
+    /// is binary.
+    NoContentMatch,
+    /// A [`FileVisitor`](crate::FileVisitor) skipped it, for the given reason.
+    Visitor(String),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::LockFile => write!(f, "lock file"),
+            SkipReason::OutputFile => write!(f, "output file"),
+            SkipReason::TooLarge => write!(f, "over size limit"),
+            SkipReason::UnsafePath => write!(f, "unsafe archive path"),
+            SkipReason::Submodule => write!(f, "submodule"),
+            SkipReason::UninitializedSubmodule => write!(f, "uninitialized submodule"),
+            SkipReason::SpecialFile => write!(f, "special file"),
+            SkipReason::ExcludedDir => write!(f, "excluded directory"),
+            SkipReason::OverBudget { priority } => {
+                write!(f, "over token budget (priority {})", priority)
+            }
+            SkipReason::OverOutputLimit => write!(f, "over output limit"),
+            SkipReason::ExcludedContent { pattern } => {
+                write!(f, "content matching '{}'", pattern)
+            }
+            SkipReason::NoContentMatch => write!(f, "content not matching"),
+            SkipReason::Visitor(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Shared record of skip decisions, filled in by the walk filter.
+///
+/// The filter closure has to be `Send + Sync + 'static`, so decisions are
+/// collected through a mutex rather than returned.
+#[derive(Debug, Clone, Default)]
+pub struct SkipLog(Arc<Mutex<Vec<(PathBuf, SkipReason)>>>);
+
+impl SkipLog {
+    /// Record that `path` was skipped for `reason`.
+    pub fn record(&self, path: PathBuf, reason: SkipReason) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((path, reason));
+    }
+
+    /// Take every decision recorded so far.
+    pub fn take(&self) -> Vec<(PathBuf, SkipReason)> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// Result of processing a single file that did not fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOutcome {
+    /// The file's section was written.
+    Written,
+    /// The file is binary; its section was written with a placeholder.
+    Binary,
+    /// The file kept changing while it was read. Its section was written
+    /// with a note, and the content may mix old and new versions.
+    Changed,
+    /// A visitor skipped the file for the given reason; nothing was written.
+    Skipped(String),
+    /// The file's content matches the excluded content regex `pattern`;
+    /// nothing was written.
+    ExcludedContent { pattern: String },
+    /// No grep regex matches the file's content; nothing was written.
+    NoContentMatch,
+}
@@ -0,0 +1,36 @@
+// src/scope.rs
+use crate::{Result, WeaveError};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// The globs a file must match one of to be kept, layered on top of the
+/// other filters.
+#[derive(Debug, Clone)]
+pub(crate) struct Scope {
+    matcher: Gitignore,
+}
+
+impl Scope {
+    pub(crate) fn new(root: &Path, globs: &[String]) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        for glob in globs {
+            builder.add_line(None, glob).map_err(|e| {
+                WeaveError::InvalidOptions(format!("invalid scope glob '{}': {}", glob, e))
+            })?;
+        }
+        let matcher = builder
+            .build()
+            .map_err(|e| WeaveError::InvalidOptions(format!("invalid scope globs: {}", e)))?;
+        Ok(Scope { matcher })
+    }
+
+    /// Whether the file at `relative_path` matches a glob. A glob matching
+    /// a directory above the file counts as a match.
+    pub(crate) fn keeps(&self, relative_path: &Path) -> bool {
+        relative_path
+            .ancestors()
+            .filter(|level| !level.as_os_str().is_empty())
+            .enumerate()
+            .any(|(depth, level)| !self.matcher.matched(level, depth > 0).is_none())
+    }
+}
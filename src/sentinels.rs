@@ -0,0 +1,59 @@
+// src/sentinels.rs
+use std::io::{self, Write};
+
+const BEGIN: &str = "===== BEGIN FILE: ";
+const END: &str = "===== END FILE: ";
+const CLOSE: &str = " =====";
+
+/// A line delimiting a file section in a document written with
+/// [`sentinels`](crate::WeaveOptionsBuilder::sentinels), with the path it
+/// names as shown in the heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sentinel<'a> {
+    /// `===== BEGIN FILE: <path> =====`, before the section's heading.
+    Begin(&'a str),
+    /// `===== END FILE: <path> =====`, after the section's code fence.
+    End(&'a str),
+}
+
+impl<'a> Sentinel<'a> {
+    /// The sentinel on `line`, if it is one. A trailing line break is
+    /// ignored.
+    ///
+    /// ```
+    /// use sourceweaver::Sentinel;
+    ///
+    /// assert_eq!(
+    ///     Sentinel::parse("===== BEGIN FILE: src/main.rs =====\n"),
+    ///     Some(Sentinel::Begin("src/main.rs"))
+    /// );
+    /// assert_eq!(
+    ///     Sentinel::parse("===== END FILE: a b.txt ====="),
+    ///     Some(Sentinel::End("a b.txt"))
+    /// );
+    /// assert_eq!(Sentinel::parse("## `src/main.rs`"), None);
+    /// ```
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim_end_matches(['\n', '\r']).strip_suffix(CLOSE)?;
+        if let Some(path) = line.strip_prefix(BEGIN) {
+            return Some(Sentinel::Begin(path));
+        }
+        line.strip_prefix(END).map(Sentinel::End)
+    }
+}
+
+/// Write the sentinel line for `path` opening a section, after a blank line.
+pub(crate) fn write_begin<W: Write + ?Sized>(writer: &mut W, path: Option<&str>) -> io::Result<()> {
+    match path {
+        Some(path) => writeln!(writer, "\n{}{}{}", BEGIN, path, CLOSE),
+        None => Ok(()),
+    }
+}
+
+/// Write the sentinel line for `path` closing a section, after a blank line.
+pub(crate) fn write_end<W: Write + ?Sized>(writer: &mut W, path: Option<&str>) -> io::Result<()> {
+    match path {
+        Some(path) => writeln!(writer, "\n{}{}{}", END, path, CLOSE),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,101 @@
+// src/split.rs
+use crate::progress::ProgressVisitor;
+use clap::ValueEnum;
+use sourceweaver::{Action, CollectedFiles, FileContent, FileVisitor};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+/// Document name for files directly in the root directory.
+pub const ROOT_GROUP: &str = "_root";
+
+/// How `--split-by` divides the files into documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SplitBy {
+    /// One document per top-level directory.
+    TopDir,
+}
+
+/// Divide the result of one walk into named groups. Walk errors and skips go
+/// with the group their path falls under, so each document's report covers
+/// just its own files.
+pub fn group(
+    collected: CollectedFiles,
+    root: &Path,
+    split_by: SplitBy,
+) -> BTreeMap<String, CollectedFiles> {
+    let SplitBy::TopDir = split_by;
+    let mut groups: BTreeMap<String, CollectedFiles> = BTreeMap::new();
+
+    for file in collected.files {
+        let name = top_dir(&file.relative_path);
+        groups.entry(name).or_default().files.push(file);
+    }
+    for (path, reason) in collected.skipped {
+        groups
+            .entry(top_dir(&path))
+            .or_default()
+            .skipped
+            .push((path, reason));
+    }
+    for submodule in collected.uninitialized_submodules {
+        groups
+            .entry(top_dir(&submodule))
+            .or_default()
+            .uninitialized_submodules
+            .push(submodule);
+    }
+    for error in collected.errors {
+        let name = error
+            .path()
+            .map(|path| top_dir(path.strip_prefix(root).unwrap_or(path)))
+            .unwrap_or_else(|| ROOT_GROUP.to_string());
+        groups.entry(name).or_default().errors.push(error);
+    }
+    groups
+}
+
+/// The visitor for one document of a split run: the progress bar, which
+/// spans every document, and the content visitors. A file is streamed or
+/// left unread only when both allow it.
+pub struct DocumentVisitor<'a> {
+    pub progress: &'a mut ProgressVisitor,
+    pub visitors: &'a mut Vec<Box<dyn FileVisitor>>,
+}
+
+impl FileVisitor for DocumentVisitor<'_> {
+    // The bar was started with the file count of every document
+    fn on_start(&mut self, total_files: usize) {
+        self.visitors.on_start(total_files);
+    }
+
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        self.progress.on_file(path, content);
+        self.visitors.on_file(path, content)
+    }
+
+    fn on_reused(&mut self, path: &Path, size: u64) {
+        self.progress.on_reused(path, size);
+        self.visitors.on_reused(path, size);
+    }
+
+    fn streams(&mut self, path: &Path) -> bool {
+        self.progress.streams(path) && self.visitors.streams(path)
+    }
+
+    fn on_streamed(&mut self, path: &Path, size: u64) {
+        self.progress.on_streamed(path, size);
+        self.visitors.on_streamed(path, size);
+    }
+}
+
+// The first directory of a relative path, or the root group for files
+// directly in the root
+fn top_dir(relative_path: &Path) -> String {
+    let mut components = relative_path.components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(dir)), Some(_)) => dir.to_string_lossy().into_owned(),
+        _ => ROOT_GROUP.to_string(),
+    }
+}
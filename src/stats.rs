@@ -0,0 +1,231 @@
+// src/stats.rs
+use crate::list::Counter;
+use crate::summary;
+use clap::ValueEnum;
+use indicatif::{HumanBytes, HumanCount};
+use serde_json::{Map, Value, json};
+use sourceweaver::{
+    Action, FileContent, FileVisitor, SkipReason, WeaveOptions, WeaveReport, detect_language,
+    estimate_tokens, generate_markdown_with,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// How `stats` prints its numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    /// An aligned table.
+    Table,
+    /// One JSON object, for scripts.
+    Json,
+}
+
+// What the document holds for one language
+#[derive(Debug, Default)]
+struct Tally {
+    files: u64,
+    lines: u64,
+    bytes: u64,
+}
+
+impl Tally {
+    fn add(&mut self, other: &Tally) {
+        self.files += other.files;
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "files": self.files,
+            "lines": self.lines,
+            "bytes": self.bytes,
+            "tokens": estimate_tokens(self.bytes),
+        })
+    }
+}
+
+// Counts the lines of every file as the document shows it, after `inner`
+// has had its say, along with the language of its code block
+struct Lines<'a> {
+    inner: &'a mut dyn FileVisitor,
+    files: HashMap<PathBuf, (String, u64)>,
+}
+
+impl FileVisitor for Lines<'_> {
+    fn on_start(&mut self, total_files: usize) {
+        self.inner.on_start(total_files);
+    }
+
+    fn on_reused(&mut self, path: &Path, size: u64) {
+        self.inner.on_reused(path, size);
+    }
+
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        let action = self.inner.on_file(path, content);
+        let text = match (&action, content) {
+            (Action::Skip(_), _) => return action,
+            (Action::EmitModified(text), _) => Some(text.as_str()),
+            (Action::Emit, FileContent::Text(text)) => Some(text),
+            (Action::Emit, FileContent::Binary(_)) => None,
+        };
+        let counted = match text {
+            Some(text) => match detect_language(path, text) {
+                "" => ("other".to_string(), text.lines().count() as u64),
+                language => (language.to_string(), text.lines().count() as u64),
+            },
+            None => ("binary".to_string(), 0),
+        };
+        self.files.insert(path.to_path_buf(), counted);
+        action
+    }
+}
+
+/// Run the walk and read pipeline as a real run would, through `visitor`,
+/// and print only the numbers: files, lines, bytes, and estimated tokens per
+/// language, the totals, and how many files were left out for each reason.
+/// With `top`, the files with the largest sections follow.
+///
+/// Bytes are those of the file sections in the document, so the tokens add
+/// up to what a real run reports.
+pub fn run(
+    options: &WeaveOptions,
+    format: StatsFormat,
+    top: Option<usize>,
+    visitor: &mut dyn FileVisitor,
+) -> sourceweaver::Result<WeaveReport> {
+    let mut lines = Lines {
+        inner: visitor,
+        files: HashMap::new(),
+    };
+    let mut counter = Counter(0);
+    let report = generate_markdown_with(&mut counter, options, &mut lines)?;
+
+    let mut languages: BTreeMap<String, Tally> = BTreeMap::new();
+    for (path, bytes) in &report.section_bytes {
+        let (language, lines) = lines
+            .files
+            .get(path)
+            .map_or(("other", 0), |(language, lines)| {
+                (language.as_str(), *lines)
+            });
+        let tally = languages.entry(language.to_string()).or_default();
+        tally.files += 1;
+        tally.lines += lines;
+        tally.bytes += bytes;
+    }
+    let mut languages: Vec<(String, Tally)> = languages.into_iter().collect();
+    languages.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+    let mut total = Tally::default();
+    for (_, tally) in &languages {
+        total.add(tally);
+    }
+
+    // Visitor reasons are free-form, so they share one count
+    let mut excluded: BTreeMap<String, u64> = BTreeMap::new();
+    for (_, reason) in &report.skipped {
+        let reason = match reason {
+            SkipReason::Visitor(_) => "skipped by visitor".to_string(),
+            SkipReason::OverBudget { .. } => "over token budget".to_string(),
+            reason => reason.to_string(),
+        };
+        *excluded.entry(reason).or_default() += 1;
+    }
+    if !report.errors.is_empty() {
+        excluded.insert("unreadable".to_string(), report.errors.len() as u64);
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match format {
+        StatsFormat::Json => {
+            let mut document = Map::new();
+            let languages: Vec<Value> = languages
+                .iter()
+                .map(|(language, tally)| {
+                    let mut entry = tally.to_json();
+                    entry["language"] = json!(language);
+                    entry
+                })
+                .collect();
+            document.insert("languages".to_string(), json!(languages));
+            document.insert("total".to_string(), total.to_json());
+            document.insert("excluded".to_string(), json!(excluded));
+            if let Some(n) = top {
+                let largest: Vec<Value> = report
+                    .largest(n)
+                    .into_iter()
+                    .map(|(path, bytes)| {
+                        json!({
+                            "path": options.display_path(path),
+                            "bytes": bytes,
+                            "tokens": estimate_tokens(bytes),
+                        })
+                    })
+                    .collect();
+                document.insert("largest".to_string(), json!(largest));
+            }
+            writeln!(out, "{}", Value::Object(document))?;
+        }
+        StatsFormat::Table => {
+            let width = languages
+                .iter()
+                .map(|(language, _)| language.chars().count())
+                .chain(["language".len(), "total".len()])
+                .max()
+                .unwrap_or(0);
+            let row = |language: &str, files: &str, lines: &str, bytes: &str, tokens: &str| {
+                format!(
+                    "{:width$}  {:>7}  {:>10}  {:>10}  {:>10}",
+                    language,
+                    files,
+                    lines,
+                    bytes,
+                    tokens,
+                    width = width
+                )
+            };
+            let tally_row = |language: &str, tally: &Tally| {
+                row(
+                    language,
+                    &HumanCount(tally.files).to_string(),
+                    &HumanCount(tally.lines).to_string(),
+                    &HumanBytes(tally.bytes).to_string(),
+                    &HumanCount(estimate_tokens(tally.bytes)).to_string(),
+                )
+            };
+            writeln!(
+                out,
+                "{}",
+                row("language", "files", "lines", "bytes", "tokens")
+            )?;
+            for (language, tally) in &languages {
+                writeln!(out, "{}", tally_row(language, tally))?;
+            }
+            writeln!(out, "{}", tally_row("total", &total))?;
+            if !excluded.is_empty() {
+                let width = excluded.keys().map(|r| r.chars().count()).max();
+                writeln!(out, "\nExcluded:")?;
+                for (reason, count) in &excluded {
+                    writeln!(
+                        out,
+                        "  {:width$}  {:>7}",
+                        reason,
+                        HumanCount(*count).to_string(),
+                        width = width.unwrap_or(0)
+                    )?;
+                }
+            }
+            if let Some(n) = top {
+                let table = summary::largest_table(&report, options, n, counter.0);
+                if !table.is_empty() {
+                    write!(out, "\n{}", table)?;
+                }
+            }
+        }
+    }
+    out.flush()?;
+    Ok(report)
+}
@@ -0,0 +1,276 @@
+// src/stream.rs
+use crate::{
+    FileOutcome, FileVisitor, Result, SectionOptions, WeaveOptions, annotations, detect_language,
+    hex, read::Stamp, sentinels, write_section,
+};
+use content_inspector::ContentType;
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+use tracing::{debug, warn};
+
+/// Text files at least this large are streamed to the output in fixed-size
+/// chunks instead of being read or mapped whole, so memory use stays flat
+/// however large they are.
+///
+/// Only files no option needs whole are streamed: not under a token
+/// budget, an output limit, or a render cache, which hold sections in
+/// memory, nor with content regexes, truncation rules, blame, or a Unicode
+/// scan. Every visitor must also agree through
+/// [`streams`](crate::FileVisitor::streams). A streamed file's lines are
+/// written exactly as they would be had it been read whole, but its
+/// language comes from its first chunk only.
+///
+/// ```
+/// # #[cfg(target_os = "linux")]
+/// # {
+/// use sourceweaver::{STREAM_THRESHOLD, WeaveOptions, generate_markdown};
+/// use std::{fs, io::Write};
+///
+/// let root = std::env::temp_dir().join(format!("sourceweaver-stream-{}", std::process::id()));
+/// fs::create_dir_all(&root)?;
+/// let mut file = fs::File::create(root.join("big.log"))?;
+/// let line = "a line of a large generated log file\r\n".repeat(1 << 10);
+/// let mut written = 0;
+/// while written < 2 * STREAM_THRESHOLD {
+///     file.write_all(line.as_bytes())?;
+///     written += line.len() as u64;
+/// }
+/// drop(file);
+///
+/// let options = WeaveOptions::new(&root).build()?;
+/// let report = generate_markdown(&mut std::io::sink(), &options)?;
+/// fs::remove_dir_all(&root)?;
+/// assert_eq!(report.files_written, 1);
+/// assert!(report.section_bytes[0].1 > STREAM_THRESHOLD);
+///
+/// // The peak resident set stays well below the size of the file
+/// let status = fs::read_to_string("/proc/self/status")?;
+/// let peak_kib: u64 = status
+///     .lines()
+///     .find_map(|line| line.strip_prefix("VmHWM:"))
+///     .and_then(|peak| peak.trim().trim_end_matches("kB").trim().parse().ok())
+///     .unwrap();
+/// assert!(peak_kib * 1024 < STREAM_THRESHOLD, "peak RSS {} KiB", peak_kib);
+/// # }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub const STREAM_THRESHOLD: u64 = 16 << 20;
+
+/// The size of the chunks a streamed file is read in.
+const CHUNK_SIZE: usize = 64 << 10;
+
+/// Whether `options` let large files be streamed: every section goes
+/// straight to the writer and no stage needs a file's whole text.
+pub(crate) fn allowed(options: &WeaveOptions) -> bool {
+    options.budget.is_none()
+        && options.max_output.is_none()
+        && options.cache.is_none()
+        && options.unicode_tricks.is_none()
+        && options.blame.is_none()
+        && options.content_regexes.is_empty()
+        && options.grep_regexes.is_empty()
+        && options.truncations.is_empty()
+}
+
+/// Whether the file at `path` is large enough to be streamed.
+pub(crate) fn is_large(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.len() >= STREAM_THRESHOLD)
+}
+
+/// Write the section for the file at `full_path` without holding its
+/// content in memory, reading it once more first when a checksum goes
+/// under the heading. A file that can't be opened gets the usual error
+/// placeholder.
+///
+/// A file that changes while it is streamed is reported as changed, but
+/// its section can't carry the note saying so, since the note comes
+/// before content already written.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_streamed<W: Write + ?Sized>(
+    writer: &mut W,
+    relative_path: &Path,
+    full_path: &Path,
+    heading: &str,
+    notes: Option<&str>,
+    section: SectionOptions<'_>,
+    visitor: &mut dyn FileVisitor,
+) -> Result<FileOutcome> {
+    let opened = File::open(full_path).and_then(|mut file| {
+        let before = Stamp::of(&file.metadata()?);
+        let mut head = Vec::with_capacity(CHUNK_SIZE);
+        Read::by_ref(&mut file)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut head)?;
+        Ok((file, before, head))
+    });
+    let (mut file, before, head) = match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            return write_section(
+                writer,
+                relative_path,
+                full_path,
+                heading,
+                notes,
+                Err(e),
+                section,
+                visitor,
+            );
+        }
+    };
+    debug!("Streaming {}", relative_path.display());
+    let binary = content_inspector::inspect(&head) == ContentType::BINARY;
+    visitor.on_streamed(relative_path, before.len);
+
+    sentinels::write_begin(writer, section.sentinel)?;
+    writeln!(writer, "\n## {}\n", heading)?;
+    if let Some(notes) = notes {
+        writeln!(writer, "{}\n", notes)?;
+    }
+    if let Some(blame) = section.blame {
+        writeln!(writer, "{}\n", annotations::italic(blame))?;
+    }
+    if let Some(checksums) = section.checksums {
+        let mut hasher = Hashing(Sha256::new());
+        file.seek(SeekFrom::Start(0))?;
+        match (checksums, binary) {
+            (crate::Checksums::PostTransform, false) => {
+                copy_lines(&mut file, &mut hasher)?;
+            }
+            _ => {
+                io::copy(&mut file, &mut hasher)?;
+            }
+        }
+        writeln!(writer, "`sha256:{}`\n", hex(&hasher.0.finalize()))?;
+    }
+
+    if binary {
+        writeln!(writer, "```\n(Binary file, content omitted)\n```")?;
+        sentinels::write_end(writer, section.sentinel)?;
+        return Ok(FileOutcome::Binary);
+    }
+    let lang = detect_language(relative_path, &String::from_utf8_lossy(&head));
+    writeln!(writer, "```{}", lang)?;
+    file.seek(SeekFrom::Start(0))?;
+    copy_lines(&mut file, writer)?;
+    writeln!(writer, "```")?;
+    sentinels::write_end(writer, section.sentinel)?;
+
+    // Stat by path, not handle, so a file replaced by rename counts as changed
+    if Stamp::of(&fs::metadata(full_path)?) != before {
+        warn!(
+            path = %relative_path.display(),
+            "{} changed while it was streamed; its content may be inconsistent",
+            full_path.display()
+        );
+        return Ok(FileOutcome::Changed);
+    }
+    Ok(FileOutcome::Written)
+}
+
+// Copy the text read from `reader` to `writer` a chunk at a time, the way
+// the lines of `String::from_utf8_lossy` are written when a file is read
+// whole: invalid UTF-8 replaced, `\r\n` endings as `\n`, and the last line
+// ended even when the file doesn't end it
+fn copy_lines<R: Read, W: Write + ?Sized>(reader: &mut R, writer: &mut W) -> io::Result<()> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    // What the last chunk ended with that the next may complete: a `\r`,
+    // or the first bytes of a multi-byte character
+    let mut pending: Vec<u8> = Vec::new();
+    let mut text = Vec::with_capacity(CHUNK_SIZE + 4);
+    let mut ends_line = true;
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        text.clear();
+        text.append(&mut pending);
+        text.extend_from_slice(&chunk[..read]);
+        let mut rest = &text[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    // A `\r` the next chunk may turn into a line ending
+                    let valid = match valid.strip_suffix('\r') {
+                        Some(valid) => {
+                            pending.push(b'\r');
+                            valid
+                        }
+                        None => valid,
+                    };
+                    write_text(writer, valid, &mut ends_line)?;
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // Everything before the error is valid
+                    write_text(
+                        writer,
+                        std::str::from_utf8(valid).unwrap_or(""),
+                        &mut ends_line,
+                    )?;
+                    match e.error_len() {
+                        Some(len) => {
+                            write_text(writer, "\u{FFFD}", &mut ends_line)?;
+                            rest = &after[len..];
+                        }
+                        // A character the next chunk finishes
+                        None => {
+                            pending.extend_from_slice(after);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    match &pending[..] {
+        [] => {}
+        b"\r" => write_text(writer, "\r", &mut ends_line)?,
+        _ => write_text(writer, "\u{FFFD}", &mut ends_line)?,
+    }
+    if !ends_line {
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+// Write `text` with `\r\n` endings as `\n`, noting whether it ends a line
+fn write_text<W: Write + ?Sized>(
+    writer: &mut W,
+    text: &str,
+    ends_line: &mut bool,
+) -> io::Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    for (index, piece) in text.split("\r\n").enumerate() {
+        if index > 0 {
+            writer.write_all(b"\n")?;
+        }
+        writer.write_all(piece.as_bytes())?;
+    }
+    *ends_line = text.ends_with('\n');
+    Ok(())
+}
+
+// Writer feeding a SHA-256 digest
+struct Hashing(Sha256);
+
+impl Write for Hashing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+// src/submodule.rs
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, warn};
+
+/// Paths of the git submodules under `root`, in the same form as the paths
+/// the walker yields (`root` joined with the path below it).
+///
+/// Submodules are read from the `.gitmodules` file at the top of the
+/// repository containing `root`, which may be above `root` itself.
+pub(crate) fn submodule_paths(root: &Path) -> Vec<PathBuf> {
+    let Ok(canonical_root) = fs::canonicalize(root) else {
+        return Vec::new();
+    };
+    // The repository top is the nearest directory with a .git entry
+    let Some(top) = canonical_root
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+    else {
+        return Vec::new();
+    };
+    let gitmodules = top.join(".gitmodules");
+    let content = match fs::read_to_string(&gitmodules) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths = Vec::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() != "path" {
+            continue;
+        }
+        let path = top.join(value.trim());
+        match path.strip_prefix(&canonical_root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                debug!("Found submodule {}", relative.display());
+                paths.push(root.join(relative));
+            }
+            Ok(_) => warn!("{} is itself a submodule", root.display()),
+            Err(_) => {} // Outside the scanned directory
+        }
+    }
+    paths
+}
+
+/// Whether the submodule at `path` is checked out.
+pub(crate) fn is_initialized(path: &Path) -> bool {
+    path.join(".git").exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SkipReason, Submodules, WeaveOptions, collect_files, generate_markdown};
+    use tempfile::TempDir;
+
+    // A repository with an initialized submodule `vendor/lib`, which ignores
+    // its `build/` directory, and an uninitialized one, `vendor/docs`
+    fn repository() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let top = dir.path();
+        fs::create_dir(top.join(".git")).unwrap();
+        fs::write(
+            top.join(".gitmodules"),
+            "[submodule \"lib\"]\n\tpath = vendor/lib\n\turl = ../lib.git\n\
+             [submodule \"docs\"]\n\tpath = vendor/docs\n\turl = ../docs.git\n",
+        )
+        .unwrap();
+        fs::write(top.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let lib = top.join("vendor/lib");
+        fs::create_dir_all(lib.join("build")).unwrap();
+        // A checked-out submodule has a .git file pointing into the parent
+        fs::write(lib.join(".git"), "gitdir: ../../.git/modules/lib\n").unwrap();
+        fs::write(lib.join(".gitignore"), "build/\n").unwrap();
+        fs::write(lib.join("lib.rs"), "pub fn lib() {}\n").unwrap();
+        fs::write(lib.join("build/out.rs"), "// generated\n").unwrap();
+        fs::create_dir_all(top.join("vendor/docs")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn gitmodules_paths_below_the_root() {
+        let dir = repository();
+        let mut paths = submodule_paths(dir.path());
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                dir.path().join("vendor/docs"),
+                dir.path().join("vendor/lib")
+            ]
+        );
+        // From below the top, only the submodules under the root count
+        let lib = dir.path().join("vendor/lib");
+        assert!(submodule_paths(&dir.path().join("vendor/docs")).is_empty());
+        assert!(is_initialized(&lib));
+        assert!(!is_initialized(&dir.path().join("vendor/docs")));
+    }
+
+    #[test]
+    fn included_submodules_honour_their_ignore_files() {
+        let dir = repository();
+        let options = WeaveOptions::new(dir.path()).build().unwrap();
+        let collected = collect_files(&options);
+        let mut files: Vec<_> = collected
+            .files
+            .iter()
+            .map(|file| file.relative_path.clone())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [Path::new("main.rs"), Path::new("vendor/lib/lib.rs")]
+        );
+        assert_eq!(
+            collected.uninitialized_submodules,
+            [PathBuf::from("vendor/docs")]
+        );
+
+        let mut document = Vec::new();
+        generate_markdown(&mut document, &options).unwrap();
+        let document = String::from_utf8(document).unwrap();
+        assert!(document.contains(
+            "\n## `vendor/docs`\n\n```\n(Uninitialized git submodule, content not available)\n```"
+        ));
+    }
+
+    #[test]
+    fn skipped_submodules_are_pruned() {
+        let dir = repository();
+        let options = WeaveOptions::new(dir.path())
+            .submodules(Submodules::Skip)
+            .build()
+            .unwrap();
+        let collected = collect_files(&options);
+        let files: Vec<_> = collected.files.iter().map(|f| &f.relative_path).collect();
+        assert_eq!(files, [Path::new("main.rs")]);
+        assert!(collected.uninitialized_submodules.is_empty());
+        let mut skipped = collected.skipped;
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            [
+                (PathBuf::from("vendor/docs"), SkipReason::Submodule),
+                (PathBuf::from("vendor/lib"), SkipReason::Submodule),
+            ]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+// src/summary.rs
+use crate::logging::{self, Value};
+use indicatif::{HumanBytes, HumanCount};
+use sourceweaver::{
+    Pattern, SkipReason, Timings, WeaveError, WeaveOptions, WeaveReport, estimate_tokens,
+    filter_stages,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{Level, debug, enabled, info, warn};
+
+// How many affected directories the permission summary names
+const MAX_AFFECTED_DIRS: usize = 10;
+
+// A report's skips and errors, grouped the way the summary presents them
+struct Categories<'a> {
+    skipped: BTreeMap<String, Vec<&'a PathBuf>>,
+    permission_denied: Vec<&'a WeaveError>,
+    unreadable: Vec<&'a WeaveError>,
+    inaccessible: Vec<&'a WeaveError>,
+}
+
+impl<'a> Categories<'a> {
+    fn new(report: &'a WeaveReport) -> Self {
+        // Group skips by category; visitor reasons are free-form, so they share one
+        let mut skipped: BTreeMap<String, Vec<&PathBuf>> = BTreeMap::new();
+        for (path, reason) in &report.skipped {
+            let category = match reason {
+                SkipReason::Visitor(_) => "skipped by visitor".to_string(),
+                reason => format!("skipped as {}", reason),
+            };
+            skipped.entry(category).or_default().push(path);
+        }
+
+        let mut permission_denied = Vec::new();
+        let mut unreadable = Vec::new();
+        let mut inaccessible = Vec::new();
+        for error in &report.errors {
+            match error {
+                _ if error.io_error_kind() == Some(io::ErrorKind::PermissionDenied) => {
+                    permission_denied.push(error)
+                }
+                WeaveError::Read { .. } => unreadable.push(error),
+                _ => inaccessible.push(error),
+            }
+        }
+        Categories {
+            skipped,
+            permission_denied,
+            unreadable,
+            inaccessible,
+        }
+    }
+}
+
+/// Log a one-glance report of what was included and why everything else
+/// wasn't. With `-v`, the affected paths are listed under each category.
+///
+/// Permission errors are logged one per entry only at debug level, so they
+/// get a warning of their own here naming the top-level directories under
+/// `root` they occurred in.
+pub fn log_summary(report: &WeaveReport, root: &Path) {
+    let categories = Categories::new(report);
+    info!("Summary: {}", summary_line(report, &categories));
+    log_permission_errors(&categories.permission_denied, root);
+    log_budget_policy(report);
+    log_unicode_tricks(report);
+
+    if !enabled!(Level::DEBUG) {
+        return;
+    }
+    for (label, paths) in [
+        ("binary, content omitted", &report.binary_files),
+        ("changed during read", &report.changed_files),
+    ] {
+        if !paths.is_empty() {
+            debug!("  {}:", label);
+            for path in paths {
+                debug!("    {}", path.display());
+            }
+        }
+    }
+    for (category, paths) in &categories.skipped {
+        debug!("  {}:", category);
+        for path in paths {
+            debug!("    {}", path.display());
+        }
+    }
+    for (label, errors) in [
+        ("permission denied", &categories.permission_denied),
+        ("unreadable", &categories.unreadable),
+        ("inaccessible", &categories.inaccessible),
+    ] {
+        if !errors.is_empty() {
+            debug!("  {}:", label);
+            for error in errors {
+                debug!("    {}", error);
+            }
+        }
+    }
+}
+
+// One warning for all permission errors, grouped by top-level directory
+fn log_permission_errors(errors: &[&WeaveError], root: &Path) {
+    if errors.is_empty() {
+        return;
+    }
+    let mut affected: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    for error in errors {
+        let top = match error.path().map(|path| path.strip_prefix(root)) {
+            Some(Ok(relative)) => relative.components().next().map(|c| PathBuf::from(&c)),
+            Some(Err(_)) => error.path().map(Path::to_path_buf),
+            None => None,
+        };
+        *affected
+            .entry(top.unwrap_or_else(|| PathBuf::from(".")))
+            .or_default() += 1;
+    }
+
+    let mut dirs: Vec<String> = affected
+        .iter()
+        .take(MAX_AFFECTED_DIRS)
+        .map(|(dir, count)| format!("{} ({})", dir.display(), count))
+        .collect();
+    if affected.len() > MAX_AFFECTED_DIRS {
+        dirs.push(format!("and {} more", affected.len() - MAX_AFFECTED_DIRS));
+    }
+    warn!(
+        "{} entries could not be read for lack of permission, under: {}. Use -v to list them.",
+        errors.len(),
+        dirs.join(", ")
+    );
+}
+
+// Say how files were chosen when the token budget dropped some
+fn log_budget_policy(report: &WeaveReport) {
+    let dropped = report
+        .skipped
+        .iter()
+        .filter(|(_, reason)| matches!(reason, SkipReason::OverBudget { .. }))
+        .count();
+    if dropped > 0 {
+        info!(
+            "{} file(s) left out to fit --budget: lowest --priority first, then largest first, \
+             then last in path order. Use -v to list them.",
+            dropped
+        );
+    }
+}
+
+// One warning per suspicious Unicode character, so none goes unnoticed
+fn log_unicode_tricks(report: &WeaveReport) {
+    if report.unicode_tricks.is_empty() {
+        return;
+    }
+    warn!(
+        "{} suspicious Unicode character(s) found:",
+        report.unicode_tricks.len()
+    );
+    for finding in &report.unicode_tricks {
+        warn!(path = %finding.path.display(), "  {}", finding);
+    }
+}
+
+/// Warn that the walk selected no files, with what could explain it: how
+/// many directories were entered and the filters that were active.
+pub fn log_no_files(options: &WeaveOptions, dirs_visited: usize) {
+    warn!("No files matched the filters.");
+    if options.root().is_dir() {
+        warn!(
+            "  {} director(ies) visited under {}",
+            dirs_visited,
+            options.root().display()
+        );
+        if dirs_visited <= 1 {
+            warn!("  Only the root itself was entered; check that it is the intended directory.");
+        }
+    }
+    let stages: Vec<&str> = filter_stages(options).iter().map(|s| s.name()).collect();
+    warn!("  Active filter stages: {}", stages.join(", "));
+    for path in options.paths() {
+        warn!("  path: {}", path.display());
+    }
+    for pattern in options.patterns() {
+        match pattern {
+            Pattern::Exclude(glob) => warn!("  --exclude {}", glob),
+            Pattern::Include(glob) => warn!("  --include {}", glob),
+        }
+    }
+    for name in options.exclude_dirs() {
+        warn!("  --exclude-dir {}", name);
+    }
+    for re in options.path_regexes() {
+        warn!("  --path-regex {}", re);
+    }
+    for re in options.path_regexes_not() {
+        warn!("  --path-regex-not {}", re);
+    }
+    if let Some(max_filesize) = options.max_filesize() {
+        warn!("  --max-filesize {}", HumanBytes(max_filesize));
+    }
+}
+
+/// Warn when the document, `name` for one document of a split run, is
+/// more than `threshold` bytes, with hints for making it smaller. A
+/// threshold of 0 never warns.
+pub fn warn_if_large(name: Option<&str>, bytes: u64, threshold: u64) {
+    if threshold == 0 || bytes <= threshold {
+        return;
+    }
+    warn!(
+        "{} is {} (about {} tokens), over the --warn-size of {}. Use --budget to fit it to a \
+         token count, --exclude to leave out files, or --top to see what takes the most room.",
+        name.map_or("The document".to_string(), |name| format!(
+            "Document {}",
+            name
+        )),
+        HumanBytes(bytes),
+        HumanCount(estimate_tokens(bytes)),
+        HumanBytes(threshold)
+    );
+}
+
+/// Emit the `summary` event for `--message-format json`. `bytes` is the
+/// total size of the document(s) written.
+pub fn emit_summary_event(report: &WeaveReport, bytes: u64) {
+    logging::emit_event(
+        "summary",
+        &[
+            ("files", Value::Num(report.files_written as u64)),
+            ("bytes", Value::Num(bytes)),
+            ("tokens", Value::Num(estimate_tokens(bytes))),
+        ],
+    );
+}
+
+/// Print the `n` files with the largest sections to stderr, as a
+/// [table](largest_table). With `--message-format json` each row is a
+/// `largest_file` event instead.
+pub fn print_largest(report: &WeaveReport, options: &WeaveOptions, n: usize, bytes: u64) {
+    if logging::json_events() {
+        for (path, size) in report.largest(n) {
+            logging::emit_event(
+                "largest_file",
+                &[
+                    ("path", Value::Str(&options.display_path(path))),
+                    ("bytes", Value::Num(size)),
+                    ("tokens", Value::Num(estimate_tokens(size))),
+                ],
+            );
+        }
+        return;
+    }
+    let _ = io::stderr()
+        .lock()
+        .write_all(largest_table(report, options, n, bytes).as_bytes());
+}
+
+/// The `n` files with the largest sections as a table of path, size,
+/// estimated tokens, and share of the `bytes` written in all, largest
+/// first. Empty when no file was written.
+pub fn largest_table(report: &WeaveReport, options: &WeaveOptions, n: usize, bytes: u64) -> String {
+    let largest: Vec<_> = report
+        .largest(n)
+        .into_iter()
+        .map(|(path, size)| (options.display_path(path), size))
+        .collect();
+    if largest.is_empty() {
+        return String::new();
+    }
+    let share = |size: u64| match bytes {
+        0 => 0.0,
+        total => size as f64 * 100.0 / total as f64,
+    };
+    let width = largest
+        .iter()
+        .map(|(shown, _)| shown.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut table = format!(
+        "Largest {} of {} file(s):\n",
+        largest.len(),
+        report.section_bytes.len()
+    );
+    for (shown, size) in &largest {
+        table.push_str(&format!(
+            "  {:width$}  {:>10}  {:>10} tokens  {:>5.1}%\n",
+            shown,
+            HumanBytes(*size).to_string(),
+            HumanCount(estimate_tokens(*size)).to_string(),
+            share(*size),
+            width = width
+        ));
+    }
+    table
+}
+
+/// Print how long each phase of the run took to stderr, with each one's
+/// share of the `wall` time of the whole run. With `--message-format json`
+/// it is a `timings` event instead, in microseconds.
+pub fn print_timings(timings: &Timings, wall: Duration) {
+    if logging::json_events() {
+        let fields: Vec<(String, u64)> = timings
+            .phases()
+            .iter()
+            .chain([("total", wall)].iter())
+            .map(|(phase, time)| (format!("{}_us", phase), time.as_micros() as u64))
+            .collect();
+        let fields: Vec<(&str, Value<'_>)> = fields
+            .iter()
+            .map(|(name, us)| (name.as_str(), Value::Num(*us)))
+            .collect();
+        logging::emit_event("timings", &fields);
+        return;
+    }
+    let share = |time: Duration| match wall.as_secs_f64() {
+        0.0 => 0.0,
+        total => time.as_secs_f64() * 100.0 / total,
+    };
+    let mut table = String::from("Timings:\n");
+    for (phase, time) in timings.phases() {
+        table.push_str(&format!(
+            "  {:9}  {:>10.1}ms  {:>5.1}%\n",
+            phase,
+            time.as_secs_f64() * 1000.0,
+            share(time)
+        ));
+    }
+    table.push_str(&format!(
+        "  {:9}  {:>10.1}ms\n",
+        "total",
+        wall.as_secs_f64() * 1000.0
+    ));
+    let _ = io::stderr().lock().write_all(table.as_bytes());
+}
+
+/// Log the one-line summary for a single document of a split run.
+pub fn log_document_summary(name: &str, report: &WeaveReport) {
+    info!(
+        "{}: {}",
+        name,
+        summary_line(report, &Categories::new(report))
+    );
+}
+
+fn summary_line(report: &WeaveReport, categories: &Categories<'_>) -> String {
+    let mut included = format!("{} file(s) included", report.files_written);
+    if !report.binary_files.is_empty() {
+        included.push_str(&format!(
+            " ({} binary, content omitted)",
+            report.binary_files.len()
+        ));
+    }
+
+    let mut parts = vec![included];
+    if !report.changed_files.is_empty() {
+        parts.push(format!(
+            "{} changed during read",
+            report.changed_files.len()
+        ));
+    }
+    parts.extend(
+        categories
+            .skipped
+            .iter()
+            .map(|(category, paths)| format!("{} {}", paths.len(), category)),
+    );
+    if !categories.permission_denied.is_empty() {
+        parts.push(format!(
+            "{} permission denied",
+            categories.permission_denied.len()
+        ));
+    }
+    if !categories.unreadable.is_empty() {
+        parts.push(format!("{} unreadable", categories.unreadable.len()));
+    }
+    if !categories.inaccessible.is_empty() {
+        parts.push(format!("{} inaccessible", categories.inaccessible.len()));
+    }
+    if !report.unicode_tricks.is_empty() {
+        parts.push(format!(
+            "{} suspicious Unicode character(s)",
+            report.unicode_tricks.len()
+        ));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    // The formatted log output of `f`, without colors or timestamps
+    fn logged(f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn warns_just_over_the_threshold_only() {
+        const THRESHOLD: u64 = 2 << 20;
+        assert_eq!(logged(|| warn_if_large(None, THRESHOLD, THRESHOLD)), "");
+        assert_eq!(logged(|| warn_if_large(None, THRESHOLD - 1, THRESHOLD)), "");
+
+        let warning = logged(|| warn_if_large(None, THRESHOLD + 1, THRESHOLD));
+        assert!(warning.starts_with(" WARN "), "{}", warning);
+        assert!(
+            warning.contains(
+                "The document is 2.00 MiB (about 524,288 tokens), over the --warn-size of 2.00 MiB."
+            ),
+            "{}",
+            warning
+        );
+        for hint in ["--budget", "--exclude", "--top"] {
+            assert!(warning.contains(hint), "{}", warning);
+        }
+    }
+
+    #[test]
+    fn split_documents_are_named_and_zero_never_warns() {
+        let warning = logged(|| warn_if_large(Some("src.md"), 5000, 4096));
+        assert!(
+            warning.contains("Document src.md is 4.88 KiB (about 1,250 tokens)"),
+            "{}",
+            warning
+        );
+        assert_eq!(logged(|| warn_if_large(None, u64::MAX, 0)), "");
+    }
+}
@@ -0,0 +1,238 @@
+// src/symbols.rs
+use crate::{Action, FileContent, FileVisitor, WeaveOptions, display, get_language_tag};
+use regex::Regex;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+/// A top-level definition found in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// What kind of definition it is, in the language's own keyword, e.g.
+    /// `fn`, `struct`, `impl`, `def`, `class`, or `function`.
+    pub kind: &'static str,
+    /// The name, or for a Rust `impl`, what is implemented.
+    pub name: String,
+}
+
+// Kinds of symbol with the pattern finding each
+type Patterns = Vec<(&'static str, Regex)>;
+
+// Per language, the kind of symbol each pattern finds; the name is the
+// pattern's first group. Patterns are anchored at column 0, so only
+// top-level definitions match.
+static EXTRACTORS: LazyLock<Vec<(&'static str, Patterns)>> = LazyLock::new(|| {
+    let compile = |patterns: &[(&'static str, &str)]| {
+        patterns
+            .iter()
+            .map(|(kind, pattern)| (*kind, Regex::new(pattern).expect("symbol pattern is valid")))
+            .collect::<Vec<_>>()
+    };
+    let rust_vis = r"(?:pub(?:[ \t]*\([^)]*\))?[ \t]+)?";
+    let js = compile(&[
+        (
+            "function",
+            r"^(?:export[ \t]+)?(?:default[ \t]+)?(?:async[ \t]+)?function[ \t]*\*?[ \t]*([A-Za-z_$][\w$]*)",
+        ),
+        (
+            "class",
+            r"^(?:export[ \t]+)?(?:default[ \t]+)?(?:abstract[ \t]+)?class[ \t]+([A-Za-z_$][\w$]*)",
+        ),
+        ("const", r"^export[ \t]+const[ \t]+([A-Za-z_$][\w$]*)"),
+    ]);
+    vec![
+        (
+            "rust",
+            compile(&[
+                (
+                    "fn",
+                    &format!(
+                        r#"^{rust_vis}(?:default[ \t]+)?(?:const[ \t]+)?(?:async[ \t]+)?(?:unsafe[ \t]+)?(?:extern[ \t]+(?:"[^"]*"[ \t]+)?)?fn[ \t]+([A-Za-z_]\w*)"#
+                    ),
+                ),
+                ("struct", &format!(r"^{rust_vis}struct[ \t]+([A-Za-z_]\w*)")),
+                ("enum", &format!(r"^{rust_vis}enum[ \t]+([A-Za-z_]\w*)")),
+                (
+                    "trait",
+                    &format!(
+                        r"^{rust_vis}(?:unsafe[ \t]+)?(?:auto[ \t]+)?trait[ \t]+([A-Za-z_]\w*)"
+                    ),
+                ),
+                (
+                    "impl",
+                    r"^(?:unsafe[ \t]+)?impl(?:[ \t]*<[^{]*?>)?[ \t]+([^{]+?)[ \t]*(?:\bwhere\b.*)?(?:\{.*)?$",
+                ),
+            ]),
+        ),
+        (
+            "python",
+            compile(&[
+                ("def", r"^(?:async[ \t]+)?def[ \t]+([A-Za-z_]\w*)"),
+                ("class", r"^class[ \t]+([A-Za-z_]\w*)"),
+            ]),
+        ),
+        ("javascript", js.clone()),
+        ("typescript", js.clone()),
+        ("jsx", js.clone()),
+        ("tsx", js),
+    ]
+});
+
+/// The top-level symbols defined in `text`, in order, for a file in the
+/// given language (as returned by [`get_language_tag`]). Languages without
+/// an extractor yield nothing.
+///
+/// Extraction is line-based and only looks at definitions starting in the
+/// first column, so it copes with any formatting but can be fooled by code
+/// inside multi-line strings or comments.
+///
+/// ```
+/// use sourceweaver::{Symbol, extract_symbols};
+///
+/// let code = "pub(crate) async fn run() {}\nimpl<T: Into<String>> From<T> for Name {\n    fn helper() {}\n}\n";
+/// let symbols = extract_symbols("rust", code);
+/// assert_eq!(
+///     symbols,
+///     [
+///         Symbol { kind: "fn", name: "run".to_string() },
+///         Symbol { kind: "impl", name: "From<T> for Name".to_string() },
+///     ]
+/// );
+///
+/// let code = "class Parser:\n    def parse(self): ...\n\nasync def main():\n    pass\n";
+/// let names: Vec<_> = extract_symbols("python", code).into_iter().map(|s| s.name).collect();
+/// assert_eq!(names, ["Parser", "main"]);
+/// assert!(extract_symbols("cobol", "IDENTIFICATION DIVISION.").is_empty());
+/// ```
+pub fn extract_symbols(language: &str, text: &str) -> Vec<Symbol> {
+    let Some((_, patterns)) = EXTRACTORS.iter().find(|(tag, _)| *tag == language) else {
+        return Vec::new();
+    };
+    let mut symbols = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end();
+        for (kind, pattern) in patterns {
+            if let Some(name) = pattern.captures(line).and_then(|c| c.get(1)) {
+                let name = name
+                    .as_str()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                symbols.push(Symbol { kind, name });
+                break;
+            }
+        }
+    }
+    symbols
+}
+
+// Each file with symbols and what it defines, in the order seen
+type FileSymbols = Vec<(PathBuf, Vec<Symbol>)>;
+
+/// Visitor collecting each file's top-level symbols with
+/// [`extract_symbols`], for an index at the end of the document.
+///
+/// Clones share what was collected, so one can be handed to the run and the
+/// other used to [write the index](Self::write_markdown) afterwards. Files
+/// whose section is [reused](FileVisitor::on_reused) from the render cache
+/// aren't seen.
+///
+/// ```
+/// use sourceweaver::{SymbolIndex, WeaveOptions, generate_markdown_with};
+///
+/// let options = WeaveOptions::new("src").build()?;
+/// let index = SymbolIndex::new();
+/// let mut document = Vec::new();
+/// generate_markdown_with(&mut document, &options, &mut index.clone())?;
+/// index.write_markdown(&mut document, &options)?;
+///
+/// let document = String::from_utf8(document).unwrap();
+/// assert!(document.contains("\n## Symbol Index\n"));
+/// assert!(document.contains("- fn `generate_markdown`"));
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    files: Arc<Mutex<FileSymbols>>,
+}
+
+impl SymbolIndex {
+    /// An empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The files with symbols so far, in the order they were seen.
+    pub fn files(&self) -> Vec<(PathBuf, Vec<Symbol>)> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Forget the files collected so far, to start the index of another
+    /// document.
+    pub fn clear(&self) {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Write the `## Symbol Index` section: one entry per file with
+    /// symbols, linking to the file's heading, and its symbols below it.
+    /// Nothing is written when no symbols were found.
+    pub fn write_markdown<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &WeaveOptions,
+    ) -> io::Result<()> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        if files.is_empty() {
+            return Ok(());
+        }
+        writeln!(writer, "\n## Symbol Index\n")?;
+        for (path, symbols) in files.iter() {
+            let shown = options.display_path(path);
+            writeln!(
+                writer,
+                "- [{}](#{})",
+                display::code_span(&shown),
+                anchor(&shown)
+            )?;
+            for symbol in symbols {
+                writeln!(
+                    writer,
+                    "  - {} {}",
+                    symbol.kind,
+                    display::code_span(&symbol.name)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FileVisitor for SymbolIndex {
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        if let FileContent::Text(text) = content {
+            let symbols = extract_symbols(get_language_tag(path), text);
+            if !symbols.is_empty() {
+                self.files
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((path.to_path_buf(), symbols));
+            }
+        }
+        Action::Emit
+    }
+}
+
+// The anchor GitHub gives a heading showing `text`: lowercased, with
+// spaces as hyphens and other punctuation dropped
+fn anchor(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,126 @@
+// src/tabs.rs
+use crate::{Action, FileContent, FileVisitor, editorconfig::EditorConfigs};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Default for [`ExpandTabs::new`], when nothing says otherwise.
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// Visitor replacing tabs with spaces up to the next tab stop, so the
+/// document lines up the way an editor shows the file and token counts
+/// don't depend on tab rendering.
+///
+/// Makefiles are left alone, as their recipes must start with a tab.
+/// With [`editorconfig`](Self::editorconfig), a file's `.editorconfig`
+/// `tab_width` or `indent_size` takes precedence over the width given.
+///
+/// ```
+/// use sourceweaver::{Action, ExpandTabs, FileContent, FileVisitor};
+/// use std::path::Path;
+///
+/// let mut expand = ExpandTabs::new(4);
+/// let text = "fn main() {\n\tlet x = 1;\t// one\n}\n";
+/// assert_eq!(
+///     expand.on_file(Path::new("main.rs"), FileContent::Text(text)),
+///     Action::EmitModified("fn main() {\n    let x = 1;  // one\n}\n".to_string())
+/// );
+///
+/// let recipe = "all:\n\tcargo build\n";
+/// assert_eq!(expand.on_file(Path::new("Makefile"), FileContent::Text(recipe)), Action::Emit);
+/// ```
+#[derive(Debug)]
+pub struct ExpandTabs {
+    width: usize,
+    // The root paths are relative to, and the files read under it
+    editorconfig: Option<(PathBuf, EditorConfigs)>,
+}
+
+impl ExpandTabs {
+    /// Expand to tab stops every `width` columns.
+    pub fn new(width: usize) -> Self {
+        ExpandTabs {
+            width: width.max(1),
+            editorconfig: None,
+        }
+    }
+
+    /// Take the width from the `.editorconfig` files that apply to each
+    /// file, with paths relative to `root`, falling back to the width
+    /// given when none sets one.
+    pub fn editorconfig(mut self, root: impl Into<PathBuf>) -> Self {
+        self.editorconfig = Some((root.into(), EditorConfigs::default()));
+        self
+    }
+
+    /// The tab width used for the file at `path`, relative to the root.
+    pub fn width_for(&mut self, path: &Path) -> usize {
+        match &mut self.editorconfig {
+            Some((root, configs)) => configs.tab_width(&root.join(path)),
+            None => None,
+        }
+        .unwrap_or(self.width)
+    }
+}
+
+impl FileVisitor for ExpandTabs {
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        match content {
+            FileContent::Text(text) if !is_makefile(path) && text.contains('\t') => {
+                match expand_tabs(text, self.width_for(path)) {
+                    Cow::Owned(text) => Action::EmitModified(text),
+                    Cow::Borrowed(_) => Action::Emit,
+                }
+            }
+            _ => Action::Emit,
+        }
+    }
+}
+
+/// `text` with each tab replaced by the spaces up to the next multiple of
+/// `width` columns, counting characters from the start of each line.
+///
+/// ```
+/// use sourceweaver::expand_tabs;
+///
+/// assert_eq!(expand_tabs("a\tbc\td\n\tx", 4), "a   bc  d\n    x");
+/// assert_eq!(expand_tabs("no tabs", 4), "no tabs");
+/// ```
+pub fn expand_tabs(text: &str, width: usize) -> Cow<'_, str> {
+    if !text.contains('\t') {
+        return Cow::Borrowed(text);
+    }
+    let width = width.max(1);
+    let mut expanded = String::with_capacity(text.len() + text.len() / 8);
+    let mut column = 0;
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = width - column % width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                expanded.push(c);
+                column = 0;
+            }
+            c => {
+                expanded.push(c);
+                column += 1;
+            }
+        }
+    }
+    Cow::Owned(expanded)
+}
+
+// Makefiles by name, where a leading tab is syntax
+fn is_makefile(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    matches!(name.as_str(), "makefile" | "gnumakefile" | "bsdmakefile")
+        || name.ends_with(".mk")
+        || name.ends_with(".mak")
+}
@@ -0,0 +1,14 @@
+// src/tokens.rs
+
+/// Rough number of tokens a language model reads in `bytes` bytes of
+/// document: one per four bytes, which holds well enough for source code.
+///
+/// Every token count sourceweaver reports comes from here, so the estimate
+/// of `list --estimate` and the summary of a real run agree.
+///
+/// ```
+/// assert_eq!(sourceweaver::estimate_tokens(4096), 1024);
+/// ```
+pub fn estimate_tokens(bytes: u64) -> u64 {
+    bytes / 4
+}
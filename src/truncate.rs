@@ -0,0 +1,114 @@
+// src/truncate.rs
+use crate::{Result, TruncateStrategy, WeaveError, elide::elide_bodies, get_language_tag};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// How much of a file a [truncation rule](crate::WeaveOptionsBuilder::truncate)
+/// keeps. With both limits set, whichever cuts more applies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TruncateLimit {
+    /// Keep at most this many lines.
+    pub lines: Option<usize>,
+    /// Keep at most this many bytes, cut at the end of a line unless even
+    /// the first line is longer.
+    pub bytes: Option<u64>,
+}
+
+impl TruncateLimit {
+    // Whether text of this many lines and bytes is within the limit
+    fn fits(&self, lines: usize, bytes: usize) -> bool {
+        self.lines.is_none_or(|max| lines <= max)
+            && self.bytes.is_none_or(|max| bytes as u64 <= max)
+    }
+}
+
+/// The truncation rules by glob, the last matching one deciding.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Truncations {
+    rules: Vec<(String, Gitignore, TruncateLimit)>,
+    strategy: TruncateStrategy,
+}
+
+impl Truncations {
+    pub(crate) fn new(
+        root: &Path,
+        rules: &[(String, TruncateLimit)],
+        strategy: TruncateStrategy,
+    ) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|(glob, limit)| {
+                let invalid = |e: ignore::Error| {
+                    WeaveError::InvalidOptions(format!("invalid truncate glob '{}': {}", glob, e))
+                };
+                let mut builder = GitignoreBuilder::new(root);
+                builder.add_line(None, glob).map_err(invalid)?;
+                Ok((glob.clone(), builder.build().map_err(invalid)?, *limit))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Truncations { rules, strategy })
+    }
+
+    /// `text`, the content of the file at `relative_path`, cut down by the
+    /// last rule whose glob matches it or a directory above it. With the
+    /// smart strategy function bodies go first; text that still doesn't
+    /// fit loses its tail, ending in a line naming the rule. None when no
+    /// rule matches or the text fits.
+    pub(crate) fn apply(&self, relative_path: &Path, text: &str) -> Option<String> {
+        let (glob, _, limit) = self.rules.iter().rev().find(|(_, matcher, _)| {
+            relative_path
+                .ancestors()
+                .filter(|level| !level.as_os_str().is_empty())
+                .enumerate()
+                .any(|(depth, level)| !matcher.matched(level, depth > 0).is_none())
+        })?;
+        if limit.fits(text.split_inclusive('\n').count(), text.len()) {
+            return None;
+        }
+        let elided = match self.strategy {
+            TruncateStrategy::Tail => None,
+            TruncateStrategy::Smart => {
+                elide_bodies(get_language_tag(relative_path), text, |lines, bytes| {
+                    limit.fits(lines, bytes)
+                })
+            }
+        };
+        let text = match elided {
+            Some(elided) if limit.fits(elided.split_inclusive('\n').count(), elided.len()) => {
+                return Some(elided);
+            }
+            Some(ref elided) => elided.as_str(),
+            None => text,
+        };
+
+        let max_bytes = limit.bytes.map_or(usize::MAX, |bytes| {
+            usize::try_from(bytes).unwrap_or(usize::MAX)
+        });
+        let mut kept = 0;
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            if limit.lines.is_some_and(|lines| index >= lines) {
+                break;
+            }
+            if kept + line.len() > max_bytes {
+                // A first line over the limit is cut where a character ends
+                if index == 0 {
+                    kept = (0..=max_bytes)
+                        .rev()
+                        .find(|&end| text.is_char_boundary(end))
+                        .unwrap_or(0);
+                }
+                break;
+            }
+            kept += line.len();
+        }
+        if kept == text.len() {
+            return None;
+        }
+        let head = text[..kept].trim_end_matches(['\r', '\n']);
+        let separator = if head.is_empty() { "" } else { "\n" };
+        Some(format!(
+            "{}{}… [truncated by rule {}] …\n",
+            head, separator, glob
+        ))
+    }
+}
@@ -0,0 +1,235 @@
+// src/unicode_tricks.rs
+use crate::{UnicodeTricks, WeaveOptions, WeaveReport};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// What makes a character found by [`find_unicode_tricks`] suspicious.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TrickKind {
+    /// A bidirectional control, which can make code display in a different
+    /// order than it compiles ("Trojan Source").
+    Bidi,
+    /// A zero-width space, joiner, or non-joiner, word joiner, or a byte
+    /// order mark past the start of the file.
+    ZeroWidth,
+    /// Any other invisible format character (Unicode category Cf), such as
+    /// a soft hyphen or a tag character.
+    Format,
+    /// A Cyrillic or Greek letter that looks like a Latin one, in a word
+    /// that also has ASCII letters, as in a spoofed identifier.
+    Confusable,
+}
+
+impl fmt::Display for TrickKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrickKind::Bidi => write!(f, "bidi control"),
+            TrickKind::ZeroWidth => write!(f, "zero-width character"),
+            TrickKind::Format => write!(f, "invisible format character"),
+            TrickKind::Confusable => write!(f, "confusable letter"),
+        }
+    }
+}
+
+/// One suspicious character in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnicodeFinding {
+    /// The file, relative to the root.
+    pub path: PathBuf,
+    /// The line it is on, from 1.
+    pub line: usize,
+    /// The character itself.
+    pub codepoint: char,
+    /// Why it was flagged.
+    pub kind: TrickKind,
+}
+
+impl fmt::Display for UnicodeFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: U+{:04X} ({})",
+            self.path.display(),
+            self.line,
+            self.codepoint as u32,
+            self.kind
+        )
+    }
+}
+
+/// The characters in `text`, the content of the file at `path`, that can
+/// hide what code does from a reader: bidi controls, zero-width and other
+/// invisible format characters, and Latin lookalikes mixed into ASCII
+/// words. A byte order mark at the very start is left alone. ASCII text
+/// is passed over without looking at each character.
+///
+/// ```
+/// use sourceweaver::{TrickKind, find_unicode_tricks};
+/// use std::path::Path;
+///
+/// let text = "let access = \"user\u{202E} \u{2066}// admin\u{2069}\";\nlet p\u{430}ypal = 1;\nlet мир = 2;\n";
+/// let found: Vec<_> = find_unicode_tricks(Path::new("a.rs"), text)
+///     .iter()
+///     .map(|finding| (finding.line, finding.codepoint, finding.kind))
+///     .collect();
+/// assert_eq!(
+///     found,
+///     [
+///         (1, '\u{202E}', TrickKind::Bidi),
+///         (1, '\u{2066}', TrickKind::Bidi),
+///         (1, '\u{2069}', TrickKind::Bidi),
+///         (2, '\u{430}', TrickKind::Confusable),
+///     ]
+/// );
+/// assert!(find_unicode_tricks(Path::new("a.rs"), "\u{FEFF}fn main() {}\n").is_empty());
+/// ```
+pub fn find_unicode_tricks(path: &Path, text: &str) -> Vec<UnicodeFinding> {
+    tricks(text)
+        .into_iter()
+        .map(|(line, _, codepoint, kind)| UnicodeFinding {
+            path: path.to_path_buf(),
+            line,
+            codepoint,
+            kind,
+        })
+        .collect()
+}
+
+/// `text` with every character [`find_unicode_tricks`] flags written as a
+/// visible escape, e.g. `\u{202E}`, so the code reads as it compiles.
+///
+/// ```
+/// use sourceweaver::escape_unicode_tricks;
+///
+/// assert_eq!(escape_unicode_tricks("a\u{200B}b"), "a\\u{200B}b");
+/// assert_eq!(escape_unicode_tricks("plain"), "plain");
+/// ```
+pub fn escape_unicode_tricks(text: &str) -> Cow<'_, str> {
+    let mut escaped = String::new();
+    let mut copied = 0;
+    for (_, offset, codepoint, _) in tricks(text) {
+        escaped.push_str(&text[copied..offset]);
+        escaped.push_str(&format!("\\u{{{:04X}}}", codepoint as u32));
+        copied = offset + codepoint.len_utf8();
+    }
+    if copied == 0 {
+        return Cow::Borrowed(text);
+    }
+    escaped.push_str(&text[copied..]);
+    Cow::Owned(escaped)
+}
+
+/// The findings of one run, collected from the writing thread as sections
+/// are written.
+#[derive(Debug)]
+pub(crate) struct TrickLog {
+    pub(crate) escape: bool,
+    found: Mutex<Vec<UnicodeFinding>>,
+}
+
+impl TrickLog {
+    /// The log for a run with `options`, if they scan for tricks.
+    pub(crate) fn of(options: &WeaveOptions) -> Option<Self> {
+        options.unicode_tricks.map(|mode| TrickLog {
+            escape: mode == UnicodeTricks::Escape,
+            found: Mutex::default(),
+        })
+    }
+
+    pub(crate) fn record(&self, findings: Vec<UnicodeFinding>) {
+        self.found
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(findings);
+    }
+
+    /// The findings in the files `report` shows were written, leaving out
+    /// those a budget or output limit dropped afterwards.
+    pub(crate) fn written(self, report: &WeaveReport) -> Vec<UnicodeFinding> {
+        let written: HashSet<&PathBuf> =
+            report.section_bytes.iter().map(|(path, _)| path).collect();
+        let mut found = self.found.into_inner().unwrap_or_else(|e| e.into_inner());
+        found.retain(|finding| written.contains(&finding.path));
+        found
+    }
+}
+
+// Each flagged character with its line, byte offset, and kind
+fn tricks(text: &str) -> Vec<(usize, usize, char, TrickKind)> {
+    let mut found = Vec::new();
+    if text.is_ascii() {
+        return found;
+    }
+    let mut start = 0;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        if !line.is_ascii() {
+            for (i, c) in line.char_indices().filter(|(_, c)| !c.is_ascii()) {
+                let kind = match classify(c) {
+                    None => continue,
+                    Some(TrickKind::ZeroWidth) if c == '\u{FEFF}' && start + i == 0 => continue,
+                    Some(TrickKind::Confusable) if !in_ascii_word(line, i, c) => continue,
+                    Some(kind) => kind,
+                };
+                found.push((index + 1, start + i, c, kind));
+            }
+        }
+        start += line.len();
+    }
+    found
+}
+
+fn classify(c: char) -> Option<TrickKind> {
+    Some(match c {
+        '\u{061C}'
+        | '\u{200E}'
+        | '\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2066}'..='\u{2069}' => TrickKind::Bidi,
+        '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' => TrickKind::ZeroWidth,
+        '\u{00AD}'
+        | '\u{0600}'..='\u{0605}'
+        | '\u{06DD}'
+        | '\u{070F}'
+        | '\u{0890}'..='\u{0891}'
+        | '\u{08E2}'
+        | '\u{180E}'
+        | '\u{2061}'..='\u{2064}'
+        | '\u{206A}'..='\u{206F}'
+        | '\u{FFF9}'..='\u{FFFB}'
+        | '\u{110BD}'
+        | '\u{110CD}'
+        | '\u{13430}'..='\u{1343F}'
+        | '\u{1BCA0}'..='\u{1BCA3}'
+        | '\u{1D173}'..='\u{1D17A}'
+        | '\u{E0001}'
+        | '\u{E0020}'..='\u{E007F}' => TrickKind::Format,
+        // Cyrillic and Greek letters drawn like Latin ones
+        'а' | 'е' | 'о' | 'р' | 'с' | 'у' | 'х' | 'і' | 'ј' | 'ѕ' | 'ԁ' | 'А' | 'В' | 'Е' | 'К'
+        | 'М' | 'Н' | 'О' | 'Р' | 'С' | 'Т' | 'Х' | 'І' | 'Ј' | 'Ѕ' | 'ο' | 'ν' | 'Α' | 'Β'
+        | 'Ε' | 'Ζ' | 'Η' | 'Ι' | 'Κ' | 'Μ' | 'Ν' | 'Ο' | 'Ρ' | 'Τ' | 'Υ' | 'Χ' => {
+            TrickKind::Confusable
+        }
+        _ => return None,
+    })
+}
+
+// Whether the word around the character `c` at byte `i` of `line` also
+// has an ASCII letter, so the lookalike passes for Latin rather than being
+// part of Cyrillic or Greek text
+fn in_ascii_word(line: &str, i: usize, c: char) -> bool {
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    line[..i]
+        .chars()
+        .rev()
+        .take_while(is_word)
+        .any(|c| c.is_ascii_alphabetic())
+        || line[i + c.len_utf8()..]
+            .chars()
+            .take_while(is_word)
+            .any(|c| c.is_ascii_alphabetic())
+}
@@ -0,0 +1,153 @@
+// src/verify.rs
+use crate::compress::{Compression, decoder};
+use crate::output;
+use sourceweaver::{Sentinel, WeaveError};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+use tracing::warn;
+
+/// How a document on disk differs from a freshly generated one, by file
+/// section heading.
+#[derive(Debug, Default)]
+pub struct Staleness {
+    /// Sections only the fresh document has.
+    pub added: Vec<String>,
+    /// Sections only the document on disk has.
+    pub removed: Vec<String>,
+    /// Sections both have, with different content.
+    pub changed: Vec<String>,
+}
+
+impl Staleness {
+    /// Log what is out of date in the document at `path`.
+    pub fn log(&self, path: &Path) {
+        let mut parts = Vec::new();
+        for (count, label) in [
+            (self.changed.len(), "changed"),
+            (self.added.len(), "added"),
+            (self.removed.len(), "removed"),
+        ] {
+            if count > 0 {
+                parts.push(format!("{} {}", count, label));
+            }
+        }
+        if parts.is_empty() {
+            parts.push("text outside the file sections differs".to_string());
+        }
+        warn!("{} is out of date: {}.", path.display(), parts.join(", "));
+        for (label, headings) in [
+            ("changed", &self.changed),
+            ("added", &self.added),
+            ("removed", &self.removed),
+        ] {
+            for heading in headings {
+                warn!("  {}: {}", label, heading);
+            }
+        }
+    }
+}
+
+/// The document at `path`, decompressed, or `None` when there is no file.
+pub fn read_document(
+    path: &Path,
+    compression: Option<Compression>,
+) -> Result<Option<String>, WeaveError> {
+    let read_error = |source| WeaveError::OutputFile {
+        path: path.to_path_buf(),
+        source,
+    };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(read_error(e)),
+    };
+    let mut document = Vec::new();
+    decoder(file, compression)
+        .and_then(|mut reader| reader.read_to_end(&mut document))
+        .map_err(read_error)?;
+    Ok(Some(String::from_utf8_lossy(&document).into_owned()))
+}
+
+/// Compare the document on disk with the fresh one, ignoring the generation
+/// marker lines, which carry a timestamp. `None` means they match.
+///
+/// Matching is exact; the per-section breakdown is only a guide. Documents
+/// written with `--sentinels` are split at their sentinel lines, which file
+/// content can't easily confuse; others at `## ` headings, which a file
+/// whose content has lines starting with `## ` does.
+pub fn compare(on_disk: &str, fresh: &str) -> Option<Staleness> {
+    let on_disk = without_markers(on_disk);
+    let fresh = without_markers(fresh);
+    if on_disk == fresh {
+        return None;
+    }
+    let old = sections(&on_disk);
+    let new = sections(&fresh);
+    let mut staleness = Staleness::default();
+    for (heading, body) in &new {
+        match old.get(heading) {
+            None => staleness.added.push(heading.to_string()),
+            Some(old_body) if old_body != body => staleness.changed.push(heading.to_string()),
+            Some(_) => {}
+        }
+    }
+    staleness.removed = old
+        .keys()
+        .filter(|heading| !new.contains_key(*heading))
+        .map(|heading| heading.to_string())
+        .collect();
+    Some(staleness)
+}
+
+fn without_markers(document: &str) -> String {
+    document
+        .split_inclusive('\n')
+        .filter(|line| !output::is_marker(line))
+        .collect()
+}
+
+// The document's file sections by heading, or by sentinel path when it
+// has sentinels; text before the first heading is left out
+fn sections(document: &str) -> BTreeMap<&str, String> {
+    if document
+        .split_inclusive('\n')
+        .any(|line| Sentinel::parse(line).is_some())
+    {
+        return sentinel_sections(document);
+    }
+    let mut sections: BTreeMap<&str, String> = BTreeMap::new();
+    let mut current: Option<&str> = None;
+    for line in document.split_inclusive('\n') {
+        if let Some(heading) = line.strip_prefix("## ") {
+            let heading = heading.trim_end();
+            sections.entry(heading).or_default();
+            current = Some(heading);
+        } else if let Some(heading) = current {
+            sections.entry(heading).or_default().push_str(line);
+        }
+    }
+    sections
+}
+
+// The sections between each BEGIN sentinel and the END sentinel naming the
+// same path; text outside them is left out
+fn sentinel_sections(document: &str) -> BTreeMap<&str, String> {
+    let mut sections: BTreeMap<&str, String> = BTreeMap::new();
+    let mut current: Option<&str> = None;
+    for line in document.split_inclusive('\n') {
+        match (Sentinel::parse(line), current) {
+            (Some(Sentinel::Begin(path)), None) => {
+                sections.entry(path).or_default();
+                current = Some(path);
+            }
+            (Some(Sentinel::End(path)), Some(open)) if path == open => current = None,
+            (_, Some(open)) => sections.entry(open).or_default().push_str(line),
+            (_, None) => {}
+        }
+    }
+    sections
+}
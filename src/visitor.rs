@@ -0,0 +1,158 @@
+// src/visitor.rs
+use std::path::Path;
+
+/// Content of a file handed to a [`FileVisitor`].
+#[derive(Debug, Clone, Copy)]
+pub enum FileContent<'a> {
+    /// Text content (invalid UTF-8 already replaced lossily).
+    Text(&'a str),
+    /// Content detected as binary. Emitted as a placeholder by default.
+    Binary(&'a [u8]),
+}
+
+/// What to do with a file after a [`FileVisitor`] has seen it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Emit the file unchanged.
+    Emit,
+    /// Emit the given text in place of the file's content.
+    EmitModified(String),
+    /// Leave the file out of the document entirely, for the given reason.
+    Skip(String),
+}
+
+/// Hook invoked for every file between reading it and writing its section.
+///
+/// Implemented for closures, so simple visitors can be written inline. A
+/// visitor that rewrites content:
+///
+/// ```
+/// use sourceweaver::{Action, FileContent, WeaveOptions, generate_markdown_with};
+///
+/// let options = WeaveOptions::new("src").build()?;
+/// let mut redact = |_path: &std::path::Path, content: FileContent<'_>| match content {
+///     FileContent::Text(text) if text.contains("WeaveOptions") => {
+///         Action::EmitModified(text.replace("WeaveOptions", "[REDACTED]"))
+///     }
+///     _ => Action::Emit,
+/// };
+///
+/// let mut document = Vec::new();
+/// generate_markdown_with(&mut document, &options, &mut redact)?;
+/// let document = String::from_utf8(document).unwrap();
+/// assert!(!document.contains("WeaveOptions"));
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+///
+/// A visitor that skips files and records what it saw:
+///
+/// ```
+/// use sourceweaver::{Action, FileContent, FileVisitor, WeaveOptions, generate_markdown_with};
+/// use std::path::{Path, PathBuf};
+///
+/// #[derive(Default)]
+/// struct OnlyLib {
+///     seen: Vec<PathBuf>,
+/// }
+///
+/// impl FileVisitor for OnlyLib {
+///     fn on_file(&mut self, path: &Path, _content: FileContent<'_>) -> Action {
+///         self.seen.push(path.to_path_buf());
+///         if path == Path::new("lib.rs") {
+///             Action::Emit
+///         } else {
+///             Action::Skip("not lib.rs".to_string())
+///         }
+///     }
+/// }
+///
+/// let options = WeaveOptions::new("src").build()?;
+/// let mut visitor = OnlyLib::default();
+/// let mut document = Vec::new();
+/// let report = generate_markdown_with(&mut document, &options, &mut visitor)?;
+///
+/// assert_eq!(report.files_written, 1);
+/// assert_eq!(report.skipped.len(), visitor.seen.len() - 1);
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+pub trait FileVisitor {
+    /// Called once after the walk, before any file is read, with the number
+    /// of files that will be processed.
+    fn on_start(&mut self, _total_files: usize) {}
+
+    /// Called with the file's path relative to the root and its content.
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action;
+
+    /// Called instead of [`on_file`](Self::on_file) for a file whose section
+    /// was reused from the [render cache](crate::WeaveOptionsBuilder::cache),
+    /// with its path relative to the root and its size in bytes.
+    fn on_reused(&mut self, _path: &Path, _size: u64) {}
+
+    /// Whether the file at `path`, relative to the root, may be written
+    /// without this visitor seeing its content: streamed to the output when
+    /// it is at least [`STREAM_THRESHOLD`](crate::STREAM_THRESHOLD) bytes
+    /// and no option needs it whole, or given the binary placeholder unread
+    /// when its extension is one of
+    /// [`BINARY_EXTENSIONS`](crate::BINARY_EXTENSIONS). Only asked about
+    /// such files. The default, `false`, reads them whole and hands them to
+    /// [`on_file`](Self::on_file) as usual.
+    fn streams(&mut self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Called instead of [`on_file`](Self::on_file) for a file written
+    /// without being read whole, with its path relative to the root and its
+    /// size in bytes.
+    fn on_streamed(&mut self, _path: &Path, _size: u64) {}
+}
+
+impl<F> FileVisitor for F
+where
+    F: FnMut(&Path, FileContent<'_>) -> Action,
+{
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        self(path, content)
+    }
+}
+
+/// Visitors run in order; each sees the content produced by the previous one,
+/// and the first `Skip` ends the chain.
+impl FileVisitor for Vec<Box<dyn FileVisitor>> {
+    fn on_start(&mut self, total_files: usize) {
+        for visitor in self.iter_mut() {
+            visitor.on_start(total_files);
+        }
+    }
+
+    fn on_reused(&mut self, path: &Path, size: u64) {
+        for visitor in self.iter_mut() {
+            visitor.on_reused(path, size);
+        }
+    }
+
+    fn streams(&mut self, path: &Path) -> bool {
+        self.iter_mut().all(|visitor| visitor.streams(path))
+    }
+
+    fn on_streamed(&mut self, path: &Path, size: u64) {
+        for visitor in self.iter_mut() {
+            visitor.on_streamed(path, size);
+        }
+    }
+
+    fn on_file(&mut self, path: &Path, content: FileContent<'_>) -> Action {
+        let mut modified: Option<String> = None;
+        for visitor in self.iter_mut() {
+            let current = match (&modified, content) {
+                (Some(text), _) => FileContent::Text(text),
+                (None, content) => content,
+            };
+            match visitor.on_file(path, current) {
+                Action::Emit => {}
+                Action::EmitModified(text) => modified = Some(text),
+                skip @ Action::Skip(_) => return skip,
+            }
+        }
+        modified.map_or(Action::Emit, Action::EmitModified)
+    }
+}
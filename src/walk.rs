@@ -0,0 +1,907 @@
+// src/walk.rs
+use crate::{
+    SkipLog, SkipReason, Submodules, WeaveError, WeaveOptions, display, is_lock_file, submodule,
+};
+use ignore::{DirEntry, Walk, WalkBuilder, WalkState, overrides::Override};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{debug, trace, warn};
+
+/// Build the directory walker for `options`.
+///
+/// The walker respects `.gitignore`, `.ignore`, git exclude files, and global
+/// gitignore rules, and additionally skips lock files, the output file, paths
+/// left out by the include and exclude globs, and files over the size limit.
+pub fn build_walker(options: &WeaveOptions) -> Walk {
+    build_walker_with_log(options, SkipLog::default())
+}
+
+/// Like [`build_walker`], but records every file skipped by the built-in
+/// filters in `skip_log`.
+pub fn build_walker_with_log(options: &WeaveOptions, skip_log: SkipLog) -> Walk {
+    walk_builder(options, skip_log).build()
+}
+
+// Configure the walk shared by the sequential and parallel walkers
+fn walk_builder(options: &WeaveOptions, skip_log: SkipLog) -> WalkBuilder {
+    // Use WalkBuilder to respect .gitignore, .ignore, etc. With explicit
+    // paths, only their directories are walked; named files are added later.
+    let mut dirs = options.paths.iter().filter(|path| path.is_dir());
+    let mut builder = WalkBuilder::new(match options.paths.is_empty() {
+        true => &options.root,
+        false => dirs.next().unwrap_or(&options.root),
+    });
+    for dir in dirs {
+        builder.add(dir);
+    }
+    apply_filters(&mut builder, options, skip_log, false);
+    builder
+}
+
+/// A named stage of the walk's filtering. Stages run in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterStage {
+    /// `.gitignore`, `.ignore`, git exclude, and global gitignore rules.
+    IgnoreFiles,
+    /// Hidden files and directories.
+    Hidden,
+    /// The output file and any other excluded paths.
+    OutputFile,
+    /// Known lock files.
+    LockFiles,
+    /// Files over the size limit.
+    SizeLimit,
+    /// Git submodules, when they are skipped.
+    Submodules,
+    /// Sockets, FIFOs, and devices, which could block the read forever.
+    SpecialFiles,
+    /// Excluded directory names.
+    ExcludedDirs,
+    /// Include and exclude globs and path regexes.
+    Patterns,
+    /// Files none of the given code owners owns.
+    Owners,
+    /// Files not on the given list of files.
+    FileList,
+    /// Files matching none of the scope's globs.
+    Scope,
+}
+
+impl FilterStage {
+    /// The stage's name, as shown in verbose output.
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterStage::IgnoreFiles => "ignore-files",
+            FilterStage::Hidden => "hidden",
+            FilterStage::OutputFile => "output-file",
+            FilterStage::LockFiles => "lock-files",
+            FilterStage::SizeLimit => "size-limit",
+            FilterStage::Submodules => "submodules",
+            FilterStage::SpecialFiles => "special-files",
+            FilterStage::ExcludedDirs => "exclude-dirs",
+            FilterStage::Patterns => "patterns",
+            FilterStage::Owners => "owners",
+            FilterStage::FileList => "file-list",
+            FilterStage::Scope => "scope",
+        }
+    }
+
+    /// Whether the stage is a built-in default, dropped by
+    /// [`default_filters(false)`](crate::WeaveOptionsBuilder::default_filters),
+    /// rather than configured explicitly or needed for the run to finish.
+    pub fn is_default(self) -> bool {
+        matches!(
+            self,
+            FilterStage::IgnoreFiles | FilterStage::Hidden | FilterStage::LockFiles
+        )
+    }
+}
+
+/// The filter stages the walk for `options` runs, in order. Stages with
+/// nothing to do, such as the size limit when none is set, are left out.
+///
+/// ```
+/// use sourceweaver::{FilterStage, WeaveOptions, filter_stages};
+///
+/// let options = WeaveOptions::new("src").max_filesize(1 << 20).build()?;
+/// assert!(filter_stages(&options).contains(&FilterStage::LockFiles));
+///
+/// let options = WeaveOptions::new("src").default_filters(false).build()?;
+/// assert_eq!(filter_stages(&options), [FilterStage::SpecialFiles]);
+/// # Ok::<(), sourceweaver::WeaveError>(())
+/// ```
+pub fn filter_stages(options: &WeaveOptions) -> Vec<FilterStage> {
+    let has_patterns = !options.patterns.is_empty()
+        || !options.path_regexes.is_empty()
+        || !options.path_regexes_not.is_empty();
+    [
+        (FilterStage::IgnoreFiles, true),
+        (FilterStage::Hidden, !options.hidden),
+        (FilterStage::OutputFile, !options.exclude_paths.is_empty()),
+        (FilterStage::LockFiles, true),
+        (FilterStage::SizeLimit, options.max_filesize.is_some()),
+        (
+            FilterStage::Submodules,
+            options.submodules == Submodules::Skip,
+        ),
+        (FilterStage::SpecialFiles, true),
+        (FilterStage::ExcludedDirs, !options.exclude_dirs.is_empty()),
+        (FilterStage::Patterns, has_patterns),
+        (FilterStage::Owners, options.owner_filter.is_some()),
+        (FilterStage::FileList, options.file_list.is_some()),
+        (FilterStage::Scope, options.scope.is_some()),
+    ]
+    .into_iter()
+    .filter(|(stage, active)| *active && (options.default_filters || !stage.is_default()))
+    .map(|(stage, _)| stage)
+    .collect()
+}
+
+// Set up ignore handling and the filter stages on `builder`. A `forced` walk
+// only sees force-included files, which bypass the lock file filter, the
+// excluded directory names, and the include and exclude globs.
+fn apply_filters(
+    builder: &mut WalkBuilder,
+    options: &WeaveOptions,
+    skip_log: SkipLog,
+    forced: bool,
+) {
+    let mut stages = filter_stages(options);
+    if forced {
+        stages.retain(|stage| {
+            !matches!(
+                stage,
+                FilterStage::LockFiles
+                    | FilterStage::ExcludedDirs
+                    | FilterStage::Patterns
+                    | FilterStage::Owners
+                    | FilterStage::FileList
+                    | FilterStage::Scope
+            )
+        });
+    }
+    let ignore_files = stages.contains(&FilterStage::IgnoreFiles);
+
+    let lock_files = options.lock_files.clone();
+    let lock_files_case_insensitive = options.lock_files_case_insensitive;
+    let output_paths = OutputPaths::new(&options.exclude_paths);
+    let max_filesize = options.max_filesize;
+    let exclude_dirs: HashSet<OsString> = options.exclude_dirs.iter().map(OsString::from).collect();
+    let skipped_submodules: HashSet<PathBuf> = match stages.contains(&FilterStage::Submodules) {
+        true => submodule::submodule_paths(&options.root)
+            .into_iter()
+            .collect(),
+        false => HashSet::new(),
+    };
+    let matcher = options.matcher.clone();
+    let owner_filter = options.owner_filter.clone();
+    let owners_root = options.root.clone();
+    let file_list = options.file_list.clone();
+    let list_root = options.root.clone();
+    let scope = options.scope.clone();
+    let scope_root = options.root.clone();
+    let root = options.root.clone();
+    let skip = move |path: &Path, reason: SkipReason| {
+        trace!("Skipping {}: {}", path.display(), reason);
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        skip_log.record(relative.to_path_buf(), reason);
+        false
+    };
+
+    builder
+        .hidden(stages.contains(&FilterStage::Hidden))
+        .parents(ignore_files)
+        .git_ignore(ignore_files)
+        .git_global(ignore_files)
+        .git_exclude(ignore_files)
+        .ignore(ignore_files)
+        .follow_links(options.follow_links)
+        // Sort entries so the document is deterministic regardless of filesystem order
+        .sort_by_file_name(|a, b| a.cmp(b))
+        // Run the remaining stages on every entry the walker yields
+        .filter_entry(move |entry| {
+            let is_file = entry.file_type().is_some_and(|ft| ft.is_file());
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            for stage in &stages {
+                match stage {
+                    // Applied by the walker itself
+                    FilterStage::IgnoreFiles | FilterStage::Hidden => {}
+
+                    FilterStage::OutputFile => {
+                        if output_paths.contains(entry.path(), entry.path_is_symlink(), |path| {
+                            fs::canonicalize(path)
+                        }) {
+                            return skip(entry.path(), SkipReason::OutputFile); // Skip output file
+                        }
+                    }
+
+                    // Check only files, to avoid matching directory names
+                    FilterStage::LockFiles => {
+                        if is_file
+                            && let Some(file_name) = entry.file_name().to_str()
+                            && is_lock_file(file_name, &lock_files, lock_files_case_insensitive)
+                        {
+                            return skip(entry.path(), SkipReason::LockFile); // Skip lock file
+                        }
+                    }
+
+                    FilterStage::SizeLimit => {
+                        if let Some(max_filesize) = max_filesize
+                            && is_file
+                            && entry.metadata().is_ok_and(|m| m.len() > max_filesize)
+                        {
+                            return skip(entry.path(), SkipReason::TooLarge); // Skip large file
+                        }
+                    }
+
+                    FilterStage::Submodules => {
+                        if is_dir && skipped_submodules.contains(entry.path()) {
+                            return skip(entry.path(), SkipReason::Submodule); // Prune submodule
+                        }
+                    }
+
+                    // Reading a socket, FIFO, or device can block forever.
+                    // Symlinks are resolved, so a link to one is skipped too.
+                    FilterStage::SpecialFiles => {
+                        if entry
+                            .file_type()
+                            .is_some_and(|ft| !ft.is_file() && !ft.is_dir())
+                            && fs::metadata(entry.path()).is_ok_and(|m| !m.is_file() && !m.is_dir())
+                        {
+                            debug!("Skipping {}: not a regular file", entry.path().display());
+                            return skip(entry.path(), SkipReason::SpecialFile);
+                        }
+                    }
+
+                    // The walk roots themselves are kept even if their name matches
+                    FilterStage::ExcludedDirs => {
+                        if entry.depth() > 0 && is_dir && exclude_dirs.contains(entry.file_name()) {
+                            return skip(entry.path(), SkipReason::ExcludedDir); // Prune directory
+                        }
+                    }
+
+                    // Directories are only pruned when no later include
+                    // could select something inside them
+                    FilterStage::Patterns => {
+                        if is_dir && matcher.prunes(entry.path())
+                            || !is_dir && matcher.is_excluded(entry.path(), false)
+                        {
+                            trace!("Skipping {}: excluded by pattern", entry.path().display());
+                            return false;
+                        }
+                    }
+
+                    // Directories are kept, as a later rule may own files inside
+                    FilterStage::Owners => {
+                        if is_file
+                            && let Some(owner_filter) = &owner_filter
+                            && let Ok(relative) = entry.path().strip_prefix(&owners_root)
+                            && !owner_filter.keeps(relative)
+                        {
+                            trace!("Skipping {}: not owned", entry.path().display());
+                            return false;
+                        }
+                    }
+
+                    // Directories without a listed file below them are
+                    // pruned; the walk root is always kept
+                    FilterStage::FileList => {
+                        if entry.depth() > 0
+                            && let Some(file_list) = &file_list
+                            && let Ok(relative) = entry.path().strip_prefix(&list_root)
+                            && !match is_dir {
+                                true => file_list.keeps_dir(relative),
+                                false => file_list.keeps_file(relative),
+                            }
+                        {
+                            trace!("Skipping {}: not listed", entry.path().display());
+                            return false;
+                        }
+                    }
+
+                    // Directories are kept, as a glob may match files inside
+                    FilterStage::Scope => {
+                        if is_file
+                            && let Some(scope) = &scope
+                            && let Ok(relative) = entry.path().strip_prefix(&scope_root)
+                            && !scope.keeps(relative)
+                        {
+                            trace!("Skipping {}: out of scope", entry.path().display());
+                            return false;
+                        }
+                    }
+                }
+            }
+            // If no stage skipped the entry, include it
+            true
+        })
+        .threads(options.threads());
+}
+
+/// A file selected by the walk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkedFile {
+    /// Path used to read the file.
+    pub path: PathBuf,
+    /// Path relative to the root, used for headings. For an external file,
+    /// `external/<file name>`.
+    pub relative_path: PathBuf,
+    /// Whether the file lies outside the root, added with
+    /// [`also`](crate::WeaveOptionsBuilder::also).
+    pub external: bool,
+}
+
+/// Files selected by [`collect_files`], plus everything it left out.
+#[derive(Debug, Default)]
+pub struct CollectedFiles {
+    /// Selected files, in walk order.
+    pub files: Vec<WalkedFile>,
+    /// Files skipped by the built-in filters, relative to the root.
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// Entries the walker could not access.
+    pub errors: Vec<WeaveError>,
+    /// Git submodules that aren't checked out, relative to the root, sorted.
+    /// Each gets a placeholder section in the document.
+    pub uninitialized_submodules: Vec<PathBuf>,
+    /// Directories the walk entered, the root included.
+    pub dirs_visited: usize,
+    /// How long the walk took.
+    pub walk_time: Duration,
+}
+
+/// Run the walk for `options` and collect every selected file, in walk order
+/// (sorted by file name within each directory).
+///
+/// With more than one [thread](WeaveOptions::threads) the directory tree is
+/// traversed in parallel and the results sorted afterwards, so the order is
+/// the same either way. With one thread the walk is purely sequential.
+///
+/// Entries the walker cannot access are logged and returned alongside the
+/// files rather than aborting the walk, unless `options.fail_fast()` is set,
+/// in which case the walk stops at the first error.
+///
+/// A root that is a file rather than a directory yields just that file,
+/// named after itself. With [explicit paths](crate::WeaveOptionsBuilder::path),
+/// only their directories are walked, named files are added as they are,
+/// and the result is sorted by path with duplicates removed. Files added
+/// with [`also`](crate::WeaveOptionsBuilder::also) come last.
+pub fn collect_files(options: &WeaveOptions) -> CollectedFiles {
+    let started = Instant::now();
+    let mut collected = if options.root.is_file() {
+        CollectedFiles {
+            files: vec![WalkedFile {
+                path: options.root.clone(),
+                relative_path: options
+                    .root
+                    .file_name()
+                    .map_or_else(|| options.root.clone(), PathBuf::from),
+                external: false,
+            }],
+            ..CollectedFiles::default()
+        }
+    } else {
+        walk_files(options)
+    };
+    if !options.also.is_empty() {
+        add_also_files(options, &mut collected);
+    }
+    collected.walk_time = started.elapsed();
+    collected
+}
+
+// Walk the root directory (or the directories among the explicit paths)
+fn walk_files(options: &WeaveOptions) -> CollectedFiles {
+    let stages: Vec<&str> = filter_stages(options).iter().map(|s| s.name()).collect();
+    debug!("Filter stages: {}", stages.join(", "));
+    let skip_log = SkipLog::default();
+    let walk_dirs = options.paths.is_empty() || options.paths.iter().any(|p| p.is_dir());
+    let mut collected = match walk_dirs {
+        true if options.threads() > 1 => collect_parallel(options, skip_log.clone()),
+        true => collect_sequential(options, skip_log.clone()),
+        false => CollectedFiles::default(),
+    };
+    collected.skipped = skip_log.take();
+    if options.submodules == Submodules::Include {
+        collected.uninitialized_submodules = uninitialized_submodules(options);
+    }
+    if let Some(overrides) = &options.force_override {
+        add_forced_files(options, overrides, &mut collected);
+    }
+    if !options.paths.is_empty() {
+        add_named_files(options, &mut collected);
+    } else if options.threads() > 1 || options.force_override.is_some() {
+        // Path ordering compares component-wise, matching the sequential walk
+        collected
+            .files
+            .sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        collected.skipped.sort();
+    }
+    collected
+}
+
+// Add the files matching the force-include globs that the walk skipped.
+// They are found by a second walk, with the globs as whitelist overrides, from
+// the deepest directory each glob spells out.
+fn add_forced_files(options: &WeaveOptions, overrides: &Override, collected: &mut CollectedFiles) {
+    let mut starts: Vec<PathBuf> = options
+        .force_includes
+        .iter()
+        .map(|glob| options.root.join(literal_prefix(glob)))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    // A start below another is kept, since the walk from above may not
+    // enter an ignored directory; files found twice are added once
+    starts.sort();
+    starts.dedup();
+    let Some((first, rest)) = starts.split_first() else {
+        return;
+    };
+    let mut builder = WalkBuilder::new(first);
+    for dir in rest {
+        builder.add(dir);
+    }
+    let skip_log = SkipLog::default();
+    apply_filters(&mut builder, options, skip_log.clone(), true);
+    builder.overrides(overrides.clone());
+
+    let mut forced = CollectedFiles::default();
+    for result in builder.build() {
+        if !collect_entry(options, result, &mut forced) {
+            break;
+        }
+    }
+    let known: HashSet<PathBuf> = collected
+        .files
+        .iter()
+        .map(|file| file.relative_path.clone())
+        .collect();
+    let mut added = HashSet::new();
+    for file in forced.files {
+        if !known.contains(&file.relative_path) && added.insert(file.relative_path.clone()) {
+            debug!("Force-including {}", file.relative_path.display());
+            collected.files.push(file);
+        }
+    }
+    // What the main walk skipped and the forced walk took in isn't skipped
+    collected.skipped.retain(|(path, _)| !added.contains(path));
+    for skipped in skip_log.take() {
+        if !collected.skipped.contains(&skipped) {
+            collected.skipped.push(skipped);
+        }
+    }
+    collected.errors.extend(forced.errors);
+}
+
+// The directories a glob spells out before its first wildcard, relative to
+// the root. A glob without a slash matches at any depth, so it starts at the
+// root; the last component may name a file, so it's never included.
+fn literal_prefix(glob: &str) -> PathBuf {
+    let glob = glob.trim_end_matches('/');
+    if !glob.contains('/') {
+        return PathBuf::new();
+    }
+    let mut components: Vec<&str> = glob
+        .trim_start_matches('/')
+        .split('/')
+        .take_while(|part| !part.contains(['*', '?', '[', '{', '\\']))
+        .collect();
+    if components.len() == glob.trim_start_matches('/').split('/').count() {
+        components.pop();
+    }
+    components.iter().collect()
+}
+
+// The output files a walk leaves out
+struct OutputPaths {
+    paths: Vec<PathBuf>,
+    // Names they could be walked under, compared without case for
+    // case-insensitive filesystems
+    names: HashSet<OsString>,
+}
+
+impl OutputPaths {
+    fn new(paths: &[PathBuf]) -> Self {
+        OutputPaths {
+            paths: paths.to_vec(),
+            names: paths
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    // Whether the entry at `path` could be an output file. Canonicalizing
+    // every entry is a syscall storm on large trees, so only entries named
+    // like an output path, or links that may point at one, are resolved.
+    fn is_candidate(&self, path: &Path, is_symlink: bool) -> bool {
+        is_symlink
+            || path
+                .file_name()
+                .is_some_and(|name| self.names.contains(&name.to_ascii_lowercase()))
+    }
+
+    // Whether the entry at `path` is an output file, resolving candidates
+    // with `canonicalize`. An entry that can't be resolved isn't one. Both
+    // sides are compared without the Windows `\\?\` prefix.
+    fn contains(
+        &self,
+        path: &Path,
+        is_symlink: bool,
+        canonicalize: impl FnOnce(&Path) -> io::Result<PathBuf>,
+    ) -> bool {
+        self.is_candidate(path, is_symlink)
+            && canonicalize(path)
+                .is_ok_and(|canonical| self.paths.contains(&display::strip_verbatim(canonical)))
+    }
+}
+
+// Add the files named in `options.paths`, then put everything in path order
+// without duplicates
+fn add_named_files(options: &WeaveOptions, collected: &mut CollectedFiles) {
+    for path in options.paths.iter().filter(|path| !path.is_dir()) {
+        let is_output = fs::canonicalize(path).is_ok_and(|canonical| {
+            options
+                .exclude_paths
+                .contains(&display::strip_verbatim(canonical))
+        });
+        if is_output {
+            continue;
+        }
+        if let Ok(relative_path) = path.strip_prefix(&options.root) {
+            collected.files.push(WalkedFile {
+                path: path.clone(),
+                relative_path: relative_path.to_path_buf(),
+                external: false,
+            });
+        }
+    }
+    collected
+        .files
+        .sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    collected
+        .files
+        .dedup_by(|a, b| a.relative_path == b.relative_path);
+    collected.skipped.sort();
+    collected.skipped.dedup();
+}
+
+// Add the files named with `also`. Those inside the root are added under
+// their relative path unless the walk already found them; the rest get an
+// `external/` display path.
+fn add_also_files(options: &WeaveOptions, collected: &mut CollectedFiles) {
+    let root = fs::canonicalize(&options.root)
+        .map(display::strip_verbatim)
+        .unwrap_or_else(|_| options.root.clone());
+    let root = match root.is_file() {
+        true => root.parent().map_or(root.clone(), Path::to_path_buf),
+        false => root,
+    };
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    for path in &options.also {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            result => {
+                let source = result.err().unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "not a regular file")
+                });
+                warn!(path = %path.display(), "Can't include {}: {}", path.display(), source);
+                collected.errors.push(WeaveError::Read {
+                    path: path.clone(),
+                    source,
+                });
+                continue;
+            }
+        };
+        let canonical = match fs::canonicalize(path) {
+            Ok(canonical) => display::strip_verbatim(canonical),
+            Err(_) => path.clone(),
+        };
+        if !seen.insert(canonical.clone()) || options.exclude_paths.contains(&canonical) {
+            continue;
+        }
+        let file = match canonical.strip_prefix(&root) {
+            Ok(relative_path) => WalkedFile {
+                path: canonical.clone(),
+                relative_path: relative_path.to_path_buf(),
+                external: false,
+            },
+            Err(_) => WalkedFile {
+                relative_path: Path::new("external")
+                    .join(canonical.file_name().unwrap_or_default()),
+                path: canonical,
+                external: true,
+            },
+        };
+        if !file.external
+            && collected
+                .files
+                .iter()
+                .any(|walked| walked.relative_path == file.relative_path)
+        {
+            continue;
+        }
+        if let Some(max_filesize) = options.max_filesize
+            && metadata.len() > max_filesize
+        {
+            trace!("Skipping {}: {}", path.display(), SkipReason::TooLarge);
+            collected
+                .skipped
+                .push((file.relative_path, SkipReason::TooLarge));
+            continue;
+        }
+        collected.files.push(file);
+    }
+}
+
+// Submodules under the root that would be walked but have nothing to walk
+fn uninitialized_submodules(options: &WeaveOptions) -> Vec<PathBuf> {
+    let walked = |path: &PathBuf| {
+        options.paths.is_empty()
+            || options
+                .paths
+                .iter()
+                .any(|dir| dir.is_dir() && path.starts_with(dir))
+    };
+    let mut paths: Vec<PathBuf> = submodule::submodule_paths(&options.root)
+        .into_iter()
+        .filter(walked)
+        .filter(|path| !submodule::is_initialized(path))
+        .filter_map(|path| path.strip_prefix(&options.root).ok().map(Path::to_path_buf))
+        .filter(|relative| !options.matcher.is_excluded(relative, true))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn collect_sequential(options: &WeaveOptions, skip_log: SkipLog) -> CollectedFiles {
+    let mut collected = CollectedFiles::default();
+    for result in build_walker_with_log(options, skip_log) {
+        if !collect_entry(options, result, &mut collected) {
+            break;
+        }
+    }
+    collected
+}
+
+fn collect_parallel(options: &WeaveOptions, skip_log: SkipLog) -> CollectedFiles {
+    let collected = Mutex::new(CollectedFiles::default());
+    walk_builder(options, skip_log).build_parallel().run(|| {
+        Box::new(|result| {
+            let mut collected = collected.lock().unwrap_or_else(|e| e.into_inner());
+            if collect_entry(options, result, &mut collected) {
+                WalkState::Continue
+            } else {
+                WalkState::Quit
+            }
+        })
+    });
+    collected.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+// Record one walk result; returns false when the walk should stop
+fn collect_entry(
+    options: &WeaveOptions,
+    result: std::result::Result<DirEntry, ignore::Error>,
+    collected: &mut CollectedFiles,
+) -> bool {
+    let root_dir = options.root.as_path();
+    match result {
+        Ok(entry) => {
+            let path = entry.path();
+            if entry.file_type().is_some_and(|t| t.is_dir()) {
+                collected.dirs_visited += 1;
+            }
+            if path == root_dir {
+                return true;
+            } // Skip root dir itself
+            if path.is_file() {
+                if let Ok(relative_path) = path.strip_prefix(root_dir) {
+                    if relative_path.as_os_str().is_empty() {
+                        return true;
+                    }
+                    collected.files.push(WalkedFile {
+                        path: path.to_path_buf(),
+                        relative_path: relative_path.to_path_buf(),
+                        external: false,
+                    });
+                } else {
+                    warn!("Could not get relative path for {}", path.display());
+                }
+            }
+            true
+        }
+        Err(err) => {
+            if options.fail_fast {
+                collected.errors.push(err.into());
+                return false;
+            }
+            let err = WeaveError::from(err);
+            // Permission errors come in floods under system directories; the
+            // caller's summary groups them instead
+            if err.io_error_kind() == Some(io::ErrorKind::PermissionDenied) {
+                debug!("Could not access entry: {}", err);
+            } else if let Some(path) = err.path() {
+                let relative = path.strip_prefix(root_dir).unwrap_or(path);
+                warn!(path = %relative.display(), "Could not access entry: {}", err);
+            } else {
+                warn!("Could not access entry: {}", err);
+            }
+            collected.errors.push(err);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, fmt, sync::Arc};
+    use tracing::{
+        Event, Level, Subscriber,
+        field::{Field, Visit},
+    };
+    use tracing_subscriber::{
+        Registry,
+        layer::{Context, Layer, SubscriberExt},
+    };
+
+    // An event's level, message, and `path` field
+    type Captured = (Level, String, Option<String>);
+
+    // Collects the events emitted while it is the default subscriber
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Captured>>>);
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            self.0
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), fields.message, fields.path));
+        }
+    }
+
+    #[derive(Default)]
+    struct Fields {
+        message: String,
+        path: Option<String>,
+    }
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            match field.name() {
+                "message" => self.message = format!("{:?}", value),
+                "path" => self.path = Some(format!("{:?}", value)),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn only_output_file_candidates_are_resolved() {
+        let outputs = OutputPaths::new(&[PathBuf::from("/work/docs/Bundle.md")]);
+        let resolved = RefCell::new(Vec::new());
+        // Resolves every path to the output file, so only the candidate
+        // check keeps unrelated entries in
+        let canonicalize = |path: &Path| {
+            resolved.borrow_mut().push(path.to_path_buf());
+            Ok(PathBuf::from("/work/docs/Bundle.md"))
+        };
+
+        for unrelated in ["/work/src/main.rs", "/work/docs", "/work/Bundle.md.bak"] {
+            assert!(!outputs.contains(Path::new(unrelated), false, canonicalize));
+        }
+        assert!(resolved.borrow().is_empty(), "{:?}", resolved.borrow());
+
+        // Named like the output, in any case, or a link that may point at it
+        assert!(outputs.contains(Path::new("/work/docs/Bundle.md"), false, canonicalize));
+        assert!(outputs.contains(Path::new("/work/copy/bundle.MD"), false, canonicalize));
+        assert!(outputs.contains(Path::new("/work/latest"), true, canonicalize));
+        assert_eq!(resolved.borrow().len(), 3);
+    }
+
+    #[test]
+    fn candidates_resolving_elsewhere_are_kept() {
+        let outputs = OutputPaths::new(&[PathBuf::from("/work/Bundle.md")]);
+        let elsewhere = |_: &Path| Ok(PathBuf::from("/work/src/Bundle.md"));
+        let unresolvable = |_: &Path| Err(io::Error::from(io::ErrorKind::NotFound));
+        assert!(!outputs.contains(Path::new("/work/src/Bundle.md"), false, elsewhere));
+        assert!(!outputs.contains(Path::new("/work/link"), true, unresolvable));
+    }
+
+    #[test]
+    fn no_output_paths_resolve_only_links() {
+        let outputs = OutputPaths::new(&[]);
+        assert!(!outputs.is_candidate(Path::new("/work/a.md"), false));
+        assert!(outputs.is_candidate(Path::new("/work/link"), true));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inaccessible_entries_are_warning_events() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink("nowhere", dir.path().join("dangling")).unwrap();
+        let options = WeaveOptions::new(dir.path())
+            .follow_links(true)
+            .threads(1)
+            .build()
+            .unwrap();
+
+        let capture = Capture::default();
+        let subscriber = Registry::default().with(capture.clone());
+        let collected = tracing::subscriber::with_default(subscriber, || collect_files(&options));
+
+        assert_eq!(collected.files.len(), 1);
+        assert_eq!(collected.errors.len(), 1);
+        let events = capture.0.lock().unwrap();
+        let warnings: Vec<_> = events
+            .iter()
+            .filter(|(level, _, _)| *level == Level::WARN)
+            .collect();
+        assert_eq!(warnings.len(), 1, "{:?}", events);
+        let (_, message, path) = warnings[0];
+        assert!(
+            message.starts_with("Could not access entry: "),
+            "{}",
+            message
+        );
+        assert_eq!(path.as_deref(), Some("dangling"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fifos_and_links_to_them_are_skipped_unread() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        fs::create_dir(dir.path().join("tmp")).unwrap();
+        // Nothing ever writes to it, so reading it would block forever
+        let fifo = dir.path().join("tmp/app.fifo");
+        let made = std::process::Command::new("mkfifo").arg(&fifo).status();
+        assert!(made.unwrap().success());
+        std::os::unix::fs::symlink(&fifo, dir.path().join("linked.fifo")).unwrap();
+
+        for follow in [false, true] {
+            let options = WeaveOptions::new(dir.path())
+                .follow_links(follow)
+                .threads(1)
+                .build()
+                .unwrap();
+            let capture = Capture::default();
+            let subscriber = Registry::default().with(capture.clone());
+            let collected =
+                tracing::subscriber::with_default(subscriber, || collect_files(&options));
+
+            let files: Vec<_> = collected.files.iter().map(|f| &f.relative_path).collect();
+            assert_eq!(files, [Path::new("main.rs")]);
+            let mut skipped = collected.skipped.clone();
+            skipped.sort();
+            assert_eq!(
+                skipped,
+                [
+                    (PathBuf::from("linked.fifo"), SkipReason::SpecialFile),
+                    (PathBuf::from("tmp/app.fifo"), SkipReason::SpecialFile),
+                ],
+                "follow: {}",
+                follow
+            );
+            let events = capture.0.lock().unwrap();
+            let notes = events
+                .iter()
+                .filter(|(level, message, _)| {
+                    *level == Level::DEBUG && message.ends_with(": not a regular file")
+                })
+                .count();
+            assert_eq!(notes, 2, "{:?}", events);
+        }
+    }
+}
@@ -0,0 +1,582 @@
+// tests/cli.rs
+use assert_cmd::{Command, assert::Assert};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+fn sourceweaver(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_sourceweaver"));
+    command.current_dir(dir).env_remove("RUST_LOG");
+    command
+}
+
+// A project with a source file and three files of about 4 KB each
+fn project() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("project");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(root.join(name), "text of some length\n".repeat(200)).unwrap();
+    }
+    dir
+}
+
+fn stderr(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).into_owned()
+}
+
+#[test]
+fn unreadable_root_exits_with_3() {
+    let dir = project();
+    fs::write(dir.path().join("file"), "").unwrap();
+    // A path through a regular file can't be opened, even by root
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "file/project"])
+        .assert()
+        .code(3);
+    assert!(stderr(&assert).contains("can't open root file/project"));
+}
+
+#[test]
+fn missing_root_exits_with_2() {
+    let dir = project();
+    sourceweaver(dir.path())
+        .args(["--root", "no-such-dir"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn unwritable_output_exits_with_3() {
+    let dir = project();
+    fs::write(dir.path().join("file"), "").unwrap();
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--output", "file/bundle.md"])
+        .assert()
+        .code(3);
+    assert!(stderr(&assert).contains("failed to write file/bundle.md"));
+}
+
+#[test]
+fn no_matching_files_exits_with_7() {
+    let dir = project();
+    sourceweaver(dir.path())
+        .args(["--root", "project", "--exclude", "*", "--fail-if-empty"])
+        .assert()
+        .code(7)
+        .stdout("");
+    // Without --fail-if-empty the empty document is still a success
+    sourceweaver(dir.path())
+        .args(["--root", "project", "--exclude", "*"])
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn max_output_truncation_exits_with_6() {
+    let dir = project();
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--max-output", "5000"])
+        .assert()
+        .code(6);
+    let document = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(
+        document.contains("additional files omitted)"),
+        "{}",
+        document
+    );
+    assert!(stderr(&assert).contains("Output truncated at --max-output"));
+}
+
+#[cfg(unix)]
+#[test]
+fn fail_fast_walk_error_exits_with_3() {
+    let dir = project();
+    std::os::unix::fs::symlink("nowhere", dir.path().join("project/dangling")).unwrap();
+    sourceweaver(dir.path())
+        .args(["--root", "project", "--follow", "--fail-fast"])
+        .assert()
+        .code(3);
+}
+
+#[cfg(unix)]
+#[test]
+fn warnings_exit_with_5_only_under_strict() {
+    let dir = project();
+    std::os::unix::fs::symlink("nowhere", dir.path().join("project/dangling")).unwrap();
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--follow", "--strict"])
+        .assert()
+        .code(5);
+    let messages = stderr(&assert);
+    assert!(
+        messages.contains("Completed with 1 warning(s)"),
+        "{}",
+        messages
+    );
+    assert!(messages.contains("--strict"), "{}", messages);
+
+    // The same run succeeds without --strict, still counting the warning
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--follow"])
+        .assert()
+        .code(0);
+    assert!(stderr(&assert).contains("Completed with 1 warning(s)"));
+}
+
+#[test]
+fn strict_run_without_warnings_succeeds() {
+    let dir = project();
+    sourceweaver(dir.path())
+        .args(["--root", "project", "--strict"])
+        .assert()
+        .code(0);
+    // --strict implies --fail-if-empty
+    sourceweaver(dir.path())
+        .args(["--root", "project", "--strict", "--exclude", "*"])
+        .assert()
+        .code(7);
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    let dir = project();
+    for args in [
+        &["--no-such-flag"][..],
+        &["--threads", "0"],
+        &["--include", "!*.rs"],
+        &["@missing-args.txt"],
+    ] {
+        sourceweaver(dir.path())
+            .args(["--root", "project"])
+            .args(args)
+            .assert()
+            .code(2);
+    }
+    fs::write(
+        dir.path().join("project/.sourceweaver.toml"),
+        "not = [valid",
+    )
+    .unwrap();
+    sourceweaver(dir.path())
+        .args(["--root", "project"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn split_documents_stream_large_files() {
+    let dir = project();
+    let logs = dir.path().join("project/logs");
+    fs::create_dir(&logs).unwrap();
+    // Just over the 16 MiB streaming threshold
+    let line = "a line of a large generated log file\n";
+    let lines = (16 << 20) / line.len() + 1;
+    fs::write(logs.join("big.log"), line.repeat(lines)).unwrap();
+
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--split-by", "top-dir"])
+        .args(["--output-dir", "out", "-v"])
+        .assert()
+        .code(0);
+    let messages = stderr(&assert);
+    assert!(messages.contains("Streaming logs/big.log"), "{}", messages);
+    let document = fs::read_to_string(dir.path().join("out/logs.md")).unwrap();
+    assert_eq!(document.matches(line).count(), lines);
+}
+
+#[test]
+fn split_documents_leave_binary_extensions_unread() {
+    let dir = project();
+    let assets = dir.path().join("project/assets");
+    fs::create_dir(&assets).unwrap();
+    // Text content, so only the extension makes it binary
+    fs::write(
+        assets.join("logo.png"),
+        "version https://git-lfs.github.com/spec/v1\n",
+    )
+    .unwrap();
+
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--split-by", "top-dir"])
+        .args(["--output-dir", "out", "-v"])
+        .assert()
+        .code(0);
+    let messages = stderr(&assert);
+    assert!(
+        messages.contains("Not reading assets/logo.png"),
+        "{}",
+        messages
+    );
+    let document = fs::read_to_string(dir.path().join("out/assets.md")).unwrap();
+    assert!(
+        document.contains("(Binary file, content omitted)"),
+        "{}",
+        document
+    );
+    assert!(!document.contains("git-lfs"));
+}
+
+#[test]
+fn compress_to_stdout_is_a_usage_error() {
+    let dir = project();
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--output", "-", "--compress", "gzip"])
+        .assert()
+        .code(2)
+        .stdout("");
+    assert!(stderr(&assert).contains("--compress only applies to --output files"));
+    // The same flag compresses a file
+    if cfg!(feature = "gzip") {
+        sourceweaver(dir.path())
+            .args([
+                "--root",
+                "project",
+                "--output",
+                "bundle.md",
+                "--compress",
+                "gzip",
+            ])
+            .assert()
+            .code(0);
+        assert!(
+            fs::read(dir.path().join("bundle.md"))
+                .unwrap()
+                .starts_with(&[0x1f, 0x8b])
+        );
+    }
+}
+
+#[test]
+fn list_tags_languages_like_the_document() {
+    let dir = project();
+    let root = dir.path().join("project");
+    fs::write(
+        root.join("CONFIG"),
+        "{\"name\": \"weaver\", \"tags\": [1, 2]}\n",
+    )
+    .unwrap();
+    fs::write(root.join("socket.h"), "namespace net {\nclass Socket;\n}\n").unwrap();
+
+    let listed = sourceweaver(dir.path())
+        .args(["--root", "project", "list", "--porcelain"])
+        .assert()
+        .code(0);
+    let listed = String::from_utf8_lossy(&listed.get_output().stdout).into_owned();
+    let document = sourceweaver(dir.path())
+        .args(["--root", "project"])
+        .assert()
+        .code(0);
+    let document = String::from_utf8_lossy(&document.get_output().stdout).into_owned();
+
+    for (name, tag) in [("CONFIG", "json"), ("socket.h", "cpp"), ("main.rs", "rust")] {
+        let row = listed
+            .lines()
+            .find(|line| line.starts_with(&format!("{}\t", name)))
+            .unwrap_or_else(|| panic!("{} not listed: {}", name, listed));
+        assert_eq!(row.split('\t').nth(2), Some(tag), "{}", row);
+        let heading = format!("## `{}`\n\n```{}\n", name, tag);
+        assert!(document.contains(&heading), "{}", document);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn invalid_utf8_names_are_encoded_everywhere() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = project();
+    let name = OsStr::from_bytes(b"caf\xE9.txt");
+    fs::write(dir.path().join("project").join(name), "menu\n".repeat(2000)).unwrap();
+
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project"])
+        .assert()
+        .code(0);
+    let document = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(document.contains("## `caf%E9.txt`"), "{}", document);
+    let messages = stderr(&assert);
+    assert!(
+        messages.contains("showing it percent-encoded as caf%E9.txt"),
+        "{}",
+        messages
+    );
+
+    // The JSON statistics name the file the same way
+    let assert = sourceweaver(dir.path())
+        .args([
+            "--root",
+            "project",
+            "--top",
+            "1",
+            "stats",
+            "--stats-format",
+            "json",
+        ])
+        .assert()
+        .code(0);
+    let stats: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(stats["largest"][0]["path"], "caf%E9.txt");
+}
+
+#[cfg(unix)]
+#[test]
+fn special_file_root_exits_with_2() {
+    let dir = project();
+    let made = std::process::Command::new("mkfifo")
+        .arg(dir.path().join("pipe"))
+        .status();
+    assert!(made.unwrap().success());
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "pipe"])
+        .assert()
+        .code(2);
+    assert!(stderr(&assert).contains("root pipe is not a directory, file, or archive"));
+}
+
+#[test]
+fn output_inside_a_relative_root_is_left_out() {
+    let dir = project();
+    for _ in 0..2 {
+        sourceweaver(dir.path())
+            .args(["--root", "project/", "--output", "project/bundle.md"])
+            .assert()
+            .code(0);
+    }
+    let document = fs::read_to_string(dir.path().join("project/bundle.md")).unwrap();
+    assert!(document.contains("## `main.rs`"), "{}", document);
+    assert!(!document.contains("bundle.md`"), "{}", document);
+}
+
+#[test]
+fn relative_outputs_are_never_woven() {
+    let dir = project();
+    let root = dir.path().join("project");
+    // Written twice, so the second run would see the first document
+    for output in ["./out.md", "./out.md", "../out.md", "../out.md"] {
+        sourceweaver(&root)
+            .args(["--output", output, "--force"])
+            .assert()
+            .code(0);
+    }
+    let inside = fs::read_to_string(root.join("out.md")).unwrap();
+    assert!(inside.contains("## `main.rs`"), "{}", inside);
+    assert!(!inside.contains("out.md`"), "{}", inside);
+
+    // From the parent, the root's own document is an ordinary file
+    sourceweaver(dir.path())
+        .args(["--root", ".", "--output", "./project/../out.md"])
+        .assert()
+        .code(0);
+    let outside = fs::read_to_string(dir.path().join("out.md")).unwrap();
+    assert!(outside.contains("## `project/out.md`"), "{}", outside);
+    assert!(!outside.contains("## `out.md`"), "{}", outside);
+}
+
+#[test]
+fn outputs_that_would_replace_the_root_are_usage_errors() {
+    let dir = project();
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project/main.rs", "--output", "project/./main.rs"])
+        .assert()
+        .code(2);
+    assert!(
+        stderr(&assert).contains("output path project/./main.rs is the root being scanned"),
+        "{}",
+        stderr(&assert)
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("project/main.rs")).unwrap(),
+        "fn main() {}\n"
+    );
+
+    // A directory gets a generated name inside it rather than an error
+    sourceweaver(dir.path())
+        .args(["--root", "project", "--output", "project"])
+        .assert()
+        .code(0);
+    let generated: Vec<_> = fs::read_dir(dir.path().join("project"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("project-") && name.ends_with(".md"))
+        .collect();
+    assert_eq!(generated.len(), 1, "{:?}", generated);
+}
+
+#[cfg(unix)]
+#[test]
+fn json_messages_are_one_object_per_line() {
+    let dir = project();
+    let root = dir.path().join("project");
+    fs::write(root.join("say \"hi\"\\\tnow.txt"), "quoted\n").unwrap();
+    std::os::unix::fs::symlink("nowhere", root.join("dangling")).unwrap();
+
+    let assert = sourceweaver(dir.path())
+        .args([
+            "--root",
+            "project",
+            "--follow",
+            "-v",
+            "--message-format",
+            "json",
+        ])
+        .assert()
+        .code(0);
+    let messages = stderr(&assert);
+    let mut types = Vec::new();
+    for line in messages.lines() {
+        let event: serde_json::Value =
+            serde_json::from_str(line).unwrap_or_else(|e| panic!("not JSON ({}): {}", e, line));
+        let kind = event["type"].as_str().unwrap().to_string();
+        match kind.as_str() {
+            "file" => assert!(event["path"].is_string() && event["bytes"].is_u64()),
+            "warning" | "error" | "status" => assert!(event["message"].is_string()),
+            "summary" => {
+                for field in ["files", "bytes", "tokens"] {
+                    assert!(event[field].is_u64(), "{}", line);
+                }
+            }
+            _ => {}
+        }
+        types.push(kind);
+    }
+    for kind in ["file", "warning", "summary"] {
+        assert!(
+            types.iter().any(|t| t == kind),
+            "no {} in {}",
+            kind,
+            messages
+        );
+    }
+
+    // Only stderr changes; the document is the same as in a plain run
+    let plain = sourceweaver(dir.path())
+        .args(["--root", "project", "--follow"])
+        .assert()
+        .code(0);
+    assert_eq!(assert.get_output().stdout, plain.get_output().stdout);
+}
+
+#[test]
+fn invalid_path_regex_fails_before_the_walk() {
+    let dir = project();
+    let assert = sourceweaver(dir.path())
+        .args(["--root", "project", "--path-regex-not", "v[0-9"])
+        .assert()
+        .code(2)
+        .stdout("");
+    let messages = stderr(&assert);
+    assert!(
+        messages.contains("invalid path regex 'v[0-9'"),
+        "{}",
+        messages
+    );
+    assert!(
+        messages.contains("unclosed character class"),
+        "{}",
+        messages
+    );
+}
+
+#[test]
+fn response_files_pass_paths_with_spaces() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("my project");
+    fs::create_dir_all(root.join("src dir")).unwrap();
+    fs::write(root.join("src dir/main file.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("notes.md"), "# Notes\n").unwrap();
+    fs::write(
+        dir.path().join("args.txt"),
+        "# weave only the sources\n--root\nmy project\n\n--include\nsrc dir/*.rs\n",
+    )
+    .unwrap();
+
+    let assert = sourceweaver(dir.path()).arg("@args.txt").assert().code(0);
+    let document = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(
+        document.contains("## `src dir/main file.rs`"),
+        "{}",
+        document
+    );
+    assert!(!document.contains("notes.md"), "{}", document);
+}
+
+#[test]
+fn warn_size_counts_the_document_written() {
+    let dir = project();
+    let document = sourceweaver(dir.path())
+        .args(["--root", "project"])
+        .assert()
+        .code(0);
+    let size = document.get_output().stdout.len();
+    let warned = |extra: &[&str], threshold: usize| {
+        let assert = sourceweaver(dir.path())
+            .args(["--root", "project", "--warn-size", &threshold.to_string()])
+            .args(extra)
+            .assert()
+            .code(0);
+        stderr(&assert).contains("over the --warn-size")
+    };
+    assert!(!warned(&[], size));
+    assert!(warned(&[], size - 1));
+    assert!(!warned(&["--quiet"], size - 1));
+    // The same for a file, whose size is what lands on disk
+    assert!(warned(&["--output", "bundle.md"], size - 1));
+    let written = fs::metadata(dir.path().join("bundle.md")).unwrap().len() as usize;
+    assert!(!warned(&["--output", "bundle.md", "--force"], written));
+}
+
+#[test]
+fn package_selects_a_workspace_member() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = [
+        ("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n"),
+        (
+            "crates/app/Cargo.toml",
+            "[package]\nname = \"app\"\n\n[dependencies]\nutil = { path = \"../util\" }\n",
+        ),
+        ("crates/app/src/main.rs", "fn main() {}\n"),
+        ("crates/util/Cargo.toml", "[package]\nname = \"util\"\n"),
+        ("crates/util/src/lib.rs", "pub fn util() {}\n"),
+        ("README.md", "# Workspace\n"),
+    ];
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    let headings = |args: &[&str]| {
+        let assert = sourceweaver(dir.path()).args(args).assert().code(0);
+        let document = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+        document
+            .lines()
+            .filter_map(|line| line.strip_prefix("## "))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let mut only = headings(&["-p", "app"]);
+    only.sort();
+    assert_eq!(
+        only,
+        [
+            "`Cargo.toml`",
+            "`crates/app/Cargo.toml`",
+            "`crates/app/src/main.rs`"
+        ]
+    );
+    let with_deps = headings(&["-p", "app", "--with-path-deps"]);
+    assert!(with_deps.contains(&"`crates/util/src/lib.rs`".to_string()));
+    assert!(!with_deps.contains(&"`README.md`".to_string()));
+
+    let assert = sourceweaver(dir.path())
+        .args(["-p", "missing"])
+        .assert()
+        .code(2);
+    assert!(
+        stderr(&assert).contains("no package 'missing' in ")
+            && stderr(&assert).contains("the packages are: app, util"),
+        "{}",
+        stderr(&assert)
+    );
+}